@@ -49,6 +49,12 @@ fn main() {
 
     println!("cargo:rerun-if-changed=build.rs");
 
+    // The binary blob is Thumb code, and can't be linked into the unit tests,
+    // which run on the host.
+    if !env::var("TARGET").unwrap().starts_with("thumb") {
+        return;
+    }
+
     // Copy the binary blob required by the Flash API somewhere the linker can
     // find it, and tell Cargo to link it.
 
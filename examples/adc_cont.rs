@@ -47,7 +47,9 @@ fn main() -> ! {
     let buffer = Pin::new(unsafe { &mut BUFFER });
 
     // Start reading ADC values
-    let mut adc = adc.start(a0, None, &mut dma.handle, dma.channels.channel1, buffer);
+    let mut adc = adc
+        .start(a0, None, &mut dma.handle, dma.channels.channel1, buffer)
+        .unwrap();
 
     loop {
         let read_available = match adc.read_available() {
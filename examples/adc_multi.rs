@@ -55,13 +55,15 @@ fn main() -> ! {
     channels.add(gpioa.pa5.into_analog());
 
     // Start reading ADC values
-    let mut adc = adc.start(
-        channels,
-        Some(adc::Trigger::TIM2_TRGO),
-        &mut dma.handle,
-        dma.channels.channel1,
-        buffer,
-    );
+    let mut adc = adc
+        .start(
+            channels,
+            Some(adc::Trigger::TIM2_TRGO),
+            &mut dma.handle,
+            dma.channels.channel1,
+            buffer,
+        )
+        .unwrap();
 
     // Enable trigger output for TIM2. This must happen after ADC has been
     // configured.
@@ -52,13 +52,15 @@ fn main() -> ! {
     let buffer = Pin::new(unsafe { &mut BUFFER });
 
     // Start reading ADC values
-    let mut adc = adc.start(
-        a0,
-        Some(adc::Trigger::TIM2_TRGO),
-        &mut dma.handle,
-        dma.channels.channel1,
-        buffer,
-    );
+    let mut adc = adc
+        .start(
+            a0,
+            Some(adc::Trigger::TIM2_TRGO),
+            &mut dma.handle,
+            dma.channels.channel1,
+            buffer,
+        )
+        .unwrap();
 
     // Enable trigger output for TIM2. This must happen after ADC has been
     // configured.
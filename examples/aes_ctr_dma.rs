@@ -48,11 +48,12 @@ fn main() -> ! {
         let mut ctr_stream = aes.enable(aes::Mode::ctr(ivr), key);
         let mut tx_transfer = ctr_stream
             .tx
-            .write_all(&mut dma.handle, data, dma.channels.channel1);
-        let mut rx_transfer =
-            ctr_stream
-                .rx
-                .read_all(&mut dma.handle, encrypted, dma.channels.channel2);
+            .write_all(&mut dma.handle, data, dma.channels.channel1)
+            .unwrap();
+        let mut rx_transfer = ctr_stream
+            .rx
+            .read_all(&mut dma.handle, encrypted, dma.channels.channel2)
+            .unwrap();
 
         let (tx_res, rx_res) = interrupt::free(|_| {
             unsafe {
@@ -96,14 +97,14 @@ fn main() -> ! {
         assert_ne!(**encrypted, **data);
 
         let mut ctr_stream = aes.enable(aes::Mode::ctr(ivr), key);
-        let mut tx_transfer =
-            ctr_stream
-                .tx
-                .write_all(&mut dma.handle, encrypted, dma.channels.channel1);
-        let mut rx_transfer =
-            ctr_stream
-                .rx
-                .read_all(&mut dma.handle, decrypted, dma.channels.channel2);
+        let mut tx_transfer = ctr_stream
+            .tx
+            .write_all(&mut dma.handle, encrypted, dma.channels.channel1)
+            .unwrap();
+        let mut rx_transfer = ctr_stream
+            .rx
+            .read_all(&mut dma.handle, decrypted, dma.channels.channel2)
+            .unwrap();
 
         let (tx_res, rx_res) = interrupt::free(|_| {
             unsafe {
@@ -0,0 +1,63 @@
+//! Measures the period of a signal on PA0 using TIM2 input capture.
+
+#![no_main]
+#![no_std]
+
+extern crate panic_halt;
+
+use cortex_m_rt::entry;
+use nb::block;
+use stm32l0xx_hal::{
+    capture::{self, Filter, Polarity},
+    pac,
+    prelude::*,
+    rcc::Config,
+};
+
+#[entry]
+fn main() -> ! {
+    let dp = pac::Peripherals::take().unwrap();
+
+    // Configure the clock.
+    let mut rcc = dp.RCC.freeze(Config::hsi16());
+
+    // Acquire the GPIOA peripheral. This also enables the clock for GPIOA in
+    // the RCC register.
+    let gpioa = dp.GPIOA.split(&mut rcc);
+
+    let mut led = gpioa.pa5.into_push_pull_output();
+
+    // Let TIM2 count at 1 MHz, so the captured values are in microseconds.
+    let timer = capture::Timer::new(dp.TIM2, 1.MHz(), &mut rcc);
+
+    let mut capture = timer.channel1.assign(
+        gpioa.pa0,
+        capture::Config::default()
+            .polarity(Polarity::RisingEdge)
+            .filter(Filter::CkIntN8),
+    );
+    capture.enable();
+
+    let mut last = block!(capture.read()).unwrap();
+
+    loop {
+        let now = match block!(capture.read()) {
+            Ok(now) => now,
+            // We were too slow and missed an edge. Start over.
+            Err(capture::Error::Overcapture) => {
+                last = block!(capture.read()).unwrap();
+                continue;
+            }
+            Err(error) => panic!("{:?}", error),
+        };
+        let period = now.wrapping_sub(last);
+        last = now;
+
+        // Light the LED, if the signal is faster than 1 kHz.
+        if period < 1000 {
+            led.set_high().unwrap();
+        } else {
+            led.set_low().unwrap();
+        }
+    }
+}
@@ -0,0 +1,46 @@
+//! Reads a rotary encoder connected to PA0 and PA1, using TIM2.
+
+#![no_main]
+#![no_std]
+
+extern crate panic_halt;
+
+use cortex_m_rt::entry;
+use stm32l0xx_hal::{
+    capture::Filter,
+    encoder::{Encoder, Mode},
+    pac,
+    prelude::*,
+    rcc::Config,
+};
+
+#[entry]
+fn main() -> ! {
+    let dp = pac::Peripherals::take().unwrap();
+
+    // Configure the clock.
+    let mut rcc = dp.RCC.freeze(Config::hsi16());
+
+    // Acquire the GPIOA peripheral. This also enables the clock for GPIOA in
+    // the RCC register.
+    let gpioa = dp.GPIOA.split(&mut rcc);
+
+    let mut led = gpioa.pa5.into_push_pull_output();
+
+    let encoder = Encoder::new(
+        dp.TIM2,
+        (gpioa.pa0, gpioa.pa1),
+        Mode::Ti1AndTi2,
+        Filter::DtsDiv32N8,
+        &mut rcc,
+    );
+
+    loop {
+        // Toggle the LED every 4 steps of the encoder.
+        if encoder.count() & 0b100 != 0 {
+            led.set_high().unwrap();
+        } else {
+            led.set_low().unwrap();
+        }
+    }
+}
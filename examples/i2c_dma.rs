@@ -55,7 +55,9 @@ fn main() -> ! {
         buffer[0] = 0xc0; // address of on of the reference registers
 
         // Prepare requesting data from reference register
-        let mut transfer = i2c.write_all(&mut dma.handle, tx_channel, address, buffer);
+        let mut transfer = i2c
+            .write_all(&mut dma.handle, tx_channel, address, buffer)
+            .unwrap();
 
         // Start DMA transfer and wait for it to finish
         let res = interrupt::free(|_| {
@@ -86,7 +88,9 @@ fn main() -> ! {
         buffer = res.buffer;
 
         // Prepare to read returned data.
-        let mut transfer = i2c.read_all(&mut dma.handle, rx_channel, address, buffer);
+        let mut transfer = i2c
+            .read_all(&mut dma.handle, rx_channel, address, buffer)
+            .unwrap();
 
         // Start DMA transfer and wait for it to finish
         let res = interrupt::free(|_| {
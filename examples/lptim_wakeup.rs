@@ -0,0 +1,48 @@
+//! Wakes up from Stop mode every 250 ms, using the Low-Power Timer.
+
+#![no_main]
+#![no_std]
+
+extern crate panic_halt;
+
+use cortex_m_rt::entry;
+use stm32l0xx_hal::{
+    exti::Exti,
+    lptim::{ClockSrc, LptimWakeup},
+    pac,
+    prelude::*,
+    pwr::{self, PWR},
+    rcc,
+};
+
+#[entry]
+fn main() -> ! {
+    let cp = pac::CorePeripherals::take().unwrap();
+    let dp = pac::Peripherals::take().unwrap();
+
+    let mut scb = cp.SCB;
+    let mut rcc = dp.RCC.freeze(rcc::Config::msi(rcc::MSIRange::Range5));
+    let mut exti = Exti::new(dp.EXTI);
+    let mut pwr = PWR::new(dp.PWR, &mut rcc);
+    let gpiob = dp.GPIOB.split(&mut rcc);
+
+    let mut led = gpiob.pb2.into_push_pull_output();
+
+    let mut wakeup = LptimWakeup::new(dp.LPTIM, &mut exti, &mut pwr, &mut rcc, ClockSrc::Lse);
+
+    loop {
+        led.toggle().unwrap();
+
+        wakeup.wait(
+            &mut exti,
+            250.ms(),
+            pwr.stop_mode(
+                &mut scb,
+                &mut rcc,
+                pwr::StopModeConfig {
+                    ultra_low_power: true,
+                },
+            ),
+        );
+    }
+}
@@ -58,7 +58,7 @@ fn main() -> ! {
     let mut rtc = RTC::new(dp.RTC, &mut rcc, &mut pwr, instant);
 
     loop {
-        let mut instant = rtc.now();
+        let mut instant = rtc.datetime();
 
         if button.is_low().unwrap() {
             let second = instant.second() + 1;
@@ -69,7 +69,7 @@ fn main() -> ! {
                 instant.set_second(0)
             };
 
-            rtc.set(instant);
+            rtc.set_datetime(instant);
         }
 
         write!(
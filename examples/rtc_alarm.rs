@@ -0,0 +1,57 @@
+//! Wakes up from Stop mode every half minute, using the RTC alarms.
+
+#![no_main]
+#![no_std]
+
+extern crate panic_halt;
+
+use cortex_m_rt::entry;
+use stm32l0xx_hal::{
+    exti::{ConfigurableLine, Exti},
+    pac,
+    prelude::*,
+    pwr::{self, PWR},
+    rcc,
+    rtc::{Alarm, AlarmId, Instant, RTC},
+};
+
+#[entry]
+fn main() -> ! {
+    let cp = pac::CorePeripherals::take().unwrap();
+    let dp = pac::Peripherals::take().unwrap();
+
+    let mut scb = cp.SCB;
+    let mut rcc = dp.RCC.freeze(rcc::Config::hsi16());
+    let mut exti = Exti::new(dp.EXTI);
+    let mut pwr = PWR::new(dp.PWR, &mut rcc);
+    let gpiob = dp.GPIOB.split(&mut rcc);
+
+    let mut led = gpiob.pb2.into_push_pull_output();
+
+    let mut rtc = RTC::new(dp.RTC, &mut rcc, &mut pwr, Instant::new());
+
+    // Only match the seconds, so the alarm fires at :00 and :30 of every
+    // minute.
+    rtc.set_alarm(AlarmId::A, Alarm::new().set_second(0));
+    rtc.set_alarm(AlarmId::B, Alarm::new().set_second(30));
+    rtc.listen_alarm(AlarmId::A, &mut exti);
+    rtc.listen_alarm(AlarmId::B, &mut exti);
+
+    loop {
+        led.toggle().unwrap();
+
+        exti.wait_for_irq(
+            ConfigurableLine::RtcAlarm,
+            pwr.stop_mode(
+                &mut scb,
+                &mut rcc,
+                pwr::StopModeConfig {
+                    ultra_low_power: true,
+                },
+            ),
+        );
+
+        rtc.wait_alarm(AlarmId::A).ok();
+        rtc.wait_alarm(AlarmId::B).ok();
+    }
+}
@@ -41,7 +41,7 @@ fn main() -> ! {
     let mut last_second = 0;
 
     loop {
-        let instant = rtc.now();
+        let instant = rtc.datetime();
 
         if instant.second() != last_second {
             last_second = instant.second();
@@ -45,7 +45,7 @@ fn main() -> ! {
 
     loop {
         // Prepare read transfer
-        let mut transfer = rx.read_all(&mut dma.handle, buffer, rx_channel);
+        let mut transfer = rx.read_all(&mut dma.handle, buffer, rx_channel).unwrap();
 
         // Start DMA transfer and wait for it to finish
         let res = interrupt::free(|_| {
@@ -75,7 +75,7 @@ fn main() -> ! {
         buffer = res.buffer;
 
         // Prepare write transfer
-        let mut transfer = tx.write_all(&mut dma.handle, buffer, tx_channel);
+        let mut transfer = tx.write_all(&mut dma.handle, buffer, tx_channel).unwrap();
 
         // Start DMA transfer and wait for it to finish
         let res = interrupt::free(|_| {
@@ -76,7 +76,7 @@ fn main() -> ! {
             RxState::READY(rx, channel) => {
                 if let Some(buffer) = rx_buffers.dequeue() {
                     // prepare transfer transaction
-                    let mut transfer = rx.read_all(dma_handle, buffer, channel);
+                    let mut transfer = rx.read_all(dma_handle, buffer, channel).unwrap();
                     transfer.enable_interrupts(dma::Interrupts {
                         transfer_error: true,
                         transfer_complete: true,
@@ -106,7 +106,7 @@ fn main() -> ! {
             TxState::READY(tx, channel) => {
                 if let Some(buffer) = tx_buffers.dequeue() {
                     // prepare transfer transaction
-                    let mut transfer = tx.write_all(dma_handle, buffer, channel);
+                    let mut transfer = tx.write_all(dma_handle, buffer, channel).unwrap();
                     transfer.enable_interrupts(dma::Interrupts {
                         transfer_error: true,
                         transfer_complete: true,
@@ -15,7 +15,7 @@ use stm32l0xx_hal::{
     pac::{self, interrupt, Interrupt},
     prelude::*,
     rcc::Config,
-    timer::Timer,
+    timer::{Event, Timer},
 };
 
 static LED: Mutex<RefCell<Option<gpioa::PA1<Output<PushPull>>>>> = Mutex::new(RefCell::new(None));
@@ -37,7 +37,7 @@ fn main() -> ! {
 
     // Configure the timer.
     let mut timer = dp.TIM2.timer(1.hz(), &mut rcc);
-    timer.listen();
+    timer.listen(Event::Update);
 
     // Store the LED and timer in mutex refcells to make them available from the
     // timer interrupt.
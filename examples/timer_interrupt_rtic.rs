@@ -6,7 +6,13 @@
 extern crate panic_halt;
 
 use rtic::app;
-use stm32l0xx_hal::{gpio::*, pac, prelude::*, rcc::Config, timer::Timer};
+use stm32l0xx_hal::{
+    gpio::*,
+    pac,
+    prelude::*,
+    rcc::Config,
+    timer::{Event, Timer},
+};
 
 #[app(device = stm32l0xx_hal::pac, peripherals = true)]
 const APP: () = {
@@ -31,7 +37,7 @@ const APP: () = {
 
         // Configure the timer.
         let mut timer = device.TIM2.timer(1.hz(), &mut rcc);
-        timer.listen();
+        timer.listen(Event::Update);
 
         // Return the initialised resources.
         init::LateResources { led, timer }
@@ -16,11 +16,18 @@ fn main() -> ! {
 
     let mut rcc = dp.RCC.freeze(rcc::Config::hsi16());
     let mut syscfg = SYSCFG::new(dp.SYSCFG, &mut rcc);
-    let hsi48 = rcc.enable_hsi48(&mut syscfg, dp.CRS);
 
     let gpioa = dp.GPIOA.split(&mut rcc);
 
-    let usb = USB::new(dp.USB, gpioa.pa11, gpioa.pa12, hsi48);
+    // Trim the HSI48 from the USB start-of-frame packets
+    let (usb, _crs) = USB::new_with_crs(
+        dp.USB,
+        gpioa.pa11,
+        gpioa.pa12,
+        &mut rcc,
+        &mut syscfg,
+        dp.CRS,
+    );
     let usb_bus = UsbBus::new(usb);
 
     let mut serial = SerialPort::new(&usb_bus);
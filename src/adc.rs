@@ -12,11 +12,39 @@ use crate::{
     gpio::*,
     hal::adc::{Channel, OneShot},
     pac::ADC,
-    rcc::Rcc,
+    rcc::{Enable, Rcc},
 };
 
 use crate::dma::{self, Buffer as _};
 
+/// Address of the factory calibration value of VREFINT (VREFINT_CAL)
+///
+/// This is the 12-bit, right-aligned conversion result of VREFINT at
+/// VDDA = [`VDDA_CAL`].
+const VREFINT_CAL: u32 = 0x1FF8_0078;
+
+/// Address of the factory calibration value of the temperature sensor at
+/// [`TS_CAL1_TEMP`] (TS_CAL1)
+const TS_CAL1: u32 = 0x1FF8_007A;
+
+/// Address of the factory calibration value of the temperature sensor at
+/// [`TS_CAL2_TEMP`] (TS_CAL2)
+const TS_CAL2: u32 = 0x1FF8_007E;
+
+/// Temperature at which TS_CAL1 was measured, in °C
+const TS_CAL1_TEMP: i32 = 30;
+
+/// Temperature at which TS_CAL2 was measured, in °C
+const TS_CAL2_TEMP: i32 = 130;
+
+/// VDDA at which the factory calibration values were measured, in mV
+const VDDA_CAL: u32 = 3000;
+
+/// Startup time of the VREFINT buffer and temperature sensor, in µs
+///
+/// See t_START in the datasheet.
+const T_START_US: u32 = 10;
+
 pub trait AdcExt {
     fn constrain(self, rcc: &mut Rcc) -> Adc<Ready>;
 }
@@ -28,7 +56,8 @@ impl AdcExt for ADC {
 }
 
 /// ADC Result Alignment
-#[derive(PartialEq)]
+#[derive(Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Align {
     /// Right aligned results (least significant bits)
     ///
@@ -45,6 +74,7 @@ pub enum Align {
 
 /// ADC Sampling Precision
 #[derive(Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Precision {
     /// 12 bit precision
     B_12 = 0b00,
@@ -56,8 +86,29 @@ pub enum Precision {
     B_6 = 0b11,
 }
 
+/// Interrupt events of the ADC
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Event {
+    /// A conversion has ended (EOC)
+    EndOfConversion,
+
+    /// A sequence of conversions has ended (EOS)
+    EndOfSequence,
+
+    /// The sampling phase has ended (EOSMP)
+    EndOfSampling,
+
+    /// A conversion result has been overwritten before it was read (OVR)
+    Overrun,
+
+    /// The analog watchdog has detected a value outside of its window (AWD)
+    AnalogWatchdog,
+}
+
 /// ADC Sampling time
 #[derive(Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum SampleTime {
     /// 1.5 ADC clock cycles
     T_1_5 = 0b000,
@@ -87,6 +138,7 @@ pub enum SampleTime {
 /// Analog to Digital converter interface
 pub struct Adc<State> {
     rb: ADC,
+    sys_clk: u32,
     sample_time: SampleTime,
     align: Align,
     precision: Precision,
@@ -96,11 +148,12 @@ pub struct Adc<State> {
 impl Adc<Ready> {
     pub fn new(adc: ADC, rcc: &mut Rcc) -> Self {
         // Enable ADC clocks
-        rcc.rb.apb2enr.modify(|_, w| w.adcen().set_bit());
+        ADC::enable(rcc);
         adc.cr.modify(|_, w| w.advregen().set_bit());
 
         Self {
             rb: adc,
+            sys_clk: rcc.clocks.sys_clk().0,
             sample_time: SampleTime::T_1_5,
             align: Align::Right,
             precision: Precision::B_12,
@@ -123,6 +176,109 @@ impl Adc<Ready> {
         self.precision = precision;
     }
 
+    /// Returns the analog supply voltage VDDA in mV
+    ///
+    /// VDDA is the reference of the ADC, so it's derived from a conversion of
+    /// VREFINT and its factory calibration value. This allows monitoring the
+    /// supply, e.g. a battery, without an external voltage divider.
+    pub fn read_vdda(&mut self) -> u16 {
+        let enabled = self.rb.ccr.read().vrefen().bit_is_set();
+        if !enabled {
+            self.rb.ccr.modify(|_, w| w.vrefen().set_bit());
+            self.wait_for_startup();
+        }
+
+        let vrefint = self.convert_internal(VRef::channel());
+
+        if !enabled {
+            self.rb.ccr.modify(|_, w| w.vrefen().clear_bit());
+        }
+
+        let vrefint_cal = read_calibration(VREFINT_CAL);
+
+        (VDDA_CAL * u32::from(vrefint_cal) / u32::from(vrefint.max(1))) as u16
+    }
+
+    /// Returns the temperature of the die in °C
+    ///
+    /// Converts the internal temperature sensor, and interpolates between its
+    /// two factory calibration points. As these were measured at a different
+    /// VDDA, the conversion is scaled by the VDDA measured by
+    /// [`Self::read_vdda`].
+    pub fn read_temperature(&mut self) -> i16 {
+        let vdda = self.read_vdda();
+
+        let enabled = self.rb.ccr.read().tsen().bit_is_set();
+        if !enabled {
+            self.rb.ccr.modify(|_, w| w.tsen().set_bit());
+            self.wait_for_startup();
+        }
+
+        let ts = self.convert_internal(VTemp::channel());
+
+        if !enabled {
+            self.rb.ccr.modify(|_, w| w.tsen().clear_bit());
+        }
+
+        let ts_cal1 = i32::from(read_calibration(TS_CAL1));
+        let ts_cal2 = i32::from(read_calibration(TS_CAL2));
+
+        // The conversion result, as if it was measured at `VDDA_CAL`
+        let ts = (u32::from(ts) * u32::from(vdda) / VDDA_CAL) as i32;
+
+        let temperature = (TS_CAL2_TEMP - TS_CAL1_TEMP) * (ts - ts_cal1)
+            / (ts_cal2 - ts_cal1).max(1)
+            + TS_CAL1_TEMP;
+        temperature as i16
+    }
+
+    /// Waits for an internal channel to become stable after enabling it
+    fn wait_for_startup(&self) {
+        cortex_m::asm::delay(self.sys_clk / 1000 * T_START_US / 1000 + 1);
+    }
+
+    /// Converts an internal channel with 12-bit resolution
+    ///
+    /// The internal channels require a sampling time of several µs, so this
+    /// uses the longest one, independent of the configured sampling time.
+    fn convert_internal(&mut self, channel: u8) -> u16 {
+        let (sample_time, align, precision) = (self.sample_time, self.align, self.precision);
+        self.sample_time = SampleTime::T_160_5;
+        self.align = Align::Right;
+        self.precision = Precision::B_12;
+
+        let value = self.convert(channel);
+
+        self.sample_time = sample_time;
+        self.align = align;
+        self.precision = precision;
+        value
+    }
+
+    /// Performs a single conversion of `channel`
+    fn convert(&mut self, channel: u8) -> u16 {
+        self.power_up();
+        self.configure(
+            Channels {
+                flags: 0x1 << channel,
+            },
+            false,
+            None,
+        );
+
+        while self.rb.isr.read().eos().bit_is_clear() {}
+
+        let res = self.rb.dr.read().bits() as u16;
+        let val = if self.align == Align::Left && self.precision == Precision::B_6 {
+            res << 8
+        } else {
+            res
+        };
+
+        self.power_down();
+        val
+    }
+
     /// Starts a continuous conversion process
     ///
     /// The `channel` argument specifies which channel should be converted.
@@ -140,9 +296,8 @@ impl Adc<Ready> {
     ///   one of the channels that supports the ADC peripheral.
     /// - `buffer` is the buffer used to buffer the conversion results.
     ///
-    /// # Panics
-    ///
-    /// Panics, if `buffer` is larger than 65535.
+    /// Returns an error, and the ADC together with the DMA resources, if
+    /// `buffer` is larger than 65535.
     pub fn start<DmaChan, Buf>(
         mut self,
         channels: impl Into<Channels>,
@@ -150,7 +305,7 @@ impl Adc<Ready> {
         dma: &mut dma::Handle,
         dma_chan: DmaChan,
         buffer: Pin<Buf>,
-    ) -> Adc<Active<DmaChan, Buf>>
+    ) -> dma::SetupResult<Adc<Active<DmaChan, Buf>>, Self, DmaChan, Buf>
     where
         DmaToken: dma::Target<DmaChan>,
         Buf: DerefMut + 'static,
@@ -170,7 +325,7 @@ impl Adc<Ready> {
         let address = &self.rb.dr as *const _ as u32;
 
         // The cast to `u16` could truncate the value, but if it does,
-        // `Transfer::new` is going to panic anyway.
+        // `Transfer::new` is going to return an error anyway.
         let buffer_unsafe = Buffer {
             ptr: buffer.as_ptr(),
             len: buffer.len() as u16,
@@ -182,7 +337,7 @@ impl Adc<Ready> {
 
         // Safe, because the trait bounds of this method guarantee that the
         // buffer can be written to.
-        let transfer = unsafe {
+        let transfer = match unsafe {
             dma::Transfer::new(
                 dma,
                 dma_token,
@@ -194,16 +349,26 @@ impl Adc<Ready> {
                 dma::Direction::peripheral_to_memory(),
                 true,
             )
-        }
-        .start();
+        } {
+            Ok(transfer) => transfer.start(),
+            Err((res, err)) => {
+                let res = dma::TransferResources {
+                    target: self,
+                    channel: res.channel,
+                    buffer: res.buffer,
+                };
+                return Err((res, err));
+            }
+        };
 
         let continous = trigger.is_none();
 
         self.power_up();
         self.configure(channels, continous, trigger);
 
-        Adc {
+        Ok(Adc {
             rb: self.rb,
+            sys_clk: self.sys_clk,
             sample_time: self.sample_time,
             align: self.align,
             precision: self.precision,
@@ -211,7 +376,7 @@ impl Adc<Ready> {
                 buffer: buffer_unsafe,
                 transfer,
             },
-        }
+        })
     }
 }
 
@@ -239,10 +404,71 @@ where
 }
 
 impl<State> Adc<State> {
-    pub fn release(self) -> ADC {
+    /// Disables the ADC and releases it
+    ///
+    /// Any ongoing conversion is stopped first.
+    pub fn release(mut self) -> ADC {
+        if self.rb.cr.read().adstart().bit_is_set() {
+            self.rb.cr.modify(|_, w| w.adstp().set_bit());
+            while self.rb.cr.read().adstp().bit_is_set() {}
+        }
+        if self.rb.cr.read().aden().bit_is_set() {
+            self.power_down();
+        }
+        self.rb.cr.modify(|_, w| w.advregen().clear_bit());
+
         self.rb
     }
 
+    /// Starts listening for an interrupt event
+    pub fn listen(&mut self, event: Event) {
+        self.rb.ier.modify(|_, w| match event {
+            Event::EndOfConversion => w.eocie().set_bit(),
+            Event::EndOfSequence => w.eosie().set_bit(),
+            Event::EndOfSampling => w.eosmpie().set_bit(),
+            Event::Overrun => w.ovrie().set_bit(),
+            Event::AnalogWatchdog => w.awdie().set_bit(),
+        });
+    }
+
+    /// Stops listening for an interrupt event
+    pub fn unlisten(&mut self, event: Event) {
+        self.rb.ier.modify(|_, w| match event {
+            Event::EndOfConversion => w.eocie().clear_bit(),
+            Event::EndOfSequence => w.eosie().clear_bit(),
+            Event::EndOfSampling => w.eosmpie().clear_bit(),
+            Event::Overrun => w.ovrie().clear_bit(),
+            Event::AnalogWatchdog => w.awdie().clear_bit(),
+        });
+    }
+
+    /// Returns whether the flag of an interrupt event is set
+    ///
+    /// This doesn't depend on whether the event is listened for.
+    pub fn is_pending(&self, event: Event) -> bool {
+        let isr = self.rb.isr.read();
+        match event {
+            Event::EndOfConversion => isr.eoc().bit_is_set(),
+            Event::EndOfSequence => isr.eos().bit_is_set(),
+            Event::EndOfSampling => isr.eosmp().bit_is_set(),
+            Event::Overrun => isr.ovr().bit_is_set(),
+            Event::AnalogWatchdog => isr.awd().bit_is_set(),
+        }
+    }
+
+    /// Clears the flag of an interrupt event
+    pub fn clear(&mut self, event: Event) {
+        // The flags are cleared by writing 1, so this doesn't affect the
+        // others.
+        self.rb.isr.write(|w| match event {
+            Event::EndOfConversion => w.eoc().set_bit(),
+            Event::EndOfSequence => w.eos().set_bit(),
+            Event::EndOfSampling => w.eosmp().set_bit(),
+            Event::Overrun => w.ovr().set_bit(),
+            Event::AnalogWatchdog => w.awd().set_bit(),
+        });
+    }
+
     fn power_up(&mut self) {
         self.rb.isr.modify(|_, w| w.adrdy().set_bit());
         self.rb.cr.modify(|_, w| w.aden().set_bit());
@@ -297,26 +523,7 @@ where
     type Error = ();
 
     fn read(&mut self, _: &mut PIN) -> nb::Result<WORD, Self::Error> {
-        self.power_up();
-        self.configure(
-            Channels {
-                flags: 0x1 << PIN::channel(),
-            },
-            false,
-            None,
-        );
-
-        while self.rb.isr.read().eos().bit_is_clear() {}
-
-        let res = self.rb.dr.read().bits() as u16;
-        let val = if self.align == Align::Left && self.precision == Precision::B_6 {
-            res << 8
-        } else {
-            res
-        };
-
-        self.power_down();
-        Ok(val.into())
+        Ok(self.convert(PIN::channel()).into())
     }
 }
 
@@ -363,6 +570,7 @@ where
 
 /// Hardware triggers that can start an ADC conversion
 #[repr(u8)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Trigger {
     /// TRG0
     TIM6_TRGO = 0b000,
@@ -600,7 +808,8 @@ where
 pub struct DmaToken(());
 
 /// Represents an ADC error
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Error {
     /// Indicates that converted data was not read in time
     ///
@@ -616,6 +825,13 @@ pub enum Error {
     BufferOverrun,
 }
 
+/// Reads a factory calibration value
+fn read_calibration(address: u32) -> u16 {
+    // Safe, as the calibration values are at valid addresses in the system
+    // memory area, which are aligned to 16 bits and read-only.
+    unsafe { (address as *const u16).read_volatile() }
+}
+
 macro_rules! int_adc {
     ($($Chan:ident: ($chan:expr, $en:ident)),+ $(,)*) => {
         $(
@@ -5,6 +5,8 @@
 //!
 //! See STM32L0x2 reference manual, chapter 18.
 
+#[cfg(feature = "cipher")]
+use core::cell::RefCell;
 use core::{
     convert::TryInto,
     ops::{Deref, DerefMut},
@@ -12,16 +14,23 @@ use core::{
 };
 
 use as_slice::{AsMutSlice, AsSlice};
+#[cfg(feature = "cipher")]
+use cipher::{
+    consts::{U1, U16},
+    inout::{InOut, InOutBuf},
+    BlockBackend, BlockCipher, BlockClosure, BlockDecrypt, BlockEncrypt, BlockSizeUser,
+    ParBlocksSizeUser, StreamCipher, StreamCipherError,
+};
 use nb::block;
 use void::Void;
 
 use crate::{
-    dma,
+    config, dma,
     pac::{
         self,
         aes::{self, cr},
     },
-    rcc::Rcc,
+    rcc::{Enable, Rcc, Reset},
 };
 
 /// Entry point to the AES API
@@ -33,11 +42,10 @@ impl AES {
     /// Initialize the AES peripheral
     pub fn new(aes: pac::AES, rcc: &mut Rcc) -> Self {
         // Reset peripheral
-        rcc.rb.ahbrstr.modify(|_, w| w.cryprst().set_bit());
-        rcc.rb.ahbrstr.modify(|_, w| w.cryprst().clear_bit());
+        pac::AES::reset(rcc);
 
         // Enable peripheral clock
-        rcc.rb.ahbenr.modify(|_, w| w.crypen().set_bit());
+        pac::AES::enable(rcc);
 
         // Configure peripheral
         aes.cr.write(|w| {
@@ -90,6 +98,77 @@ impl AES {
             tx: Tx(()),
         }
     }
+
+    /// Derives the decryption key from an encryption key
+    ///
+    /// ECB and CBC decryption require the decryption key, which the
+    /// peripheral derives from the encryption key. [`Mode::ecb_decrypt`] and
+    /// [`Mode::cbc_decrypt`] do this automatically, every time a [`Stream`]
+    /// is created. When decrypting repeatedly with the same key, the
+    /// derivation can be done once using this method instead, and the result
+    /// used with [`Mode::ecb_decrypt_derived`] or
+    /// [`Mode::cbc_decrypt_derived`].
+    pub fn derive_key(&mut self, key: [u32; 4]) -> [u32; 4] {
+        // Write key. This is safe, as the register accepts the full range of
+        // `u32`.
+        self.aes.keyr0.write(|w| unsafe { w.bits(key[0]) });
+        self.aes.keyr1.write(|w| unsafe { w.bits(key[1]) });
+        self.aes.keyr2.write(|w| unsafe { w.bits(key[2]) });
+        self.aes.keyr3.write(|w| unsafe { w.bits(key[3]) });
+
+        derive_key(&self.aes);
+
+        // The key registers now contain the derived key.
+        [
+            self.aes.keyr0.read().bits(),
+            self.aes.keyr1.read().bits(),
+            self.aes.keyr2.read().bits(),
+            self.aes.keyr3.read().bits(),
+        ]
+    }
+
+    /// Returns AES-128 with the given key, implementing the `cipher` block
+    /// cipher traits
+    ///
+    /// Unlike [`AES::enable`], this expects the key in the byte order of the
+    /// AES standard, as used by the RustCrypto crates. Derives the decryption
+    /// key once, see [`AES::derive_key`].
+    ///
+    /// Requires the `cipher` feature.
+    #[cfg(feature = "cipher")]
+    pub fn block_cipher(mut self, key: &[u8; 16]) -> Aes128 {
+        let encrypt_key = key_words(key);
+        let decrypt_key = self.derive_key(encrypt_key);
+
+        Aes128 {
+            aes: RefCell::new(Some(self)),
+            encrypt_key,
+            decrypt_key,
+        }
+    }
+
+    /// Returns AES-128 in CTR mode with the given key and nonce, implementing
+    /// the `cipher` stream cipher traits
+    ///
+    /// Expects the key and nonce in standard byte order, see
+    /// [`AES::block_cipher`].
+    ///
+    /// Requires the `cipher` feature.
+    #[cfg(feature = "cipher")]
+    pub fn stream_cipher(self, key: &[u8; 16], nonce: &[u8; 12]) -> Ctr {
+        let mut init_vector = [0; 3];
+        for (word, bytes) in init_vector.iter_mut().zip(nonce.chunks_exact(4)) {
+            // Can't panic, because `bytes` is 4 bytes long.
+            *word = u32::from_be_bytes(bytes.try_into().unwrap());
+        }
+
+        Ctr {
+            stream: self.enable(<dyn Mode>::ctr(init_vector), key_words(key)),
+            keystream: [0; 16],
+            pos: 16,
+            blocks: u32::MAX,
+        }
+    }
 }
 
 /// An active encryption/decryption stream
@@ -120,6 +199,63 @@ impl Stream {
         Ok(output)
     }
 
+    /// Starts processing one block of data
+    ///
+    /// This is the non-blocking alternative to [`Stream::process`]. Use
+    /// [`Stream::poll`] or the [`Event::ComputationComplete`] interrupt to
+    /// find out when the block has been processed, then get it using
+    /// [`Stream::result`].
+    pub fn start(&mut self, input: &Block) -> Result<(), Error> {
+        self.tx.write(input)
+    }
+
+    /// Returns whether the processing of the last block has completed
+    pub fn poll(&self) -> bool {
+        self.aes.aes.sr.read().ccf().bit_is_set()
+    }
+
+    /// Returns the processed block, once processing has completed
+    ///
+    /// This also clears the computation complete flag, which acknowledges
+    /// the [`Event::ComputationComplete`] interrupt.
+    pub fn result(&mut self) -> nb::Result<Block, Void> {
+        self.rx.read()
+    }
+
+    /// Starts listening for an interrupt event
+    ///
+    /// Both events trigger the AES interrupt, which is shared with the RNG
+    /// and LPUART1 interrupts.
+    pub fn listen(&mut self, event: Event) {
+        match event {
+            Event::ComputationComplete => self.aes.aes.cr.modify(|_, w| w.ccfie().set_bit()),
+            Event::Error => self.aes.aes.cr.modify(|_, w| w.errie().set_bit()),
+        }
+    }
+
+    /// Stops listening for an interrupt event
+    pub fn unlisten(&mut self, event: Event) {
+        match event {
+            Event::ComputationComplete => self.aes.aes.cr.modify(|_, w| w.ccfie().clear_bit()),
+            Event::Error => self.aes.aes.cr.modify(|_, w| w.errie().clear_bit()),
+        }
+    }
+
+    /// Returns whether a read or write error has occurred
+    ///
+    /// Errors occur, if data is written while a block is being processed, or
+    /// read before processing has completed.
+    pub fn is_error(&self) -> bool {
+        let sr = self.aes.aes.sr.read();
+        sr.wrerr().bit_is_set() || sr.rderr().bit_is_set()
+    }
+
+    /// Clears the error flags, which acknowledges the [`Event::Error`]
+    /// interrupt
+    pub fn clear_error(&mut self) {
+        self.aes.aes.cr.modify(|_, w| w.errc().set_bit());
+    }
+
     /// Disable the AES peripheral
     ///
     /// Consumes the stream and returns the disabled [`AES`] instance. Call this
@@ -127,7 +263,11 @@ impl Stream {
     /// another `Stream` using [`AES::enable`].
     pub fn disable(self) -> AES {
         // Disable AES
-        self.aes.aes.cr.modify(|_, w| w.en().clear_bit());
+        self.aes.aes.cr.modify(|_, w| {
+            w.ccfie().clear_bit();
+            w.errie().clear_bit();
+            w.en().clear_bit()
+        });
 
         self.aes
     }
@@ -186,27 +326,30 @@ impl Tx {
     /// Returns a DMA transfer that is ready to be started. It needs to be
     /// started for anything to happen.
     ///
-    /// # Panics
-    ///
-    /// Panics, if the buffer length is larger than `u16::max_value()`.
-    ///
     /// The AES peripheral works with 128-bit blocks, which means the buffer
-    /// length must be a multiple of 16. Panics, if this is not the case.
-    ///
-    /// Panics, if the buffer is not aligned to a word boundary.
+    /// length must be a multiple of 16. Returns an error, if this is not the
+    /// case, if the buffer is too long for a DMA transfer, or if it is not
+    /// aligned to a word boundary.
     pub fn write_all<Buffer, Channel>(
         self,
         dma: &mut dma::Handle,
         buffer: Pin<Buffer>,
         channel: Channel,
-    ) -> Transfer<Self, Channel, Buffer, dma::Ready>
+    ) -> dma::SetupResult<Transfer<Self, Channel, Buffer, dma::Ready>, Self, Channel, Buffer>
     where
         Self: dma::Target<Channel>,
         Buffer: Deref + 'static,
         Buffer::Target: AsSlice<Element = u8>,
         Channel: dma::Channel,
     {
-        assert!(buffer.as_slice().len() % 16 == 0);
+        if buffer.as_slice().len() % 16 != 0 {
+            let res = dma::TransferResources {
+                target: self,
+                channel,
+                buffer,
+            };
+            return Err((res, config::Error::BufferLength));
+        }
 
         // Safe, because we're only taking the address of a register.
         let address = &unsafe { &*pac::AES::ptr() }.dinr as *const _ as u32;
@@ -277,27 +420,30 @@ impl Rx {
     /// Returns a DMA transfer that is ready to be started. It needs to be
     /// started for anything to happen.
     ///
-    /// # Panics
-    ///
-    /// Panics, if the buffer length is larger than `u16::max_value()`.
-    ///
     /// The AES peripheral works with 128-bit blocks, which means the buffer
-    /// length must be a multiple of 16. Panics, if this is not the case.
-    ///
-    /// Panics, if the buffer is not aligned to a word boundary.
+    /// length must be a multiple of 16. Returns an error, if this is not the
+    /// case, if the buffer is too long for a DMA transfer, or if it is not
+    /// aligned to a word boundary.
     pub fn read_all<Buffer, Channel>(
         self,
         dma: &mut dma::Handle,
         buffer: Pin<Buffer>,
         channel: Channel,
-    ) -> Transfer<Self, Channel, Buffer, dma::Ready>
+    ) -> dma::SetupResult<Transfer<Self, Channel, Buffer, dma::Ready>, Self, Channel, Buffer>
     where
         Self: dma::Target<Channel>,
         Buffer: DerefMut + 'static,
         Buffer::Target: AsMutSlice<Element = u8>,
         Channel: dma::Channel,
     {
-        assert!(buffer.as_slice().len() % 16 == 0);
+        if buffer.as_slice().len() % 16 != 0 {
+            let res = dma::TransferResources {
+                target: self,
+                channel,
+                buffer,
+            };
+            return Err((res, config::Error::BufferLength));
+        }
 
         // Safe, because we're only taking the address of a register.
         let address = &unsafe { &*pac::AES::ptr() }.doutr as *const _ as u32;
@@ -344,6 +490,14 @@ impl dyn Mode {
         ECB(Decrypt)
     }
 
+    /// Use this with [`AES::enable`] to decrypt using ECB and a derived key
+    ///
+    /// The key passed to [`AES::enable`] must be the decryption key returned
+    /// by [`AES::derive_key`].
+    pub fn ecb_decrypt_derived() -> ECB<DecryptDerived> {
+        ECB(DecryptDerived)
+    }
+
     /// Use this with [`AES::enable`] to encrypt using CBC
     pub fn cbc_encrypt(init_vector: [u32; 4]) -> CBC<Encrypt> {
         CBC {
@@ -360,6 +514,17 @@ impl dyn Mode {
         }
     }
 
+    /// Use this with [`AES::enable`] to decrypt using CBC and a derived key
+    ///
+    /// The key passed to [`AES::enable`] must be the decryption key returned
+    /// by [`AES::derive_key`].
+    pub fn cbc_decrypt_derived(init_vector: [u32; 4]) -> CBC<DecryptDerived> {
+        CBC {
+            _mode: DecryptDerived,
+            init_vector,
+        }
+    }
+
     /// Use this with [`AES::enable`] to encrypt or decrypt using CTR
     pub fn ctr(init_vector: [u32; 3]) -> CTR {
         CTR { init_vector }
@@ -369,7 +534,7 @@ impl dyn Mode {
 /// The ECB (electronic code book) chaining mode
 ///
 /// Can be passed [`AES::enable`], to start encrypting or decrypting using ECB
-/// mode. `Mode` must be either [`Encrypt`] or [`Decrypt`].
+/// mode. `Mode` must be [`Encrypt`], [`Decrypt`] or [`DecryptDerived`].
 ///
 /// You gen get an instance of this struct via [`Mode::ecb_encrypt`] or
 /// [`Mode::ecb_decrypt`].
@@ -392,8 +557,24 @@ impl Mode for ECB<Encrypt> {
 }
 
 impl Mode for ECB<Decrypt> {
-    fn prepare(&self, aes: &aes::RegisterBlock) {
-        derive_key(aes)
+    fn prepare(&self, _: &aes::RegisterBlock) {
+        // Nothing to do.
+    }
+
+    fn select(&self, w: &mut cr::W) {
+        // Safe, as we're only writing valid bit patterns.
+        unsafe {
+            // Select ECB chaining mode
+            w.chmod().bits(0b00);
+            // Select key derivation and decryption mode
+            w.mode().bits(0b11);
+        }
+    }
+}
+
+impl Mode for ECB<DecryptDerived> {
+    fn prepare(&self, _: &aes::RegisterBlock) {
+        // Nothing to do.
     }
 
     fn select(&self, w: &mut cr::W) {
@@ -410,7 +591,7 @@ impl Mode for ECB<Decrypt> {
 /// The CBC (cipher block chaining) chaining mode
 ///
 /// Can be passed [`AES::enable`], to start encrypting or decrypting using CBC
-/// mode. `Mode` must be either [`Encrypt`] or [`Decrypt`].
+/// mode. `Mode` must be [`Encrypt`], [`Decrypt`] or [`DecryptDerived`].
 ///
 /// You gen get an instance of this struct via [`Mode::cbc_encrypt`] or
 /// [`Mode::cbc_decrypt`].
@@ -441,8 +622,26 @@ impl Mode for CBC<Encrypt> {
 
 impl Mode for CBC<Decrypt> {
     fn prepare(&self, aes: &aes::RegisterBlock) {
-        derive_key(aes);
+        // Safe, as the registers accept the full range of `u32`.
+        aes.ivr3.write(|w| unsafe { w.bits(self.init_vector[0]) });
+        aes.ivr2.write(|w| unsafe { w.bits(self.init_vector[1]) });
+        aes.ivr1.write(|w| unsafe { w.bits(self.init_vector[2]) });
+        aes.ivr0.write(|w| unsafe { w.bits(self.init_vector[3]) });
+    }
 
+    fn select(&self, w: &mut cr::W) {
+        // Safe, as we're only writing valid bit patterns.
+        unsafe {
+            // Select CBC chaining mode
+            w.chmod().bits(0b01);
+            // Select key derivation and decryption mode
+            w.mode().bits(0b11);
+        }
+    }
+}
+
+impl Mode for CBC<DecryptDerived> {
+    fn prepare(&self, aes: &aes::RegisterBlock) {
         // Safe, as the registers accept the full range of `u32`.
         aes.ivr3.write(|w| unsafe { w.bits(self.init_vector[0]) });
         aes.ivr2.write(|w| unsafe { w.bits(self.init_vector[1]) });
@@ -507,6 +706,9 @@ fn derive_key(aes: &aes::RegisterBlock) {
 
     // Wait for key derivation to finish
     while aes.sr.read().ccf().bit_is_clear() {}
+
+    // Clear CCF flag
+    aes.cr.modify(|_, w| w.ccfc().set_bit());
 }
 
 /// Used to identify encryption mode
@@ -515,13 +717,27 @@ pub struct Encrypt;
 /// Used to identify decryption mode
 pub struct Decrypt;
 
+/// Used to identify decryption mode with a key from [`AES::derive_key`]
+pub struct DecryptDerived;
+
 /// A 128-bit block
 ///
 /// The AES peripheral processes 128 bits at a time, so this represents one unit
 /// of processing.
 pub type Block = [u8; 16];
 
-#[derive(Debug)]
+/// Interrupt events of the AES peripheral
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Event {
+    /// A block has been processed (CCF)
+    ComputationComplete,
+
+    /// A read or write error has occurred (RDERR, WRERR)
+    Error,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Error {
     /// AES peripheral is busy
     Busy,
@@ -563,7 +779,7 @@ where
         address: u32,
         priority: dma::Priority,
         dir: dma::Direction,
-    ) -> Self {
+    ) -> dma::SetupResult<Self, Target, Channel, Buffer> {
         let num_words = buffer.as_slice().len() / 4;
 
         let transfer = dma::Transfer::new(
@@ -583,9 +799,16 @@ where
             false,
         );
 
-        Self {
-            buffer: buffer,
-            inner: transfer,
+        match transfer {
+            Ok(inner) => Ok(Self { buffer, inner }),
+            Err((res, err)) => {
+                let res = dma::TransferResources {
+                    target: res.target,
+                    channel: res.channel,
+                    buffer,
+                };
+                Err((res, err))
+            }
         }
     }
 
@@ -649,3 +872,169 @@ where
         }
     }
 }
+
+/// AES-128 on the AES peripheral, implementing the `cipher` block cipher traits
+///
+/// You can get an instance of this struct by calling [`AES::block_cipher`].
+/// It implements [`BlockEncrypt`] and [`BlockDecrypt`], so it can be used with
+/// the RustCrypto mode and AEAD crates. Blocks are processed in ECB mode, with
+/// the peripheral enabled for each call.
+///
+/// Requires the `cipher` feature.
+#[cfg(feature = "cipher")]
+pub struct Aes128 {
+    aes: RefCell<Option<AES>>,
+    encrypt_key: [u32; 4],
+    decrypt_key: [u32; 4],
+}
+
+#[cfg(feature = "cipher")]
+impl Aes128 {
+    /// Returns the [`AES`] instance
+    pub fn free(self) -> AES {
+        // Can't panic, as the `AES` instance is always put back after use.
+        self.aes.into_inner().unwrap()
+    }
+
+    fn process(&self, mode: impl Mode, key: [u32; 4], f: impl BlockClosure<BlockSize = U16>) {
+        let mut aes = self.aes.borrow_mut();
+
+        // Can't panic, as the `AES` instance is always put back after use.
+        let mut backend = Backend(aes.take().unwrap().enable(mode, key));
+        f.call(&mut backend);
+        *aes = Some(backend.0.disable());
+    }
+}
+
+#[cfg(feature = "cipher")]
+impl BlockSizeUser for Aes128 {
+    type BlockSize = U16;
+}
+
+#[cfg(feature = "cipher")]
+impl BlockCipher for Aes128 {}
+
+#[cfg(feature = "cipher")]
+impl BlockEncrypt for Aes128 {
+    fn encrypt_with_backend(&self, f: impl BlockClosure<BlockSize = U16>) {
+        self.process(<dyn Mode>::ecb_encrypt(), self.encrypt_key, f)
+    }
+}
+
+#[cfg(feature = "cipher")]
+impl BlockDecrypt for Aes128 {
+    fn decrypt_with_backend(&self, f: impl BlockClosure<BlockSize = U16>) {
+        self.process(<dyn Mode>::ecb_decrypt_derived(), self.decrypt_key, f)
+    }
+}
+
+/// Processes the blocks of [`Aes128`] using an ECB stream
+#[cfg(feature = "cipher")]
+struct Backend(Stream);
+
+#[cfg(feature = "cipher")]
+impl BlockSizeUser for Backend {
+    type BlockSize = U16;
+}
+
+#[cfg(feature = "cipher")]
+impl ParBlocksSizeUser for Backend {
+    type ParBlocksSize = U1;
+}
+
+#[cfg(feature = "cipher")]
+impl BlockBackend for Backend {
+    fn proc_block(&mut self, mut block: InOut<'_, '_, cipher::Block<Self>>) {
+        let input = swap_words(block.get_in());
+        // Can't panic, as every block is read back before the next one is
+        // written.
+        let output = self.0.process(&input).unwrap();
+        block.get_out().copy_from_slice(&swap_words(&output));
+    }
+}
+
+/// AES-128 in CTR mode, implementing the `cipher` stream cipher traits
+///
+/// You can get an instance of this struct by calling [`AES::stream_cipher`].
+/// The counter block consists of the 96-bit nonce and a 32-bit big-endian
+/// counter, which starts at `1`. Once the counter would wrap around,
+/// [`StreamCipher::try_apply_keystream`] returns an error.
+///
+/// Requires the `cipher` feature.
+#[cfg(feature = "cipher")]
+pub struct Ctr {
+    stream: Stream,
+    keystream: Block,
+    /// Position of the next unused byte in `keystream`
+    pos: usize,
+    /// Number of keystream blocks left, before the counter wraps around
+    blocks: u32,
+}
+
+#[cfg(feature = "cipher")]
+impl Ctr {
+    /// Disables the AES peripheral and returns the [`AES`] instance
+    pub fn disable(self) -> AES {
+        self.stream.disable()
+    }
+}
+
+#[cfg(feature = "cipher")]
+impl StreamCipher for Ctr {
+    fn try_apply_keystream_inout(
+        &mut self,
+        mut buf: InOutBuf<'_, '_, u8>,
+    ) -> Result<(), StreamCipherError> {
+        let unused = self.keystream.len() - self.pos;
+        let needed = buf.len().saturating_sub(unused).div_ceil(16);
+        if needed > self.blocks as usize {
+            return Err(StreamCipherError);
+        }
+
+        for i in 0..buf.len() {
+            if self.pos == self.keystream.len() {
+                // In CTR mode, the peripheral XORs the input with the
+                // encrypted counter, so processing zeros yields the keystream.
+                // Can't panic, as every block is read back before the next one
+                // is written.
+                let output = self.stream.process(&[0; 16]).unwrap();
+                self.keystream = swap_words(&output);
+                self.pos = 0;
+                self.blocks -= 1;
+            }
+
+            let mut byte = buf.get(i);
+            *byte.get_out() = *byte.get_in() ^ self.keystream[self.pos];
+            self.pos += 1;
+        }
+
+        Ok(())
+    }
+}
+
+/// Converts an AES-128 key in standard byte order to the words of the key
+/// registers, as expected by [`AES::enable`]
+#[cfg(feature = "cipher")]
+fn key_words(key: &[u8; 16]) -> [u32; 4] {
+    let mut words = [0; 4];
+    for (word, bytes) in words.iter_mut().rev().zip(key.chunks_exact(4)) {
+        // Can't panic, because `bytes` is 4 bytes long.
+        *word = u32::from_be_bytes(bytes.try_into().unwrap());
+    }
+    words
+}
+
+/// Converts between the standard byte order of a block and the one used by
+/// [`Stream::process`]
+///
+/// The peripheral expects the most significant word of a block first (see
+/// STM32L0x2 reference manual, section 18.4.10), while `Stream::process`
+/// writes and reads the last word of a block first.
+#[cfg(feature = "cipher")]
+fn swap_words(block: &[u8]) -> Block {
+    let mut swapped = [0; 16];
+    for (word, bytes) in swapped.chunks_exact_mut(4).zip(block.chunks_exact(4).rev()) {
+        word.copy_from_slice(bytes);
+    }
+    swapped
+}
@@ -0,0 +1,66 @@
+//! Support for the async APIs
+//!
+//! Requires the `async` feature.
+//!
+//! The async implementations of the `embedded-hal-async` traits wait for the
+//! peripheral's interrupts, instead of busy-waiting. For that, the interrupt
+//! needs to be unmasked in the NVIC, and its handler needs to call
+//! [`InterruptHandler::on_interrupt`] for the peripheral:
+//!
+//! ``` ignore
+//! #[interrupt]
+//! fn I2C1() {
+//!     use stm32l0xx_hal::asynch::InterruptHandler;
+//!     pac::I2C1::on_interrupt();
+//! }
+//! ```
+//!
+//! As `on_interrupt` disables the interrupts used by the async APIs, they
+//! can't be combined with `listen` for the same peripheral.
+
+use core::cell::RefCell;
+use core::task::Waker;
+
+use cortex_m::interrupt::{self, Mutex};
+
+/// Handles a peripheral's interrupt for the async APIs
+pub trait InterruptHandler {
+    /// Disables the peripheral's interrupts, and wakes the waiting task
+    ///
+    /// This needs to be called from the peripheral's interrupt handler. The
+    /// task enables the interrupts again, if it needs to keep waiting.
+    fn on_interrupt();
+}
+
+/// Stores the waker of a task that waits for an interrupt
+pub struct InterruptWaker(Mutex<RefCell<Option<Waker>>>);
+
+impl InterruptWaker {
+    pub const fn new() -> Self {
+        InterruptWaker(Mutex::new(RefCell::new(None)))
+    }
+
+    /// Registers `waker` to be woken by the next call to `wake`
+    pub fn register(&self, waker: &Waker) {
+        interrupt::free(|cs| {
+            let mut stored = self.0.borrow(cs).borrow_mut();
+            match stored.as_ref() {
+                Some(stored) if stored.will_wake(waker) => {}
+                _ => *stored = Some(waker.clone()),
+            }
+        });
+    }
+
+    /// Wakes the registered waker, if any
+    pub fn wake(&self) {
+        if let Some(waker) = interrupt::free(|cs| self.0.borrow(cs).borrow_mut().take()) {
+            waker.wake();
+        }
+    }
+}
+
+impl Default for InterruptWaker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,71 @@
+//! Helpers for firmware updates
+//!
+//! See application note AN2606 for details on the ST bootloader in system
+//! memory. Bank swapping on dual-bank devices is configured via
+//! `FLASH::set_boot_from_bank2`.
+
+use cortex_m::{
+    interrupt,
+    peripheral::{NVIC, SCB, SYST},
+};
+
+use crate::{
+    rcc::{Config, Rcc},
+    syscfg::SYSCFG,
+};
+
+/// The first address of the system memory, containing the ST bootloader
+pub const SYSTEM_MEMORY_START: u32 = 0x1FF0_0000;
+
+/// Jumps to the ST bootloader in system memory
+///
+/// Before jumping, the microcontroller is brought back into a state close to
+/// the one after reset, which the bootloader expects:
+/// - The system clock is switched back to the MSI at its default range
+/// - SysTick is stopped and all interrupts are disabled and cleared
+/// - All APB peripherals are reset
+/// - The system memory is mapped at address 0, and the vector table is moved
+///   there
+///
+/// `rcc` and `syscfg` are consumed, as they are no longer valid afterwards.
+pub fn jump_to_system_memory(mut rcc: Rcc, syscfg: SYSCFG) -> ! {
+    interrupt::disable();
+
+    // Return to the clock configuration after reset
+    rcc.reconfigure(Config::default());
+
+    // Safe, as interrupts are disabled and we never return to code that
+    // could own the core peripherals.
+    unsafe {
+        let syst = &*SYST::PTR;
+        syst.csr.write(0);
+        syst.rvr.write(0);
+        syst.cvr.write(0);
+
+        let nvic = &*NVIC::ptr();
+        nvic.icer[0].write(0xffff_ffff);
+        nvic.icpr[0].write(0xffff_ffff);
+    }
+
+    // Reset all APB peripherals. This doesn't touch the peripheral clocks, so
+    // SYSCFG is still enabled afterwards.
+    rcc.rb.apb1rstr.write(|w| unsafe { w.bits(0xffff_ffff) });
+    rcc.rb.apb1rstr.reset();
+    rcc.rb.apb2rstr.write(|w| unsafe { w.bits(0xffff_ffff) });
+    rcc.rb.apb2rstr.reset();
+
+    // Map the system memory at address 0
+    syscfg
+        .syscfg
+        .cfgr1
+        .modify(|_, w| w.mem_mode().system_flash());
+
+    // Safe, as the system memory contains a valid vector table.
+    unsafe {
+        (*SCB::ptr()).vtor.write(0);
+
+        interrupt::enable();
+
+        cortex_m::asm::bootload(SYSTEM_MEMORY_START as *const u32)
+    }
+}
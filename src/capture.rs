@@ -0,0 +1,739 @@
+//! Timer input capture
+//!
+//! Uses the capture/compare channels of the general-purpose timers to latch
+//! the counter value on edges of an input signal. This can be used to
+//! timestamp external events or to measure pulse widths and periods.
+//!
+//! The pins that can be assigned to a channel are the same ones that are used
+//! for PWM output, see [`pwm::Pin`](../pwm/trait.Pin.html). The comparator
+//! outputs can be assigned to some channels as well, see [`Pin`].
+
+use core::marker::PhantomData;
+
+use cortex_m::interrupt;
+
+use crate::config;
+use crate::pac::{tim2, TIM21};
+use crate::pwm::{self, Assigned, Instance, Unassigned, C1, C2, C3, C4};
+use crate::rcc::Rcc;
+use crate::time::Hertz;
+use crate::timer::counter_prescaler;
+use cast::u16;
+
+pub struct Timer<I> {
+    instance: I,
+
+    pub channel1: Capture<I, C1, Unassigned>,
+    pub channel2: Capture<I, C2, Unassigned>,
+    pub channel3: Capture<I, C3, Unassigned>,
+    pub channel4: Capture<I, C4, Unassigned>,
+}
+
+impl<I> Timer<I>
+where
+    I: Instance,
+{
+    /// Create a new timer instance for input capture
+    ///
+    /// The counter is started right away, counting up at the given frequency
+    /// over its full 16-bit range. Captured values are in units of this
+    /// frequency.
+    ///
+    /// # Panics
+    ///
+    /// Panics, if the timer clock can't be divided down to the given
+    /// frequency. See [`Timer::try_new`].
+    pub fn new<T>(timer: I, frequency: T, rcc: &mut Rcc) -> Self
+    where
+        T: Into<Hertz>,
+    {
+        Self::try_new(timer, frequency, rcc)
+            .map_err(|(_, err)| err)
+            .unwrap()
+    }
+
+    /// Create a new timer instance for input capture, if the frequency is
+    /// valid
+    ///
+    /// Returns [`config::Error::Frequency`], if the timer clock can't be
+    /// divided down to the given frequency. The timer is returned along with
+    /// the error, untouched.
+    pub fn try_new<T>(timer: I, frequency: T, rcc: &mut Rcc) -> Result<Self, (I, config::Error)>
+    where
+        T: Into<Hertz>,
+    {
+        let psc = match counter_prescaler(timer.clock_frequency(rcc), frequency.into().0) {
+            Ok(psc) => psc,
+            Err(err) => return Err((timer, err)),
+        };
+
+        timer.enable(rcc);
+
+        // Safe, as we own the timer instance.
+        setup(unsafe { &*I::ptr() }, psc);
+
+        Ok(Self {
+            instance: timer,
+            channel1: Capture::new(),
+            channel2: Capture::new(),
+            channel3: Capture::new(),
+            channel4: Capture::new(),
+        })
+    }
+
+    /// Returns the frequency the counter is running at
+    pub fn frequency(&self, rcc: &Rcc) -> Hertz {
+        let psc = u32::from(self.tim().psc.read().psc().bits());
+        Hertz(self.instance.clock_frequency(rcc) / (psc + 1))
+    }
+
+    /// Returns the current counter value
+    pub fn counter(&self) -> u16 {
+        self.tim().cnt.read().cnt().bits()
+    }
+
+    /// Starts the counter
+    pub fn start(&mut self) {
+        self.tim().cr1.modify(|_, w| w.cen().set_bit());
+    }
+
+    /// Stops the counter
+    pub fn stop(&mut self) {
+        self.tim().cr1.modify(|_, w| w.cen().clear_bit());
+    }
+
+    /// Returns the timer, so it can be used by any else
+    pub fn free(self) -> I {
+        self.instance
+    }
+
+    fn tim(&self) -> &tim2::RegisterBlock {
+        // Safe, as we own the timer instance.
+        unsafe { &*I::ptr() }
+    }
+}
+
+/// Lets the counter run freely, with the given prescaler value
+fn setup(tim: &tim2::RegisterBlock, psc: u16) {
+    tim.psc.write(|w| w.psc().bits(psc));
+    tim.arr.write(|w| w.arr().bits(u16::MAX));
+    // Load the prescaler value, without raising an update interrupt
+    tim.cr1.modify(|_, w| w.urs().set_bit());
+    tim.egr.write(|w| w.ug().set_bit());
+    tim.cr1.modify(|_, w| w.urs().clear_bit().cen().set_bit());
+}
+
+/// Selects which input a capture channel is connected to
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mapping {
+    /// The channel captures its own input (e.g. IC1 on TI1)
+    Direct = 0b01,
+    /// The channel captures the input of its neighbouring channel (e.g. IC1
+    /// on TI2)
+    ///
+    /// Channels 1 and 2, as well as channels 3 and 4, form pairs. The pin
+    /// the signal is on still has to be assigned to its own channel.
+    Indirect = 0b10,
+    /// The channel captures the trigger input TRC, as selected in the slave
+    /// mode controller
+    Trigger = 0b11,
+}
+
+/// The active edge(s) of the capture input
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Polarity {
+    RisingEdge,
+    FallingEdge,
+    BothEdges,
+}
+
+/// Number of active edges needed to trigger a capture
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Prescaler {
+    NotDivided = 0b00,
+    Div2 = 0b01,
+    Div4 = 0b10,
+    Div8 = 0b11,
+}
+
+/// Digital input filter
+///
+/// The variants name the sampling frequency, relative to the timer clock
+/// (CK_INT) or the dead-time clock (DTS), and the number of consecutive
+/// samples that need to agree before a transition is validated.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Filter {
+    NoFilter = 0b0000,
+    CkIntN2 = 0b0001,
+    CkIntN4 = 0b0010,
+    CkIntN8 = 0b0011,
+    DtsDiv2N6 = 0b0100,
+    DtsDiv2N8 = 0b0101,
+    DtsDiv4N6 = 0b0110,
+    DtsDiv4N8 = 0b0111,
+    DtsDiv8N6 = 0b1000,
+    DtsDiv8N8 = 0b1001,
+    DtsDiv16N5 = 0b1010,
+    DtsDiv16N6 = 0b1011,
+    DtsDiv16N8 = 0b1100,
+    DtsDiv32N5 = 0b1101,
+    DtsDiv32N6 = 0b1110,
+    DtsDiv32N8 = 0b1111,
+}
+
+/// Input capture configuration
+pub struct Config {
+    pub mapping: Mapping,
+    pub polarity: Polarity,
+    pub prescaler: Prescaler,
+    pub filter: Filter,
+}
+
+impl Config {
+    pub fn mapping(mut self, mapping: Mapping) -> Self {
+        self.mapping = mapping;
+        self
+    }
+
+    pub fn polarity(mut self, polarity: Polarity) -> Self {
+        self.polarity = polarity;
+        self
+    }
+
+    pub fn prescaler(mut self, prescaler: Prescaler) -> Self {
+        self.prescaler = prescaler;
+        self
+    }
+
+    pub fn filter(mut self, filter: Filter) -> Self {
+        self.filter = filter;
+        self
+    }
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            mapping: Mapping::Direct,
+            polarity: Polarity::RisingEdge,
+            prescaler: Prescaler::NotDivided,
+            filter: Filter::NoFilter,
+        }
+    }
+}
+
+/// Input capture error
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    /// A new value was captured before the previous one was read
+    ///
+    /// The previous value has been lost. The flag is cleared when this error
+    /// is returned, so the next read will return the latest captured value.
+    Overcapture,
+    /// No value was captured within two overflows of the counter
+    ///
+    /// Returned by [`ClockMeasurement::measure`], e.g. if the clock isn't
+    /// running, or is too slow to be measured.
+    Timeout,
+    /// The captured values don't result in a frequency
+    ///
+    /// Returned by [`ClockMeasurement::measure`], if `cycles` is zero, or no
+    /// timer ticks elapsed between the captures.
+    InvalidMeasurement,
+}
+
+/// A pin or internal signal that can be assigned to a capture channel
+///
+/// Implemented for all pins that can be assigned to the same channel for PWM
+/// output (see [`pwm::Pin`]), and for the comparator outputs, which can be
+/// connected to some of the channel inputs internally.
+pub trait Pin<I, C> {
+    fn setup(&self);
+}
+
+impl<I, C, P> Pin<I, C> for P
+where
+    P: pwm::Pin<I, C>,
+{
+    fn setup(&self) {
+        pwm::Pin::setup(self)
+    }
+}
+
+pub trait Channel {
+    fn configure(_: &tim2::RegisterBlock, config: &Config);
+    fn disable(_: &tim2::RegisterBlock);
+    fn enable(_: &tim2::RegisterBlock);
+    fn listen(_: &tim2::RegisterBlock);
+    fn unlisten(_: &tim2::RegisterBlock);
+    fn read(_: &tim2::RegisterBlock) -> nb::Result<u16, Error>;
+}
+
+macro_rules! impl_channel {
+    (
+        $(
+            $name:ident,
+            $ccmr_input:ident,
+            $ccxs:ident,
+            $icxf:ident,
+            $icxpsc:ident,
+            $ccxe:ident,
+            $ccxp:ident,
+            $ccxnp:ident,
+            $ccxie:ident,
+            $ccxif:ident,
+            $ccxof:ident,
+            $ccrx:ident;
+        )*
+    ) => {
+        $(
+            impl Channel for $name {
+                fn configure(tim: &tim2::RegisterBlock, config: &Config) {
+                    // The channel must be disabled, for CCxS to be writable.
+                    tim.ccer.modify(|_, w| w.$ccxe().clear_bit());
+
+                    tim.$ccmr_input().modify(|_, w| {
+                        // Safe, as all `Mapping` variants are valid values
+                        // for this field.
+                        unsafe { w.$ccxs().bits(config.mapping as u8) };
+                        w.$icxf().bits(config.filter as u8);
+                        w.$icxpsc().bits(config.prescaler as u8)
+                    });
+
+                    let (ccxp, ccxnp) = match config.polarity {
+                        Polarity::RisingEdge => (false, false),
+                        Polarity::FallingEdge => (true, false),
+                        Polarity::BothEdges => (true, true),
+                    };
+                    tim.ccer.modify(|_, w| w.$ccxp().bit(ccxp).$ccxnp().bit(ccxnp));
+                }
+
+                fn disable(tim: &tim2::RegisterBlock) {
+                    tim.ccer.modify(|_, w| w.$ccxe().clear_bit());
+                }
+
+                fn enable(tim: &tim2::RegisterBlock) {
+                    tim.ccer.modify(|_, w| w.$ccxe().set_bit());
+                }
+
+                fn listen(tim: &tim2::RegisterBlock) {
+                    tim.dier.modify(|_, w| w.$ccxie().set_bit());
+                }
+
+                fn unlisten(tim: &tim2::RegisterBlock) {
+                    tim.dier.modify(|_, w| w.$ccxie().clear_bit());
+                }
+
+                fn read(tim: &tim2::RegisterBlock) -> nb::Result<u16, Error> {
+                    let sr = tim.sr.read();
+
+                    if sr.$ccxof().bit_is_set() {
+                        // The flags are cleared by writing zero, so writing
+                        // ones to the other flags doesn't clear a capture of
+                        // another channel. This is safe, as all bits are
+                        // valid.
+                        tim.sr.write(|w| unsafe { w.bits(!0) }.$ccxof().clear_bit());
+                        return Err(nb::Error::Other(Error::Overcapture));
+                    }
+                    if sr.$ccxif().bit_is_clear() {
+                        return Err(nb::Error::WouldBlock);
+                    }
+
+                    // Reading the capture register clears the CCxIF flag.
+                    Ok(tim.$ccrx.read().ccr().bits())
+                }
+            }
+        )*
+    }
+}
+
+impl_channel!(
+    C1, ccmr1_input, cc1s, ic1f, ic1psc, cc1e, cc1p, cc1np, cc1ie, cc1if, cc1of, ccr1;
+    C2, ccmr1_input, cc2s, ic2f, ic2psc, cc2e, cc2p, cc2np, cc2ie, cc2if, cc2of, ccr2;
+    C3, ccmr2_input, cc3s, ic3f, ic3psc, cc3e, cc3p, cc3np, cc3ie, cc3if, cc3of, ccr3;
+    C4, ccmr2_input, cc4s, ic4f, ic4psc, cc4e, cc4p, cc4np, cc4ie, cc4if, cc4of, ccr4;
+);
+
+/// An input capture channel
+pub struct Capture<I, C, State> {
+    channel: PhantomData<C>,
+    timer: PhantomData<I>,
+    _state: State,
+}
+
+impl<I, C> Capture<I, C, Unassigned> {
+    fn new() -> Self {
+        Self {
+            channel: PhantomData,
+            timer: PhantomData,
+            _state: Unassigned,
+        }
+    }
+
+    /// Assigns a pin to the channel and configures it
+    ///
+    /// The channel needs to be enabled before captures take place.
+    pub fn assign<P>(self, pin: P, config: Config) -> Capture<I, C, Assigned<P>>
+    where
+        I: Instance,
+        C: Channel,
+        P: Pin<I, C>,
+    {
+        pin.setup();
+        interrupt::free(|_|
+            // Safe, as the read-modify-write within the critical section
+            C::configure(unsafe { &*I::ptr() }, &config));
+
+        Capture {
+            channel: self.channel,
+            timer: self.timer,
+            _state: Assigned(pin),
+        }
+    }
+}
+
+impl<I, C, P> Capture<I, C, Assigned<P>>
+where
+    I: Instance,
+    C: Channel,
+{
+    /// Enables capturing on this channel
+    pub fn enable(&mut self) {
+        interrupt::free(|_|
+            // Safe, as the read-modify-write within the critical section
+            C::enable(unsafe { &*I::ptr() }))
+    }
+
+    /// Disables capturing on this channel
+    pub fn disable(&mut self) {
+        interrupt::free(|_|
+            // Safe, as the read-modify-write within the critical section
+            C::disable(unsafe { &*I::ptr() }))
+    }
+
+    /// Enables the capture interrupt for this channel
+    pub fn listen(&mut self) {
+        interrupt::free(|_|
+            // Safe, as the read-modify-write within the critical section
+            C::listen(unsafe { &*I::ptr() }))
+    }
+
+    /// Disables the capture interrupt for this channel
+    pub fn unlisten(&mut self) {
+        interrupt::free(|_|
+            // Safe, as the read-modify-write within the critical section
+            C::unlisten(unsafe { &*I::ptr() }))
+    }
+
+    /// Returns the last captured counter value
+    ///
+    /// Returns `WouldBlock`, if nothing has been captured since the last
+    /// read, and `Error::Overcapture`, if captured values have been lost.
+    pub fn read(&mut self) -> nb::Result<u16, Error> {
+        interrupt::free(|_|
+            // Safe, as the read-modify-write within the critical section
+            C::read(unsafe { &*I::ptr() }))
+    }
+}
+
+/// Measures frequency and duty cycle of a PWM signal in hardware
+///
+/// The signal is connected to channel 1 and captured by channels 1 and 2 (on
+/// TI1FP1 and TI1FP2, respectively). Channel 1 captures the period on the
+/// rising edge, which also resets the counter through the slave mode
+/// controller. Channel 2 captures the pulse width on the falling edge.
+pub struct PwmInput<I, P> {
+    instance: I,
+    pin: P,
+    frequency: Hertz,
+}
+
+impl<I, P> PwmInput<I, P>
+where
+    I: Instance,
+    P: Pin<I, C1>,
+{
+    /// Starts measuring the signal on the given pin
+    ///
+    /// The counter runs at the given frequency, which limits the resolution
+    /// of the measurement. Signals with a period longer than the 16-bit
+    /// range of the counter can't be measured.
+    ///
+    /// # Panics
+    ///
+    /// Panics, if the timer clock can't be divided down to the given
+    /// frequency. See [`PwmInput::try_new`].
+    pub fn new<T>(timer: I, pin: P, frequency: T, rcc: &mut Rcc) -> Self
+    where
+        T: Into<Hertz>,
+    {
+        Self::try_new(timer, pin, frequency, rcc)
+            .map_err(|(_, err)| err)
+            .unwrap()
+    }
+
+    /// Starts measuring the signal on the given pin, if the frequency is
+    /// valid
+    ///
+    /// Returns [`config::Error::Frequency`], if the timer clock can't be
+    /// divided down to the given frequency. The timer and the pin are returned
+    /// along with the error, untouched.
+    pub fn try_new<T>(
+        timer: I,
+        pin: P,
+        frequency: T,
+        rcc: &mut Rcc,
+    ) -> Result<Self, ((I, P), config::Error)>
+    where
+        T: Into<Hertz>,
+    {
+        let clk = timer.clock_frequency(rcc);
+        let psc = match counter_prescaler(clk, frequency.into().0) {
+            Ok(psc) => psc,
+            Err(err) => return Err(((timer, pin), err)),
+        };
+
+        timer.enable(rcc);
+        pin.setup();
+
+        // Safe, as we own the timer instance.
+        let tim = unsafe { &*I::ptr() };
+
+        C1::configure(
+            tim,
+            &Config::default()
+                .mapping(Mapping::Direct)
+                .polarity(Polarity::RisingEdge),
+        );
+        C2::configure(
+            tim,
+            &Config::default()
+                .mapping(Mapping::Indirect)
+                .polarity(Polarity::FallingEdge),
+        );
+
+        // Reset the counter on every rising edge of the input
+        tim.smcr.modify(|_, w| w.ts().ti1fp1().sms().reset_mode());
+
+        C1::enable(tim);
+        C2::enable(tim);
+
+        setup(tim, psc);
+
+        Ok(Self {
+            instance: timer,
+            pin,
+            frequency: Hertz(clk / (u32::from(psc) + 1)),
+        })
+    }
+
+    /// Returns the latest measurement
+    ///
+    /// Returns `WouldBlock`, if no full period has been captured since the
+    /// last read.
+    pub fn read(&mut self) -> nb::Result<Measurement, Error> {
+        // Safe, as we own the timer instance.
+        let tim = unsafe { &*I::ptr() };
+
+        let period = C1::read(tim)?;
+        let pulse_width = tim.ccr2.read().ccr().bits();
+
+        Ok(Measurement {
+            period,
+            pulse_width,
+            tick: self.frequency,
+        })
+    }
+
+    /// Releases the timer and the pin
+    pub fn free(self) -> (I, P) {
+        // Safe, as we own the timer instance.
+        let tim = unsafe { &*I::ptr() };
+        C1::disable(tim);
+        C2::disable(tim);
+        tim.smcr.modify(|_, w| w.sms().disabled());
+
+        (self.instance, self.pin)
+    }
+}
+
+/// A PWM input measurement, in counter ticks
+#[derive(Clone, Copy, Debug)]
+pub struct Measurement {
+    /// Length of the period
+    pub period: u16,
+
+    /// Length of the high phase of the signal
+    pub pulse_width: u16,
+
+    /// Frequency of the counter
+    pub tick: Hertz,
+}
+
+impl Measurement {
+    /// Returns the frequency of the measured signal
+    pub fn frequency(&self) -> Hertz {
+        if self.period == 0 {
+            return Hertz(0);
+        }
+        Hertz(self.tick.0 / u32::from(self.period))
+    }
+
+    /// Returns the duty cycle, scaled to `0..=max`
+    ///
+    /// Using the value returned by `PwmPin::get_max_duty` here, allows the
+    /// result to be used directly with another PWM output.
+    pub fn duty_cycle(&self, max: u16) -> u16 {
+        if self.period == 0 {
+            return 0;
+        }
+        let duty = u32::from(self.pulse_width) * u32::from(max) / u32::from(self.period);
+        u16(duty).unwrap_or(max)
+    }
+}
+
+/// Internal clocks that can be connected to channel 1 of TIM21
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InternalClock {
+    /// HSE, divided by the RTC prescaler
+    HseRtc = 0b010,
+    Msi = 0b011,
+    Lse = 0b100,
+    Lsi = 0b101,
+    /// The microcontroller clock output, as configured in the RCC
+    Mco = 0b111,
+}
+
+/// Measures the frequency of internal clocks, using TIM21
+///
+/// Channel 1 of TIM21 can be connected to some internal clocks instead of a
+/// pin. Measuring them against the timer clock allows determining the actual
+/// frequency of the LSI, e.g. to calculate accurate watchdog timeouts. When
+/// running from MSI, measuring the LSE instead shows the deviation of the
+/// MSI from its nominal frequency, which can be used to trim it.
+pub struct ClockMeasurement {
+    tim: TIM21,
+}
+
+impl ClockMeasurement {
+    /// Starts TIM21, counting at the full timer clock
+    pub fn new(tim: TIM21, rcc: &mut Rcc) -> Self {
+        tim.enable(rcc);
+        // Count at the full timer clock
+        setup(Self::regs(), 0);
+
+        Self { tim }
+    }
+
+    /// Measures the frequency of an internal clock
+    ///
+    /// The clock needs to be enabled. Its frequency is averaged over
+    /// `8 * cycles` of its periods, based on the timer clock as configured in
+    /// the RCC. Eight periods of the clock need to fit into the range of the
+    /// counter, so it can't be slower than `timer clock * 8 / 65536`. For
+    /// fast clocks, captures might happen faster than they can be read, in
+    /// which case `Error::Overcapture` is returned. If the clock doesn't
+    /// produce a capture within two overflows of the counter,
+    /// `Error::Timeout` is returned.
+    ///
+    /// Returns `Error::InvalidMeasurement`, if `cycles` is zero.
+    pub fn measure(
+        &mut self,
+        clock: InternalClock,
+        cycles: u16,
+        rcc: &Rcc,
+    ) -> Result<Hertz, Error> {
+        if cycles == 0 {
+            return Err(Error::InvalidMeasurement);
+        }
+
+        let tim = Self::regs();
+
+        // Safe, as TI1_RMP is 3 bits wide on TIM21 and all `InternalClock`
+        // variants are valid values. The SVD file only lists some of them.
+        #[allow(unused_unsafe)]
+        self.tim
+            .or
+            .modify(|_, w| unsafe { w.ti1_rmp().bits(clock as u8) });
+
+        C1::configure(
+            tim,
+            &Config::default()
+                .mapping(Mapping::Direct)
+                .prescaler(Prescaler::Div8),
+        );
+        tim.sr
+            .modify(|_, w| w.cc1if().clear_bit().cc1of().clear_bit());
+        C1::enable(tim);
+
+        let ticks = Self::capture(tim, cycles);
+
+        C1::disable(tim);
+        self.tim.or.modify(|_, w| w.ti1_rmp().gpio());
+
+        let ticks = ticks?;
+        if ticks == 0 {
+            return Err(Error::InvalidMeasurement);
+        }
+
+        let clk = u64::from(self.tim.clock_frequency(rcc));
+        let freq = clk * 8 * u64::from(cycles) / ticks;
+        Ok(Hertz(freq as u32))
+    }
+
+    /// Releases the timer
+    pub fn free(self) -> TIM21 {
+        Self::regs().cr1.modify(|_, w| w.cen().clear_bit());
+        self.tim
+    }
+
+    /// Sums up the ticks between `cycles + 1` captures
+    fn capture(tim: &tim2::RegisterBlock, cycles: u16) -> Result<u64, Error> {
+        let mut last = Self::wait(tim)?;
+        let mut ticks = 0;
+        for _ in 0..cycles {
+            let now = Self::wait(tim)?;
+            ticks += u64::from(now.wrapping_sub(last));
+            last = now;
+        }
+        Ok(ticks)
+    }
+
+    /// Waits for the next capture, for up to two overflows of the counter
+    ///
+    /// As the captures need to be less than one counter period apart, the
+    /// counter can overflow at most once while waiting for a valid capture.
+    fn wait(tim: &tim2::RegisterBlock) -> Result<u16, Error> {
+        Self::clear_overflow(tim);
+        let mut overflows = 0;
+        loop {
+            match C1::read(tim) {
+                Ok(value) => return Ok(value),
+                Err(nb::Error::Other(error)) => return Err(error),
+                Err(nb::Error::WouldBlock) => {}
+            }
+
+            if tim.sr.read().uif().bit_is_set() {
+                Self::clear_overflow(tim);
+                overflows += 1;
+                if overflows == 2 {
+                    return Err(Error::Timeout);
+                }
+            }
+        }
+    }
+
+    /// Clears the update flag
+    fn clear_overflow(tim: &tim2::RegisterBlock) {
+        // The flags are cleared by writing zero, so writing ones to the other
+        // flags doesn't clear a capture that happens concurrently. This is
+        // safe, as all bits are valid.
+        tim.sr.write(|w| unsafe { w.bits(!1) });
+    }
+
+    fn regs() -> &'static tim2::RegisterBlock {
+        // Safe, as we own the timer instance.
+        unsafe { &*<TIM21 as Instance>::ptr() }
+    }
+}
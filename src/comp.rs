@@ -0,0 +1,446 @@
+//! Interface to the comparators
+//!
+//! The two comparators COMP1 and COMP2 are controlled through the SYSCFG
+//! peripheral.
+//!
+//! Ownership of the comparators is taken once, with [`SYSCFG::comparators`].
+//!
+//! The GPIO pins used as comparator inputs should be put into analog mode.
+//! COMP1's non-inverting input is always connected to PA1.
+//!
+//! The comparator outputs can be routed to the timers, see [`Comp1Out`] and
+//! [`Comp2Out`].
+//!
+//! The comparators keep running in Stop mode, and can wake up the
+//! microcontroller through their EXTI lines 21 and 22. Please note that the
+//! internal voltage reference is disabled in Stop mode, if the ultra-low-power
+//! mode is enabled in the PWR (see `PWR::set_ultra_low_power`), so it can't be
+//! used as input then.
+
+use cortex_m::interrupt;
+
+use crate::{
+    capture::Pin,
+    counter::EtrPin,
+    exti::{ConfigurableLine, Exti, TriggerEdge},
+    lptim::{Input1Pin, Input2Pin},
+    pac::{self, TIM2, TIM21, TIM22},
+    pwm::{C1, C2, C4},
+    syscfg::SYSCFG,
+};
+
+/// Ownership token of COMP1, see [`SYSCFG::comparators`]
+pub struct COMP1(pub(crate) ());
+
+/// Ownership token of COMP2, see [`SYSCFG::comparators`]
+pub struct COMP2(pub(crate) ());
+
+/// Comparator 1
+///
+/// An ultra-low-power comparator, whose non-inverting input is PA1.
+pub struct Comp1(COMP1);
+
+impl Comp1 {
+    /// Configures COMP1, comparing PA1 to `inverting`
+    ///
+    /// The comparator is left disabled. Use [`Comp1::enable`] to enable it.
+    pub fn new(comp: COMP1, syscfg: &mut SYSCFG, inverting: Comp1Inverting) -> Self {
+        if let Comp1Inverting::Vrefint = inverting {
+            syscfg.syscfg.cfgr3.modify(|_, w| w.en_vrefint().set_bit());
+        }
+
+        Self::csr().write(|w| w.comp1innsel().bits(inverting as u8));
+
+        Comp1(comp)
+    }
+
+    /// Disables COMP1 and returns its ownership token
+    pub fn free(mut self) -> COMP1 {
+        self.disable();
+        self.0
+    }
+
+    /// Enables the comparator
+    pub fn enable(&mut self) {
+        Self::csr().modify(|_, w| w.comp1en().set_bit());
+    }
+
+    /// Disables the comparator
+    pub fn disable(&mut self) {
+        Self::csr().modify(|_, w| w.comp1en().clear_bit());
+    }
+
+    /// Sets the polarity of the output
+    pub fn set_polarity(&mut self, polarity: Polarity) {
+        Self::csr().modify(|_, w| w.comp1polarity().bit(polarity == Polarity::Inverted));
+    }
+
+    /// Returns the output level of the comparator
+    ///
+    /// Unless the polarity is inverted, the output is high, if the voltage on
+    /// the non-inverting input is above the voltage on the inverting input.
+    pub fn output(&self) -> bool {
+        Self::csr().read().comp1value().bit_is_set()
+    }
+
+    /// Starts listening for output edges on EXTI line 21
+    ///
+    /// The `ADC_COMP` interrupt is triggered on the selected edges of the
+    /// output, which also wakes up the microcontroller from Stop mode. The
+    /// pending bit needs to be cleared in the interrupt handler, using
+    /// `Exti::unpend(ConfigurableLine::Comp1)`.
+    pub fn listen(&mut self, exti: &mut Exti, edge: TriggerEdge) {
+        exti.listen_configurable(ConfigurableLine::Comp1, edge);
+    }
+
+    /// Starts generating events on output edges on EXTI line 21
+    ///
+    /// An event wakes up the microcontroller from a low-power mode entered
+    /// with `WFE`, without triggering an interrupt.
+    pub fn listen_event(&mut self, exti: &mut Exti, edge: TriggerEdge) {
+        exti.listen_configurable_event(ConfigurableLine::Comp1, edge);
+    }
+
+    /// Stops listening for output edges
+    pub fn unlisten(&mut self, exti: &mut Exti) {
+        exti.unlisten(ConfigurableLine::Comp1);
+    }
+
+    fn csr() -> &'static pac::syscfg::COMP1_CSR {
+        // Safe, as this struct owns the COMP1 token, which is handed out once.
+        unsafe { &(*pac::SYSCFG::ptr()).comp1_csr }
+    }
+}
+
+/// Comparator 2
+///
+/// A fast or low-power comparator, with selectable inputs.
+pub struct Comp2(COMP2);
+
+impl Comp2 {
+    /// Configures COMP2, comparing `non_inverting` to `inverting`
+    ///
+    /// The comparator is left disabled, and is configured for low speed. Use
+    /// [`Comp2::enable`] to enable it.
+    pub fn new(
+        comp: COMP2,
+        syscfg: &mut SYSCFG,
+        non_inverting: Comp2NonInverting,
+        inverting: Comp2Inverting,
+    ) -> Self {
+        match inverting {
+            Comp2Inverting::Vrefint => {
+                syscfg.syscfg.cfgr3.modify(|_, w| w.en_vrefint().set_bit());
+            }
+            Comp2Inverting::VrefintDiv4
+            | Comp2Inverting::VrefintDiv2
+            | Comp2Inverting::Vrefint3Div4 => {
+                // The fractions of VREFINT are generated by a buffered
+                // voltage divider.
+                syscfg
+                    .syscfg
+                    .cfgr3
+                    .modify(|_, w| w.en_vrefint().set_bit().enbuf_vrefint_comp2().set_bit());
+            }
+            _ => {}
+        }
+
+        Self::csr().write(|w| unsafe {
+            w.comp2inpsel()
+                .bits(non_inverting as u8)
+                .comp2innsel()
+                .bits(inverting as u8)
+        });
+
+        Comp2(comp)
+    }
+
+    /// Disables COMP2 and returns its ownership token
+    pub fn free(mut self) -> COMP2 {
+        self.disable();
+        self.0
+    }
+
+    /// Enables the comparator
+    pub fn enable(&mut self) {
+        Self::csr().modify(|_, w| w.comp2en().set_bit());
+    }
+
+    /// Disables the comparator
+    pub fn disable(&mut self) {
+        Self::csr().modify(|_, w| w.comp2en().clear_bit());
+    }
+
+    /// Sets the polarity of the output
+    pub fn set_polarity(&mut self, polarity: Polarity) {
+        Self::csr().modify(|_, w| w.comp2polarity().bit(polarity == Polarity::Inverted));
+    }
+
+    /// Selects between low-power and fast operation
+    pub fn set_speed(&mut self, speed: Speed) {
+        Self::csr().modify(|_, w| w.comp2speed().bit(speed == Speed::Fast));
+    }
+
+    /// Returns the output level of the comparator
+    ///
+    /// Unless the polarity is inverted, the output is high, if the voltage on
+    /// the non-inverting input is above the voltage on the inverting input.
+    pub fn output(&self) -> bool {
+        Self::csr().read().comp2value().bit_is_set()
+    }
+
+    /// Starts listening for output edges on EXTI line 22
+    ///
+    /// The `ADC_COMP` interrupt is triggered on the selected edges of the
+    /// output, which also wakes up the microcontroller from Stop mode. The
+    /// pending bit needs to be cleared in the interrupt handler, using
+    /// `Exti::unpend(ConfigurableLine::Comp2)`.
+    pub fn listen(&mut self, exti: &mut Exti, edge: TriggerEdge) {
+        exti.listen_configurable(ConfigurableLine::Comp2, edge);
+    }
+
+    /// Starts generating events on output edges on EXTI line 22
+    ///
+    /// An event wakes up the microcontroller from a low-power mode entered
+    /// with `WFE`, without triggering an interrupt.
+    pub fn listen_event(&mut self, exti: &mut Exti, edge: TriggerEdge) {
+        exti.listen_configurable_event(ConfigurableLine::Comp2, edge);
+    }
+
+    /// Stops listening for output edges
+    pub fn unlisten(&mut self, exti: &mut Exti) {
+        exti.unlisten(ConfigurableLine::Comp2);
+    }
+
+    fn csr() -> &'static pac::syscfg::COMP2_CSR {
+        // Safe, as this struct owns the COMP2 token, which is handed out once.
+        unsafe { &(*pac::SYSCFG::ptr()).comp2_csr }
+    }
+}
+
+/// Both comparators in window mode
+///
+/// In window mode, COMP1's non-inverting input is connected to COMP2's
+/// non-inverting input, so that a single voltage is compared to the two
+/// inverting inputs, which define the lower and the upper bound of a window.
+/// It doesn't matter which comparator is connected to which bound.
+pub struct Window {
+    comp1: Comp1,
+    comp2: Comp2,
+}
+
+impl Window {
+    /// Combines both comparators in window mode and enables them
+    ///
+    /// The voltage is applied to COMP2's non-inverting input, as selected in
+    /// [`Comp2::new`]. The polarity of both comparators is reset to
+    /// non-inverted.
+    pub fn new(mut comp1: Comp1, mut comp2: Comp2) -> Self {
+        comp1.set_polarity(Polarity::NonInverted);
+        comp2.set_polarity(Polarity::NonInverted);
+
+        Comp1::csr().modify(|_, w| w.comp1wm().set_bit());
+
+        comp1.enable();
+        comp2.enable();
+
+        Window { comp1, comp2 }
+    }
+
+    /// Returns the position of the voltage relative to the window
+    pub fn state(&self) -> WindowState {
+        match (self.comp1.output(), self.comp2.output()) {
+            (true, true) => WindowState::Above,
+            (false, false) => WindowState::Below,
+            _ => WindowState::Inside,
+        }
+    }
+
+    /// Returns whether the voltage is inside the window
+    pub fn is_inside(&self) -> bool {
+        self.state() == WindowState::Inside
+    }
+
+    /// Starts listening for changes of the state on EXTI lines 21 and 22
+    ///
+    /// The `ADC_COMP` interrupt is triggered whenever the voltage enters or
+    /// leaves the window, which also wakes up the microcontroller from Stop
+    /// mode. The pending bits of both lines need to be cleared in the
+    /// interrupt handler.
+    pub fn listen(&mut self, exti: &mut Exti) {
+        self.comp1.listen(exti, TriggerEdge::Both);
+        self.comp2.listen(exti, TriggerEdge::Both);
+    }
+
+    /// Stops listening for changes of the state
+    pub fn unlisten(&mut self, exti: &mut Exti) {
+        self.comp1.unlisten(exti);
+        self.comp2.unlisten(exti);
+    }
+
+    /// Disables window mode and both comparators
+    pub fn free(mut self) -> (Comp1, Comp2) {
+        self.comp1.disable();
+        self.comp2.disable();
+
+        Comp1::csr().modify(|_, w| w.comp1wm().clear_bit());
+
+        (self.comp1, self.comp2)
+    }
+}
+
+/// The output of COMP1, as an internal input of the timers
+///
+/// Can be used in place of a pin, to connect the comparator output to a timer
+/// without CPU involvement:
+/// - As input capture pin of TIM2 channel 4, and of TIM21 and TIM22 channel 1,
+///   to timestamp threshold crossings (see `capture::Capture::assign`)
+/// - As external trigger input of TIM2, TIM21 and TIM22, e.g. as OCREF clear
+///   input for cycle-by-cycle current limiting (see
+///   `pwm::Timer::select_ocref_clear`)
+/// - As Input1 of the Low-Power Timer
+///
+/// The remapping of the timer input stays in place, until it is changed to a
+/// different input.
+pub struct Comp1Out;
+
+/// The output of COMP2, as an internal input of the timers
+///
+/// Can be used in place of a pin, like [`Comp1Out`]. Additionally, it can be
+/// used as input capture pin of TIM21 channel 2 and as Input2 of the
+/// Low-Power Timer.
+pub struct Comp2Out;
+
+macro_rules! impl_timer_input {
+    (
+        $(
+            $output:ty, $variant:ident: (
+                $(
+                    $trait:ty, $instance:ident, $rmp:ident;
+                )*
+            )
+        )*
+    ) => {
+        $(
+            $(
+                impl $trait for $output {
+                    fn setup(&self) {
+                        interrupt::free(|_| {
+                            // Safe, as the read-modify-write within the
+                            // critical section only changes the input remap.
+                            let tim = unsafe { &*$instance::ptr() };
+                            tim.or.modify(|_, w| w.$rmp().$variant());
+                        })
+                    }
+                }
+            )*
+        )*
+    }
+}
+
+impl_timer_input!(
+    Comp1Out, comp1_out: (
+        Pin<TIM2, C4>,  TIM2,  ti4_rmp;
+        Pin<TIM21, C1>, TIM21, ti1_rmp;
+        Pin<TIM22, C1>, TIM22, ti1_rmp;
+        EtrPin<TIM2>,   TIM2,  etr_rmp;
+        EtrPin<TIM21>,  TIM21, etr_rmp;
+        EtrPin<TIM22>,  TIM22, etr_rmp;
+    )
+    Comp2Out, comp2_out: (
+        Pin<TIM2, C4>,  TIM2,  ti4_rmp;
+        Pin<TIM21, C1>, TIM21, ti1_rmp;
+        Pin<TIM21, C2>, TIM21, ti2_rmp;
+        Pin<TIM22, C1>, TIM22, ti1_rmp;
+        EtrPin<TIM2>,   TIM2,  etr_rmp;
+        EtrPin<TIM21>,  TIM21, etr_rmp;
+        EtrPin<TIM22>,  TIM22, etr_rmp;
+    )
+);
+
+impl Input1Pin for Comp1Out {
+    fn setup(&self) {
+        interrupt::free(|_| Comp1::csr().modify(|_, w| w.comp1lptimin1().set_bit()));
+    }
+}
+
+impl Input1Pin for Comp2Out {
+    fn setup(&self) {
+        interrupt::free(|_| Comp2::csr().modify(|_, w| w.comp2lptimin1().set_bit()));
+    }
+}
+
+impl Input2Pin for Comp2Out {
+    fn setup(&self) {
+        interrupt::free(|_| Comp2::csr().modify(|_, w| w.comp2lptimin2().set_bit()));
+    }
+}
+
+/// The inverting input of COMP1
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Comp1Inverting {
+    /// The internal voltage reference
+    Vrefint = 0b00,
+    PA0 = 0b01,
+    /// PA4, or the output of DAC channel 1
+    PA4 = 0b10,
+    /// PA5, or the output of DAC channel 2
+    PA5 = 0b11,
+}
+
+/// The inverting input of COMP2
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Comp2Inverting {
+    /// The internal voltage reference
+    Vrefint = 0b000,
+    PA2 = 0b001,
+    /// PA4, or the output of DAC channel 1
+    PA4 = 0b010,
+    /// PA5, or the output of DAC channel 2
+    PA5 = 0b011,
+    /// A quarter of the internal voltage reference
+    VrefintDiv4 = 0b100,
+    /// Half of the internal voltage reference
+    VrefintDiv2 = 0b101,
+    /// Three quarters of the internal voltage reference
+    Vrefint3Div4 = 0b110,
+    PB3 = 0b111,
+}
+
+/// The non-inverting input of COMP2
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Comp2NonInverting {
+    PA3 = 0b000,
+    PB4 = 0b001,
+    PB5 = 0b010,
+    PB6 = 0b011,
+    PB7 = 0b100,
+    PA7 = 0b101,
+}
+
+/// The polarity of a comparator output
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Polarity {
+    NonInverted,
+    Inverted,
+}
+
+/// The speed of COMP2
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Speed {
+    /// Low power consumption, but slow response
+    Slow,
+    /// Fast response, but higher power consumption
+    Fast,
+}
+
+/// The position of a voltage relative to the window of a [`Window`]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum WindowState {
+    /// The voltage is below both bounds
+    Below,
+    /// The voltage is between the bounds
+    Inside,
+    /// The voltage is above both bounds
+    Above,
+}
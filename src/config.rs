@@ -0,0 +1,31 @@
+//! Configuration errors
+//!
+//! The fallible configuration APIs (e.g. [`RccExt::try_freeze`]) return these
+//! errors instead of panicking, so misconfigurations can be handled at runtime.
+//!
+//! [`RccExt::try_freeze`]: crate::rcc::RccExt::try_freeze
+
+/// Invalid configuration
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    /// A clock that the operation depends on isn't running or selected
+    ///
+    /// E.g. the HSE when enabling its clock security system.
+    ClockNotReady,
+    /// A clock frequency is outside of the supported range
+    ///
+    /// This applies to the HSE, the PLL input, the PLL VCO output, the
+    /// system clock and the HSI16 trimming value.
+    ClockFrequency,
+    /// The baud rate can't be generated from the peripheral clock
+    BaudRate,
+    /// The requested frequency can't be derived from the peripheral clock
+    Frequency,
+    /// The buffer is too long for a DMA transfer, or shorter than requested
+    BufferLength,
+    /// The buffer is not aligned to the word size of a DMA transfer
+    BufferAlignment,
+    /// The number of registers of a DMA burst is not supported
+    BurstLength,
+}
@@ -0,0 +1,232 @@
+//! External pulse counting
+//!
+//! Uses the external clock modes of the general-purpose timers to count
+//! pulses on an input pin, e.g. from flow meters or tachometers. External
+//! clock mode 1 counts edges on one of the capture inputs (TI1 or TI2),
+//! external clock mode 2 counts edges on the external trigger input (ETR).
+
+use crate::capture::{Channel as _, Filter, Mapping, Polarity, Prescaler};
+use crate::gpio::{AltMode, PinMode};
+use crate::pac::{tim2, TIM2, TIM21};
+use crate::pwm::{Instance, Pin, C1, C2};
+use crate::rcc::Rcc;
+
+use crate::gpio::gpioa::{PA0, PA1};
+
+#[cfg(any(feature = "stm32l0x2", feature = "stm32l0x3"))]
+use crate::gpio::gpioa::{PA15, PA5};
+
+#[cfg(any(
+    feature = "io-STM32L031",
+    feature = "io-STM32L051",
+    feature = "io-STM32L071"
+))]
+use crate::{gpio::gpioa::PA4, pac::TIM22};
+
+/// Configuration of the external trigger input
+pub struct EtrConfig {
+    /// Count falling instead of rising edges
+    pub inverted: bool,
+    /// Divides the ETR signal, before it is filtered
+    ///
+    /// The frequency of the prescaled signal must not exceed a quarter of
+    /// the timer clock.
+    pub prescaler: Prescaler,
+    pub filter: Filter,
+}
+
+impl EtrConfig {
+    pub fn inverted(mut self, inverted: bool) -> Self {
+        self.inverted = inverted;
+        self
+    }
+
+    pub fn prescaler(mut self, prescaler: Prescaler) -> Self {
+        self.prescaler = prescaler;
+        self
+    }
+
+    pub fn filter(mut self, filter: Filter) -> Self {
+        self.filter = filter;
+        self
+    }
+}
+
+impl Default for EtrConfig {
+    fn default() -> EtrConfig {
+        EtrConfig {
+            inverted: false,
+            prescaler: Prescaler::NotDivided,
+            filter: Filter::NoFilter,
+        }
+    }
+}
+
+/// Counts pulses on an external input
+pub struct PulseCounter<I, P> {
+    instance: I,
+    pin: P,
+}
+
+impl<I, P> PulseCounter<I, P>
+where
+    I: Instance,
+{
+    /// Counts edges on channel 1 (external clock mode 1, TI1FP1)
+    pub fn ti1(timer: I, pin: P, polarity: Polarity, filter: Filter, rcc: &mut Rcc) -> Self
+    where
+        P: Pin<I, C1>,
+    {
+        timer.enable(rcc);
+        pin.setup();
+
+        // Safe, as we own the timer instance.
+        let tim = unsafe { &*I::ptr() };
+        C1::configure(tim, &channel_config(polarity, filter));
+        tim.smcr
+            .modify(|_, w| w.ts().ti1fp1().sms().ext_clock_mode());
+
+        Self::start(timer, pin)
+    }
+
+    /// Counts edges on channel 2 (external clock mode 1, TI2FP2)
+    pub fn ti2(timer: I, pin: P, polarity: Polarity, filter: Filter, rcc: &mut Rcc) -> Self
+    where
+        P: Pin<I, C2>,
+    {
+        timer.enable(rcc);
+        pin.setup();
+
+        // Safe, as we own the timer instance.
+        let tim = unsafe { &*I::ptr() };
+        C2::configure(tim, &channel_config(polarity, filter));
+        tim.smcr
+            .modify(|_, w| w.ts().ti2fp2().sms().ext_clock_mode());
+
+        Self::start(timer, pin)
+    }
+
+    /// Counts edges on the external trigger input (external clock mode 2)
+    pub fn etr(timer: I, pin: P, config: EtrConfig, rcc: &mut Rcc) -> Self
+    where
+        P: EtrPin<I>,
+    {
+        timer.enable(rcc);
+        pin.setup();
+
+        // Safe, as we own the timer instance.
+        let tim = unsafe { &*I::ptr() };
+        tim.smcr.modify(|_, w| {
+            w.etp().bit(config.inverted);
+            w.etps().bits(config.prescaler as u8);
+            w.etf().bits(config.filter as u8);
+            w.ece().set_bit()
+        });
+
+        Self::start(timer, pin)
+    }
+
+    fn start(timer: I, pin: P) -> Self {
+        let counter = Self {
+            instance: timer,
+            pin,
+        };
+
+        counter.tim().psc.write(|w| w.psc().bits(0));
+        counter.tim().arr.write(|w| w.arr().bits(u16::MAX));
+        counter.tim().egr.write(|w| w.ug().set_bit());
+        counter.tim().cr1.modify(|_, w| w.cen().set_bit());
+
+        counter
+    }
+
+    /// Returns the number of pulses counted
+    ///
+    /// The counter wraps around after 65535.
+    pub fn count(&self) -> u16 {
+        self.tim().cnt.read().cnt().bits()
+    }
+
+    /// Resets the count to zero
+    pub fn reset(&mut self) {
+        self.tim().cnt.write(|w| w.cnt().bits(0));
+    }
+
+    /// Releases the timer and the pin
+    pub fn free(self) -> (I, P) {
+        self.tim().cr1.modify(|_, w| w.cen().clear_bit());
+        self.tim()
+            .smcr
+            .modify(|_, w| w.sms().disabled().ece().clear_bit());
+
+        (self.instance, self.pin)
+    }
+
+    fn tim(&self) -> &tim2::RegisterBlock {
+        // Safe, as we own the timer instance.
+        unsafe { &*I::ptr() }
+    }
+}
+
+fn channel_config(polarity: Polarity, filter: Filter) -> crate::capture::Config {
+    crate::capture::Config::default()
+        .mapping(Mapping::Direct)
+        .polarity(polarity)
+        .filter(filter)
+}
+
+/// Pins that can be used as external trigger input of a timer
+pub trait EtrPin<I> {
+    fn setup(&self);
+}
+
+macro_rules! impl_etr_pin {
+    (
+        $(
+            $instance:ty: (
+                $(
+                    $name:ident,
+                    $alternate_function:ident;
+                )*
+            )
+        )*
+    ) => {
+        $(
+            $(
+                impl<State: PinMode> EtrPin<$instance> for $name<State> {
+                    fn setup(&self) {
+                        self.set_alt_mode(AltMode::$alternate_function);
+                    }
+                }
+            )*
+        )*
+    }
+}
+
+impl_etr_pin!(
+    TIM2: (
+        PA0, AF5;
+    )
+    TIM21: (
+        PA1, AF5;
+    )
+);
+
+#[cfg(any(feature = "stm32l0x2", feature = "stm32l0x3"))]
+impl_etr_pin!(
+    TIM2: (
+        PA5,  AF2;
+        PA15, AF2;
+    )
+);
+
+#[cfg(any(
+    feature = "io-STM32L031",
+    feature = "io-STM32L051",
+    feature = "io-STM32L071"
+))]
+impl_etr_pin!(
+    TIM22: (
+        PA4, AF5;
+    )
+);
@@ -0,0 +1,242 @@
+//! Interface to the CRC calculation unit
+//!
+//! The polynomial, its size, the initial value and the bit reversal of input
+//! and output data are programmable, so the checksums of most common
+//! protocols can be calculated in hardware. [`Config`] provides presets for
+//! some of them.
+//!
+//! Checksums can be calculated incrementally, over data that is fed in
+//! chunks, using [`Crc::update`] and [`Crc::finalize`]. [`Crc`] also
+//! implements `core::hash::Hasher`.
+
+use core::{hash::Hasher, ptr};
+
+use crate::rcc::{Enable, Rcc, Reset};
+
+pub use crate::pac::CRC;
+
+/// The CRC calculation unit
+pub struct Crc {
+    crc: CRC,
+    final_xor: u32,
+    mask: u32,
+}
+
+impl Crc {
+    /// Enables the CRC calculation unit and applies `config`
+    pub fn new(crc: CRC, config: Config, rcc: &mut Rcc) -> Self {
+        // Reset peripheral
+        CRC::reset(rcc);
+
+        // Enable peripheral clock
+        CRC::enable(rcc);
+
+        let mut crc = Crc {
+            crc,
+            final_xor: 0,
+            mask: 0,
+        };
+        crc.configure(config);
+
+        crc
+    }
+
+    /// Changes the configuration
+    ///
+    /// This also starts a new calculation.
+    pub fn configure(&mut self, config: Config) {
+        let (polysize, polynomial, mask) = match config.polynomial {
+            Polynomial::L7(poly) => (0b11, u32::from(poly & 0x7f), 0x7f),
+            Polynomial::L8(poly) => (0b10, u32::from(poly), 0xff),
+            Polynomial::L16(poly) => (0b01, u32::from(poly), 0xffff),
+            Polynomial::L32(poly) => (0b00, poly, 0xffff_ffff),
+        };
+
+        self.crc.pol.write(|w| w.pol().bits(polynomial));
+        self.crc
+            .init
+            .write(|w| w.crc_init().bits(config.initial_value));
+        self.crc.cr.write(|w| {
+            w.polysize().bits(polysize);
+            if config.reverse_input {
+                w.rev_in().reverse_byte();
+            }
+            w.rev_out().bit(config.reverse_output)
+        });
+
+        self.final_xor = config.final_xor;
+        self.mask = mask;
+
+        self.reset();
+    }
+
+    /// Starts a new calculation, from the initial value
+    pub fn reset(&mut self) {
+        self.crc.cr.modify(|_, w| w.reset().set_bit());
+    }
+
+    /// Calculates the checksum of `data`
+    ///
+    /// The result is only as wide as the polynomial. Any calculation that was
+    /// in progress is discarded.
+    pub fn checksum(&mut self, data: &[u8]) -> u32 {
+        self.reset();
+        self.update(data);
+        self.finalize()
+    }
+
+    /// Feeds `data` into the calculation
+    ///
+    /// This allows calculating the checksum of data that is not available at
+    /// once, e.g. received in chunks. Use [`Crc::finalize`] to get the result.
+    pub fn update(&mut self, data: &[u8]) {
+        // The data register needs to be accessed byte-wise, so the input
+        // reversal and the polynomial are applied to every byte.
+        // Safe, as this only computes the address of the register.
+        let dr = unsafe { ptr::addr_of!((*CRC::ptr()).dr) } as *mut u8;
+
+        for &byte in data {
+            // Safe, as this is a byte-sized write to the data register.
+            unsafe { dr.write_volatile(byte) };
+        }
+    }
+
+    /// Returns the checksum of the data fed in so far
+    ///
+    /// This also starts a new calculation.
+    pub fn finalize(&mut self) -> u32 {
+        let result = self.result();
+        self.reset();
+        result
+    }
+
+    fn result(&self) -> u32 {
+        (self.crc.dr.read().dr().bits() ^ self.final_xor) & self.mask
+    }
+
+    /// Releases the CRC calculation unit
+    pub fn free(self) -> CRC {
+        self.crc
+    }
+}
+
+/// Calculates the checksum of the written data
+///
+/// `finish` returns the checksum of the data written so far, without starting
+/// a new calculation. Use [`Crc::reset`] for that.
+impl Hasher for Crc {
+    fn write(&mut self, bytes: &[u8]) {
+        self.update(bytes);
+    }
+
+    fn finish(&self) -> u64 {
+        u64::from(self.result())
+    }
+}
+
+/// Configuration of the CRC calculation unit
+///
+/// [`Default`] is the configuration after reset, which calculates the
+/// CRC-32/MPEG-2.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Config {
+    pub polynomial: Polynomial,
+    /// The value the calculation starts from
+    ///
+    /// Only the lower bits are used for polynomials shorter than 32 bits.
+    pub initial_value: u32,
+    /// Reverses the bit order of every input byte, as done by reflected CRCs
+    pub reverse_input: bool,
+    /// Reverses the bit order of the result, as done by reflected CRCs
+    pub reverse_output: bool,
+    /// Is XOR-ed with the result
+    ///
+    /// This isn't supported by the hardware, and is applied when reading the
+    /// result instead.
+    pub final_xor: u32,
+}
+
+impl Config {
+    /// CRC-32, as used by Ethernet, zlib and PNG
+    pub fn crc32() -> Self {
+        Config {
+            polynomial: Polynomial::L32(0x04C1_1DB7),
+            initial_value: 0xffff_ffff,
+            reverse_input: true,
+            reverse_output: true,
+            final_xor: 0xffff_ffff,
+        }
+    }
+
+    /// CRC-16-CCITT, starting from `0xffff`
+    ///
+    /// This variant is also known as CRC-16/CCITT-FALSE or CRC-16/IBM-3740.
+    pub fn crc16_ccitt() -> Self {
+        Config {
+            polynomial: Polynomial::L16(0x1021),
+            initial_value: 0xffff,
+            reverse_input: false,
+            reverse_output: false,
+            final_xor: 0,
+        }
+    }
+
+    /// CRC-8, as used by SMBus packet error checking
+    pub fn crc8() -> Self {
+        Config {
+            polynomial: Polynomial::L8(0x07),
+            initial_value: 0,
+            reverse_input: false,
+            reverse_output: false,
+            final_xor: 0,
+        }
+    }
+
+    pub fn polynomial(mut self, polynomial: Polynomial) -> Self {
+        self.polynomial = polynomial;
+        self
+    }
+
+    pub fn initial_value(mut self, initial_value: u32) -> Self {
+        self.initial_value = initial_value;
+        self
+    }
+
+    pub fn reverse_input(mut self, reverse_input: bool) -> Self {
+        self.reverse_input = reverse_input;
+        self
+    }
+
+    pub fn reverse_output(mut self, reverse_output: bool) -> Self {
+        self.reverse_output = reverse_output;
+        self
+    }
+
+    pub fn final_xor(mut self, final_xor: u32) -> Self {
+        self.final_xor = final_xor;
+        self
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            polynomial: Polynomial::L32(0x04C1_1DB7),
+            initial_value: 0xffff_ffff,
+            reverse_input: false,
+            reverse_output: false,
+            final_xor: 0,
+        }
+    }
+}
+
+/// A generator polynomial, in normal representation without the highest bit
+///
+/// The polynomial needs to be odd.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Polynomial {
+    L7(u8),
+    L8(u8),
+    L16(u16),
+    L32(u32),
+}
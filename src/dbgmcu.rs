@@ -0,0 +1,192 @@
+//! Interface to the debug support (DBGMCU) peripheral
+//!
+//! See STM32L0x2 reference manual, section "Debug support (DBG)".
+
+use crate::{
+    pac,
+    rcc::{Enable, Rcc},
+};
+
+// Not covered by the PAC
+const APB1_FZ_TIM3: u32 = 1 << 1;
+const APB1_FZ_TIM7: u32 = 1 << 5;
+
+/// Entry point to the DBGMCU API
+pub struct DBGMCU(pac::DBG);
+
+impl DBGMCU {
+    /// Create an instance of the DBGMCU API
+    pub fn new(dbg: pac::DBG, rcc: &mut Rcc) -> Self {
+        // Enable peripheral clock
+        pac::DBG::enable(rcc);
+
+        Self(dbg)
+    }
+
+    /// Configures debugging in low-power modes
+    ///
+    /// By default, the debug connection is lost when the microcontroller
+    /// enters a low-power mode, as the clocks of the debug interface are
+    /// stopped. The modes set to `true` in `modes` keep the clocks running, at
+    /// the cost of an increased current consumption. Those set to `false`
+    /// restore the default behavior.
+    ///
+    /// Please note that this configuration is only reset by a power-on reset,
+    /// so make sure to clear it again, if you want to measure the current
+    /// consumption.
+    pub fn set_low_power_debug(&mut self, modes: LowPowerDebug) {
+        self.0.cr.modify(|_, w| {
+            w.dbg_sleep().bit(modes.sleep);
+            w.dbg_stop().bit(modes.stop);
+            w.dbg_standby().bit(modes.standby)
+        });
+    }
+
+    /// Returns the current debug configuration for low-power modes
+    pub fn low_power_debug(&self) -> LowPowerDebug {
+        let cr = self.0.cr.read();
+        LowPowerDebug {
+            sleep: cr.dbg_sleep().bit_is_set(),
+            stop: cr.dbg_stop().bit_is_set(),
+            standby: cr.dbg_standby().bit_is_set(),
+        }
+    }
+
+    /// Configures which peripherals are stopped while the core is halted
+    ///
+    /// By default, watchdogs, timers and the RTC keep running while the core
+    /// is halted by a debugger, e.g. at a breakpoint, so a running watchdog
+    /// resets the microcontroller. The peripherals set to `true` in
+    /// `peripherals` are frozen instead. Those set to `false` restore the
+    /// default behavior.
+    pub fn set_freeze(&mut self, peripherals: Freeze) {
+        self.0.apb1_fz.modify(|r, w| {
+            let mut bits = r.bits() & !(APB1_FZ_TIM3 | APB1_FZ_TIM7);
+            if peripherals.tim3 {
+                bits |= APB1_FZ_TIM3;
+            }
+            if peripherals.tim7 {
+                bits |= APB1_FZ_TIM7;
+            }
+            // Safe, as only the reserved bits for TIM3 and TIM7 are modified.
+            unsafe { w.bits(bits) };
+
+            w.dbg_timer2_stop().bit(peripherals.tim2);
+            w.dbg_timer6_stop().bit(peripherals.tim6);
+            w.dbg_rtc_stop().bit(peripherals.rtc);
+            w.dbg_wwdg_stop().bit(peripherals.wwdg);
+            w.dbg_iwdg_stop().bit(peripherals.iwdg);
+            w.dbg_i2c1_stop().bit(peripherals.i2c1);
+            w.dbg_i2c2_stop().bit(peripherals.i2c2);
+            w.dbg_lptimer_stop().bit(peripherals.lptim)
+        });
+        self.0.apb2_fz.modify(|_, w| {
+            w.dbg_timer21_stop().bit(peripherals.tim21);
+            w.dbg_timer22_sto().bit(peripherals.tim22)
+        });
+    }
+
+    /// Returns which peripherals are stopped while the core is halted
+    pub fn freeze(&self) -> Freeze {
+        let apb1 = self.0.apb1_fz.read();
+        let apb2 = self.0.apb2_fz.read();
+        Freeze {
+            iwdg: apb1.dbg_iwdg_stop().bit_is_set(),
+            wwdg: apb1.dbg_wwdg_stop().bit_is_set(),
+            rtc: apb1.dbg_rtc_stop().bit_is_set(),
+            lptim: apb1.dbg_lptimer_stop().bit_is_set(),
+            i2c1: apb1.dbg_i2c1_stop().bit_is_set(),
+            i2c2: apb1.dbg_i2c2_stop().bit_is_set(),
+            tim2: apb1.dbg_timer2_stop().bit_is_set(),
+            tim3: apb1.bits() & APB1_FZ_TIM3 != 0,
+            tim6: apb1.dbg_timer6_stop().bit_is_set(),
+            tim7: apb1.bits() & APB1_FZ_TIM7 != 0,
+            tim21: apb2.dbg_timer21_stop().bit_is_set(),
+            tim22: apb2.dbg_timer22_sto().bit_is_set(),
+        }
+    }
+
+    /// Destroys this `DBGMCU` instance, returning the raw peripheral
+    pub fn free(self) -> pac::DBG {
+        self.0
+    }
+}
+
+/// Low-power modes that keep the debug connection alive
+///
+/// Used by [`DBGMCU::set_low_power_debug`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LowPowerDebug {
+    /// Sleep and low-power sleep mode
+    pub sleep: bool,
+    /// Stop mode
+    pub stop: bool,
+    /// Standby mode
+    pub standby: bool,
+}
+
+impl LowPowerDebug {
+    /// Keep the debug connection alive in all low-power modes
+    pub fn all() -> Self {
+        Self {
+            sleep: true,
+            stop: true,
+            standby: true,
+        }
+    }
+}
+
+/// Peripherals that are stopped while the core is halted by a debugger
+///
+/// Used by [`DBGMCU::set_freeze`]. Peripherals that don't exist on a device
+/// are ignored.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Freeze {
+    /// Independent watchdog
+    pub iwdg: bool,
+    /// Window watchdog
+    pub wwdg: bool,
+    /// RTC
+    pub rtc: bool,
+    /// Low-power timer
+    pub lptim: bool,
+    /// SMBus timeout of I2C1
+    pub i2c1: bool,
+    /// SMBus timeout of I2C2
+    pub i2c2: bool,
+    pub tim2: bool,
+    pub tim3: bool,
+    pub tim6: bool,
+    pub tim7: bool,
+    pub tim21: bool,
+    pub tim22: bool,
+}
+
+impl Freeze {
+    /// Stop both watchdogs, so the core can be halted indefinitely
+    pub fn watchdogs() -> Self {
+        Self {
+            iwdg: true,
+            wwdg: true,
+            ..Self::default()
+        }
+    }
+
+    /// Stop all peripherals that support it
+    pub fn all() -> Self {
+        Self {
+            iwdg: true,
+            wwdg: true,
+            rtc: true,
+            lptim: true,
+            i2c1: true,
+            i2c2: true,
+            tim2: true,
+            tim3: true,
+            tim6: true,
+            tim7: true,
+            tim21: true,
+            tim22: true,
+        }
+    }
+}
@@ -1,6 +1,17 @@
 //! Delays
+use core::cmp;
+#[cfg(feature = "async")]
+use core::{future::poll_fn, task::Poll};
+
+#[cfg(feature = "async")]
+use crate::asynch::{InterruptHandler, InterruptWaker};
 use crate::hal::blocking::delay::{DelayMs, DelayUs};
-use crate::rcc::Clocks;
+#[cfg(feature = "io-STM32L071")]
+use crate::pac::TIM3;
+#[cfg(any(feature = "io-STM32L051", feature = "io-STM32L071"))]
+use crate::pac::TIM6;
+use crate::pac::{TIM2, TIM21, TIM22};
+use crate::rcc::{Clocks, Enable, Rcc, Reset};
 use crate::time::MicroSeconds;
 use cast::u32;
 use cortex_m::peripheral::syst::SystClkSource;
@@ -17,8 +28,11 @@ impl DelayExt for SYST {
 }
 
 /// System timer (SysTick) as a delay provider
+///
+/// Delays longer than the 24-bit reload value of the system timer are split
+/// into several periods, so the full range of the delay traits is supported.
 pub struct Delay {
-    ticks_per_us: u32,
+    sys_clk: u32,
     syst: SYST,
 }
 
@@ -26,11 +40,14 @@ impl Delay {
     /// Configures the system timer (SysTick) as a delay provider
     pub fn new(mut syst: SYST, clocks: Clocks) -> Self {
         syst.set_clock_source(SystClkSource::Core);
-        let freq = clocks.sys_clk().0;
-        assert!(freq > 1_000_000_u32);
-        let ticks_per_us = freq / 1_000_000_u32;
-        Delay { syst, ticks_per_us }
+        syst.disable_interrupt();
+        syst.disable_counter();
+        Delay {
+            syst,
+            sys_clk: clocks.sys_clk().0,
+        }
     }
+
     pub fn delay<T>(&mut self, delay: T)
     where
         T: Into<MicroSeconds>,
@@ -42,11 +59,37 @@ impl Delay {
     pub fn free(self) -> SYST {
         self.syst
     }
+
+    /// Waits for at least `us` microseconds, i.e. rounds up to full ticks
+    fn wait_us(&mut self, us: u64) {
+        let cycles = us * u64::from(self.sys_clk);
+        let mut ticks = cycles / 1_000_000;
+        if ticks * 1_000_000 < cycles {
+            ticks += 1;
+        }
+        self.wait_ticks(ticks);
+    }
+
+    fn wait_ticks(&mut self, mut ticks: u64) {
+        // The counter wraps after `reload + 1` ticks, and doesn't wrap at all
+        // for a reload value of 0.
+        const MAX_TICKS: u64 = 0x0100_0000;
+
+        while ticks > 0 {
+            let current = cmp::min(ticks, MAX_TICKS);
+            self.syst.set_reload(cmp::max(current - 1, 1) as u32);
+            self.syst.clear_current();
+            self.syst.enable_counter();
+            while !self.syst.has_wrapped() {}
+            self.syst.disable_counter();
+            ticks -= current;
+        }
+    }
 }
 
 impl DelayMs<u32> for Delay {
     fn delay_ms(&mut self, ms: u32) {
-        self.delay_us(ms * 1_000);
+        self.wait_us(u64::from(ms) * 1_000);
     }
 }
 
@@ -64,21 +107,7 @@ impl DelayMs<u8> for Delay {
 
 impl DelayUs<u32> for Delay {
     fn delay_us(&mut self, us: u32) {
-        const MAX_RVR: u32 = 0x00FF_FFFF;
-        let mut total_rvr = self.ticks_per_us * us;
-        while total_rvr > 0 {
-            let current_rvr = if total_rvr <= MAX_RVR {
-                total_rvr
-            } else {
-                MAX_RVR
-            };
-            self.syst.set_reload(current_rvr);
-            self.syst.clear_current();
-            self.syst.enable_counter();
-            total_rvr -= current_rvr;
-            while !self.syst.has_wrapped() {}
-            self.syst.disable_counter();
-        }
+        self.wait_us(u64::from(us));
     }
 }
 
@@ -93,3 +122,245 @@ impl DelayUs<u8> for Delay {
         self.delay_us(u32(us))
     }
 }
+
+#[cfg(feature = "eh1")]
+impl eh1::delay::DelayNs for Delay {
+    fn delay_ns(&mut self, ns: u32) {
+        // Round up, so we never wait too short.
+        let mut us = ns / 1_000;
+        if us * 1_000 < ns {
+            us += 1;
+        }
+        DelayUs::delay_us(self, us);
+    }
+
+    fn delay_us(&mut self, us: u32) {
+        DelayUs::delay_us(self, us);
+    }
+
+    fn delay_ms(&mut self, ms: u32) {
+        DelayMs::delay_ms(self, ms);
+    }
+}
+
+/// Timer as a delay provider
+///
+/// Runs the timer at the timer clock, in one-pulse mode. This leaves the
+/// system timer (SysTick) free for other uses.
+pub struct TimerDelay<TIM> {
+    tim: TIM,
+    tim_clk: u32,
+}
+
+macro_rules! timer_delays {
+    ($($TIM:ident: ($timclk:ident),)+) => {
+        $(
+            impl TimerDelay<$TIM> {
+                /// Configures a TIM peripheral as a delay provider
+                pub fn new(tim: $TIM, rcc: &mut Rcc) -> Self {
+                    $TIM::enable(rcc);
+                    $TIM::reset(rcc);
+
+                    // Only let counter overflows set the update flag, and stop
+                    // the counter on overflow.
+                    tim.cr1.write(|w| w.urs().set_bit().opm().set_bit());
+
+                    TimerDelay {
+                        tim,
+                        tim_clk: rcc.clocks.$timclk().0,
+                    }
+                }
+
+                /// Releases the TIM peripheral
+                pub fn free(self) -> $TIM {
+                    self.tim
+                }
+
+                /// Returns the number of timer clock cycles in `us`
+                /// microseconds, rounded up
+                fn ticks(&self, us: u32) -> u64 {
+                    let cycles = u64::from(us) * u64::from(self.tim_clk);
+                    let mut ticks = cycles / 1_000_000;
+                    if ticks * 1_000_000 < cycles {
+                        ticks += 1;
+                    }
+                    ticks
+                }
+
+                /// Starts the counter, for a period of `ticks` timer clock
+                /// cycles
+                ///
+                /// `ticks` must be in `1..=0x1_0000`.
+                fn start(&mut self, ticks: u64) {
+                    // The counter runs for ARR + 1 ticks, before it overflows.
+                    // This is only unsafe for some timers, so we need this to
+                    // suppress the warnings.
+                    #[allow(unused_unsafe)]
+                    self.tim.arr.write(|w| unsafe {
+                        w.arr().bits((ticks - 1) as u16)
+                    });
+                    // Load the new value and reset the counter
+                    self.tim.egr.write(|w| w.ug().set_bit());
+                    self.tim.sr.modify(|_, w| w.uif().clear_bit());
+                    self.tim.cr1.modify(|_, w| w.cen().set_bit());
+                }
+            }
+
+            impl DelayUs<u32> for TimerDelay<$TIM> {
+                fn delay_us(&mut self, us: u32) {
+                    let mut remaining = self.ticks(us);
+                    while remaining > 0 {
+                        let ticks = cmp::min(remaining, 0x1_0000);
+                        self.start(ticks);
+
+                        while self.tim.sr.read().uif().bit_is_clear() {}
+
+                        remaining -= ticks;
+                    }
+                }
+            }
+
+            impl DelayUs<u16> for TimerDelay<$TIM> {
+                fn delay_us(&mut self, us: u16) {
+                    self.delay_us(u32(us))
+                }
+            }
+
+            impl DelayUs<u8> for TimerDelay<$TIM> {
+                fn delay_us(&mut self, us: u8) {
+                    self.delay_us(u32(us))
+                }
+            }
+
+            impl DelayMs<u32> for TimerDelay<$TIM> {
+                fn delay_ms(&mut self, ms: u32) {
+                    // Split up the delay, to avoid overflowing the
+                    // microseconds.
+                    const MAX_MS: u32 = u32::MAX / 1_000;
+
+                    let mut remaining = ms;
+                    while remaining > 0 {
+                        let chunk = cmp::min(remaining, MAX_MS);
+                        self.delay_us(chunk * 1_000);
+                        remaining -= chunk;
+                    }
+                }
+            }
+
+            impl DelayMs<u16> for TimerDelay<$TIM> {
+                fn delay_ms(&mut self, ms: u16) {
+                    self.delay_ms(u32(ms));
+                }
+            }
+
+            impl DelayMs<u8> for TimerDelay<$TIM> {
+                fn delay_ms(&mut self, ms: u8) {
+                    self.delay_ms(u32(ms));
+                }
+            }
+
+            #[cfg(feature = "eh1")]
+            impl eh1::delay::DelayNs for TimerDelay<$TIM> {
+                fn delay_ns(&mut self, ns: u32) {
+                    // Round up, so we never wait too short.
+                    let us = ns / 1_000 + u32::from(ns % 1_000 != 0);
+                    DelayUs::delay_us(self, us);
+                }
+
+                fn delay_us(&mut self, us: u32) {
+                    DelayUs::delay_us(self, us);
+                }
+
+                fn delay_ms(&mut self, ms: u32) {
+                    DelayMs::delay_ms(self, ms);
+                }
+            }
+
+            #[cfg(feature = "async")]
+            impl TimerDelay<$TIM> {
+                fn waker() -> &'static InterruptWaker {
+                    static WAKER: InterruptWaker = InterruptWaker::new();
+                    &WAKER
+                }
+
+                async fn wait_us(&mut self, us: u32) {
+                    let mut remaining = self.ticks(us);
+                    while remaining > 0 {
+                        let ticks = cmp::min(remaining, 0x1_0000);
+                        self.start(ticks);
+                        self.tim.dier.modify(|_, w| w.uie().set_bit());
+
+                        poll_fn(|cx| {
+                            Self::waker().register(cx.waker());
+                            if self.tim.sr.read().uif().bit_is_set() {
+                                Poll::Ready(())
+                            } else {
+                                Poll::Pending
+                            }
+                        })
+                        .await;
+
+                        remaining -= ticks;
+                    }
+                }
+            }
+
+            #[cfg(feature = "async")]
+            impl InterruptHandler for $TIM {
+                fn on_interrupt() {
+                    // Safe, as the interrupt is only enabled by the delay,
+                    // which isn't accessing the register at the same time.
+                    let tim = unsafe { &*$TIM::ptr() };
+                    tim.dier.modify(|_, w| w.uie().clear_bit());
+
+                    TimerDelay::<$TIM>::waker().wake();
+                }
+            }
+
+            #[cfg(feature = "async")]
+            impl embedded_hal_async::delay::DelayNs for TimerDelay<$TIM> {
+                async fn delay_ns(&mut self, ns: u32) {
+                    // Round up, so we never wait too short.
+                    let mut us = ns / 1_000;
+                    if us * 1_000 < ns {
+                        us += 1;
+                    }
+                    self.wait_us(us).await;
+                }
+
+                async fn delay_us(&mut self, us: u32) {
+                    self.wait_us(us).await;
+                }
+
+                async fn delay_ms(&mut self, ms: u32) {
+                    // Split up the delay, to avoid overflowing the
+                    // microseconds.
+                    const MAX_MS: u32 = u32::MAX / 1_000;
+
+                    let mut remaining = ms;
+                    while remaining > 0 {
+                        let chunk = cmp::min(remaining, MAX_MS);
+                        self.wait_us(chunk * 1_000).await;
+                        remaining -= chunk;
+                    }
+                }
+            }
+        )+
+    }
+}
+
+timer_delays! {
+    TIM2: (apb1_tim_clk),
+    TIM21: (apb2_tim_clk),
+    TIM22: (apb2_tim_clk),
+}
+
+#[cfg(feature = "io-STM32L071")]
+timer_delays! {
+    TIM3: (apb1_tim_clk),
+}
+
+#[cfg(any(feature = "io-STM32L051", feature = "io-STM32L071"))]
+timer_delays! {
+    TIM6: (apb1_tim_clk),
+}
@@ -9,8 +9,7 @@
 //
 // This should be removed once there are any STM32L0x2 modules making use of
 // DMA.
-#![cfg_attr(not(feature = "stm32l082"), allow(dead_code, unused_imports))]
-
+#![cfg_attr(not(feature = "aes"), allow(dead_code, unused_imports))]
 
 use core::{
     fmt, mem,
@@ -22,27 +21,44 @@ use core::{
 use as_slice::AsSlice;
 
 use crate::{
-    adc,
-    pac::{self, dma1::ch::cr},
-    rcc::Rcc,
+    adc, config,
+    pac::{self, dma1::ch::cr, TIM2},
+    pwm,
+    rcc::{Enable, Rcc, Reset},
 };
 
 #[cfg(any(feature = "io-STM32L051", feature = "io-STM32L071"))]
 use crate::pac::USART1;
 
+#[cfg(feature = "io-STM32L071")]
+use crate::pac::TIM3;
+
 #[cfg(any(
     feature = "io-STM32L021",
     feature = "io-STM32L031",
     feature = "io-STM32L051",
     feature = "io-STM32L071",
 ))]
-use crate::{
-    i2c,
-    pac::{I2C1, I2C2, I2C3, USART2},
-    serial,
-};
+use crate::{pac::USART2, serial};
+
+#[cfg(feature = "stm32l0x2")]
+#[cfg(any(
+    feature = "io-STM32L021",
+    feature = "io-STM32L031",
+    feature = "io-STM32L051",
+    feature = "io-STM32L071",
+))]
+use crate::{i2c, pac::I2C1};
 
-#[cfg(feature = "stm32l082")]
+#[cfg(feature = "stm32l0x2")]
+#[cfg(any(feature = "io-STM32L051", feature = "io-STM32L071"))]
+use crate::pac::I2C2;
+
+#[cfg(feature = "stm32l0x2")]
+#[cfg(feature = "io-STM32L071")]
+use crate::pac::I2C3;
+
+#[cfg(feature = "aes")]
 use crate::aes;
 
 /// Entry point to the DMA API
@@ -58,17 +74,23 @@ impl DMA {
     /// Create an instance of the DMA API
     pub fn new(dma: pac::DMA1, rcc: &mut Rcc) -> Self {
         // Reset peripheral
-        rcc.rb.ahbrstr.modify(|_, w| w.dmarst().set_bit());
-        rcc.rb.ahbrstr.modify(|_, w| w.dmarst().clear_bit());
+        pac::DMA1::reset(rcc);
 
         // Enable peripheral clock
-        rcc.rb.ahbenr.modify(|_, w| w.dmaen().set_bit());
+        pac::DMA1::enable(rcc);
 
         Self {
             handle: Handle { dma },
             channels: Channels::new(),
         }
     }
+
+    /// Releases the DMA peripheral
+    ///
+    /// As this requires all channels, no transfer can be in progress.
+    pub fn release(self) -> pac::DMA1 {
+        self.handle.dma
+    }
 }
 
 /// Handle to the DMA peripheral
@@ -96,11 +118,9 @@ where
     /// If this is used to prepare a peripheral-to-memory transfer, the caller
     /// must make sure that the buffer can be written to safely.
     ///
-    /// # Panics
-    ///
-    /// Panics, if the length of the buffer is larger than `u16::max_value()`.
-    ///
-    /// Panics, if the buffer is not aligned to the word size.
+    /// Returns an error, if the buffer is shorter than `num_words`, if
+    /// `num_words` is larger than `u16::max_value()`, or if the buffer is not
+    /// aligned to the word size.
     pub(crate) unsafe fn new<Word>(
         handle: &mut Handle,
         target: T,
@@ -111,15 +131,22 @@ where
         priority: Priority,
         dir: Direction,
         circular: bool,
-    ) -> Self
+    ) -> SetupResult<Self, T, C, B>
     where
         B: Deref,
         B::Target: Buffer<Word>,
         Word: SupportedWordSize,
     {
-        assert!(buffer.len() >= num_words);
-        assert!(num_words <= u16::max_value() as usize);
-        assert_eq!(buffer.as_ptr().align_offset(mem::size_of::<Word>()), 0);
+        if let Err(err) = check_buffer(&*buffer, num_words) {
+            return Err((
+                TransferResources {
+                    target,
+                    channel,
+                    buffer,
+                },
+                err,
+            ));
+        }
 
         channel.select_target(handle, &target);
         channel.set_peripheral_address(handle, address);
@@ -127,14 +154,14 @@ where
         channel.set_transfer_len(handle, num_words as u16);
         channel.configure::<Word>(handle, priority.0, dir.0, circular);
 
-        Transfer {
+        Ok(Transfer {
             res: TransferResources {
                 target,
                 channel,
                 buffer,
             },
             _state: Ready,
-        }
+        })
     }
 
     /// Enables the provided interrupts
@@ -161,6 +188,36 @@ where
     }
 }
 
+impl<T, C, B, State> Transfer<T, C, B, State>
+where
+    C: Channel,
+{
+    /// Starts listening for an interrupt event of the channel
+    pub fn listen(&mut self, event: Event) {
+        self.res.channel.listen(event);
+    }
+
+    /// Stops listening for an interrupt event of the channel
+    pub fn unlisten(&mut self, event: Event) {
+        self.res.channel.unlisten(event);
+    }
+
+    /// Returns whether the flag of an interrupt event of the channel is set
+    ///
+    /// This doesn't depend on whether the event is listened for.
+    pub fn is_pending(&self, event: Event) -> bool {
+        self.res.channel.is_pending(event)
+    }
+
+    /// Clears the flag of an interrupt event of the channel
+    ///
+    /// Be careful when calling this for a transfer that has been started, as
+    /// [`Transfer::wait`] depends on the flags.
+    pub fn clear(&mut self, event: Event) {
+        self.res.channel.clear(event);
+    }
+}
+
 impl<T, C, B> Transfer<T, C, B, Started>
 where
     C: Channel,
@@ -229,6 +286,29 @@ impl<T, C, B> fmt::Debug for TransferResources<T, C, B> {
     }
 }
 
+/// The resources of a transfer that couldn't be prepared, and the reason
+pub type SetupError<T, C, B> = (TransferResources<T, C, B>, config::Error);
+
+/// The result of preparing a transfer
+///
+/// On error, the resources are returned to the caller.
+pub type SetupResult<R, T, C, B> = Result<R, SetupError<T, C, B>>;
+
+/// Checks whether `num_words` of `buffer` can be transferred
+pub(crate) fn check_buffer<Word, B>(buffer: &B, num_words: usize) -> Result<(), config::Error>
+where
+    B: ?Sized + Buffer<Word>,
+{
+    if buffer.len() < num_words || num_words > u16::max_value() as usize {
+        return Err(config::Error::BufferLength);
+    }
+    if buffer.as_ptr().align_offset(mem::size_of::<Word>()) != 0 {
+        return Err(config::Error::BufferAlignment);
+    }
+
+    Ok(())
+}
+
 /// The priority of the DMA transfer
 pub struct Priority(cr::PL_A);
 
@@ -263,7 +343,8 @@ impl Direction {
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct Error;
 
 pub trait Channel: Sized {
@@ -275,6 +356,10 @@ pub trait Channel: Sized {
     where
         Word: SupportedWordSize;
     fn enable_interrupts(&self, interrupts: Interrupts);
+    fn listen(&self, event: Event);
+    fn unlisten(&self, event: Event);
+    fn is_pending(&self, event: Event) -> bool;
+    fn clear(&self, event: Event);
     fn start(&self);
     fn is_active(&self) -> bool;
     fn clear_complete_flag(&self);
@@ -385,6 +470,53 @@ macro_rules! impl_channel {
                     );
                 }
 
+                fn listen(&self, event: Event) {
+                    // Safe, because we're only accessing a register that this
+                    // channel has exclusive access to.
+                    let ccr = &unsafe { &*pac::DMA1::ptr() }.$chfield.cr;
+
+                    ccr.modify(|_, w| match event {
+                        Event::TransferComplete => w.tcie().enabled(),
+                        Event::HalfTransfer => w.htie().enabled(),
+                        Event::TransferError => w.teie().enabled(),
+                    });
+                }
+
+                fn unlisten(&self, event: Event) {
+                    // Safe, because we're only accessing a register that this
+                    // channel has exclusive access to.
+                    let ccr = &unsafe { &*pac::DMA1::ptr() }.$chfield.cr;
+
+                    ccr.modify(|_, w| match event {
+                        Event::TransferComplete => w.tcie().disabled(),
+                        Event::HalfTransfer => w.htie().disabled(),
+                        Event::TransferError => w.teie().disabled(),
+                    });
+                }
+
+                fn is_pending(&self, event: Event) -> bool {
+                    // Safe, as we're only doing an atomic read.
+                    let isr = unsafe { &*pac::DMA1::ptr() }.isr.read();
+
+                    match event {
+                        Event::TransferComplete => isr.$tcif().bit_is_set(),
+                        Event::HalfTransfer => isr.$htif().bit_is_set(),
+                        Event::TransferError => isr.$teif().bit_is_set(),
+                    }
+                }
+
+                fn clear(&self, event: Event) {
+                    // Safe, as we're only doing an atomic write to a stateless
+                    // register.
+                    let dma = unsafe { &*pac::DMA1::ptr() };
+
+                    dma.ifcr.write(|w| match event {
+                        Event::TransferComplete => w.$ctcif().set_bit(),
+                        Event::HalfTransfer => w.$chtif().set_bit(),
+                        Event::TransferError => w.$cteif().set_bit(),
+                    });
+                }
+
                 fn start(&self) {
                     // Safe, because we're only accessing a register that this
                     // channel has exclusive access to.
@@ -505,6 +637,17 @@ impl_target!(
     adc::DmaToken, Channel2, 0;
 );
 
+impl_target!(
+    // TIM2_UP
+    pwm::DmaToken<TIM2>, Channel2, 8;
+);
+
+#[cfg(feature = "io-STM32L071")]
+impl_target!(
+    // TIM3_UP
+    pwm::DmaToken<TIM3>, Channel3, 10;
+);
+
 #[cfg(any(feature = "io-STM32L051", feature = "io-STM32L071"))]
 impl_target!(
     // USART1
@@ -541,11 +684,19 @@ impl_target!(
     i2c::Rx<I2C1>, Channel3, 6;
     i2c::Tx<I2C1>, Channel6, 6;
     i2c::Rx<I2C1>, Channel7, 6;
+);
 
+#[cfg(feature = "stm32l0x2")]
+#[cfg(any(feature = "io-STM32L051", feature = "io-STM32L071"))]
+impl_target!(
     // I2C2
     i2c::Tx<I2C2>, Channel4, 7;
     i2c::Rx<I2C2>, Channel5, 7;
+);
 
+#[cfg(feature = "stm32l0x2")]
+#[cfg(feature = "io-STM32L071")]
+impl_target!(
     // I2C3
     i2c::Tx<I2C3>, Channel2, 14;
     i2c::Rx<I2C3>, Channel3, 14;
@@ -554,7 +705,7 @@ impl_target!(
 );
 
 // See STM32L0x2 Reference Manual, table 51 (page 267).
-#[cfg(feature = "stm32l082")]
+#[cfg(feature = "aes")]
 impl_target!(
     aes::Tx, Channel1, 11;
     aes::Tx, Channel5, 11;
@@ -635,7 +786,22 @@ impl SupportedWordSize for u32 {
     }
 }
 
+/// Interrupt events of a DMA channel
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Event {
+    /// The transfer has completed (TCIF)
+    TransferComplete,
+
+    /// Half of the transfer has completed (HTIF)
+    HalfTransfer,
+
+    /// A transfer error has occurred (TEIF)
+    TransferError,
+}
+
 #[derive(Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct Interrupts {
     pub transfer_error: bool,
     pub half_transfer: bool,
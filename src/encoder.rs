@@ -0,0 +1,115 @@
+//! Quadrature encoder interface
+//!
+//! Uses the encoder mode of the general-purpose timers, to count the edges of
+//! two quadrature signals connected to channels 1 and 2. The counter counts up
+//! or down, depending on the phase relation of the signals.
+
+use crate::capture::{self, Channel as _, Filter, Mapping};
+use crate::hal::{self, Direction};
+use crate::pac::tim2;
+use crate::pwm::{Instance, Pin, C1, C2};
+use crate::rcc::Rcc;
+
+/// The edges that are counted
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mode {
+    /// Count on edges of TI1, depending on the level of TI2
+    Ti1 = 0b001,
+    /// Count on edges of TI2, depending on the level of TI1
+    Ti2 = 0b010,
+    /// Count on edges of both inputs
+    ///
+    /// This results in 4 counts per cycle of the input signals.
+    Ti1AndTi2 = 0b011,
+}
+
+pub struct Encoder<I, P1, P2> {
+    instance: I,
+    pins: (P1, P2),
+}
+
+impl<I, P1, P2> Encoder<I, P1, P2>
+where
+    I: Instance,
+    P1: Pin<I, C1>,
+    P2: Pin<I, C2>,
+{
+    /// Starts counting the edges of the signals on the given pins
+    ///
+    /// The filter is applied to both inputs. Mechanical encoders typically
+    /// need some filtering, to suppress contact bounce.
+    pub fn new(timer: I, pins: (P1, P2), mode: Mode, filter: Filter, rcc: &mut Rcc) -> Self {
+        timer.enable(rcc);
+        pins.0.setup();
+        pins.1.setup();
+
+        // Safe, as we own the timer instance.
+        let tim = unsafe { &*I::ptr() };
+
+        let config = capture::Config::default()
+            .mapping(Mapping::Direct)
+            .filter(filter);
+        C1::configure(tim, &config);
+        C2::configure(tim, &config);
+
+        tim.smcr.modify(|_, w| w.sms().bits(mode as u8));
+        tim.psc.write(|w| w.psc().bits(0));
+        tim.arr.write(|w| w.arr().bits(u16::MAX));
+        tim.cnt.write(|w| w.cnt().bits(0));
+        tim.cr1.modify(|_, w| w.cen().set_bit());
+
+        Self {
+            instance: timer,
+            pins,
+        }
+    }
+
+    /// Returns the current count
+    pub fn count(&self) -> u16 {
+        self.tim().cnt.read().cnt().bits()
+    }
+
+    /// Sets the current count
+    pub fn set_count(&mut self, count: u16) {
+        self.tim().cnt.write(|w| w.cnt().bits(count));
+    }
+
+    /// Returns the direction the counter last counted in
+    pub fn direction(&self) -> Direction {
+        if self.tim().cr1.read().dir().bit_is_set() {
+            Direction::Downcounting
+        } else {
+            Direction::Upcounting
+        }
+    }
+
+    /// Releases the timer and the pins
+    pub fn free(self) -> (I, (P1, P2)) {
+        self.tim().cr1.modify(|_, w| w.cen().clear_bit());
+        self.tim().smcr.modify(|_, w| w.sms().disabled());
+
+        (self.instance, self.pins)
+    }
+
+    fn tim(&self) -> &tim2::RegisterBlock {
+        // Safe, as we own the timer instance.
+        unsafe { &*I::ptr() }
+    }
+}
+
+impl<I, P1, P2> hal::Qei for Encoder<I, P1, P2>
+where
+    I: Instance,
+    P1: Pin<I, C1>,
+    P2: Pin<I, C2>,
+{
+    type Count = u16;
+
+    fn count(&self) -> u16 {
+        Encoder::count(self)
+    }
+
+    fn direction(&self) -> Direction {
+        Encoder::direction(self)
+    }
+}
@@ -0,0 +1,132 @@
+//! Crate-level error type
+//!
+//! [`Error`] wraps the error types of the peripheral APIs, tagged with the
+//! peripheral they come from. As it implements `From` for all of them, errors
+//! from different peripherals can be propagated with `?` in application code:
+//!
+//! ``` ignore
+//! fn sample(i2c: &mut I2c<I2C1, SDA, SCL>, serial: &mut Tx<USART2>) -> Result<(), stm32l0xx_hal::Error> {
+//!     let mut buffer = [0; 2];
+//!     i2c.write_read(ADDRESS, &[REGISTER], &mut buffer)?;
+//!     for byte in buffer.iter() {
+//!         block!(serial.write(*byte))?;
+//!     }
+//!     Ok(())
+//! }
+//! ```
+
+#[cfg(feature = "aes")]
+use crate::aes;
+use crate::{adc, capture, config, dma, flash, spi, storage, timeout};
+#[cfg(any(
+    feature = "io-STM32L021",
+    feature = "io-STM32L031",
+    feature = "io-STM32L051",
+    feature = "io-STM32L071",
+))]
+use crate::{i2c, serial};
+#[cfg(any(feature = "stm32l0x2", feature = "stm32l0x3"))]
+use crate::{rng, tsc};
+
+/// Error of any peripheral API
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    Adc(adc::Error),
+    #[cfg(feature = "aes")]
+    Aes(aes::Error),
+    Capture(capture::Error),
+    Config(config::Error),
+    Dma(dma::Error),
+    Flash(flash::Error),
+    #[cfg(any(
+        feature = "io-STM32L021",
+        feature = "io-STM32L031",
+        feature = "io-STM32L051",
+        feature = "io-STM32L071",
+    ))]
+    I2c(i2c::Error),
+    #[cfg(any(feature = "stm32l0x2", feature = "stm32l0x3"))]
+    Rng(rng::Error),
+    #[cfg(any(
+        feature = "io-STM32L021",
+        feature = "io-STM32L031",
+        feature = "io-STM32L051",
+        feature = "io-STM32L071",
+    ))]
+    Serial(serial::Error),
+    Spi(spi::Error),
+    Storage(storage::Error),
+    /// A blocking operation timed out, see [`timeout`]
+    Timeout,
+    #[cfg(any(feature = "stm32l0x2", feature = "stm32l0x3"))]
+    Tsc(tsc::Error),
+}
+
+macro_rules! impl_from {
+    ($($variant:ident: $error:ty,)+) => {
+        $(
+            impl From<$error> for Error {
+                fn from(error: $error) -> Self {
+                    Error::$variant(error)
+                }
+            }
+        )+
+    };
+}
+
+impl_from!(
+    Adc: adc::Error,
+    Capture: capture::Error,
+    Config: config::Error,
+    Dma: dma::Error,
+    Flash: flash::Error,
+    Spi: spi::Error,
+    Storage: storage::Error,
+);
+
+#[cfg(feature = "aes")]
+impl_from!(
+    Aes: aes::Error,
+);
+
+#[cfg(any(
+    feature = "io-STM32L021",
+    feature = "io-STM32L031",
+    feature = "io-STM32L051",
+    feature = "io-STM32L071",
+))]
+impl_from!(
+    I2c: i2c::Error,
+    Serial: serial::Error,
+);
+
+#[cfg(any(
+    feature = "io-STM32L021",
+    feature = "io-STM32L031",
+    feature = "io-STM32L051",
+    feature = "io-STM32L071",
+))]
+impl From<serial::InvalidConfig> for Error {
+    fn from(error: serial::InvalidConfig) -> Self {
+        Error::Config(error.into())
+    }
+}
+
+#[cfg(any(feature = "stm32l0x2", feature = "stm32l0x3"))]
+impl_from!(
+    Rng: rng::Error,
+    Tsc: tsc::Error,
+);
+
+impl<E> From<timeout::Error<E>> for Error
+where
+    E: Into<Error>,
+{
+    fn from(error: timeout::Error<E>) -> Self {
+        match error {
+            timeout::Error::Timeout => Error::Timeout,
+            timeout::Error::Bus(error) => error.into(),
+        }
+    }
+}
@@ -59,6 +59,7 @@ impl Exti {
             gpio::Port::PB => 1,
             gpio::Port::PC => 2,
             gpio::Port::PD => 3,
+            #[cfg(feature = "io-STM32L071")]
             gpio::Port::PE => 4,
             gpio::Port::PH => {
                 assert!((line < 2) | (line == 9) | (line == 10));
@@ -112,16 +113,9 @@ impl Exti {
 
         let bm: u32 = 1 << line;
 
-        unsafe {
-            match edge {
-                TriggerEdge::Rising => self.raw.rtsr.modify(|r, w| w.bits(r.bits() | bm)),
-                TriggerEdge::Falling => self.raw.ftsr.modify(|r, w| w.bits(r.bits() | bm)),
-                TriggerEdge::Both => {
-                    self.raw.rtsr.modify(|r, w| w.bits(r.bits() | bm));
-                    self.raw.ftsr.modify(|r, w| w.bits(r.bits() | bm));
-                }
-            }
+        self.set_trigger_edge(bm, edge);
 
+        unsafe {
             self.raw.imr.modify(|r, w| w.bits(r.bits() | bm));
         }
     }
@@ -134,20 +128,30 @@ impl Exti {
     pub fn listen_configurable(&mut self, line: ConfigurableLine, edge: TriggerEdge) {
         let bm: u32 = 1 << line.raw_line();
 
-        unsafe {
-            match edge {
-                TriggerEdge::Rising => self.raw.rtsr.modify(|r, w| w.bits(r.bits() | bm)),
-                TriggerEdge::Falling => self.raw.ftsr.modify(|r, w| w.bits(r.bits() | bm)),
-                TriggerEdge::Both => {
-                    self.raw.rtsr.modify(|r, w| w.bits(r.bits() | bm));
-                    self.raw.ftsr.modify(|r, w| w.bits(r.bits() | bm));
-                }
-            }
+        self.set_trigger_edge(bm, edge);
 
+        unsafe {
             self.raw.imr.modify(|r, w| w.bits(r.bits() | bm));
         }
     }
 
+    /// Starts generating events on a configurable interrupt line.
+    ///
+    /// Unlike an interrupt, an event wakes up the microcontroller from a
+    /// low-power mode entered with `WFE`, without an interrupt handler being
+    /// executed, and without setting the pending bit. The edges that should
+    /// trigger the event can be configured with `edge`.
+    #[inline]
+    pub fn listen_configurable_event(&mut self, line: ConfigurableLine, edge: TriggerEdge) {
+        let bm: u32 = 1 << line.raw_line();
+
+        self.set_trigger_edge(bm, edge);
+
+        unsafe {
+            self.raw.emr.modify(|r, w| w.bits(r.bits() | bm));
+        }
+    }
+
     /// Starts listening on a "direct" interrupt line.
     #[inline]
     pub fn listen_direct(&mut self, line: DirectLine) {
@@ -158,18 +162,33 @@ impl Exti {
         }
     }
 
-    /// Disables the interrupt on `line`.
+    /// Disables the interrupt and the event on `line`.
     pub fn unlisten<L: ExtiLine>(&mut self, line: L) {
         let bm = 1 << line.raw_line();
 
         // Safety: We clear the correct bit and have unique ownership of the EXTI registers here.
         unsafe {
             self.raw.imr.modify(|r, w| w.bits(r.bits() & !bm));
+            self.raw.emr.modify(|r, w| w.bits(r.bits() & !bm));
             self.raw.rtsr.modify(|r, w| w.bits(r.bits() & !bm));
             self.raw.ftsr.modify(|r, w| w.bits(r.bits() & !bm));
         }
     }
 
+    #[inline]
+    fn set_trigger_edge(&mut self, bm: u32, edge: TriggerEdge) {
+        unsafe {
+            match edge {
+                TriggerEdge::Rising => self.raw.rtsr.modify(|r, w| w.bits(r.bits() | bm)),
+                TriggerEdge::Falling => self.raw.ftsr.modify(|r, w| w.bits(r.bits() | bm)),
+                TriggerEdge::Both => {
+                    self.raw.rtsr.modify(|r, w| w.bits(r.bits() | bm));
+                    self.raw.ftsr.modify(|r, w| w.bits(r.bits() | bm));
+                }
+            }
+        }
+    }
+
     /// Marks `line` as "pending".
     ///
     /// This will cause an interrupt if the EXTI was previously configured to
@@ -6,7 +6,7 @@ use cortex_m::interrupt;
 
 use crate::{
     pac::{self, flash::acr::LATENCY_A},
-    rcc::Rcc,
+    rcc::{Enable, Rcc, Reset},
 };
 
 /// The first address of flash memory
@@ -15,6 +15,23 @@ pub const FLASH_START: u32 = 0x0800_0000;
 /// The size of a Flash memory page, in bytes
 pub const PAGE_SIZE: u32 = 128;
 
+/// The size of a Flash memory sector, in bytes
+///
+/// Sectors are the unit of the Flash write protection.
+pub const SECTOR_SIZE: u32 = 4096;
+
+/// The address of the option bytes containing the read protection
+const OPTION_BYTES_RDP: u32 = 0x1FF8_0000;
+
+/// The address of the option bytes containing the user options
+const OPTION_BYTES_USER: u32 = 0x1FF8_0004;
+
+/// The address of the option bytes containing the write protection
+///
+/// WRPROT1 is split into two half-words at this and the next address,
+/// followed by WRPROT2 on Category 5 devices.
+const OPTION_BYTES_WRPROT: u32 = 0x1FF8_0008;
+
 /// Entry point to the non-volatile memory (NVM) API
 pub struct FLASH {
     flash: pac::FLASH,
@@ -36,12 +53,14 @@ impl FLASH {
         let flash_size_in_kb = flash_size_in_kb();
         let flash_end = FLASH_START + flash_size_in_kb * 1024;
 
-        // As of this writing, this module is only enabled for STM32L0x2.
-        // According to the STM32L0x2 reference manual, section 1.4, the
-        // following should determine whether this is a Category 5 device.
-        // Please make sure to adapt this when porting this module to other
-        // targets.
-        let is_category_5 = cfg!(any(feature = "stm32l072", feature = "stm32l082"));
+        // The io-* features correspond to the product categories, which
+        // determine the size of the EEPROM. See the reference manuals,
+        // section 1.4.
+        let is_category_5 = cfg!(any(
+            feature = "io-STM32L071",
+            feature = "stm32l072",
+            feature = "stm32l082"
+        ));
 
         // Determine the start of the EEPROM, according to the tables in the
         // STM32L0x2 reference manual, section 3.3.1.
@@ -57,16 +76,19 @@ impl FLASH {
         // first byte after it.
         let eeprom_end = if is_category_5 {
             0x0808_1800
+        } else if cfg!(feature = "io-STM32L021") {
+            0x0808_0200
+        } else if cfg!(feature = "io-STM32L031") {
+            0x0808_0400
         } else {
             0x0808_0800
         };
 
         // Reset the peripheral interface
-        rcc.rb.ahbrstr.modify(|_, w| w.mifrst().set_bit());
-        rcc.rb.ahbrstr.modify(|_, w| w.mifrst().clear_bit());
+        pac::FLASH::reset(rcc);
 
         // Enable the peripheral interface
-        rcc.rb.ahbenr.modify(|_, w| w.mifen().set_bit());
+        pac::FLASH::enable(rcc);
 
         Self {
             flash,
@@ -84,12 +106,52 @@ impl FLASH {
     ///
     /// This is explained, for example, in the STM32L0x2 Reference Manual,
     /// section 3.3.3.
+    ///
+    /// `Rcc::freeze` and `Rcc::reconfigure` already configure the required
+    /// number of wait states, so you only need this, if you want to add a
+    /// wait state that isn't required.
     pub fn set_wait_states(&mut self, wait_states: LATENCY_A) {
         self.flash
             .acr
             .modify(|_, w| w.latency().variant(wait_states));
     }
 
+    /// Returns the number of wait states
+    pub fn wait_states(&self) -> LATENCY_A {
+        self.flash.acr.read().latency().variant()
+    }
+
+    /// Enables or disables the prefetch buffer (PRFTEN)
+    ///
+    /// The prefetch buffer only has an effect, if a wait state is configured.
+    /// It is configured by `Rcc::freeze`, see `rcc::Config::flash_prefetch`.
+    pub fn set_prefetch(&mut self, enabled: bool) {
+        self.flash.acr.modify(|_, w| w.prften().bit(enabled));
+    }
+
+    /// Enables or disables the pre-read (PRE_READ)
+    ///
+    /// With pre-read enabled, the next 64 bits are read from memory in
+    /// advance, even without a wait state. This improves the performance of
+    /// sequential code execution, at the cost of a higher current
+    /// consumption. Requires the buffers to be enabled.
+    pub fn set_pre_read(&mut self, enabled: bool) {
+        self.flash.acr.modify(|_, w| w.pre_read().bit(enabled));
+    }
+
+    /// Enables or disables the Flash read buffers (inverse of DISAB_BUF)
+    ///
+    /// The buffers are enabled by default. Disabling them reduces the
+    /// performance and disables the pre-read.
+    pub fn set_buffers(&mut self, enabled: bool) {
+        self.flash.acr.modify(|_, w| {
+            if !enabled {
+                w.pre_read().clear_bit();
+            }
+            w.disab_buf().bit(!enabled)
+        });
+    }
+
     /// Erases a page of flash memory
     ///
     /// Attention: You must make sure that your program is not executed from the
@@ -179,6 +241,31 @@ impl FLASH {
     /// - `address` is aligned to a half-page boundary (16 words, 64 bytes)
     /// - `words` has a length of 16
     pub fn write_flash_half_page(&mut self, address: *mut u32, words: &[u32]) -> Result {
+        if words.len() != 16 {
+            panic!("`words` is not exactly a half-page of memory");
+        }
+
+        self.write_flash(address, words)
+    }
+
+    /// Writes any number of half-pages (16 words each) of Flash memory
+    ///
+    /// This uses the half-page programming mode, which is much faster than
+    /// programming word by word, as each half-page is programmed in the time
+    /// it takes to program a single word. This makes it the method of choice
+    /// for writing large amounts of data, like firmware updates.
+    ///
+    /// The memory written to must have been erased before, otherwise this
+    /// method will return an error.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic, unless all of the following is true:
+    /// - `address` points to Flash memory, and so does the end of the written
+    ///   range
+    /// - `address` is aligned to a half-page boundary (16 words, 64 bytes)
+    /// - the length of `words` is a multiple of 16
+    pub fn write_flash(&mut self, address: *mut u32, words: &[u32]) -> Result {
         self.unlock(|self_| {
             let memory = self_.verify_address(address);
 
@@ -188,8 +275,11 @@ impl FLASH {
             if address as u32 & 0x3f != 0 {
                 panic!("Address is not aligned to half-page boundary");
             }
-            if words.len() != 16 {
-                panic!("`words` is not exactly a half-page of memory");
+            if words.len() & 0xf != 0 {
+                panic!("`words` is not a multiple of a half-page of memory");
+            }
+            if address as u32 + words.len() as u32 * 4 > self_.flash_end {
+                panic!("`words` doesn't fit into Flash memory");
             }
 
             // Wait, while the memory interface is busy.
@@ -205,45 +295,333 @@ impl FLASH {
                 w
             });
 
-            // We absoluty can't have any access to Flash while preparing the
-            // write, or the process will be interrupted. This includes any
-            // access to the vector table or interrupt handlers that might be
-            // caused by an interrupt.
-            interrupt::free(|_| {
-                // Safe, because we've verified the valididty of `address` and
-                // the length of `words`.
-                unsafe {
-                    write_half_page(address, words.as_ptr());
-                }
-            });
+            for (i, half_page) in words.chunks(16).enumerate() {
+                // We absoluty can't have any access to Flash while preparing
+                // the write, or the process will be interrupted. This
+                // includes any access to the vector table or interrupt
+                // handlers that might be caused by an interrupt.
+                interrupt::free(|_| {
+                    // Safe, because we've verified the valididty of `address`
+                    // and the length of `words`.
+                    unsafe {
+                        write_half_page(address.add(i * 16), half_page.as_ptr());
+                    }
+                });
+
+                // Wait for operation to complete
+                while self_.flash.sr.read().bsy().is_active() {}
+
+                self_.check_errors()?;
+            }
+
+            Ok(())
+
+            // No need to reset PECR flags, that's done by `unlock`.
+        })
+    }
+
+    /// Returns the brown out reset (BOR) level
+    ///
+    /// This is the level that was loaded from the option bytes during the
+    /// last reset.
+    pub fn bor_level(&self) -> BorLevel {
+        match self.flash.optr.read().bor_lev().bits() {
+            0b1000 => BorLevel::Level1,
+            0b1001 => BorLevel::Level2,
+            0b1010 => BorLevel::Level3,
+            0b1011 => BorLevel::Level4,
+            0b1100 => BorLevel::Level5,
+            _ => BorLevel::Off,
+        }
+    }
+
+    /// Programs the brown out reset (BOR) level into the option bytes
+    ///
+    /// The new level only takes effect after the option bytes have been
+    /// reloaded, either by a power-on reset, or by calling
+    /// [`FLASH::reload_option_bytes`].
+    pub fn set_bor_level(&mut self, level: BorLevel) -> Result {
+        let user = self.read_option_bytes(OPTION_BYTES_USER);
+        let user = user & !0xf | level as u16;
+
+        self.write_option_bytes(OPTION_BYTES_USER as *mut u32, user)
+    }
+
+    /// Returns the read protection (RDP) level
+    ///
+    /// This is the level that was loaded from the option bytes during the
+    /// last reset.
+    pub fn read_protection(&self) -> ReadProtection {
+        ReadProtection::from_bits(self.flash.optr.read().rdprot().bits())
+    }
+
+    /// Programs the read protection (RDP) level into the option bytes
+    ///
+    /// The new level only takes effect after the option bytes have been
+    /// reloaded, either by a power-on reset, or by calling
+    /// [`FLASH::reload_option_bytes`].
+    ///
+    /// **Be careful!** Changing the read protection has drastic consequences:
+    /// - Going back from level 1 to level 0 causes a mass erase of the Flash
+    ///   memory and the EEPROM, including the program calling this method.
+    /// - Level 2 can **never** be reverted. It permanently disables the debug
+    ///   interface and the boot loader, and the option bytes can no longer be
+    ///   changed. The only way to update the device afterwards is through
+    ///   code that is already running on it.
+    ///
+    /// See STM32L0x2 reference manual, section 3.4.1.
+    pub fn set_read_protection(&mut self, level: ReadProtection) -> Result {
+        let rdp = self.read_option_bytes(OPTION_BYTES_RDP);
+        let rdp = rdp & !0xff | level as u16;
+
+        self.write_option_bytes(OPTION_BYTES_RDP as *mut u32, rdp)
+    }
+
+    /// Returns whether the write protection bits control read protection
+    ///
+    /// If the WPRMOD bit is set, the WRPROT bits select sectors that are
+    /// protected against reads (PCROP), instead of writes.
+    pub fn pcrop_mode(&self) -> bool {
+        self.flash.optr.read().wprmod().bit_is_set()
+    }
+
+    /// Programs the WPRMOD bit into the option bytes
+    ///
+    /// See [`FLASH::pcrop_mode`]. Takes effect after the option bytes have
+    /// been reloaded. The WPRMOD bit can only be cleared again while
+    /// switching from read protection level 1 to level 0.
+    pub fn set_pcrop_mode(&mut self, enabled: bool) -> Result {
+        let rdp = self.read_option_bytes(OPTION_BYTES_RDP);
+        let rdp = rdp & !(1 << 8) | (enabled as u16) << 8;
+
+        self.write_option_bytes(OPTION_BYTES_RDP as *mut u32, rdp)
+    }
+
+    /// Returns the write protected sectors
+    ///
+    /// Bit `n` of the returned mask is set, if sector `n` (the Flash memory
+    /// from `FLASH_START + n * SECTOR_SIZE` to the start of the next sector)
+    /// is protected against writes and erasure. This is the protection that
+    /// was loaded from the option bytes during the last reset.
+    ///
+    /// If [`FLASH::pcrop_mode`] is enabled, the bits select sectors that are
+    /// protected against reads instead.
+    pub fn write_protection(&self) -> u64 {
+        let wrprot1 = self.flash.wrprot1.read().bits() as u64;
+        let wrprot2 = self.flash.wrprot2.read().bits() as u64 & 0xffff;
+
+        wrprot1 | wrprot2 << 32
+    }
+
+    /// Programs the write protected sectors into the option bytes
+    ///
+    /// See [`FLASH::write_protection`] for the meaning of `sectors`. This
+    /// can be used, for example, by a resident boot loader to protect its
+    /// own sectors against accidental erasure by the application.
+    ///
+    /// The new protection only takes effect after the option bytes have been
+    /// reloaded, either by a power-on reset, or by calling
+    /// [`FLASH::reload_option_bytes`].
+    ///
+    /// # Panics
+    ///
+    /// Panics, if `sectors` selects sectors beyond the end of Flash memory.
+    pub fn set_write_protection(&mut self, sectors: u64) -> Result {
+        let num_sectors = (self.flash_end - FLASH_START) / SECTOR_SIZE;
+        assert!(sectors >> num_sectors == 0);
+
+        // Each option byte word holds 16 bits of the protection mask.
+        for first_sector in (0..num_sectors).step_by(16) {
+            let address = OPTION_BYTES_WRPROT + first_sector / 4;
+            let value = (sectors >> first_sector) as u16;
+
+            if self.read_option_bytes(address) != value {
+                self.write_option_bytes(address as *mut u32, value)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the user options
+    ///
+    /// These are the options that were loaded from the option bytes during
+    /// the last reset.
+    pub fn user_options(&self) -> UserOptions {
+        let optr = self.flash.optr.read().bits();
+
+        UserOptions {
+            hardware_watchdog: optr & (1 << 20) == 0,
+            reset_on_stop: optr & (1 << 21) == 0,
+            reset_on_standby: optr & (1 << 22) == 0,
+        }
+    }
+
+    /// Programs the user options into the option bytes
+    ///
+    /// The other user option bytes (BOR level, boot configuration) are left
+    /// unchanged. The new options only take effect after the option bytes
+    /// have been reloaded, either by a power-on reset, or by calling
+    /// [`FLASH::reload_option_bytes`].
+    pub fn set_user_options(&mut self, options: UserOptions) -> Result {
+        let user = self.read_option_bytes(OPTION_BYTES_USER);
+        let user = user & !(0b111 << 4)
+            | (!options.hardware_watchdog as u16) << 4
+            | (!options.reset_on_stop as u16) << 5
+            | (!options.reset_on_standby as u16) << 6;
+
+        self.write_option_bytes(OPTION_BYTES_USER as *mut u32, user)
+    }
+
+    /// Returns the first address of the second Flash bank
+    ///
+    /// Category 5 devices with more than 64 Kbytes of Flash memory have two
+    /// banks of equal size.
+    #[cfg(any(feature = "io-STM32L071", feature = "stm32l072", feature = "stm32l082"))]
+    pub fn bank2_start(&self) -> u32 {
+        FLASH_START + (self.flash_end - FLASH_START) / 2
+    }
+
+    /// Returns whether the device boots from the second Flash bank (BFB2)
+    ///
+    /// This is the value that was loaded from the option bytes during the
+    /// last reset.
+    #[cfg(any(feature = "io-STM32L071", feature = "stm32l072", feature = "stm32l082"))]
+    pub fn boot_from_bank2(&self) -> bool {
+        self.flash.optr.read().bits() & (1 << 23) != 0
+    }
+
+    /// Programs whether the device boots from the second Flash bank (BFB2)
+    ///
+    /// If enabled, the bootloader in system memory jumps to the second bank
+    /// after reset, if it contains a valid stack pointer. Otherwise it falls
+    /// back to the first bank. This allows a new firmware to be written to
+    /// the bank that isn't executed, and be switched to atomically.
+    ///
+    /// The setting only takes effect after the option bytes have been
+    /// reloaded, either by a power-on reset, or by calling
+    /// [`FLASH::reload_option_bytes`].
+    #[cfg(any(feature = "io-STM32L071", feature = "stm32l072", feature = "stm32l082"))]
+    pub fn set_boot_from_bank2(&mut self, enabled: bool) -> Result {
+        let user = self.read_option_bytes(OPTION_BYTES_USER);
+        let user = user & !(1 << 7) | (enabled as u16) << 7;
+
+        self.write_option_bytes(OPTION_BYTES_USER as *mut u32, user)
+    }
+
+    /// Reloads the option bytes
+    ///
+    /// This resets the microcontroller.
+    pub fn reload_option_bytes(&mut self) -> ! {
+        self.unlock_pecr();
+        self.unlock_optkeyr();
+
+        self.flash.pecr.modify(|_, w| w.obl_launch().set_bit());
+
+        // The reset happens immediately, but let's make sure the compiler
+        // knows that we won't return.
+        loop {
+            cortex_m::asm::nop();
+        }
+    }
+
+    /// Reads the lower half-word of option bytes, which holds the value
+    fn read_option_bytes(&self, address: u32) -> u16 {
+        // The lower half-word holds the options, the upper half-word holds
+        // their complement.
+        //
+        // Safe, as the caller passes a valid and aligned option byte address.
+        unsafe { (address as *const u32).read_volatile() as u16 }
+    }
+
+    /// Writes a half-word of option bytes, along with its complement
+    fn write_option_bytes(&mut self, address: *mut u32, value: u16) -> Result {
+        self.unlock_option_bytes(|self_| {
+            // Wait, while the memory interface is busy.
+            while self_.flash.sr.read().bsy().is_active() {}
+
+            // Write option bytes
+            // Safe, as the caller passes a valid option byte address.
+            unsafe { address.write_volatile((!value as u32) << 16 | value as u32) }
 
             // Wait for operation to complete
             while self_.flash.sr.read().bsy().is_active() {}
 
             self_.check_errors()
-
-            // No need to reset PECR flags, that's done by `unlock`.
         })
     }
 
+    /// Returns the Flash memory, as `embedded-storage` NOR flash
+    ///
+    /// Requires the `embedded-storage` feature.
+    #[cfg(feature = "embedded-storage")]
+    pub fn program_flash(&mut self) -> ProgramFlash<'_> {
+        ProgramFlash { flash: self }
+    }
+
+    /// Returns the EEPROM, as `embedded-storage` storage
+    ///
+    /// Requires the `embedded-storage` feature.
+    #[cfg(feature = "embedded-storage")]
+    pub fn eeprom(&mut self) -> Eeprom<'_> {
+        Eeprom { flash: self }
+    }
+
+    /// Unlocks the Flash memory and EEPROM for the duration of `f`
+    ///
+    /// See STM32L0x2 reference manual, section 3.3.4.
     fn unlock(&mut self, f: impl FnOnce(&mut Self) -> Result) -> Result {
-        // Unlock everything that needs unlocking
-        self.flash.pekeyr.write(|w| w.pekeyr().bits(0x89ABCDEF));
-        self.flash.pekeyr.write(|w| w.pekeyr().bits(0x02030405));
+        self.unlock_pecr();
         self.flash.prgkeyr.write(|w| w.prgkeyr().bits(0x8C9DAEBF));
         self.flash.prgkeyr.write(|w| w.prgkeyr().bits(0x13141516));
-        self.flash.optkeyr.write(|w| w.optkeyr().bits(0xFBEAD9C8));
-        self.flash.optkeyr.write(|w| w.optkeyr().bits(0x24252627));
 
         let result = f(self);
 
-        // Reset operations and write protection
-        self.flash.pecr.reset();
+        self.lock();
+
+        result
+    }
+
+    /// Unlocks the option bytes for the duration of `f`
+    fn unlock_option_bytes(&mut self, f: impl FnOnce(&mut Self) -> Result) -> Result {
+        self.unlock_pecr();
+        self.unlock_optkeyr();
+
+        let result = f(self);
+
+        self.lock();
 
         result
     }
 
-    fn verify_address(&self, address: *mut u32) -> Memory {
+    /// Unlocks the PECR register and the data EEPROM
+    fn unlock_pecr(&mut self) {
+        if self.flash.pecr.read().pelock().bit_is_set() {
+            self.flash.pekeyr.write(|w| w.pekeyr().bits(0x89ABCDEF));
+            self.flash.pekeyr.write(|w| w.pekeyr().bits(0x02030405));
+        }
+    }
+
+    /// Resets operations and write protection
+    ///
+    /// Leaves the interrupt enable bits untouched.
+    fn lock(&mut self) {
+        self.flash.pecr.modify(|r, w| {
+            // Safe, as 0x7 is the reset value of the register, which locks
+            // all memories and resets all operations.
+            unsafe { w.bits(0x7) };
+            w.eopie().bit(r.eopie().bit());
+            w.errie().bit(r.errie().bit())
+        });
+    }
+
+    /// Unlocks the option bytes, after PECR has been unlocked
+    fn unlock_optkeyr(&mut self) {
+        self.flash.optkeyr.write(|w| w.optkeyr().bits(0xFBEAD9C8));
+        self.flash.optkeyr.write(|w| w.optkeyr().bits(0x24252627));
+    }
+
+    pub(crate) fn verify_address(&self, address: *mut u32) -> Memory {
         let address = address as u32;
 
         let memory = match address {
@@ -259,6 +637,44 @@ impl FLASH {
         memory
     }
 
+    /// Starts listening for an interrupt event
+    ///
+    /// The FLASH interrupt is triggered by both events.
+    pub fn listen(&mut self, event: Event) {
+        self.unlock_pecr();
+        match event {
+            Event::EndOfProgram => self.flash.pecr.modify(|_, w| w.eopie().set_bit()),
+            Event::Error => self.flash.pecr.modify(|_, w| w.errie().set_bit()),
+        }
+        self.lock();
+    }
+
+    /// Stops listening for an interrupt event
+    pub fn unlisten(&mut self, event: Event) {
+        self.unlock_pecr();
+        match event {
+            Event::EndOfProgram => self.flash.pecr.modify(|_, w| w.eopie().clear_bit()),
+            Event::Error => self.flash.pecr.modify(|_, w| w.errie().clear_bit()),
+        }
+        self.lock();
+    }
+
+    /// Returns whether a write/erase operation has completed
+    ///
+    /// The flag is only set if [`Event::EndOfProgram`] is being listened for.
+    pub fn is_end_of_program(&self) -> bool {
+        self.flash.sr.read().eop().bit_is_set()
+    }
+
+    /// Clears the end of program flag
+    pub fn clear_end_of_program(&mut self) {
+        self.flash.sr.write(|w| w.eop().set_bit());
+    }
+
+    /// Checks the status register for errors and clears the reported one
+    ///
+    /// This also acknowledges the interrupt for [`Event::Error`], so it should
+    /// be called from the interrupt handler until it returns `Ok(())`.
     pub fn check_errors(&self) -> Result {
         let sr = self.flash.sr.read();
 
@@ -310,14 +726,7 @@ impl FLASH {
 }
 
 pub fn flash_size_in_kb() -> u32 {
-    // Determine size of the flash memory. According to the STM32L0x2 reference
-    // manual, section 33.1, there's a register that we can get that information
-    // from. It doesn't seem to be exposed through the PAC, so we have to read
-    // it manually.
-    //
-    // This is safe, as we're reading from a valid address (as per the
-    // reference manual) which is aligned to 16 bits.
-    unsafe { (0x1FF8_007C as *const u16).read() as u32 }
+    crate::signature::flash_size_kb() as u32
 }
 
 extern "C" {
@@ -341,23 +750,77 @@ extern "C" {
     fn write_half_page(address: *mut u32, words: *const u32);
 }
 
+/// Brown out reset (BOR) level
+///
+/// The voltages given are the approximate thresholds for a rising supply
+/// voltage. See the datasheet for the exact values.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum BorLevel {
+    /// BOR disabled, the reset threshold is determined by the power-on reset
+    /// (about 1.5 V)
+    Off = 0b0000,
+    /// About 1.8 V
+    Level1 = 0b1000,
+    /// About 2.0 V
+    Level2 = 0b1001,
+    /// About 2.5 V
+    Level3 = 0b1010,
+    /// About 2.7 V
+    Level4 = 0b1011,
+    /// About 3.0 V
+    Level5 = 0b1100,
+}
+
+/// Read protection (RDP) level
+///
+/// See [`FLASH::set_read_protection`] before changing this.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ReadProtection {
+    /// No protection
+    Level0 = 0xAA,
+    /// Memory read protection, reversible by a mass erase
+    Level1 = 0x00,
+    /// Chip read protection, **irreversible**
+    Level2 = 0xCC,
+}
+
+impl ReadProtection {
+    fn from_bits(bits: u8) -> Self {
+        match bits {
+            0xAA => ReadProtection::Level0,
+            0xCC => ReadProtection::Level2,
+            // Any other value selects level 1.
+            _ => ReadProtection::Level1,
+        }
+    }
+}
+
+/// User options stored in the option bytes
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct UserOptions {
+    /// Start the independent watchdog automatically after reset (WDG_SW)
+    pub hardware_watchdog: bool,
+    /// Reset when entering Stop mode, instead of stopping (nRST_STOP)
+    pub reset_on_stop: bool,
+    /// Reset when entering Standby mode, instead of standing by (nRST_STDBY)
+    pub reset_on_standby: bool,
+}
+
 #[derive(Copy, Clone, Eq, PartialEq)]
-enum Memory {
+pub(crate) enum Memory {
     Flash,
     Eeprom,
     Other,
 }
 
 impl Memory {
-    fn is_flash(&self) -> bool {
+    pub(crate) fn is_flash(&self) -> bool {
         *self == Memory::Flash
     }
 
-    // The following method is not currently used, but I left it here, in case
-    // new methods need this functionality later.
-    // fn is_eeprom(&self) -> bool {
-    //     *self == Memory::Eeprom
-    // }
+    pub(crate) fn is_eeprom(&self) -> bool {
+        *self == Memory::Eeprom
+    }
 
     fn is_other(&self) -> bool {
         *self == Memory::Other
@@ -366,7 +829,22 @@ impl Memory {
 
 type Result = core::result::Result<(), Error>;
 
-#[derive(Debug)]
+/// Interrupt events of the FLASH peripheral
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Event {
+    /// A write/erase operation has completed
+    ///
+    /// See EOPIE bit in PECR register.
+    EndOfProgram,
+
+    /// A write/erase operation has failed
+    ///
+    /// See ERRIE bit in PECR register.
+    Error,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Error {
     /// Write/erase was aborted by fetch operation
     ///
@@ -402,4 +880,203 @@ pub enum Error {
     ///
     /// See WRPERR in SR register.
     WriteProtection,
+
+    /// The offset or length is not aligned to the word or page size
+    ///
+    /// Returned by the `embedded-storage` implementations.
+    NotAligned,
+
+    /// The memory range exceeds the Flash memory or EEPROM
+    ///
+    /// Returned by the `embedded-storage` implementations.
+    OutOfBounds,
+}
+
+/// The Flash memory, implementing the `embedded-storage` NOR flash traits
+///
+/// Offsets are relative to [`FLASH_START`]. Words are written individually,
+/// and pages are erased individually.
+///
+/// Unlike most NOR flash, the STM32L0 Flash memory reads as zeros after
+/// erasing, not as ones. Writing to a word that isn't erased returns
+/// [`Error::NotErased`].
+///
+/// Attention: You must make sure that your program is not executed from the
+/// same Flash bank that is being written to. See [`FLASH::erase_flash_page`].
+#[cfg(feature = "embedded-storage")]
+pub struct ProgramFlash<'a> {
+    flash: &'a mut FLASH,
+}
+
+#[cfg(feature = "embedded-storage")]
+impl ProgramFlash<'_> {
+    fn check(&self, offset: u32, len: usize, align: u32) -> Result {
+        let capacity = self.capacity() as u32;
+        let len = len as u32;
+        if len > capacity || offset > capacity - len {
+            return Err(Error::OutOfBounds);
+        }
+        if offset & (align - 1) != 0 || len & (align - 1) != 0 {
+            return Err(Error::NotAligned);
+        }
+        Ok(())
+    }
+
+    fn capacity(&self) -> usize {
+        (self.flash.flash_end - FLASH_START) as usize
+    }
+}
+
+#[cfg(feature = "embedded-storage")]
+impl embedded_storage::nor_flash::NorFlashError for Error {
+    fn kind(&self) -> embedded_storage::nor_flash::NorFlashErrorKind {
+        use embedded_storage::nor_flash::NorFlashErrorKind;
+
+        match self {
+            Error::NotAligned => NorFlashErrorKind::NotAligned,
+            Error::OutOfBounds => NorFlashErrorKind::OutOfBounds,
+            _ => NorFlashErrorKind::Other,
+        }
+    }
+}
+
+#[cfg(feature = "embedded-storage")]
+impl embedded_storage::nor_flash::ErrorType for ProgramFlash<'_> {
+    type Error = Error;
+}
+
+#[cfg(feature = "embedded-storage")]
+impl embedded_storage::nor_flash::ReadNorFlash for ProgramFlash<'_> {
+    const READ_SIZE: usize = 1;
+
+    fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result {
+        self.check(offset, bytes.len(), 1)?;
+        read_bytes(FLASH_START + offset, bytes);
+        Ok(())
+    }
+
+    fn capacity(&self) -> usize {
+        ProgramFlash::capacity(self)
+    }
+}
+
+#[cfg(feature = "embedded-storage")]
+impl embedded_storage::nor_flash::NorFlash for ProgramFlash<'_> {
+    const WRITE_SIZE: usize = 4;
+    const ERASE_SIZE: usize = PAGE_SIZE as usize;
+
+    fn erase(&mut self, from: u32, to: u32) -> Result {
+        if from > to {
+            return Err(Error::OutOfBounds);
+        }
+        self.check(from, (to - from) as usize, PAGE_SIZE)?;
+
+        for page in (from..to).step_by(PAGE_SIZE as usize) {
+            self.flash
+                .erase_flash_page((FLASH_START + page) as *mut u32)?;
+        }
+        Ok(())
+    }
+
+    fn write(&mut self, offset: u32, bytes: &[u8]) -> Result {
+        self.check(offset, bytes.len(), 4)?;
+
+        for (i, chunk) in bytes.chunks(4).enumerate() {
+            let word = u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+            let address = (FLASH_START + offset) as *mut u32;
+
+            // Safe, as the range has been checked to be within Flash memory.
+            let address = unsafe { address.add(i) };
+
+            // Erased words are zero already.
+            if word != 0 {
+                self.flash.write_word(address, word)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The EEPROM, implementing the `embedded-storage` storage traits
+///
+/// Offsets are relative to the start of the EEPROM. Any range of bytes can be
+/// written, as the EEPROM is erased automatically. Words that only partially
+/// overlap the range are read and written back, and words that don't change
+/// aren't written at all.
+#[cfg(feature = "embedded-storage")]
+pub struct Eeprom<'a> {
+    flash: &'a mut FLASH,
+}
+
+#[cfg(feature = "embedded-storage")]
+impl Eeprom<'_> {
+    fn check(&self, offset: u32, len: usize) -> Result {
+        let capacity = self.capacity() as u32;
+        let len = len as u32;
+        if len > capacity || offset > capacity - len {
+            return Err(Error::OutOfBounds);
+        }
+        Ok(())
+    }
+
+    fn capacity(&self) -> usize {
+        (self.flash.eeprom_end - self.flash.eeprom_start) as usize
+    }
+}
+
+#[cfg(feature = "embedded-storage")]
+impl embedded_storage::ReadStorage for Eeprom<'_> {
+    type Error = Error;
+
+    fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result {
+        self.check(offset, bytes.len())?;
+        read_bytes(self.flash.eeprom_start + offset, bytes);
+        Ok(())
+    }
+
+    fn capacity(&self) -> usize {
+        Eeprom::capacity(self)
+    }
+}
+
+#[cfg(feature = "embedded-storage")]
+impl embedded_storage::Storage for Eeprom<'_> {
+    fn write(&mut self, offset: u32, bytes: &[u8]) -> Result {
+        self.check(offset, bytes.len())?;
+
+        let start = self.flash.eeprom_start + offset;
+        let end = start + bytes.len() as u32;
+
+        let mut address = start & !0x3;
+        while address < end {
+            // Safe, as the range has been checked to be within the EEPROM.
+            let old = unsafe { (address as *const u32).read_volatile() };
+
+            let mut word = old.to_le_bytes();
+            for (i, byte) in word.iter_mut().enumerate() {
+                let a = address + i as u32;
+                if start <= a && a < end {
+                    *byte = bytes[(a - start) as usize];
+                }
+            }
+
+            let word = u32::from_le_bytes(word);
+            if word != old {
+                self.flash.write_word(address as *mut u32, word)?;
+            }
+
+            address += 4;
+        }
+        Ok(())
+    }
+}
+
+/// Copies bytes from Flash memory or EEPROM
+#[cfg(feature = "embedded-storage")]
+fn read_bytes(address: u32, bytes: &mut [u8]) {
+    // Safe, as the callers check that the range is within Flash memory or
+    // EEPROM, which is always readable.
+    unsafe {
+        core::ptr::copy_nonoverlapping(address as *const u8, bytes.as_mut_ptr(), bytes.len());
+    }
 }
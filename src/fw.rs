@@ -0,0 +1,161 @@
+//! Interface to the firewall
+//!
+//! The firewall protects code and data from accesses by the rest of the
+//! application, e.g. to keep key material secret. It protects up to three
+//! segments:
+//!
+//! - A code segment in Flash memory, which may only be entered through its
+//!   call gate.
+//! - A non-volatile data segment in Flash memory.
+//! - A volatile data segment in SRAM.
+//!
+//! Any other access to these segments, while the firewall is closed, resets
+//! the microcontroller. Once enabled, the firewall can only be disabled by a
+//! reset, and its configuration can't be changed anymore.
+//!
+//! See STM32L0x2 reference manual, section "Firewall (FW)".
+
+use crate::{
+    pac::{self, FW},
+    rcc::{Enable, Rcc},
+    syscfg::SYSCFG,
+};
+
+/// Offset of the call gate from the start of the code segment
+///
+/// The first word of the code segment is reserved, and the call gate is the
+/// function that starts right after it.
+pub const CALL_GATE_OFFSET: u32 = 4;
+
+/// The firewall
+pub struct Firewall {
+    fw: FW,
+    code_start: u32,
+}
+
+impl Firewall {
+    /// Enables the firewall's clock
+    ///
+    /// The firewall isn't armed until [`Firewall::enable`] is called.
+    pub fn new(fw: FW, rcc: &mut Rcc) -> Self {
+        // Enable peripheral clock
+        FW::enable(rcc);
+
+        Self { fw, code_start: 0 }
+    }
+
+    /// Sets the protected code segment
+    ///
+    /// `segment` needs to be located in Flash memory, and its start and length
+    /// need to be multiples of 256 bytes.
+    pub fn set_code_segment(&mut self, segment: Segment) {
+        assert!(segment.start & 0xff == 0 && segment.len & 0xff == 0);
+
+        self.fw
+            .cssa
+            .write(|w| w.add().bits((segment.start >> 8) as u16));
+        self.fw
+            .csl
+            .write(|w| w.leng().bits((segment.len >> 8) as u16));
+
+        self.code_start = segment.start;
+    }
+
+    /// Sets the protected non-volatile data segment
+    ///
+    /// `segment` needs to be located in Flash memory, and its start and length
+    /// need to be multiples of 256 bytes.
+    pub fn set_non_volatile_data_segment(&mut self, segment: Segment) {
+        assert!(segment.start & 0xff == 0 && segment.len & 0xff == 0);
+
+        self.fw
+            .nvdssa
+            .write(|w| w.add().bits((segment.start >> 8) as u16));
+        self.fw
+            .nvdsl
+            .write(|w| w.leng().bits((segment.len >> 8) as u16));
+    }
+
+    /// Sets the protected volatile data segment
+    ///
+    /// `segment` needs to be located in SRAM, and its start and length need to
+    /// be multiples of 64 bytes.
+    ///
+    /// If `shared` is `true`, the segment can be accessed by the rest of the
+    /// application while the firewall is closed, e.g. to pass buffers to the
+    /// protected code. If `executable` is `true`, code can be executed from
+    /// the segment while the firewall is open.
+    pub fn set_volatile_data_segment(&mut self, segment: Segment, shared: bool, executable: bool) {
+        assert!(segment.start & 0x3f == 0 && segment.len & 0x3f == 0);
+
+        self.fw
+            .vdssa
+            .write(|w| w.add().bits((segment.start >> 6) as u16));
+        self.fw
+            .vdsl
+            .write(|w| w.leng().bits((segment.len >> 6) as u16));
+        self.fw
+            .cr
+            .modify(|_, w| w.vds().bit(shared).vde().bit(executable));
+    }
+
+    /// Arms the firewall
+    ///
+    /// From now on, the protected segments may only be accessed after
+    /// entering the protected code through its call gate. This can't be
+    /// undone, except by a reset.
+    pub fn enable(&mut self, syscfg: &mut SYSCFG) {
+        syscfg.syscfg.cfgr2.modify(|_, w| w.fwdis().clear_bit());
+    }
+
+    /// Enters the protected code through its call gate
+    ///
+    /// Calls the function at [`CALL_GATE_OFFSET`] from the start of the code
+    /// segment with `arg`, and returns its result. This opens the firewall.
+    /// The protected code needs to call [`pre_arm`] before returning, to
+    /// close the firewall again, or the return resets the microcontroller.
+    ///
+    /// # Safety
+    ///
+    /// The code segment needs to have been set, and there needs to be an
+    /// `extern "C" fn(u32) -> u32` at the call gate.
+    pub unsafe fn call_gate(&self, arg: u32) -> u32 {
+        // Set the Thumb bit of the address
+        let entry = (self.code_start + CALL_GATE_OFFSET) | 1;
+        let call_gate: extern "C" fn(u32) -> u32 = core::mem::transmute(entry as usize);
+        call_gate(arg)
+    }
+
+    /// Releases the firewall
+    ///
+    /// The firewall stays armed, if it has been enabled.
+    pub fn free(self) -> FW {
+        self.fw
+    }
+}
+
+/// Closes the firewall, once the protected code is left
+///
+/// This needs to be called by the protected code, before returning from the
+/// call gate.
+pub fn pre_arm() {
+    // Safe, as the firewall only allows the protected code to access this
+    // register, while it is armed.
+    let fw = unsafe { &*pac::FW::ptr() };
+    fw.cr.modify(|_, w| w.fpa().set_bit());
+}
+
+/// A memory segment protected by the firewall
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Segment {
+    /// The address of the first byte
+    pub start: u32,
+    /// The length in bytes
+    pub len: u32,
+}
+
+impl Segment {
+    pub const fn new(start: u32, len: u32) -> Self {
+        Self { start, len }
+    }
+}
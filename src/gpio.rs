@@ -125,12 +125,65 @@ pub enum Port {
     PB,
     PC,
     PD,
+    #[cfg(feature = "io-STM32L071")]
     PE,
     PH,
 }
 
+/// Implements the embedded-hal 1.0 digital traits, based on the 0.2 ones
+#[cfg(feature = "eh1")]
+macro_rules! eh1_digital {
+    ($PXi:ident) => {
+        impl<MODE> eh1::digital::ErrorType for $PXi<MODE> {
+            type Error = core::convert::Infallible;
+        }
+
+        impl<MODE> eh1::digital::OutputPin for $PXi<Output<MODE>> {
+            fn set_high(&mut self) -> Result<(), Self::Error> {
+                OutputPin::set_high(self).map_err(|e| match e {})
+            }
+
+            fn set_low(&mut self) -> Result<(), Self::Error> {
+                OutputPin::set_low(self).map_err(|e| match e {})
+            }
+        }
+
+        impl<MODE> eh1::digital::StatefulOutputPin for $PXi<Output<MODE>> {
+            fn is_set_high(&mut self) -> Result<bool, Self::Error> {
+                StatefulOutputPin::is_set_low(self)
+                    .map(|is_set_low| !is_set_low)
+                    .map_err(|e| match e {})
+            }
+
+            fn is_set_low(&mut self) -> Result<bool, Self::Error> {
+                StatefulOutputPin::is_set_low(self).map_err(|e| match e {})
+            }
+        }
+
+        impl<MODE> eh1::digital::InputPin for $PXi<Output<MODE>> {
+            fn is_high(&mut self) -> Result<bool, Self::Error> {
+                InputPin::is_high(self).map_err(|e| match e {})
+            }
+
+            fn is_low(&mut self) -> Result<bool, Self::Error> {
+                InputPin::is_low(self).map_err(|e| match e {})
+            }
+        }
+
+        impl<MODE> eh1::digital::InputPin for $PXi<Input<MODE>> {
+            fn is_high(&mut self) -> Result<bool, Self::Error> {
+                InputPin::is_high(self).map_err(|e| match e {})
+            }
+
+            fn is_low(&mut self) -> Result<bool, Self::Error> {
+                InputPin::is_low(self).map_err(|e| match e {})
+            }
+        }
+    };
+}
+
 macro_rules! gpio {
-    ($GPIOX:ident, $gpiox:ident, $iopxenr:ident, $PXx:ident, [
+    ($GPIOX:ident, $gpiox:ident, $PXx:ident, [
         $($PXi:ident: ($pxi:ident, $i:expr, $MODE:ty),)+
     ]) => {
         /// GPIO
@@ -139,7 +192,7 @@ macro_rules! gpio {
 
             use crate::hal::digital::v2::{toggleable, InputPin, OutputPin, StatefulOutputPin};
             use crate::pac::$GPIOX;
-            use crate::rcc::Rcc;
+            use crate::rcc::{Enable, Rcc};
             use super::{
                 Floating, GpioExt, Input, OpenDrain, Output, Speed,
                 PullDown, PullUp, PushPull, AltMode, Analog, Port,
@@ -158,7 +211,7 @@ macro_rules! gpio {
                 type Parts = Parts;
 
                 fn split(self, rcc: &mut Rcc) -> Parts {
-                    rcc.rb.iopenr.modify(|_, w| w.$iopxenr().set_bit());
+                    $GPIOX::enable(rcc);
 
                     Parts {
                         $(
@@ -252,6 +305,9 @@ macro_rules! gpio {
                 }
             }
 
+            #[cfg(feature = "eh1")]
+            eh1_digital!($PXx);
+
             $(
                 /// Pin
                 pub struct $PXi<MODE> {
@@ -578,12 +634,15 @@ macro_rules! gpio {
                         Ok(is_low)
                     }
                 }
+
+                #[cfg(feature = "eh1")]
+                eh1_digital!($PXi);
             )+
         }
     }
 }
 
-gpio!(GPIOA, gpioa, iopaen, PA, [
+gpio!(GPIOA, gpioa, PA, [
     PA0: (pa0, 0, Analog),
     PA1: (pa1, 1, Analog),
     PA2: (pa2, 2, Analog),
@@ -602,7 +661,7 @@ gpio!(GPIOA, gpioa, iopaen, PA, [
     PA15: (pa15, 15, Analog),
 ]);
 
-gpio!(GPIOB, gpiob, iopben, PB, [
+gpio!(GPIOB, gpiob, PB, [
     PB0: (pb0, 0, Analog),
     PB1: (pb1, 1, Analog),
     PB2: (pb2, 2, Analog),
@@ -621,7 +680,7 @@ gpio!(GPIOB, gpiob, iopben, PB, [
     PB15: (pb15, 15, Analog),
 ]);
 
-gpio!(GPIOC, gpioc, iopcen, PC, [
+gpio!(GPIOC, gpioc, PC, [
     PC0: (pc0, 0, Analog),
     PC1: (pc1, 1, Analog),
     PC2: (pc2, 2, Analog),
@@ -640,7 +699,7 @@ gpio!(GPIOC, gpioc, iopcen, PC, [
     PC15: (pc15, 15, Analog),
 ]);
 
-gpio!(GPIOD, gpiod, iopden, PD, [
+gpio!(GPIOD, gpiod, PD, [
     PD0: (pd0, 0, Analog),
     PD1: (pd1, 1, Analog),
     PD2: (pd2, 2, Analog),
@@ -659,7 +718,8 @@ gpio!(GPIOD, gpiod, iopden, PD, [
     PD15: (pd15, 15, Analog),
 ]);
 
-gpio!(GPIOE, gpioe, iopeen, PE, [
+#[cfg(feature = "io-STM32L071")]
+gpio!(GPIOE, gpioe, PE, [
     PE0:  (pe0,  0,  Analog),
     PE1:  (pe1,  1,  Analog),
     PE2:  (pe2,  2,  Analog),
@@ -678,7 +738,7 @@ gpio!(GPIOE, gpioe, iopeen, PE, [
     PE15: (pe15, 15, Analog),
 ]);
 
-gpio!(GPIOH, gpioh, iophen, PH, [
+gpio!(GPIOH, gpioh, PH, [
     PH0: (ph0, 0, Analog),
     PH1: (ph1, 1, Analog),
     PH9: (ph9, 9, Analog),
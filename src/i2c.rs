@@ -2,24 +2,30 @@
 
 use core::ops::Deref;
 
+#[cfg(feature = "async")]
+use core::{future::poll_fn, task::Poll};
 #[cfg(feature = "stm32l0x2")]
 use core::{marker::PhantomData, ops::DerefMut, pin::Pin};
 
 #[cfg(feature = "stm32l0x2")]
 use as_slice::{AsMutSlice, AsSlice};
 
+#[cfg(feature = "async")]
+use crate::asynch::{InterruptHandler, InterruptWaker};
+use crate::config;
 #[cfg(feature = "stm32l0x2")]
 use crate::dma::{self, Buffer};
 use crate::pac::i2c1::{
     cr2::{AUTOEND_A, RD_WRN_A},
-    RegisterBlock,
+    isr, RegisterBlock,
 };
-use crate::rcc::Rcc;
+use crate::rcc::{Enable, Rcc, Reset};
 use crate::time::Hertz;
-use cast::u8;
+use crate::timeout::{self, TimeoutGuard};
 
 // I²C traits
 use crate::hal::blocking::i2c::{Read, Write, WriteRead};
+use crate::hal::timer::CountDown;
 
 // I/O Imports
 use crate::gpio::{AltMode, OpenDrain, Output};
@@ -65,9 +71,28 @@ impl<I, SDA, SCL> I2c<I, SDA, SCL>
 where
     I: Instance,
 {
-    pub fn new(i2c: I, sda: SDA, scl: SCL, freq: Hertz, rcc: &mut Rcc) -> Self
+    pub fn new<T>(i2c: I, sda: SDA, scl: SCL, freq: T, rcc: &mut Rcc) -> Self
     where
         I: Instance,
+        T: Into<Hertz>,
+        SDA: SDAPin<I>,
+        SCL: SCLPin<I>,
+    {
+        let timing = match Timing::new(i2c.clock_frequency(rcc), freq.into().0) {
+            Ok(timing) => timing,
+            Err(_) => panic!("I2C frequency can't be derived from the I2C clock"),
+        };
+
+        Self::with_timing(i2c, sda, scl, timing, rcc)
+    }
+
+    /// Creates a new I2C driver with a precomputed [`Timing`]
+    ///
+    /// This skips the timing calculation at runtime. The timing needs to have
+    /// been computed for the I2C clock frequency (`I2CCLK`) that is configured
+    /// in `rcc`.
+    pub fn with_timing(i2c: I, sda: SDA, scl: SCL, timing: Timing, rcc: &mut Rcc) -> Self
+    where
         SDA: SDAPin<I>,
         SCL: SCLPin<I>,
     {
@@ -75,75 +100,7 @@ where
         scl.setup();
 
         i2c.initialize(rcc);
-
-        let freq = freq.0;
-
-        assert!(freq <= 1_000_000);
-
-        // TODO review compliance with the timing requirements of I2C
-        // t_I2CCLK = 1 / PCLK1
-        // t_PRESC  = (PRESC + 1) * t_I2CCLK
-        // t_SCLL   = (SCLL + 1) * t_PRESC
-        // t_SCLH   = (SCLH + 1) * t_PRESC
-        //
-        // t_SYNC1 + t_SYNC2 > 4 * t_I2CCLK
-        // t_SCL ~= t_SYNC1 + t_SYNC2 + t_SCLL + t_SCLH
-        let i2cclk = rcc.clocks.apb1_clk().0;
-        let ratio = i2cclk / freq - 4;
-        let (presc, scll, sclh, sdadel, scldel) = if freq >= 100_000 {
-            // fast-mode or fast-mode plus
-            // here we pick SCLL + 1 = 2 * (SCLH + 1)
-            let presc = ratio / 387;
-
-            let sclh = ((ratio / (presc + 1)) - 3) / 3;
-            let scll = 2 * (sclh + 1) - 1;
-
-            let (sdadel, scldel) = if freq > 400_000 {
-                // fast-mode plus
-                let sdadel = 0;
-                let scldel = i2cclk / 4_000_000 / (presc + 1) - 1;
-
-                (sdadel, scldel)
-            } else {
-                // fast-mode
-                let sdadel = i2cclk / 8_000_000 / (presc + 1);
-                let scldel = i2cclk / 2_000_000 / (presc + 1) - 1;
-
-                (sdadel, scldel)
-            };
-
-            (presc, scll, sclh, sdadel, scldel)
-        } else {
-            // standard-mode
-            // here we pick SCLL = SCLH
-            let presc = ratio / 514;
-
-            let sclh = ((ratio / (presc + 1)) - 2) / 2;
-            let scll = sclh;
-
-            let sdadel = i2cclk / 2_000_000 / (presc + 1);
-            let scldel = i2cclk / 800_000 / (presc + 1) - 1;
-
-            (presc, scll, sclh, sdadel, scldel)
-        };
-
-        let presc = u8(presc).unwrap();
-        assert!(presc < 16);
-        let scldel = u8(scldel).unwrap();
-        assert!(scldel < 16);
-        let sdadel = u8(sdadel).unwrap();
-        assert!(sdadel < 16);
-        let sclh = u8(sclh).unwrap();
-        let scll = u8(scll).unwrap();
-
-        // Configure for "fast mode" (400 KHz)
-        i2c.timingr.write(|w| {
-            w.presc().bits(presc);
-            w.scll().bits(scll);
-            w.sclh().bits(sclh);
-            w.sdadel().bits(sdadel);
-            w.scldel().bits(scldel)
-        });
+        timing.apply(&i2c);
 
         i2c.cr1.write(|w| {
             // Enable DMA reception
@@ -157,7 +114,9 @@ where
         I2c { i2c, sda, scl }
     }
 
+    /// Disables the I2C peripheral and releases it, together with the pins
     pub fn release(self) -> (I, SDA, SCL) {
+        self.i2c.cr1.modify(|_, w| w.pe().clear_bit());
         (self.i2c, self.sda, self.scl)
     }
 
@@ -198,28 +157,105 @@ where
         });
     }
 
-    fn send_byte(&self, byte: u8) -> Result<(), Error> {
-        // Wait until we're ready for sending
-        while self.i2c.isr.read().txe().bit_is_clear() {
+    /// Waits until `flag` is set, checking for errors and calling `timeout`
+    /// while waiting
+    fn wait<E>(
+        &self,
+        flag: fn(&isr::R) -> bool,
+        timeout: &mut impl FnMut() -> Result<(), E>,
+    ) -> Result<(), E>
+    where
+        E: From<Error>,
+    {
+        while !flag(&self.i2c.isr.read()) {
             self.check_errors()?;
+            timeout()?;
         }
+        Ok(())
+    }
+
+    fn send_byte<E>(&self, byte: u8, timeout: &mut impl FnMut() -> Result<(), E>) -> Result<(), E>
+    where
+        E: From<Error>,
+    {
+        // Wait until we're ready for sending
+        self.wait(|isr| isr.txe().bit_is_set(), timeout)?;
 
         // Push out a byte of data
         self.i2c.txdr.write(|w| w.txdata().bits(byte));
 
         // check for any errors
-        self.check_errors()
+        Ok(self.check_errors()?)
     }
 
-    fn recv_byte(&self) -> Result<u8, Error> {
-        while self.i2c.isr.read().rxne().bit_is_clear() {
-            self.check_errors()?;
-        }
+    fn recv_byte<E>(&self, timeout: &mut impl FnMut() -> Result<(), E>) -> Result<u8, E>
+    where
+        E: From<Error>,
+    {
+        self.wait(|isr| isr.rxne().bit_is_set(), timeout)?;
 
         let value = self.i2c.rxdr.read().rxdata().bits();
         Ok(value)
     }
 
+    fn write_read_with<E>(
+        &mut self,
+        addr: u8,
+        bytes: &[u8],
+        buffer: &mut [u8],
+        timeout: &mut impl FnMut() -> Result<(), E>,
+    ) -> Result<(), E>
+    where
+        E: From<Error>,
+    {
+        let writing = bytes.len() > 0;
+        let reading = buffer.len() > 0;
+
+        // wait for i2c device to be available
+        self.wait(|isr| isr.busy().is_not_busy(), timeout)?;
+
+        // if we are writing bytes
+        if writing {
+            // if the previous write has failed, we need to flush the TX
+            // buffer to prevent sending old data
+            self.i2c.isr.write(|w| w.txe().set_bit());
+
+            if reading {
+                self.start_transfer(addr, bytes.len(), RD_WRN_A::WRITE, AUTOEND_A::SOFTWARE);
+            } else {
+                self.start_transfer(addr, bytes.len(), RD_WRN_A::WRITE, AUTOEND_A::AUTOMATIC);
+            }
+
+            // Send bytes
+            for c in bytes {
+                self.send_byte(*c, timeout)?;
+            }
+
+            // if we are going to read afterwards, we need to wait for
+            // the tx to complete
+            if reading {
+                self.wait(|isr| isr.tc().is_complete(), timeout)?;
+            }
+        }
+
+        if reading {
+            // force a read of the rx data register, so that we dont
+            // get stale data from the last transaction (if there is
+            // anything left over)
+            self.i2c.rxdr.read();
+
+            //send a new start condition and transfer
+            self.start_transfer(addr, buffer.len(), RD_WRN_A::READ, AUTOEND_A::AUTOMATIC);
+
+            // Receive bytes into buffer
+            for c in buffer {
+                *c = self.recv_byte(timeout)?;
+            }
+        }
+
+        Ok(())
+    }
+
     #[cfg(feature = "stm32l0x2")]
     pub fn write_all<Channel, Buffer>(
         self,
@@ -227,7 +263,7 @@ where
         channel: Channel,
         address: u8,
         buffer: Pin<Buffer>,
-    ) -> Transfer<Self, Tx<I>, Channel, Buffer, dma::Ready>
+    ) -> SetupResult<Self, Tx<I>, Channel, Buffer>
     where
         Tx<I>: dma::Target<Channel>,
         Channel: dma::Channel,
@@ -246,20 +282,22 @@ where
         address: u8,
         buffer: Pin<Buffer>,
         num_words: usize,
-    ) -> Transfer<Self, Tx<I>, Channel, Buffer, dma::Ready>
+    ) -> SetupResult<Self, Tx<I>, Channel, Buffer>
     where
         Tx<I>: dma::Target<Channel>,
         Channel: dma::Channel,
         Buffer: Deref + 'static,
         Buffer::Target: AsSlice<Element = u8>,
     {
-        assert!(buffer.len() >= num_words);
-        self.start_transfer(
-            address,
-            buffer.as_slice().len(),
-            RD_WRN_A::WRITE,
-            AUTOEND_A::AUTOMATIC,
-        );
+        // The number of bytes of a transfer is limited to the NBYTES field.
+        if num_words > 255 {
+            let res = dma::TransferResources {
+                target: self,
+                channel,
+                buffer,
+            };
+            return Err((res, config::Error::BufferLength));
+        }
 
         // This token represents the transmission capability of I2C and this is
         // what the `dma::Target` trait is implemented for. It can't be
@@ -275,7 +313,7 @@ where
         let token = Tx(PhantomData);
 
         // Safe, because we're only taking the address of a register.
-        let address = &unsafe { &*I::ptr() }.txdr as *const _ as u32;
+        let txdr = &unsafe { &*I::ptr() }.txdr as *const _ as u32;
 
         // Safe, because the trait bounds of this method guarantee that the
         // buffer can be read from.
@@ -286,17 +324,30 @@ where
                 channel,
                 buffer,
                 num_words,
-                address,
+                txdr,
                 dma::Priority::high(),
                 dma::Direction::memory_to_peripheral(),
                 false,
             )
         };
+        let transfer = match transfer {
+            Ok(transfer) => transfer,
+            Err((res, err)) => {
+                let res = dma::TransferResources {
+                    target: self,
+                    channel: res.channel,
+                    buffer: res.buffer,
+                };
+                return Err((res, err));
+            }
+        };
 
-        Transfer {
+        self.start_transfer(address, num_words, RD_WRN_A::WRITE, AUTOEND_A::AUTOMATIC);
+
+        Ok(Transfer {
             target: self,
             inner: transfer,
-        }
+        })
     }
 
     #[cfg(feature = "stm32l0x2")]
@@ -306,7 +357,7 @@ where
         channel: Channel,
         address: u8,
         buffer: Pin<Buffer>,
-    ) -> Transfer<Self, Rx<I>, Channel, Buffer, dma::Ready>
+    ) -> SetupResult<Self, Rx<I>, Channel, Buffer>
     where
         Rx<I>: dma::Target<Channel>,
         Channel: dma::Channel,
@@ -325,28 +376,29 @@ where
         address: u8,
         buffer: Pin<Buffer>,
         num_words: usize,
-    ) -> Transfer<Self, Rx<I>, Channel, Buffer, dma::Ready>
+    ) -> SetupResult<Self, Rx<I>, Channel, Buffer>
     where
         Rx<I>: dma::Target<Channel>,
         Channel: dma::Channel,
         Buffer: DerefMut + 'static,
         Buffer::Target: AsMutSlice<Element = u8>,
     {
-        assert!(buffer.len() >= num_words);
-        self.start_transfer(
-            address,
-            buffer.as_slice().len(),
-            RD_WRN_A::READ,
-            AUTOEND_A::AUTOMATIC,
-        );
+        // See `write_some`.
+        if num_words > 255 {
+            let res = dma::TransferResources {
+                target: self,
+                channel,
+                buffer,
+            };
+            return Err((res, config::Error::BufferLength));
+        }
 
         // See explanation of tokens in `write_all`.
         let token = Rx(PhantomData);
 
         // Safe, because we're only taking the address of a register.
-        let address = &unsafe { &*I::ptr() }.rxdr as *const _ as u32;
+        let rxdr = &unsafe { &*I::ptr() }.rxdr as *const _ as u32;
 
-        let num_words = buffer.len();
         // Safe, because the trait bounds of this method guarantee that the
         // buffer can be written to.
         let transfer = unsafe {
@@ -356,17 +408,30 @@ where
                 channel,
                 buffer,
                 num_words,
-                address,
+                rxdr,
                 dma::Priority::high(),
                 dma::Direction::peripheral_to_memory(),
                 false,
             )
         };
+        let transfer = match transfer {
+            Ok(transfer) => transfer,
+            Err((res, err)) => {
+                let res = dma::TransferResources {
+                    target: self,
+                    channel: res.channel,
+                    buffer: res.buffer,
+                };
+                return Err((res, err));
+            }
+        };
 
-        Transfer {
+        self.start_transfer(address, num_words, RD_WRN_A::READ, AUTOEND_A::AUTOMATIC);
+
+        Ok(Transfer {
             target: self,
             inner: transfer,
-        }
+        })
     }
 }
 
@@ -377,84 +442,499 @@ where
     type Error = Error;
 
     fn write_read(&mut self, addr: u8, bytes: &[u8], buffer: &mut [u8]) -> Result<(), Self::Error> {
-        let writing = bytes.len() > 0;
-        let reading = buffer.len() > 0;
+        self.write_read_with(addr, bytes, buffer, &mut || Ok(()))
+    }
+}
 
-        // wait for i2c device to be available
-        while self.i2c.isr.read().busy().is_busy() {
-            self.check_errors()?;
+impl<I, SDA, SCL> Write for I2c<I, SDA, SCL>
+where
+    I: Instance,
+{
+    type Error = Error;
+
+    fn write(&mut self, addr: u8, bytes: &[u8]) -> Result<(), Self::Error> {
+        self.write_read(addr, bytes, &mut [])
+    }
+}
+
+impl<I, SDA, SCL> Read for I2c<I, SDA, SCL>
+where
+    I: Instance,
+{
+    type Error = Error;
+
+    fn read(&mut self, addr: u8, buffer: &mut [u8]) -> Result<(), Self::Error> {
+        self.write_read(addr, &[], buffer)
+    }
+}
+
+impl<I, SDA, SCL, Timer> TimeoutGuard<I2c<I, SDA, SCL>, Timer>
+where
+    I: Instance,
+    Timer: CountDown,
+    Timer::Time: Clone,
+{
+    /// Performs a write-read transfer, and resets the peripheral on a timeout
+    fn transfer_with_timeout(
+        &mut self,
+        addr: u8,
+        bytes: &[u8],
+        buffer: &mut [u8],
+    ) -> Result<(), timeout::Error<Error>> {
+        self.start();
+        let (i2c, timer) = self.split_mut();
+        let result = i2c.write_read_with(addr, bytes, buffer, &mut || Self::check(timer));
+        if let Err(timeout::Error::Timeout) = result {
+            // A software reset releases the lines and returns the state
+            // machine to idle. PE needs to stay low for at least 3 APB clock
+            // cycles, which reading it back ensures.
+            i2c.i2c.cr1.modify(|_, w| w.pe().clear_bit());
+            while i2c.i2c.cr1.read().pe().bit_is_set() {}
+            i2c.i2c.cr1.modify(|_, w| w.pe().set_bit());
         }
+        result
+    }
+}
 
-        // if we are writing bytes
-        if writing {
-            // if the previous write has failed, we need to flush the TX
-            // buffer to prevent sending old data
+impl<I, SDA, SCL, Timer> WriteRead for TimeoutGuard<I2c<I, SDA, SCL>, Timer>
+where
+    I: Instance,
+    Timer: CountDown,
+    Timer::Time: Clone,
+{
+    type Error = timeout::Error<Error>;
+
+    fn write_read(&mut self, addr: u8, bytes: &[u8], buffer: &mut [u8]) -> Result<(), Self::Error> {
+        self.transfer_with_timeout(addr, bytes, buffer)
+    }
+}
+
+impl<I, SDA, SCL, Timer> Write for TimeoutGuard<I2c<I, SDA, SCL>, Timer>
+where
+    I: Instance,
+    Timer: CountDown,
+    Timer::Time: Clone,
+{
+    type Error = timeout::Error<Error>;
+
+    fn write(&mut self, addr: u8, bytes: &[u8]) -> Result<(), Self::Error> {
+        self.transfer_with_timeout(addr, bytes, &mut [])
+    }
+}
+
+impl<I, SDA, SCL, Timer> Read for TimeoutGuard<I2c<I, SDA, SCL>, Timer>
+where
+    I: Instance,
+    Timer: CountDown,
+    Timer::Time: Clone,
+{
+    type Error = timeout::Error<Error>;
+
+    fn read(&mut self, addr: u8, buffer: &mut [u8]) -> Result<(), Self::Error> {
+        self.transfer_with_timeout(addr, &[], buffer)
+    }
+}
+
+#[cfg(feature = "eh1")]
+impl<I, SDA, SCL> I2c<I, SDA, SCL>
+where
+    I: Instance,
+{
+    /// Starts a transfer of adjacent operations of the same direction
+    ///
+    /// Returns the number of bytes in the first chunk, and the number of bytes
+    /// remaining after it.
+    fn start_operations(
+        &mut self,
+        addr: u8,
+        operations: &[eh1::i2c::Operation],
+        stop: bool,
+    ) -> (usize, usize) {
+        use eh1::i2c::Operation;
+
+        let reading = matches!(operations.first(), Some(Operation::Read(_)));
+        let total: usize = operations
+            .iter()
+            .map(|operation| match operation {
+                Operation::Read(buffer) => buffer.len(),
+                Operation::Write(bytes) => bytes.len(),
+            })
+            .sum();
+
+        if reading {
+            // Discard stale data from a previous transfer
+            self.i2c.rxdr.read();
+        } else {
+            // Flush the TX buffer, if a previous write has failed
             self.i2c.isr.write(|w| w.txe().set_bit());
+        }
 
+        let chunk = total.min(255);
+        let remaining = total - chunk;
+        self.i2c.cr2.write(|w| {
+            w.start().set_bit();
+            w.nbytes().bits(chunk as u8);
+            w.sadd().bits((addr << 1) as u16);
             if reading {
-                self.start_transfer(addr, bytes.len(), RD_WRN_A::WRITE, AUTOEND_A::SOFTWARE);
+                w.rd_wrn().read();
             } else {
-                self.start_transfer(addr, bytes.len(), RD_WRN_A::WRITE, AUTOEND_A::AUTOMATIC);
+                w.rd_wrn().write();
             }
-
-            // Send bytes
-            for c in bytes {
-                self.send_byte(*c)?;
+            w.reload().bit(remaining > 0);
+            if stop {
+                w.autoend().automatic()
+            } else {
+                w.autoend().software()
             }
+        });
 
-            // if we are going to read afterwards, we need to wait for
-            // the tx to complete
-            if reading {
-                while self.i2c.isr.read().tc().is_not_complete() {
-                    self.check_errors()?;
+        (chunk, remaining)
+    }
+
+    /// Continues the transfer with the next chunk, once the current one is
+    /// complete
+    ///
+    /// Returns the number of bytes in the next chunk.
+    fn reload(&mut self, remaining: &mut usize) -> usize {
+        let chunk = (*remaining).min(255);
+        *remaining -= chunk;
+        self.i2c
+            .cr2
+            .modify(|_, w| w.nbytes().bits(chunk as u8).reload().bit(*remaining > 0));
+        chunk
+    }
+
+    /// Transfers adjacent operations of the same direction, without a repeated
+    /// start condition in between
+    ///
+    /// Transfers of more than 255 bytes are split up, using the reload mode.
+    fn transfer_operations(
+        &mut self,
+        addr: u8,
+        operations: &mut [eh1::i2c::Operation],
+        stop: bool,
+    ) -> Result<(), Error> {
+        use eh1::i2c::Operation;
+
+        let (mut chunk, mut remaining) = self.start_operations(addr, operations, stop);
+
+        for operation in operations {
+            let len = match operation {
+                Operation::Read(buffer) => buffer.len(),
+                Operation::Write(bytes) => bytes.len(),
+            };
+            for i in 0..len {
+                if chunk == 0 {
+                    while self.i2c.isr.read().tcr().bit_is_clear() {
+                        self.check_errors()?;
+                    }
+                    chunk = self.reload(&mut remaining);
                 }
+
+                match operation {
+                    Operation::Read(buffer) => buffer[i] = self.recv_byte(&mut || Ok(()))?,
+                    Operation::Write(bytes) => self.send_byte(bytes[i], &mut || Ok(()))?,
+                }
+                chunk -= 1;
             }
         }
 
-        if reading {
-            // force a read of the rx data register, so that we dont
-            // get stale data from the last transaction (if there is
-            // anything left over)
-            self.i2c.rxdr.read();
+        if !stop {
+            while self.i2c.isr.read().tc().is_not_complete() {
+                self.check_errors()?;
+            }
+        }
 
-            //send a new start condition and transfer
-            self.start_transfer(addr, buffer.len(), RD_WRN_A::READ, AUTOEND_A::AUTOMATIC);
+        Ok(())
+    }
+}
 
-            // Receive bytes into buffer
-            for c in buffer {
-                *c = self.recv_byte()?;
-            }
+#[cfg(feature = "eh1")]
+impl eh1::i2c::Error for Error {
+    fn kind(&self) -> eh1::i2c::ErrorKind {
+        use eh1::i2c::{ErrorKind, NoAcknowledgeSource};
+
+        match self {
+            Error::Overrun => ErrorKind::Overrun,
+            Error::Nack => ErrorKind::NoAcknowledge(NoAcknowledgeSource::Unknown),
+            Error::PECError => ErrorKind::Other,
+            Error::BusError => ErrorKind::Bus,
+            Error::ArbitrationLost => ErrorKind::ArbitrationLoss,
+        }
+    }
+}
+
+#[cfg(feature = "eh1")]
+impl<I, SDA, SCL> eh1::i2c::ErrorType for I2c<I, SDA, SCL> {
+    type Error = Error;
+}
+
+#[cfg(feature = "eh1")]
+impl<I, SDA, SCL> eh1::i2c::I2c for I2c<I, SDA, SCL>
+where
+    I: Instance,
+{
+    fn transaction(
+        &mut self,
+        address: u8,
+        operations: &mut [eh1::i2c::Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        use eh1::i2c::Operation;
+
+        // wait for i2c device to be available
+        while self.i2c.isr.read().busy().is_busy() {
+            self.check_errors()?;
+        }
+
+        let mut operations = operations;
+        while !operations.is_empty() {
+            let reading = matches!(operations[0], Operation::Read(_));
+            let len = operations
+                .iter()
+                .position(|operation| matches!(operation, Operation::Read(_)) != reading)
+                .unwrap_or(operations.len());
+
+            let (current, rest) = operations.split_at_mut(len);
+            self.transfer_operations(address, current, rest.is_empty())?;
+            operations = rest;
         }
 
         Ok(())
     }
 }
 
-impl<I, SDA, SCL> Write for I2c<I, SDA, SCL>
+#[cfg(feature = "async")]
+impl<I, SDA, SCL> I2c<I, SDA, SCL>
 where
     I: Instance,
 {
-    type Error = Error;
+    /// Waits for `flag` to be set, using the interrupts
+    async fn wait_for(&mut self, flag: fn(&isr::R) -> bool) -> Result<(), Error> {
+        poll_fn(|cx| {
+            I::waker().register(cx.waker());
 
-    fn write(&mut self, addr: u8, bytes: &[u8]) -> Result<(), Self::Error> {
-        self.write_read(addr, bytes, &mut [])
+            self.check_errors()?;
+            if flag(&self.i2c.isr.read()) {
+                return Poll::Ready(Ok(()));
+            }
+
+            self.i2c.cr1.modify(|_, w| {
+                w.txie().set_bit();
+                w.rxie().set_bit();
+                w.tcie().set_bit();
+                w.nackie().set_bit();
+                w.errie().set_bit()
+            });
+            Poll::Pending
+        })
+        .await
+    }
+
+    /// Async version of `transfer_operations`
+    async fn transfer_operations_async(
+        &mut self,
+        addr: u8,
+        operations: &mut [eh1::i2c::Operation<'_>],
+        stop: bool,
+    ) -> Result<(), Error> {
+        use eh1::i2c::Operation;
+
+        let (mut chunk, mut remaining) = self.start_operations(addr, operations, stop);
+
+        for operation in operations {
+            let len = match operation {
+                Operation::Read(buffer) => buffer.len(),
+                Operation::Write(bytes) => bytes.len(),
+            };
+            for i in 0..len {
+                if chunk == 0 {
+                    self.wait_for(|isr| isr.tcr().bit_is_set()).await?;
+                    chunk = self.reload(&mut remaining);
+                }
+
+                match operation {
+                    Operation::Read(buffer) => {
+                        self.wait_for(|isr| isr.rxne().bit_is_set()).await?;
+                        buffer[i] = self.i2c.rxdr.read().rxdata().bits();
+                    }
+                    Operation::Write(bytes) => {
+                        self.wait_for(|isr| isr.txe().bit_is_set()).await?;
+                        self.i2c.txdr.write(|w| w.txdata().bits(bytes[i]));
+                    }
+                }
+                chunk -= 1;
+            }
+        }
+
+        if !stop {
+            self.wait_for(|isr| isr.tc().bit_is_set()).await?;
+        }
+
+        Ok(())
     }
 }
 
-impl<I, SDA, SCL> Read for I2c<I, SDA, SCL>
+#[cfg(feature = "async")]
+impl<I, SDA, SCL> embedded_hal_async::i2c::I2c for I2c<I, SDA, SCL>
 where
     I: Instance,
 {
-    type Error = Error;
+    async fn transaction(
+        &mut self,
+        address: u8,
+        operations: &mut [eh1::i2c::Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        use eh1::i2c::Operation;
 
-    fn read(&mut self, addr: u8, buffer: &mut [u8]) -> Result<(), Self::Error> {
-        self.write_read(addr, &[], buffer)
+        // wait for i2c device to be available
+        while self.i2c.isr.read().busy().is_busy() {
+            self.check_errors()?;
+        }
+
+        let mut operations = operations;
+        while !operations.is_empty() {
+            let reading = matches!(operations[0], Operation::Read(_));
+            let len = operations
+                .iter()
+                .position(|operation| matches!(operation, Operation::Read(_)) != reading)
+                .unwrap_or(operations.len());
+
+            let (current, rest) = operations.split_at_mut(len);
+            self.transfer_operations_async(address, current, rest.is_empty())
+                .await?;
+            operations = rest;
+        }
+
+        Ok(())
     }
 }
 
 pub trait Instance: Deref<Target = RegisterBlock> {
     fn ptr() -> *const RegisterBlock;
     fn initialize(&self, rcc: &mut Rcc);
+    fn clock_frequency(&self, rcc: &Rcc) -> u32;
+    #[cfg(feature = "async")]
+    fn waker() -> &'static InterruptWaker;
+}
+
+/// Timing configuration of the I2C bus
+///
+/// The values of the fields are written to the timing register (`TIMINGR`) as
+/// they are. See STM32L0x2 reference manual, section "I2C timings".
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Timing {
+    /// Timing prescaler (`PRESC`), 0 to 15
+    pub presc: u8,
+    /// SCL low period (`SCLL`)
+    pub scll: u8,
+    /// SCL high period (`SCLH`)
+    pub sclh: u8,
+    /// Data hold time (`SDADEL`), 0 to 15
+    pub sdadel: u8,
+    /// Data setup time (`SCLDEL`), 0 to 15
+    pub scldel: u8,
+}
+
+impl Timing {
+    /// Computes the timing for the bus frequency `freq` (in Hz), from the I2C
+    /// clock frequency `i2cclk` (in Hz)
+    ///
+    /// Returns [`config::Error::Frequency`], if `freq` is above 1 MHz, or
+    /// can't be derived from `i2cclk`.
+    ///
+    /// As this is a `const fn`, the timing can be computed at compile time,
+    /// so an invalid combination fails the build:
+    ///
+    /// ``` ignore
+    /// const TIMING: Timing = match Timing::new(16_000_000, 400_000) {
+    ///     Ok(timing) => timing,
+    ///     Err(_) => panic!("invalid I2C timing"),
+    /// };
+    ///
+    /// let i2c = I2c::with_timing(dp.I2C1, sda, scl, TIMING, &mut rcc);
+    /// ```
+    pub const fn new(i2cclk: u32, freq: u32) -> Result<Self, config::Error> {
+        if freq == 0 || freq > 1_000_000 || i2cclk / freq < 4 {
+            return Err(config::Error::Frequency);
+        }
+
+        // TODO review compliance with the timing requirements of I2C
+        // t_I2CCLK = 1 / PCLK1
+        // t_PRESC  = (PRESC + 1) * t_I2CCLK
+        // t_SCLL   = (SCLL + 1) * t_PRESC
+        // t_SCLH   = (SCLH + 1) * t_PRESC
+        //
+        // t_SYNC1 + t_SYNC2 > 4 * t_I2CCLK
+        // t_SCL ~= t_SYNC1 + t_SYNC2 + t_SCLL + t_SCLH
+        let ratio = i2cclk / freq - 4;
+        let (presc, scll, sclh, sdadel, scldel) = if freq >= 100_000 {
+            // fast-mode or fast-mode plus
+            // here we pick SCLL + 1 = 2 * (SCLH + 1)
+            let presc = ratio / 387;
+
+            let sclh = match (ratio / (presc + 1)).checked_sub(3) {
+                Some(sclh) => sclh / 3,
+                None => return Err(config::Error::Frequency),
+            };
+            let scll = 2 * (sclh + 1) - 1;
+
+            let (sdadel, scldel) = if freq > 400_000 {
+                // fast-mode plus
+                (0, (i2cclk / 4_000_000 / (presc + 1)).checked_sub(1))
+            } else {
+                // fast-mode
+                (
+                    i2cclk / 8_000_000 / (presc + 1),
+                    (i2cclk / 2_000_000 / (presc + 1)).checked_sub(1),
+                )
+            };
+
+            (presc, scll, sclh, sdadel, scldel)
+        } else {
+            // standard-mode
+            // here we pick SCLL = SCLH
+            let presc = ratio / 514;
+
+            let sclh = match (ratio / (presc + 1)).checked_sub(2) {
+                Some(sclh) => sclh / 2,
+                None => return Err(config::Error::Frequency),
+            };
+            let scll = sclh;
+
+            let sdadel = i2cclk / 2_000_000 / (presc + 1);
+            let scldel = (i2cclk / 800_000 / (presc + 1)).checked_sub(1);
+
+            (presc, scll, sclh, sdadel, scldel)
+        };
+
+        let scldel = match scldel {
+            Some(scldel) => scldel,
+            None => return Err(config::Error::Frequency),
+        };
+
+        if presc >= 16 || scldel >= 16 || sdadel >= 16 || sclh > 0xff || scll > 0xff {
+            return Err(config::Error::Frequency);
+        }
+
+        Ok(Timing {
+            presc: presc as u8,
+            scll: scll as u8,
+            sclh: sclh as u8,
+            sdadel: sdadel as u8,
+            scldel: scldel as u8,
+        })
+    }
+
+    fn apply(&self, i2c: &RegisterBlock) {
+        i2c.timingr.write(|w| {
+            w.presc().bits(self.presc);
+            w.scll().bits(self.scll);
+            w.sclh().bits(self.sclh);
+            w.sdadel().bits(self.sdadel);
+            w.scldel().bits(self.scldel)
+        });
+    }
 }
 
 // I2C SDA pin
@@ -468,7 +948,8 @@ pub trait SCLPin<I2C> {
 }
 
 // I2C error
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Error {
     Overrun,
     Nack,
@@ -478,14 +959,15 @@ pub enum Error {
 }
 
 pub trait I2cExt<I2C> {
-    fn i2c<SDA, SCL>(self, sda: SDA, scl: SCL, freq: Hertz, rcc: &mut Rcc) -> I2c<I2C, SDA, SCL>
+    fn i2c<SDA, SCL, T>(self, sda: SDA, scl: SCL, freq: T, rcc: &mut Rcc) -> I2c<I2C, SDA, SCL>
     where
         SDA: SDAPin<I2C>,
-        SCL: SCLPin<I2C>;
+        SCL: SCLPin<I2C>,
+        T: Into<Hertz>;
 }
 
 macro_rules! i2c {
-    ($I2CX:ident, $i2cxen:ident, $i2crst:ident,
+    ($I2CX:ident, $i2cclk:ident,
         sda: [ $(($PSDA:ty, $afsda:expr),)+ ],
         scl: [ $(($PSCL:ty, $afscl:expr),)+ ],
     ) => {
@@ -506,16 +988,17 @@ macro_rules! i2c {
         )+
 
         impl I2cExt<$I2CX> for $I2CX {
-            fn i2c<SDA, SCL>(
+            fn i2c<SDA, SCL, T>(
                 self,
                 sda: SDA,
                 scl: SCL,
-                freq: Hertz,
+                freq: T,
                 rcc: &mut Rcc,
             ) -> I2c<$I2CX, SDA, SCL>
             where
                 SDA: SDAPin<$I2CX>,
                 SCL: SCLPin<$I2CX>,
+                T: Into<Hertz>,
             {
                 I2c::new(self, sda, scl, freq, rcc)
             }
@@ -528,11 +1011,38 @@ macro_rules! i2c {
 
             fn initialize(&self, rcc: &mut Rcc) {
                 // Enable clock for I2C
-                rcc.rb.apb1enr.modify(|_, w| w.$i2cxen().set_bit());
+                $I2CX::enable(rcc);
 
                 // Reset I2C
-                rcc.rb.apb1rstr.modify(|_, w| w.$i2crst().set_bit());
-                rcc.rb.apb1rstr.modify(|_, w| w.$i2crst().clear_bit());
+                $I2CX::reset(rcc);
+            }
+
+            fn clock_frequency(&self, rcc: &Rcc) -> u32 {
+                rcc.clocks.$i2cclk().0
+            }
+
+            #[cfg(feature = "async")]
+            fn waker() -> &'static InterruptWaker {
+                static WAKER: InterruptWaker = InterruptWaker::new();
+                &WAKER
+            }
+        }
+
+        #[cfg(feature = "async")]
+        impl InterruptHandler for $I2CX {
+            fn on_interrupt() {
+                // Safe, as the interrupts are only enabled by the async API,
+                // which is waiting for them.
+                let i2c = unsafe { &*$I2CX::ptr() };
+                i2c.cr1.modify(|_, w| {
+                    w.txie().clear_bit();
+                    w.rxie().clear_bit();
+                    w.tcie().clear_bit();
+                    w.nackie().clear_bit();
+                    w.errie().clear_bit()
+                });
+
+                <$I2CX as Instance>::waker().wake();
             }
         }
     };
@@ -540,7 +1050,7 @@ macro_rules! i2c {
 
 #[cfg(feature = "io-STM32L021")]
 i2c!(
-    I2C1, i2c1en, i2c1rst,
+    I2C1, i2c1_clk,
     sda: [
         (PA10<Output<OpenDrain>>, AltMode::AF1),
         (PA13<Output<OpenDrain>>, AltMode::AF3),
@@ -556,7 +1066,7 @@ i2c!(
 
 #[cfg(feature = "io-STM32L031")]
 i2c!(
-    I2C1, i2c1en, i2c1rst,
+    I2C1, i2c1_clk,
     sda: [
         (PA10<Output<OpenDrain>>, AltMode::AF1),
         (PB7<Output<OpenDrain>>, AltMode::AF1),
@@ -571,7 +1081,7 @@ i2c!(
 
 #[cfg(feature = "io-STM32L051")]
 i2c!(
-    I2C1, i2c1en, i2c1rst,
+    I2C1, i2c1_clk,
     sda: [
         (PB7<Output<OpenDrain>>, AltMode::AF1),
         (PB9<Output<OpenDrain>>, AltMode::AF4),
@@ -584,7 +1094,7 @@ i2c!(
 
 #[cfg(feature = "io-STM32L051")]
 i2c!(
-    I2C2, i2c2en, i2c2rst,
+    I2C2, apb1_clk,
     sda: [
         (PB11<Output<OpenDrain>>, AltMode::AF6),
         (PB14<Output<OpenDrain>>, AltMode::AF5),
@@ -597,7 +1107,7 @@ i2c!(
 
 #[cfg(feature = "io-STM32L071")]
 i2c!(
-    I2C1, i2c1en, i2c1rst,
+    I2C1, i2c1_clk,
     sda: [
         (PA10<Output<OpenDrain>>, AltMode::AF6),
         (PB7<Output<OpenDrain>>, AltMode::AF1),
@@ -612,7 +1122,7 @@ i2c!(
 
 #[cfg(feature = "io-STM32L071")]
 i2c!(
-    I2C2, i2c2en, i2c2rst,
+    I2C2, apb1_clk,
     sda: [
         (PB11<Output<OpenDrain>>, AltMode::AF6),
         (PB14<Output<OpenDrain>>, AltMode::AF5),
@@ -625,7 +1135,7 @@ i2c!(
 
 #[cfg(feature = "io-STM32L071")]
 i2c!(
-    I2C3, i2c3en, i2c3rst,
+    I2C3, i2c3_clk,
     sda: [
         (PB4<Output<OpenDrain>>, AltMode::AF7),
         (PC1<Output<OpenDrain>>, AltMode::AF7),
@@ -651,6 +1161,11 @@ pub struct Tx<I>(PhantomData<I>);
 #[cfg(feature = "stm32l0x2")]
 pub struct Rx<I>(PhantomData<I>);
 
+/// The result of preparing a DMA transfer, see [`dma::SetupResult`]
+#[cfg(feature = "stm32l0x2")]
+pub type SetupResult<Target, Token, Channel, Buffer> =
+    dma::SetupResult<Transfer<Target, Token, Channel, Buffer, dma::Ready>, Target, Channel, Buffer>;
+
 /// I2C-specific wrapper around [`dma::Transfer`]
 #[cfg(feature = "stm32l0x2")]
 pub struct Transfer<Target, Token, Channel, Buffer, State> {
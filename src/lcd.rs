@@ -0,0 +1,600 @@
+//! Interface to the LCD controller
+//!
+//! Drives passive LCD glass with up to 8 common terminals (COM) and the
+//! segment terminals (SEG) of the package. The display is refreshed from the
+//! display memory by the hardware, also in Stop mode.
+//!
+//! The LCD controller is clocked from the RTC clock, so the RTC clock source
+//! needs to be selected before, e.g. using `BackupDomain::set_rtc_clock`. The
+//! frame rate is
+//!
+//! ```text
+//! f_frame = f_rtcclk / (2^prescaler * (16 + divider)) * duty
+//! ```
+//!
+//! [`SegmentDisplay`] shows text on 7-segment or 14-segment displays, based
+//! on a mapping of their segments to the terminals, which is provided by the
+//! board.
+
+use core::ptr;
+
+use crate::{
+    gpio::{AltMode, PinMode},
+    pac::{lcd::fcr, LCD},
+    rcc::{Enable, Rcc, Reset},
+};
+
+use crate::gpio::{
+    gpioa::{PA1, PA10, PA15, PA2, PA3, PA6, PA7, PA8, PA9},
+    gpiob::{PB0, PB1, PB10, PB11, PB12, PB13, PB14, PB15, PB3, PB4, PB5, PB8, PB9},
+    gpioc::{PC0, PC1, PC10, PC11, PC12, PC2, PC3, PC4, PC5, PC6, PC7, PC8, PC9},
+    gpiod::PD2,
+};
+
+/// The LCD controller
+pub struct Lcd {
+    lcd: LCD,
+}
+
+impl Lcd {
+    /// Configures and enables the LCD controller
+    ///
+    /// All segments are off, until the display memory is written and an
+    /// update is requested, see [`Lcd::write`] and [`Lcd::update`].
+    pub fn new(lcd: LCD, config: Config, rcc: &mut Rcc) -> Self {
+        // Reset peripheral
+        LCD::reset(rcc);
+
+        // Enable peripheral clock
+        LCD::enable(rcc);
+
+        // The frame control register is synchronized to the LCD clock.
+        lcd.fcr.write(|w| unsafe {
+            w.ps()
+                .bits(config.prescaler & 0xf)
+                .div()
+                .bits(config.divider & 0xf)
+        });
+        while lcd.sr.read().fcrsf().bit_is_clear() {}
+
+        lcd.cr.write(|w| unsafe {
+            w.duty()
+                .bits(config.duty as u8)
+                .bias()
+                .bits(config.bias as u8)
+                .vsel()
+                .bit(config.voltage_source == VoltageSource::External)
+        });
+
+        lcd.cr.modify(|_, w| w.lcden().set_bit());
+        while lcd.sr.read().ens().bit_is_clear() {}
+
+        // Wait for the internal step-up converter
+        if config.voltage_source == VoltageSource::Internal {
+            while lcd.sr.read().rdy().bit_is_clear() {}
+        }
+
+        Lcd { lcd }
+    }
+
+    /// Configures a pin as segment or common terminal
+    pub fn setup_pin<P: Pin>(&mut self, pin: &P) {
+        pin.setup();
+    }
+
+    /// Writes the segments driven by common terminal `com` to the display
+    /// memory
+    ///
+    /// Bit `n` of `segments` turns on segment `n`. The display only shows the
+    /// new contents after [`Lcd::update`]. If an update is still in progress,
+    /// this blocks until it is done, as the display memory is write-protected
+    /// in the meantime.
+    ///
+    /// # Panics
+    ///
+    /// Panics, if `com` is not in `0..8`.
+    pub fn write(&mut self, com: usize, segments: u64) {
+        assert!(com < 8);
+
+        while self.is_update_pending() {}
+
+        // The display memory is made up of two words per common terminal,
+        // only the first of which is described by the PAC.
+        // Safe, as this only computes the address of the register.
+        let ram = unsafe { ptr::addr_of!((*LCD::ptr()).ram_com0) } as *mut u32;
+
+        // Safe, as the addresses are within the display memory.
+        unsafe {
+            ram.add(2 * com).write_volatile(segments as u32);
+            ram.add(2 * com + 1).write_volatile((segments >> 32) as u32);
+        }
+    }
+
+    /// Turns a single segment on or off in the display memory
+    ///
+    /// Like [`Lcd::write`], but leaves the other segments unchanged. The
+    /// display only shows the change after [`Lcd::update`].
+    ///
+    /// # Panics
+    ///
+    /// Panics, if the common terminal of `segment` is not in `0..8`, or its
+    /// segment terminal is not in `0..64`.
+    pub fn set_segment(&mut self, segment: Segment, on: bool) {
+        let com = usize::from(segment.com);
+        assert!(com < 8 && segment.seg < 64);
+
+        while self.is_update_pending() {}
+
+        // Safe, as this only computes the address of the register.
+        let ram = unsafe { ptr::addr_of!((*LCD::ptr()).ram_com0) } as *mut u32;
+        let word = 2 * com + usize::from(segment.seg >> 5);
+        let mask = 1 << (segment.seg & 0x1f);
+
+        // Safe, as the address is within the display memory.
+        unsafe {
+            let ram = ram.add(word);
+            let value = ram.read_volatile();
+            let value = if on { value | mask } else { value & !mask };
+            ram.write_volatile(value);
+        }
+    }
+
+    /// Transfers the display memory to the display
+    ///
+    /// Blocks until the display shows the new contents, which is at the start
+    /// of the next frame.
+    pub fn update(&mut self) {
+        while self.is_update_pending() {}
+
+        self.lcd.sr.write(|w| w.udr().set_bit());
+        while self.lcd.sr.read().udd().bit_is_clear() {}
+        self.lcd.clr.write(|w| w.uddc().set_bit());
+    }
+
+    /// Clears the display
+    pub fn clear(&mut self) {
+        for com in 0..8 {
+            self.write(com, 0);
+        }
+        self.update();
+    }
+
+    /// Makes segments blink
+    ///
+    /// The blink frequency is derived from the clock after prescaler and
+    /// divider.
+    pub fn set_blink(&mut self, mode: BlinkMode, frequency: BlinkFrequency) {
+        self.modify_fcr(|w| unsafe { w.blink().bits(mode as u8).blinkf().bits(frequency as u8) });
+    }
+
+    /// Sets the contrast, which selects the maximum segment voltage
+    ///
+    /// `contrast` ranges from 0 (2.60 V) to 7 (3.51 V), and is only effective
+    /// with the internal step-up converter. A higher contrast keeps the
+    /// display readable at low temperatures, or with glass that needs higher
+    /// voltages.
+    ///
+    /// # Panics
+    ///
+    /// Panics, if `contrast` is greater than 7.
+    pub fn set_contrast(&mut self, contrast: u8) {
+        assert!(contrast <= 7);
+        self.modify_fcr(|w| unsafe { w.cc().bits(contrast) });
+    }
+
+    /// Sets the dead time between frames, in phase periods
+    ///
+    /// During the dead time, all terminals are grounded, which reduces the
+    /// contrast without changing the voltages. `phases` ranges from 0 (no
+    /// dead time) to 7.
+    ///
+    /// # Panics
+    ///
+    /// Panics, if `phases` is greater than 7.
+    pub fn set_dead_time(&mut self, phases: u8) {
+        assert!(phases <= 7);
+        self.modify_fcr(|w| unsafe { w.dead().bits(phases) });
+    }
+
+    /// Sets the duration of the low-impedance drive at the start of every
+    /// phase
+    ///
+    /// `duration` ranges from 0 to 7, in units of `1 / f_ck_ps` (the clock
+    /// after the prescaler). Longer pulses help displays with high
+    /// capacitance to reach the full voltage, at the cost of current
+    /// consumption. With `high_drive`, the low impedance is used
+    /// permanently, which is needed with some glass at low supply voltages.
+    ///
+    /// # Panics
+    ///
+    /// Panics, if `duration` is greater than 7.
+    pub fn set_pulse_on_duration(&mut self, duration: u8, high_drive: bool) {
+        assert!(duration <= 7);
+        self.modify_fcr(|w| unsafe { w.pon().bits(duration).hd().bit(high_drive) });
+    }
+
+    /// Disables the LCD controller and releases it
+    pub fn free(self) -> LCD {
+        self.lcd.cr.modify(|_, w| w.lcden().clear_bit());
+        while self.lcd.sr.read().ens().bit_is_set() {}
+        self.lcd
+    }
+
+    /// Modifies the frame control register
+    ///
+    /// The register is synchronized to the LCD clock, so this waits until the
+    /// new value has been taken over.
+    fn modify_fcr<F>(&mut self, f: F)
+    where
+        F: for<'w> FnOnce(&'w mut fcr::W) -> &'w mut fcr::W,
+    {
+        while self.lcd.sr.read().fcrsf().bit_is_clear() {}
+        self.lcd.fcr.modify(|_, w| f(w));
+        while self.lcd.sr.read().fcrsf().bit_is_clear() {}
+    }
+
+    fn is_update_pending(&self) -> bool {
+        // The update display request bit (UDR) is missing from the PAC's
+        // status register reader.
+        self.lcd.sr.read().bits() & (1 << 2) != 0
+    }
+}
+
+/// Configuration of the LCD controller
+pub struct Config {
+    pub duty: Duty,
+    pub bias: Bias,
+    /// Divides the RTC clock by `2^prescaler`, up to 15
+    pub prescaler: u8,
+    /// Divides the prescaled clock by `16 + divider`, with `divider` up to 15
+    pub divider: u8,
+    pub voltage_source: VoltageSource,
+}
+
+impl Config {
+    pub fn duty(mut self, duty: Duty) -> Self {
+        self.duty = duty;
+        self
+    }
+
+    pub fn bias(mut self, bias: Bias) -> Self {
+        self.bias = bias;
+        self
+    }
+
+    pub fn prescaler(mut self, prescaler: u8) -> Self {
+        self.prescaler = prescaler;
+        self
+    }
+
+    pub fn divider(mut self, divider: u8) -> Self {
+        self.divider = divider;
+        self
+    }
+
+    pub fn voltage_source(mut self, voltage_source: VoltageSource) -> Self {
+        self.voltage_source = voltage_source;
+        self
+    }
+}
+
+impl Default for Config {
+    /// 1/4 duty and 1/3 bias, with a frame rate of 60 Hz from the LSE
+    fn default() -> Self {
+        Config {
+            duty: Duty::OneFourth,
+            bias: Bias::OneThird,
+            prescaler: 3,
+            divider: 1,
+            voltage_source: VoltageSource::Internal,
+        }
+    }
+}
+
+/// The segments that blink
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum BlinkMode {
+    Disabled = 0b00,
+    /// Only SEG0 on COM0
+    Seg0Com0 = 0b01,
+    /// SEG0 on all common terminals
+    Seg0AllCom = 0b10,
+    /// All segments on all common terminals
+    All = 0b11,
+}
+
+/// The blink frequency, as division of the clock after prescaler and divider
+///
+/// From a 1 kHz clock, `Div512` results in a frequency of about 2 Hz.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum BlinkFrequency {
+    Div8 = 0b000,
+    Div16 = 0b001,
+    Div32 = 0b010,
+    Div64 = 0b011,
+    Div128 = 0b100,
+    Div256 = 0b101,
+    Div512 = 0b110,
+    Div1024 = 0b111,
+}
+
+/// The duty ratio, which determines the number of common terminals
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Duty {
+    /// Only COM0 is used
+    Static = 0b000,
+    /// COM0 and COM1 are used
+    OneHalf = 0b001,
+    /// COM0 to COM2 are used
+    OneThird = 0b010,
+    /// COM0 to COM3 are used
+    OneFourth = 0b011,
+    /// COM0 to COM7 are used
+    OneEighth = 0b100,
+}
+
+/// The bias, which determines the number of voltage levels
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Bias {
+    OneFourth = 0b00,
+    OneHalf = 0b01,
+    OneThird = 0b10,
+}
+
+/// The source of the LCD supply voltage
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum VoltageSource {
+    /// The internal step-up converter
+    Internal,
+    /// An external supply on the VLCD pin
+    External,
+}
+
+/// Pins that can be used as segment or common terminal of the LCD controller
+pub trait Pin {
+    fn setup(&self);
+}
+
+macro_rules! impl_pin {
+    ($($name:ident,)*) => {
+        $(
+            impl<State: PinMode> Pin for $name<State> {
+                fn setup(&self) {
+                    self.set_alt_mode(AltMode::AF1);
+                }
+            }
+        )*
+    };
+}
+
+// COM0 to COM3
+impl_pin!(PA8, PA9, PA10, PB9,);
+
+// SEG0 to SEG27
+impl_pin!(
+    PA1, PA2, PA3, PA6, PA7, PB0, PB1, PB3, PB4, PB5, PB10, PB11, PB12, PB13, PB14, PB15, PB8,
+    PA15, PC0, PC1, PC2, PC3, PC4, PC5, PC6, PC7, PC8, PC9,
+);
+
+// COM4 to COM7, or SEG28 to SEG31
+impl_pin!(PC10, PC11, PC12, PD2,);
+
+/// A segment of the glass, connected to a common and a segment terminal
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Segment {
+    pub com: u8,
+    pub seg: u8,
+}
+
+impl Segment {
+    pub const fn new(com: u8, seg: u8) -> Self {
+        Segment { com, seg }
+    }
+}
+
+/// A character position of a segment display
+///
+/// Describes how the segments of a character position are connected to the
+/// terminals of the LCD controller, which depends on the glass and the board.
+pub trait Digit {
+    /// Returns the segments of the character position
+    ///
+    /// The order of the segments is defined by [`Digit::encode`].
+    fn segments(&self) -> &[Segment];
+
+    /// Returns the decimal point after the character position, if any
+    fn point(&self) -> Option<Segment>;
+
+    /// Returns the segments that show `c`, with bit `n` representing the
+    /// segment at index `n` of [`Digit::segments`]
+    ///
+    /// Returns `None`, if `c` can't be shown.
+    fn encode(c: char) -> Option<u16>;
+}
+
+/// A 7-segment character position
+///
+/// The segments are, in this order: a (top), b (top right), c (bottom
+/// right), d (bottom), e (bottom left), f (top left) and g (middle).
+pub struct SevenSegment {
+    pub segments: [Segment; 7],
+    pub point: Option<Segment>,
+}
+
+impl Digit for SevenSegment {
+    fn segments(&self) -> &[Segment] {
+        &self.segments
+    }
+
+    fn point(&self) -> Option<Segment> {
+        self.point
+    }
+
+    fn encode(c: char) -> Option<u16> {
+        Some(match c {
+            ' ' => 0x00,
+            '0' | 'O' => 0x3f,
+            '1' => 0x06,
+            '2' => 0x5b,
+            '3' => 0x4f,
+            '4' => 0x66,
+            '5' | 'S' | 's' => 0x6d,
+            '6' => 0x7d,
+            '7' => 0x07,
+            '8' => 0x7f,
+            '9' => 0x6f,
+            'A' | 'a' => 0x77,
+            'B' | 'b' => 0x7c,
+            'C' => 0x39,
+            'c' => 0x58,
+            'D' | 'd' => 0x5e,
+            'E' | 'e' => 0x79,
+            'F' | 'f' => 0x71,
+            'G' | 'g' => 0x3d,
+            'H' => 0x76,
+            'h' => 0x74,
+            'I' | 'i' => 0x30,
+            'J' | 'j' => 0x1e,
+            'L' | 'l' => 0x38,
+            'N' | 'n' => 0x54,
+            'o' => 0x5c,
+            'P' | 'p' => 0x73,
+            'R' | 'r' => 0x50,
+            'T' | 't' => 0x78,
+            'U' => 0x3e,
+            'u' => 0x1c,
+            'Y' | 'y' => 0x6e,
+            '-' => 0x40,
+            '_' => 0x08,
+            '=' => 0x48,
+            _ => return None,
+        })
+    }
+}
+
+/// A 14-segment character position
+///
+/// The segments are, in this order: a (top), b (top right), c (bottom
+/// right), d (bottom), e (bottom left), f (top left), g1 (middle left), g2
+/// (middle right), h (upper left diagonal), i (upper vertical), j (upper
+/// right diagonal), k (lower left diagonal), l (lower vertical) and m (lower
+/// right diagonal).
+pub struct FourteenSegment {
+    pub segments: [Segment; 14],
+    pub point: Option<Segment>,
+}
+
+impl Digit for FourteenSegment {
+    fn segments(&self) -> &[Segment] {
+        &self.segments
+    }
+
+    fn point(&self) -> Option<Segment> {
+        self.point
+    }
+
+    fn encode(c: char) -> Option<u16> {
+        Some(match c.to_ascii_uppercase() {
+            ' ' => 0x0000,
+            '0' => 0x0c3f,
+            '1' => 0x0406,
+            '2' => 0x00db,
+            '3' => 0x008f,
+            '4' => 0x00e6,
+            '5' | 'S' => 0x00ed,
+            '6' => 0x00fd,
+            '7' => 0x0007,
+            '8' => 0x00ff,
+            '9' => 0x00ef,
+            'A' => 0x00f7,
+            'B' => 0x128f,
+            'C' => 0x0039,
+            'D' => 0x120f,
+            'E' => 0x0079,
+            'F' => 0x0071,
+            'G' => 0x00bd,
+            'H' => 0x00f6,
+            'I' => 0x1209,
+            'J' => 0x001e,
+            'K' => 0x2470,
+            'L' => 0x0038,
+            'M' => 0x0536,
+            'N' => 0x2136,
+            'O' => 0x003f,
+            'P' => 0x00f3,
+            'Q' => 0x203f,
+            'R' => 0x20f3,
+            'T' => 0x1201,
+            'U' => 0x003e,
+            'V' => 0x0c30,
+            'W' => 0x2836,
+            'X' => 0x2d00,
+            'Y' => 0x1500,
+            'Z' => 0x0c09,
+            '-' => 0x00c0,
+            '+' => 0x12c0,
+            '*' => 0x3fc0,
+            '/' => 0x0c00,
+            '\\' => 0x2100,
+            '_' => 0x0008,
+            '=' => 0x00c8,
+            _ => return None,
+        })
+    }
+}
+
+/// A segment display, made up of character positions
+///
+/// Converts text into segments, according to the mapping of the character
+/// positions to the terminals of the LCD controller.
+pub struct SegmentDisplay<'d, D> {
+    lcd: Lcd,
+    digits: &'d [D],
+}
+
+impl<'d, D> SegmentDisplay<'d, D>
+where
+    D: Digit,
+{
+    /// Creates a display from the character positions, from left to right
+    pub fn new(lcd: Lcd, digits: &'d [D]) -> Self {
+        SegmentDisplay { lcd, digits }
+    }
+
+    /// Shows `text`, left-aligned
+    ///
+    /// A `.` after a character turns on the decimal point of its position,
+    /// so `"12.5"` takes up three positions. Characters that can't be shown
+    /// leave their position blank. Text that doesn't fit is cut off.
+    pub fn print(&mut self, text: &str) {
+        let mut chars = text.chars().peekable();
+
+        for digit in self.digits {
+            let (c, point) = match chars.next() {
+                Some('.') => (' ', true),
+                Some(c) => (c, chars.next_if_eq(&'.').is_some()),
+                None => (' ', false),
+            };
+
+            let pattern = D::encode(c).unwrap_or(0);
+            for (i, &segment) in digit.segments().iter().enumerate() {
+                self.lcd.set_segment(segment, pattern & (1 << i) != 0);
+            }
+            if let Some(segment) = digit.point() {
+                self.lcd.set_segment(segment, point);
+            }
+        }
+
+        self.lcd.update();
+    }
+
+    /// Gives access to the LCD controller, e.g. to control other segments
+    pub fn lcd(&mut self) -> &mut Lcd {
+        &mut self.lcd
+    }
+
+    /// Releases the LCD controller
+    pub fn free(self) -> Lcd {
+        self.lcd
+    }
+}
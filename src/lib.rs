@@ -15,14 +15,30 @@ pub use stm32l0::stm32l0x2 as pac;
 #[cfg(feature = "stm32l0x3")]
 pub use stm32l0::stm32l0x3 as pac;
 
+pub use fugit;
+
+pub use crate::error::Error;
+
 pub mod adc;
+#[cfg(feature = "aes")]
 pub mod aes;
+#[cfg(feature = "async")]
+pub mod asynch;
+pub mod bootloader;
 pub mod calibration;
+pub mod capture;
+pub mod comp;
+pub mod config;
+pub mod counter;
+pub mod crc;
+pub mod dbgmcu;
 pub mod delay;
 pub mod dma;
+pub mod encoder;
+pub mod error;
 pub mod exti;
-#[cfg(feature = "stm32l0x2")]
 pub mod flash;
+pub mod fw;
 pub mod gpio;
 #[cfg(any(
     feature = "io-STM32L021",
@@ -31,7 +47,11 @@ pub mod gpio;
     feature = "io-STM32L071",
 ))]
 pub mod i2c;
+#[cfg(feature = "stm32l0x3")]
+pub mod lcd;
 pub mod lptim;
+#[cfg(feature = "rtic")]
+pub mod monotonic;
 pub mod prelude;
 pub mod pwm;
 pub mod pwr;
@@ -46,10 +66,17 @@ pub mod rtc;
     feature = "io-STM32L071",
 ))]
 pub mod serial;
+pub mod signature;
 pub mod spi;
+pub mod storage;
 pub mod syscfg;
 pub mod time;
+pub mod timeout;
+#[cfg(feature = "time-driver-lptim")]
+pub mod time_driver;
 pub mod timer;
+#[cfg(any(feature = "stm32l0x2", feature = "stm32l0x3"))]
+pub mod tsc;
 #[cfg(all(
     feature = "stm32-usbd",
     any(feature = "stm32l0x2", feature = "stm32l0x3")
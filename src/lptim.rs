@@ -1,15 +1,29 @@
 //! Low-Power Timer (LPTIM) support.
 
+use crate::exti::{DirectLine, Exti};
 use crate::hal;
 use crate::pac::LPTIM;
-use crate::pwr::PWR;
-use crate::rcc::Rcc;
+use crate::pwr::{PowerMode, PWR};
+use crate::rcc::{Enable, Rcc, Reset};
 use crate::time::{Hertz, MicroSeconds};
 use cast::{u32, u64};
 use core::convert::TryFrom;
 use core::marker::PhantomData;
 use void::Void;
 
+#[cfg(any(
+    feature = "io-STM32L031",
+    feature = "io-STM32L051",
+    feature = "io-STM32L071"
+))]
+use crate::gpio::{
+    gpiob::{PB2, PB5, PB6, PB7},
+    AltMode, PinMode,
+};
+
+#[cfg(any(feature = "io-STM32L051", feature = "io-STM32L071"))]
+use crate::gpio::gpioc::{PC0, PC1, PC2, PC3};
+
 mod sealed {
     pub trait Sealed {}
 }
@@ -20,14 +34,34 @@ pub enum OneShot {}
 /// Low-Power Timer counting in periodic mode.
 pub enum Periodic {}
 
+/// Low-Power Timer generating a PWM signal on its output pin.
+pub enum Pwm {}
+
+/// Low-Power Timer counting edges on its Input1 pin.
+pub enum PulseCounter {}
+
+/// Low-Power Timer counting the signals of a quadrature encoder on its Input1 and Input2 pins.
+pub enum Encoder {}
+
+/// Low-Power Timer detecting missing pulses on its external trigger pin.
+pub enum Timeout {}
+
 impl sealed::Sealed for OneShot {}
 impl sealed::Sealed for Periodic {}
+impl sealed::Sealed for Pwm {}
+impl sealed::Sealed for PulseCounter {}
+impl sealed::Sealed for Encoder {}
+impl sealed::Sealed for Timeout {}
 
 /// Marker trait for counter directions.
 pub trait CountMode: sealed::Sealed {}
 
 impl CountMode for OneShot {}
 impl CountMode for Periodic {}
+impl CountMode for Pwm {}
+impl CountMode for PulseCounter {}
+impl CountMode for Encoder {}
+impl CountMode for Timeout {}
 
 /// Clock source selection for the Low-Power Timer `LPTIM`.
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
@@ -51,6 +85,59 @@ pub enum ClockSrc {
     Lse = 0b11,
 }
 
+/// Active edges of an external input.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Edge {
+    Rising,
+    Falling,
+    Both,
+}
+
+/// Digital filter for external inputs.
+///
+/// A change of the input level is only accepted once it has been stable for the given number of
+/// LPTIM clock cycles.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Filter {
+    Immediate = 0b00,
+    Clocks2 = 0b01,
+    Clocks4 = 0b10,
+    Clocks8 = 0b11,
+}
+
+/// Interrupt events.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Event {
+    /// Encoder direction change to down (DOWN).
+    EncDirDown,
+    /// Encoder direction change to up (UP).
+    EncDirUp,
+    /// ARR register update successful (ARROK).
+    AutoreloadUpdateOk,
+    /// CMP register update successful (CMPOK).
+    CompareUpdateOk,
+    /// Valid edge on ext. trigger input (EXTTRIG).
+    ExtTrig,
+    /// ARR register matches current CNT value (ARRM).
+    AutoreloadMatch,
+    /// CMP register matches current CNT value (CMPM).
+    CompareMatch,
+}
+
+impl Event {
+    fn interrupts(self) -> Interrupts {
+        Interrupts {
+            enc_dir_down: self == Event::EncDirDown,
+            enc_dir_up: self == Event::EncDirUp,
+            autoreload_update_ok: self == Event::AutoreloadUpdateOk,
+            compare_update_ok: self == Event::CompareUpdateOk,
+            ext_trig: self == Event::ExtTrig,
+            autoreload_match: self == Event::AutoreloadMatch,
+            compare_match: self == Event::CompareMatch,
+        }
+    }
+}
+
 /// Interrupt enable flags.
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
 pub struct Interrupts {
@@ -81,8 +168,12 @@ pub struct Interrupts {
 /// implemented and the `CountDown` implementation uses `Hertz` as the time unit. In one-shot mode,
 /// the `CountDown` implementation instead uses `MicroSeconds`, allowing for a multi-second timeout
 /// to be configured (with the tradeoff being a larger code size due to use of 64-bit arithmetic).
+///
+/// Using `init_pwm`, the timer generates a PWM signal on its output pin instead, implementing the
+/// embedded-hal `PwmPin` trait. `init_pulse_counter` and `init_encoder` count external signals,
+/// and `init_timeout` detects missing pulses on the external trigger.
 pub struct LpTimer<M: CountMode> {
-    lptim: LPTIM,
+    pub(crate) lptim: LPTIM,
     input_freq: Hertz,
     _mode: PhantomData<M>,
 }
@@ -105,21 +196,234 @@ impl LpTimer<OneShot> {
     }
 }
 
-impl<M: CountMode> LpTimer<M> {
-    fn init(lptim: LPTIM, pwr: &mut PWR, rcc: &mut Rcc, clk: ClockSrc) -> Self {
-        // `pwr` is not used. It is used as a marker that guarantees that `PWR.CR` is set so this
-        // function can set the `RCC.LSEON` bit, which is otherwise write protected.
-        let _ = pwr;
+impl LpTimer<Pwm> {
+    /// Initializes the Low-Power Timer for PWM output on the given pin.
+    ///
+    /// The timer needs to be started by calling `.set_frequency(freq)`. Clocked from LSE or LSI,
+    /// the output keeps running in Sleep and Stop mode.
+    ///
+    /// The output is high for one cycle more than the duty cycle, which can be at most
+    /// `get_max_duty() - 1`. This means the output never stays low or high for a full period.
+    /// Setting the duty cycle blocks until the new value has been written, which takes a few LPTIM
+    /// clock cycles, so the timer must be running.
+    pub fn init_pwm<P: OutPin>(
+        lptim: LPTIM,
+        pin: P,
+        pwr: &mut PWR,
+        rcc: &mut Rcc,
+        clk: ClockSrc,
+    ) -> Self {
+        pin.setup();
+
+        let timer = Self::init(lptim, pwr, rcc, clk);
+
+        // Invert the output, so it's high from the start of the period up to the compare value.
+        timer.lptim.cfgr.modify(|_, w| w.wavpol().set_bit());
+
+        timer
+    }
 
+    /// Sets the frequency of the PWM signal and starts the timer.
+    ///
+    /// The duty cycle needs to be set again afterwards.
+    pub fn set_frequency<T>(&mut self, freq: T)
+    where
+        T: Into<Hertz>,
+    {
+        self.configure(TimeConf::calculate_freq(self.input_freq, freq.into()));
+
+        // Start LPTIM in continuous mode.
+        self.lptim
+            .cr
+            .write(|w| w.enable().set_bit().cntstrt().set_bit());
+    }
+}
+
+impl hal::PwmPin for LpTimer<Pwm> {
+    type Duty = u16;
+
+    fn disable(&mut self) {
+        self.lptim.cr.write(|w| w.enable().clear_bit());
+    }
+
+    fn enable(&mut self) {
+        self.lptim.cr.write(|w| w.enable().set_bit());
+
+        // See `configure` for the required delay.
+        cortex_m::asm::delay(5000);
+
+        self.lptim
+            .cr
+            .write(|w| w.enable().set_bit().cntstrt().set_bit());
+    }
+
+    fn get_duty(&self) -> u16 {
+        self.lptim.cmp.read().cmp().bits()
+    }
+
+    fn get_max_duty(&self) -> u16 {
+        self.lptim.arr.read().arr().bits()
+    }
+
+    fn set_duty(&mut self, duty: u16) {
+        // The compare value must be below the autoreload value.
+        let cmp = duty.min(self.get_max_duty().saturating_sub(1));
+        self.write_compare(cmp);
+    }
+}
+
+impl LpTimer<PulseCounter> {
+    /// Initializes the Low-Power Timer to count edges on the given Input1 pin.
+    ///
+    /// The input is sampled by the clock selected with `clk`, so the input signal must be slower
+    /// than that. Clocked from LSE or LSI, pulses keep being counted in Stop mode. The counter
+    /// starts right away.
+    pub fn init_pulse_counter<P: Input1Pin>(
+        lptim: LPTIM,
+        pin: P,
+        pwr: &mut PWR,
+        rcc: &mut Rcc,
+        clk: ClockSrc,
+        edge: Edge,
+        filter: Filter,
+    ) -> Self {
+        pin.setup();
+
+        let mut timer = Self::init(lptim, pwr, rcc, clk);
+        timer.lptim.cfgr.modify(|_, w| {
+            match edge {
+                Edge::Rising => w.ckpol().rising_edge(),
+                Edge::Falling => w.ckpol().falling_edge(),
+                Edge::Both => w.ckpol().both_edges(),
+            };
+            w.ckflt().bits(filter as u8);
+            w.countmode().set_bit()
+        });
+        timer.start_counting();
+
+        timer
+    }
+
+    /// Returns the number of counted edges.
+    ///
+    /// The counter wraps around after 65535.
+    pub fn count(&self) -> u16 {
+        self.read_counter()
+    }
+}
+
+impl LpTimer<Encoder> {
+    /// Initializes the Low-Power Timer in encoder mode.
+    ///
+    /// The counter counts up or down on the given edges of both inputs, depending on the level of
+    /// the other input. Counting on both edges results in 4 counts per cycle of the input signals.
+    ///
+    /// The input signals are sampled by the clock selected with `clk`, so they must be slower
+    /// than that. Clocked from LSE or LSI, the encoder keeps being tracked in Stop mode. Changes of
+    /// the direction can be signaled through the `enc_dir_up` and `enc_dir_down` interrupts. The
+    /// counter starts right away.
+    pub fn init_encoder<P1: Input1Pin, P2: Input2Pin>(
+        lptim: LPTIM,
+        pins: (P1, P2),
+        pwr: &mut PWR,
+        rcc: &mut Rcc,
+        clk: ClockSrc,
+        edge: Edge,
+        filter: Filter,
+    ) -> Self {
+        pins.0.setup();
+        pins.1.setup();
+
+        let mut timer = Self::init(lptim, pwr, rcc, clk);
+        timer.lptim.cfgr.modify(|_, w| {
+            match edge {
+                Edge::Rising => w.ckpol().rising_edge(),
+                Edge::Falling => w.ckpol().falling_edge(),
+                Edge::Both => w.ckpol().both_edges(),
+            };
+            w.ckflt().bits(filter as u8);
+            w.enc().set_bit()
+        });
+        timer.start_counting();
+
+        timer
+    }
+
+    /// Returns the current count.
+    pub fn count(&self) -> u16 {
+        self.read_counter()
+    }
+}
+
+impl LpTimer<Timeout> {
+    /// Initializes the Low-Power Timer in timeout mode.
+    ///
+    /// Every active edge on the external trigger pin restarts the counter. If no edge arrives
+    /// within the timeout, the compare match flag is set, which can wake up the microcontroller
+    /// through the `compare_match` interrupt. Clocked from LSE or LSI, this keeps working in Stop
+    /// mode.
+    ///
+    /// The timer needs to be started by calling `.start(timeout)`.
+    pub fn init_timeout<P: EtrPin>(
+        lptim: LPTIM,
+        pin: P,
+        pwr: &mut PWR,
+        rcc: &mut Rcc,
+        clk: ClockSrc,
+        edge: Edge,
+        filter: Filter,
+    ) -> Self {
+        pin.setup();
+
+        let timer = Self::init(lptim, pwr, rcc, clk);
+        timer.lptim.cfgr.modify(|_, w| {
+            match edge {
+                Edge::Rising => w.trigen().rising_edge(),
+                Edge::Falling => w.trigen().falling_edge(),
+                Edge::Both => w.trigen().both_edges(),
+            };
+            w.trigsel().trig0();
+            w.trgflt().bits(filter as u8)
+        });
+
+        timer
+    }
+
+    /// Starts watching the external trigger with the given timeout.
+    ///
+    /// Counting only starts with the first trigger. Interrupts need to be enabled before calling
+    /// this method, as doing so disables the timer.
+    ///
+    /// # Panics
+    ///
+    /// Panics, if the timeout is shorter than a cycle of the LPTIM clock, or longer than
+    /// 128 * 65535 cycles.
+    pub fn start<T>(&mut self, timeout: T)
+    where
+        T: Into<MicroSeconds>,
+    {
+        self.configure_compare(timeout.into());
+
+        self.lptim
+            .cr
+            .write(|w| w.enable().set_bit().cntstrt().set_bit());
+    }
+
+    /// Returns whether the timeout has expired since the last call, and clears the flag.
+    pub fn timed_out(&mut self) -> bool {
+        let expired = self.lptim.isr.read().cmpm().bit_is_set();
+        self.lptim.icr.write(|w| w.cmpmcf().set_bit());
+        expired
+    }
+}
+
+impl<M: CountMode> LpTimer<M> {
+    pub(crate) fn init(lptim: LPTIM, pwr: &mut PWR, rcc: &mut Rcc, clk: ClockSrc) -> Self {
         // Enable selected clock and determine its frequency
         let input_freq = match clk {
             ClockSrc::Apb1 => rcc.clocks.apb1_clk(), // always enabled
             ClockSrc::Lsi => {
-                // Turn on LSI
-                rcc.rb.csr.modify(|_, w| w.lsion().set_bit());
-
-                // Wait for LSI to be ready
-                while rcc.rb.csr.read().lsirdy().bit_is_clear() {}
+                rcc.enable_lsi();
 
                 Hertz(37_000)
             }
@@ -133,11 +437,9 @@ impl<M: CountMode> LpTimer<M> {
                 Hertz(16_000_000)
             }
             ClockSrc::Lse => {
-                // Turn on LSE
-                rcc.rb.csr.modify(|_, w| w.lseon().set_bit());
-
-                // Wait for LSE to be ready
-                while rcc.rb.csr.read().lserdy().bit_is_clear() {}
+                // Turn on LSE, which is write protected as part of the backup
+                // domain
+                pwr.with_backup_domain_access(rcc, |bd| bd.enable_lse());
 
                 Hertz(32_768)
             }
@@ -146,10 +448,8 @@ impl<M: CountMode> LpTimer<M> {
         // Select and enable clock. Right now we only support the internal RCC clocks, but LPTIM can
         // also run as a counter with a dedicated external input.
         rcc.rb.ccipr.modify(|_, w| w.lptim1sel().bits(clk as u8));
-        rcc.rb.apb1enr.modify(|_, w| w.lptim1en().set_bit());
-
-        rcc.rb.apb1rstr.modify(|_, w| w.lptim1rst().set_bit());
-        rcc.rb.apb1rstr.modify(|_, w| w.lptim1rst().clear_bit());
+        LPTIM::enable(rcc);
+        LPTIM::reset(rcc);
 
         Self {
             lptim,
@@ -166,7 +466,7 @@ impl<M: CountMode> LpTimer<M> {
 
         self.lptim
             .cfgr
-            .write(|w| w.presc().bits(conf.psc_encoded).timout().set_bit());
+            .modify(|_, w| w.presc().bits(conf.psc_encoded).timout().set_bit());
 
         self.lptim.cr.write(|w| w.enable().set_bit());
 
@@ -178,6 +478,63 @@ impl<M: CountMode> LpTimer<M> {
 
         // ARR can only be changed while the timer is *en*abled
         self.lptim.arr.write(|w| w.arr().bits(conf.arr));
+
+        // Clear a match of the previous configuration, so `wait` doesn't
+        // return early.
+        self.lptim.icr.write(|w| w.arrmcf().set_bit());
+    }
+
+    /// Starts counting over the full 16-bit range, in continuous mode.
+    pub(crate) fn start_counting(&mut self) {
+        // The encoder mode requires the prescaler to be disabled.
+        self.configure(TimeConf {
+            psc_encoded: 0,
+            arr: TimeConf::ARR_MAX,
+        });
+
+        self.lptim
+            .cr
+            .write(|w| w.enable().set_bit().cntstrt().set_bit());
+    }
+
+    /// Configures the timer to count over the full range, with the compare value matching after
+    /// `period`.
+    ///
+    /// Clears the compare match flag.
+    fn configure_compare(&mut self, period: MicroSeconds) {
+        let conf = TimeConf::calculate_period(self.input_freq, period);
+
+        self.configure(TimeConf {
+            psc_encoded: conf.psc_encoded,
+            arr: TimeConf::ARR_MAX,
+        });
+
+        // The compare value needs to be below the autoreload value.
+        self.write_compare(conf.arr.min(TimeConf::ARR_MAX - 1));
+        self.lptim.icr.write(|w| w.cmpmcf().set_bit());
+    }
+
+    /// Writes the compare register and waits until the write has completed.
+    ///
+    /// The timer must be enabled.
+    pub(crate) fn write_compare(&self, cmp: u16) {
+        self.lptim.cmp.write(|w| w.cmp().bits(cmp));
+
+        while self.lptim.isr.read().cmpok().bit_is_clear() {}
+        self.lptim.icr.write(|w| w.cmpokcf().set_bit());
+    }
+
+    /// Reads the counter.
+    ///
+    /// The counter may be clocked asynchronously to the APB clock, so it's only read reliably if
+    /// two consecutive reads return the same value.
+    pub(crate) fn read_counter(&self) -> u16 {
+        loop {
+            let cnt = self.lptim.cnt.read().cnt().bits();
+            if cnt == self.lptim.cnt.read().cnt().bits() {
+                return cnt;
+            }
+        }
     }
 
     /// Disables and destructs the timer, returning the raw `LPTIM` peripheral.
@@ -216,6 +573,45 @@ impl<M: CountMode> LpTimer<M> {
         })
     }
 
+    /// Disables the timer and starts listening for an interrupt event.
+    pub fn listen(&mut self, event: Event) {
+        self.enable_interrupts(event.interrupts());
+    }
+
+    /// Disables the timer and stops listening for an interrupt event.
+    pub fn unlisten(&mut self, event: Event) {
+        self.disable_interrupts(event.interrupts());
+    }
+
+    /// Returns whether the flag of an interrupt event is set.
+    ///
+    /// This doesn't depend on whether the event is listened for.
+    pub fn is_pending(&self, event: Event) -> bool {
+        let isr = self.lptim.isr.read();
+        match event {
+            Event::EncDirDown => isr.down().bit_is_set(),
+            Event::EncDirUp => isr.up().bit_is_set(),
+            Event::AutoreloadUpdateOk => isr.arrok().bit_is_set(),
+            Event::CompareUpdateOk => isr.cmpok().bit_is_set(),
+            Event::ExtTrig => isr.exttrig().bit_is_set(),
+            Event::AutoreloadMatch => isr.arrm().bit_is_set(),
+            Event::CompareMatch => isr.cmpm().bit_is_set(),
+        }
+    }
+
+    /// Clears the flag of an interrupt event.
+    pub fn clear(&mut self, event: Event) {
+        self.lptim.icr.write(|w| match event {
+            Event::EncDirDown => w.downcf().set_bit(),
+            Event::EncDirUp => w.upcf().set_bit(),
+            Event::AutoreloadUpdateOk => w.arrokcf().set_bit(),
+            Event::CompareUpdateOk => w.cmpokcf().set_bit(),
+            Event::ExtTrig => w.exttrigcf().set_bit(),
+            Event::AutoreloadMatch => w.arrmcf().set_bit(),
+            Event::CompareMatch => w.cmpmcf().set_bit(),
+        });
+    }
+
     /// Disables the timer and disables the given interrupts.
     pub fn disable_interrupts(&mut self, interrupts: Interrupts) {
         // IER can only be modified when the timer is disabled
@@ -299,6 +695,219 @@ impl hal::timer::CountDown for LpTimer<OneShot> {
     }
 }
 
+/// Wakes up the microcontroller from low-power modes, using the Low-Power Timer.
+///
+/// Each wakeup is scheduled through a compare interrupt, with a resolution of a single LPTIM
+/// clock cycle (about 30 µs from LSE). This is finer than what the RTC wakeup timer can provide
+/// with most of its clock settings.
+pub struct LptimWakeup {
+    timer: LpTimer<OneShot>,
+}
+
+impl LptimWakeup {
+    /// Initializes the Low-Power Timer as a wakeup timer.
+    ///
+    /// Starts listening for the `LPTIM1` EXTI line, which is required to leave Stop mode.
+    ///
+    /// # Panics
+    ///
+    /// Panics, if `clk` is neither LSE nor LSI. The other clocks don't run in Stop mode.
+    pub fn new(lptim: LPTIM, exti: &mut Exti, pwr: &mut PWR, rcc: &mut Rcc, clk: ClockSrc) -> Self {
+        assert!(clk == ClockSrc::Lse || clk == ClockSrc::Lsi);
+
+        let timer = LpTimer::init(lptim, pwr, rcc, clk);
+
+        // IER can only be modified when the timer is disabled, which it is after `init`.
+        timer.lptim.ier.write(|w| w.cmpmie().enabled());
+        exti.listen_direct(DirectLine::Lptim1);
+
+        Self { timer }
+    }
+
+    /// Enters the given low-power mode, until `period` has passed.
+    ///
+    /// The power mode is typically Stop mode, as returned by `PWR::stop_mode`. Returns `true`, if
+    /// the wakeup was caused by the timer, `false` if another interrupt woke the microcontroller up
+    /// early.
+    ///
+    /// # Panics
+    ///
+    /// Panics, if the period is shorter than a cycle of the LPTIM clock, or longer than 128 * 65535
+    /// cycles.
+    pub fn wait<T, M>(&mut self, exti: &mut Exti, period: T, power_mode: M) -> bool
+    where
+        T: Into<MicroSeconds>,
+        M: PowerMode,
+    {
+        self.timer.configure_compare(period.into());
+
+        let lptim = &self.timer.lptim;
+        lptim.cr.write(|w| w.enable().set_bit().sngstrt().set_bit());
+
+        exti.wait_for_irq(DirectLine::Lptim1, power_mode);
+
+        let expired = lptim.isr.read().cmpm().bit_is_set();
+        lptim.icr.write(|w| w.cmpmcf().set_bit());
+        expired
+    }
+
+    /// Disables the timer, returning the raw `LPTIM` peripheral.
+    ///
+    /// The `LPTIM1` EXTI line is left enabled.
+    pub fn free(self) -> LPTIM {
+        // IER can only be modified when the timer is disabled
+        self.timer.lptim.cr.reset();
+        self.timer.lptim.ier.reset();
+        self.timer.free()
+    }
+}
+
+/// A 64-bit monotonic clock, using the Low-Power Timer.
+///
+/// The 16-bit counter is extended in software, by counting its overflows in the `LPTIM1`
+/// interrupt. Clocked from LSE or LSI, it keeps counting through Stop mode, which makes it suitable
+/// for timestamping events in applications that spend most of their time asleep.
+///
+/// The `LPTIM1` interrupt needs to be unmasked and call `on_interrupt` at least once per overflow
+/// of the counter, i.e. every 2 seconds when running from LSE.
+pub struct LptimMonotonic {
+    timer: LpTimer<Periodic>,
+    overflows: u64,
+}
+
+impl LptimMonotonic {
+    /// Initializes the Low-Power Timer as a monotonic clock and starts it.
+    ///
+    /// The counter runs at the frequency of the selected clock. Starts listening for the `LPTIM1`
+    /// EXTI line, so overflows wake up the microcontroller from Stop mode.
+    pub fn new(lptim: LPTIM, exti: &mut Exti, pwr: &mut PWR, rcc: &mut Rcc, clk: ClockSrc) -> Self {
+        let mut timer = LpTimer::init(lptim, pwr, rcc, clk);
+
+        // IER can only be modified when the timer is disabled, which it is after `init`.
+        timer.lptim.ier.write(|w| w.arrmie().enabled());
+        exti.listen_direct(DirectLine::Lptim1);
+
+        timer.start_counting();
+
+        Self {
+            timer,
+            overflows: 0,
+        }
+    }
+
+    /// Handles the overflow interrupt.
+    ///
+    /// Needs to be called from the `LPTIM1` interrupt handler.
+    pub fn on_interrupt(&mut self) {
+        let lptim = &self.timer.lptim;
+        if lptim.isr.read().arrm().bit_is_set() {
+            lptim.icr.write(|w| w.arrmcf().set_bit());
+            self.overflows += 1;
+        }
+    }
+
+    /// Returns the number of ticks since the clock was started.
+    pub fn now(&self) -> u64 {
+        let cnt = self.timer.read_counter();
+
+        // An overflow might have happened, that hasn't been handled in the interrupt yet. If the
+        // counter value is low, it has been read after that overflow.
+        let overflows = if self.timer.lptim.isr.read().arrm().bit_is_set() && cnt < 0x8000 {
+            self.overflows + 1
+        } else {
+            self.overflows
+        };
+
+        overflows << 16 | u64(cnt)
+    }
+
+    /// Returns the frequency the clock is counting at.
+    pub fn frequency(&self) -> Hertz {
+        self.timer.input_freq
+    }
+
+    /// Disables the timer, returning the raw `LPTIM` peripheral.
+    ///
+    /// The `LPTIM1` EXTI line is left enabled.
+    pub fn free(self) -> LPTIM {
+        // IER can only be modified when the timer is disabled
+        self.timer.lptim.cr.reset();
+        self.timer.lptim.ier.reset();
+        self.timer.free()
+    }
+}
+
+/// Pins that can be used as the output of the Low-Power Timer.
+pub trait OutPin {
+    fn setup(&self);
+}
+
+/// Pins that can be used as Input1 of the Low-Power Timer.
+pub trait Input1Pin {
+    fn setup(&self);
+}
+
+/// Pins that can be used as Input2 of the Low-Power Timer.
+pub trait Input2Pin {
+    fn setup(&self);
+}
+
+/// Pins that can be used as external trigger of the Low-Power Timer.
+pub trait EtrPin {
+    fn setup(&self);
+}
+
+#[cfg(any(
+    feature = "io-STM32L031",
+    feature = "io-STM32L051",
+    feature = "io-STM32L071"
+))]
+macro_rules! impl_pin {
+    ($trait:ident: $($name:ident, $alternate_function:ident;)*) => {
+        $(
+            impl<State: PinMode> $trait for $name<State> {
+                fn setup(&self) {
+                    self.set_alt_mode(AltMode::$alternate_function);
+                }
+            }
+        )*
+    };
+}
+
+#[cfg(any(
+    feature = "io-STM32L031",
+    feature = "io-STM32L051",
+    feature = "io-STM32L071"
+))]
+impl_pin!(OutPin: PB2, AF2;);
+#[cfg(any(
+    feature = "io-STM32L031",
+    feature = "io-STM32L051",
+    feature = "io-STM32L071"
+))]
+impl_pin!(Input1Pin: PB5, AF2;);
+#[cfg(any(
+    feature = "io-STM32L031",
+    feature = "io-STM32L051",
+    feature = "io-STM32L071"
+))]
+impl_pin!(Input2Pin: PB7, AF2;);
+#[cfg(any(
+    feature = "io-STM32L031",
+    feature = "io-STM32L051",
+    feature = "io-STM32L071"
+))]
+impl_pin!(EtrPin: PB6, AF2;);
+
+#[cfg(any(feature = "io-STM32L051", feature = "io-STM32L071"))]
+impl_pin!(OutPin: PC1, AF0;);
+#[cfg(any(feature = "io-STM32L051", feature = "io-STM32L071"))]
+impl_pin!(Input1Pin: PC0, AF0;);
+#[cfg(any(feature = "io-STM32L051", feature = "io-STM32L071"))]
+impl_pin!(Input2Pin: PC2, AF0;);
+#[cfg(any(feature = "io-STM32L051", feature = "io-STM32L071"))]
+impl_pin!(EtrPin: PC3, AF0;);
+
 #[derive(Copy, Clone)]
 struct TimeConf {
     psc_encoded: u8,
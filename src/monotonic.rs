@@ -0,0 +1,161 @@
+//! RTIC monotonic timers
+//!
+//! Implements `rtic_monotonic::Monotonic` on top of the 16-bit general-purpose
+//! timers. The counter is extended to 32 bits in software, by counting
+//! overflows in the update interrupt. Capture/compare channel 1 is used to
+//! schedule the timer queue interrupt.
+//!
+//! Both the update and the compare events are signaled through the timer's
+//! interrupt, which needs to be bound to RTIC's timer queue (`binds = TIM2`,
+//! for example).
+
+use fugit::{TimerDurationU32, TimerInstantU32};
+use rtic_monotonic::Monotonic;
+
+#[cfg(feature = "io-STM32L071")]
+use crate::pac::TIM3;
+use crate::pac::{TIM2, TIM21, TIM22};
+use crate::rcc::{Enable, Rcc, Reset};
+use cast::u16;
+
+/// A monotonic timer, counting at `FREQ` Hz
+pub struct MonoTimer<TIM, const FREQ: u32> {
+    tim: TIM,
+    overflows: u16,
+}
+
+macro_rules! mono_timers {
+    ($($TIM:ident: ($timclk:ident),)+) => {
+        $(
+            impl<const FREQ: u32> MonoTimer<$TIM, FREQ> {
+                /// Configures a TIM peripheral as a monotonic timer
+                ///
+                /// # Panics
+                ///
+                /// Panics, if the timer clock is not a multiple of `FREQ`.
+                pub fn new(tim: $TIM, rcc: &mut Rcc) -> Self {
+                    $TIM::enable(rcc);
+                    $TIM::reset(rcc);
+
+                    let clk = rcc.clocks.$timclk().0;
+                    assert!(FREQ > 0 && clk % FREQ == 0);
+                    let psc = u16(clk / FREQ - 1).unwrap();
+                    tim.psc.write(|w| w.psc().bits(psc));
+
+                    // This is only unsafe for some timers, so we need this to
+                    // suppress the warnings.
+                    #[allow(unused_unsafe)]
+                    tim.arr.write(|w| unsafe { w.arr().bits(u16::MAX) });
+
+                    // Load the prescaler value, without raising an update
+                    // interrupt.
+                    tim.cr1.modify(|_, w| w.urs().set_bit());
+                    tim.egr.write(|w| w.ug().set_bit());
+
+                    MonoTimer { tim, overflows: 0 }
+                }
+
+                /// Releases the TIM peripheral
+                pub fn release(self) -> $TIM {
+                    self.tim.cr1.modify(|_, w| w.cen().clear_bit());
+                    self.tim.dier.modify(|_, w| w.uie().clear_bit().cc1ie().clear_bit());
+                    self.tim
+                }
+
+                /// Clears the status flags in `mask`, leaving the others
+                /// unchanged
+                fn clear_flags(tim: &$TIM, mask: u32) {
+                    // This is safe, as all bits are valid. It's only unsafe
+                    // for some timers, so we need this to suppress the
+                    // warnings.
+                    #[allow(unused_unsafe)]
+                    tim.sr.write(|w| unsafe { w.bits(!mask) });
+                }
+            }
+
+            impl<const FREQ: u32> Monotonic for MonoTimer<$TIM, FREQ> {
+                type Instant = TimerInstantU32<FREQ>;
+                type Duration = TimerDurationU32<FREQ>;
+
+                fn now(&mut self) -> Self::Instant {
+                    let cnt = self.tim.cnt.read().cnt().bits();
+
+                    // An overflow might have happened, that hasn't been
+                    // handled in the interrupt yet. If the counter value is
+                    // low, it has been read after that overflow.
+                    let overflows = if self.tim.sr.read().uif().bit_is_set() && cnt < 0x8000 {
+                        self.overflows.wrapping_add(1)
+                    } else {
+                        self.overflows
+                    };
+
+                    Self::Instant::from_ticks(u32::from(overflows) << 16 | u32::from(cnt))
+                }
+
+                fn set_compare(&mut self, instant: Self::Instant) {
+                    let now = self.now();
+
+                    // The compare event can only be scheduled within the
+                    // current range of the counter. Anything further out is
+                    // handled, once the overflow interrupt has fired often
+                    // enough.
+                    let ticks = instant.ticks().wrapping_sub(now.ticks());
+                    if ticks <= u32::from(u16::MAX) {
+                        let ccr = instant.ticks() as u16;
+
+                        // This is only unsafe for some timers, so we need
+                        // this to suppress the warnings.
+                        #[allow(unused_unsafe)]
+                        self.tim.ccr1.write(|w| unsafe { w.ccr().bits(ccr) });
+                    }
+                }
+
+                fn clear_compare_flag(&mut self) {
+                    // The flags are cleared by writing zero, so writing ones
+                    // to the other flags doesn't clear an overflow that
+                    // happens concurrently.
+                    Self::clear_flags(&self.tim, 1 << 1);
+                }
+
+                fn zero() -> Self::Instant {
+                    Self::Instant::from_ticks(0)
+                }
+
+                unsafe fn reset(&mut self) {
+                    self.tim.cnt.reset();
+                    self.overflows = 0;
+
+                    Self::clear_flags(&self.tim, 1 | 1 << 1);
+                    self.tim.dier.modify(|_, w| w.uie().set_bit().cc1ie().set_bit());
+                    self.tim.cr1.modify(|_, w| w.cen().set_bit());
+                }
+
+                fn on_interrupt(&mut self) {
+                    if self.tim.sr.read().uif().bit_is_set() {
+                        Self::clear_flags(&self.tim, 1);
+                        self.overflows = self.overflows.wrapping_add(1);
+                    }
+                }
+
+                fn enable_timer(&mut self) {
+                    self.tim.dier.modify(|_, w| w.cc1ie().set_bit());
+                }
+
+                fn disable_timer(&mut self) {
+                    self.tim.dier.modify(|_, w| w.cc1ie().clear_bit());
+                }
+            }
+        )+
+    }
+}
+
+mono_timers! {
+    TIM2: (apb1_tim_clk),
+    TIM21: (apb2_tim_clk),
+    TIM22: (apb2_tim_clk),
+}
+
+#[cfg(feature = "io-STM32L071")]
+mono_timers! {
+    TIM3: (apb1_tim_clk),
+}
@@ -1,3 +1,13 @@
+//! Commonly used traits
+//!
+//! Import everything with `use stm32l0xx_hal::prelude::*;`. This brings the
+//! extension traits of this crate and the embedded-hal 0.2 traits into scope.
+//!
+//! The embedded-hal 1.0 and embedded-io traits are mostly not included, as
+//! their methods have the same names as the ones of the embedded-hal 0.2
+//! traits, which would make calls ambiguous. Import them explicitly, if
+//! needed.
+
 pub use embedded_hal::{
     adc::OneShot as _,
     digital::v2::*,
@@ -6,18 +16,27 @@ pub use embedded_hal::{
     watchdog::{Watchdog as _, WatchdogEnable as _},
 };
 
+#[cfg(feature = "eh1")]
+pub use eh1::pwm::SetDutyCycle as _;
+
+pub use fugit::{ExtU32 as _, RateExtU32 as _};
+
 pub use crate::{
     adc::AdcExt as _,
     delay::DelayExt as _,
+    exti::ExtiLine as _,
     gpio::GpioExt as _,
     pwr::PowerMode as _,
     rcc::RccExt as _,
     spi::SpiExt as _,
     time::U32Ext as _,
-    timer::TimerExt as _,
+    timer::{GeneralPurposeTimer as _, SlaveTimer as _, TimerExt as _},
     watchdog::{IndependedWatchdogExt as _, WindowWatchdogExt as _},
 };
 
+#[cfg(feature = "async")]
+pub use crate::asynch::InterruptHandler as _;
+
 #[cfg(any(
     feature = "io-STM32L021",
     feature = "io-STM32L031",
@@ -1,15 +1,29 @@
 use core::marker::PhantomData;
 use core::ops::Deref;
+use core::pin::Pin as PinnedBuffer;
 
+use as_slice::AsSlice;
 use cortex_m::interrupt;
 
+use crate::config;
+use crate::counter::EtrPin;
+use crate::dma::{self, Buffer as _};
 use crate::gpio::gpioa::{PA0, PA1, PA2, PA3};
 use crate::gpio::{AltMode, PinMode};
 use crate::hal;
-use crate::pac::{tim2, TIM2, TIM3};
-use crate::rcc::Rcc;
+#[cfg(any(
+    feature = "io-STM32L031",
+    feature = "io-STM32L051",
+    feature = "io-STM32L071"
+))]
+use crate::pac::TIM22;
+#[cfg(feature = "io-STM32L071")]
+use crate::pac::TIM3;
+use crate::pac::{tim2, TIM2, TIM21};
+use crate::rcc::{Enable, Rcc, Reset};
 use crate::time::Hertz;
-use cast::{u16, u32};
+use crate::timer::Prescaler;
+use cast::u32;
 
 #[cfg(any(feature = "stm32l0x2", feature = "stm32l0x3"))]
 use crate::gpio::{
@@ -17,15 +31,38 @@ use crate::gpio::{
     gpiob::{PB10, PB11, PB3},
 };
 
+#[cfg(any(
+    feature = "io-STM32L031",
+    feature = "io-STM32L051",
+    feature = "io-STM32L071",
+    feature = "stm32l072",
+    feature = "stm32l082"
+))]
+use crate::gpio::gpioa::{PA6, PA7};
+
+#[cfg(any(
+    feature = "io-STM32L051",
+    feature = "io-STM32L071",
+    feature = "stm32l072",
+    feature = "stm32l082"
+))]
+use crate::gpio::gpiob::{PB4, PB5};
+
 #[cfg(any(feature = "stm32l072", feature = "stm32l082"))]
-use crate::gpio::{
-    gpioa::{PA6, PA7},
-    gpiob::{PB0, PB1, PB4, PB5},
-};
+use crate::gpio::gpiob::{PB0, PB1};
+
+#[cfg(any(feature = "io-STM32L051", feature = "io-STM32L071"))]
+use crate::gpio::gpiob::{PB13, PB14};
+
+#[cfg(any(feature = "io-STM32L071", feature = "stm32l072"))]
+use crate::gpio::gpioc::{PC6, PC7};
+
+#[cfg(feature = "io-STM32L071")]
+use crate::gpio::gpiod::{PD0, PD7};
 
 #[cfg(feature = "stm32l072")]
 use crate::gpio::{
-    gpioc::{PC6, PC7, PC8, PC9},
+    gpioc::{PC8, PC9},
     gpioe::{PE10, PE11, PE12, PE3, PE4, PE5, PE6, PE9},
 };
 
@@ -43,7 +80,36 @@ where
     I: Instance,
 {
     /// Create new timer instance that is automatically started with given frequency
-    pub fn new(timer: I, frequency: Hertz, rcc: &mut Rcc) -> Self {
+    ///
+    /// # Panics
+    ///
+    /// Panics, if the frequency can't be derived from the timer clock. See
+    /// [`Timer::try_new`].
+    pub fn new<T>(timer: I, frequency: T, rcc: &mut Rcc) -> Self
+    where
+        T: Into<Hertz>,
+    {
+        Self::try_new(timer, frequency, rcc)
+            .map_err(|(_, err)| err)
+            .unwrap()
+    }
+
+    /// Create new timer instance that is automatically started with given frequency
+    ///
+    /// Returns [`config::Error::Frequency`], if the frequency is 0 Hz, or
+    /// can't be derived from the timer clock. The timer is returned along
+    /// with the error, untouched.
+    pub fn try_new<T>(timer: I, frequency: T, rcc: &mut Rcc) -> Result<Self, (I, config::Error)>
+    where
+        T: Into<Hertz>,
+    {
+        // Enabling the timer resets it to the edge-aligned mode.
+        let prescaler =
+            match frequency_prescaler(timer.clock_frequency(rcc), frequency.into().0, false) {
+                Ok(prescaler) => prescaler,
+                Err(err) => return Err((timer, err)),
+            };
+
         timer.enable(rcc);
 
         let mut tim = Self {
@@ -53,18 +119,18 @@ where
             channel3: Pwm::new(),
             channel4: Pwm::new(),
         };
-        tim.set_frequency(frequency, rcc);
-        tim
+        tim.set_period(prescaler);
+        Ok(tim)
     }
 
     /// Starts the PWM timer
     pub fn start(&mut self) {
-        self.instance.cr1.write(|w| w.cen().set_bit());
+        self.tim().cr1.modify(|_, w| w.cen().set_bit());
     }
 
     /// Stops the PWM timer
     pub fn stop(&mut self) {
-        self.instance.cr1.write(|w| w.cen().clear_bit());
+        self.tim().cr1.modify(|_, w| w.cen().clear_bit());
     }
 
     /// Update frequency of the timer
@@ -72,28 +138,266 @@ where
     /// In order to do this operation properly the function stop the timer and then starts it again.
     /// The duty cycle that was set before for given pin needs to adjusted according to the
     /// frequency
-    pub fn set_frequency(&mut self, frequency: Hertz, rcc: &Rcc) {
+    ///
+    /// Returns [`config::Error::Frequency`], if the frequency is 0 Hz, or
+    /// can't be derived from the timer clock. The timer is left stopped in
+    /// that case.
+    pub fn set_frequency<T>(&mut self, frequency: T, rcc: &Rcc) -> Result<(), config::Error>
+    where
+        T: Into<Hertz>,
+    {
+        self.stop();
+        let clk = self.instance.clock_frequency(rcc);
+        let prescaler = frequency_prescaler(clk, frequency.into().0, self.is_center_aligned())?;
+        self.set_prescaler(prescaler);
+        self.start();
+        Ok(())
+    }
+
+    /// Sets the period of the timer from a precomputed [`Prescaler`]
+    ///
+    /// This skips the prescaler calculation of [`Timer::set_frequency`] at
+    /// runtime. In the center-aligned modes, the counter counts up to `arr`
+    /// and back down again, so the PWM period is `2 * arr * (psc + 1)` timer
+    /// clock cycles.
+    pub fn set_period(&mut self, prescaler: Prescaler) {
         self.stop();
-        let (psc, arr) = get_clock_config(frequency.0, self.instance.clock_frequency(rcc));
-        self.instance.psc.write(|w| w.psc().bits(psc));
-        self.instance.arr.write(|w| w.arr().bits(arr.into()));
+        self.set_prescaler(prescaler);
         self.start();
     }
 
-    /// Returns the timer, so it can be used by any else
-    pub fn free(self) -> I {
+    /// Selects edge-aligned or one of the center-aligned modes
+    ///
+    /// In the center-aligned modes, the counter counts up to the
+    /// auto-reload value and back down again, so the outputs of all channels
+    /// are centered around the same point of the period. The PWM frequency is
+    /// kept by halving the auto-reload value, so the duty cycles need to be
+    /// set again afterwards.
+    ///
+    /// The variant also selects whether the compare flags (and the
+    /// corresponding interrupts and DMA requests) are raised while counting
+    /// up, down or both.
+    ///
+    /// Returns [`config::Error::Frequency`], if the PWM frequency can't be
+    /// kept in the new mode. The timer is left stopped in its previous mode in
+    /// that case.
+    pub fn set_alignment(&mut self, alignment: tim2::cr1::CMS_A) -> Result<(), config::Error> {
+        self.stop();
+        let center_aligned = alignment != tim2::cr1::CMS_A::EDGEALIGNED;
+        let prescaler = period_prescaler(self.period_ticks(), center_aligned)?;
+        self.tim().cr1.modify(|_, w| w.cms().variant(alignment));
+        self.set_prescaler(prescaler);
+        self.start();
+        Ok(())
+    }
+
+    /// Sets the counting direction
+    ///
+    /// Only has an effect in edge-aligned mode. In the center-aligned modes,
+    /// the direction is controlled by the hardware.
+    pub fn set_direction(&mut self, direction: hal::Direction) {
+        self.tim().cr1.modify(|_, w| match direction {
+            hal::Direction::Upcounting => w.dir().up(),
+            hal::Direction::Downcounting => w.dir().down(),
+        });
+    }
+
+    /// Returns the direction the counter is currently counting in
+    pub fn direction(&self) -> hal::Direction {
+        if self.tim().cr1.read().dir().bit_is_set() {
+            hal::Direction::Downcounting
+        } else {
+            hal::Direction::Upcounting
+        }
+    }
+
+    /// Returns the number of times the counter passes through the range of
+    /// the auto-reload register per PWM period
+    fn is_center_aligned(&self) -> bool {
+        !self.tim().cr1.read().cms().is_edge_aligned()
+    }
+
+    /// Returns the PWM period in timer clock cycles
+    fn period_ticks(&self) -> u32 {
+        let psc = u32(self.tim().psc.read().psc().bits());
+        let arr = u32(self.tim().arr.read().arr().bits());
+        if self.is_center_aligned() {
+            (psc + 1).saturating_mul(2 * arr)
+        } else {
+            (psc + 1).saturating_mul(arr + 1)
+        }
+    }
+
+    fn set_prescaler(&mut self, prescaler: Prescaler) {
+        self.tim().psc.write(|w| w.psc().bits(prescaler.psc));
+        self.tim().arr.write(|w| w.arr().bits(prescaler.arr));
+    }
+
+    fn tim(&self) -> &tim2::RegisterBlock {
+        // Safe, as we own the timer instance.
+        unsafe { &*I::ptr() }
+    }
+
+    /// Select master mode
+    ///
+    /// The trigger output (TRGO) can be used to synchronize other timers or
+    /// to trigger ADC conversions. TIM21 and TIM22 don't support
+    /// `COMPAREOC3` and `COMPAREOC4`.
+    pub fn select_master_mode(&mut self, variant: tim2::cr2::MMS_A) {
+        self.tim().cr2.modify(|_, w| w.mms().variant(variant));
+    }
+
+    /// Select slave mode and trigger input
+    ///
+    /// Allows the PWM timer to be synchronized to another timer.
+    pub fn select_slave_mode(&mut self, mode: tim2::smcr::SMS_A, trigger: tim2::smcr::TS_A) {
+        // The trigger input must only be changed while the slave mode is
+        // disabled.
+        self.tim().smcr.modify(|_, w| w.sms().disabled());
+        self.tim().smcr.modify(|_, w| w.ts().variant(trigger));
+        self.tim().smcr.modify(|_, w| w.sms().variant(mode));
+    }
+
+    /// Uses the external trigger input (ETR) as OCREF clear input
+    ///
+    /// Channels with OCREF clear enabled, see [`Pwm::set_ocref_clear`], are
+    /// forced inactive for the rest of the PWM period, whenever `etr` goes
+    /// high, or low if `inverted` is set. Connected to a comparator output,
+    /// this implements cycle-by-cycle current limiting in hardware.
+    pub fn select_ocref_clear<P>(&mut self, etr: &P, inverted: bool)
+    where
+        P: EtrPin<I>,
+    {
+        etr.setup();
+        self.tim().smcr.modify(|_, w| {
+            w.etp().bit(inverted);
+            w.etps().bits(0);
+            w.etf().bits(0)
+        });
+    }
+
+    /// Prepares a DMA burst transfer to the capture/compare registers
+    ///
+    /// On every update event, the DMA writes the next `channels` values from
+    /// the buffer into CCR1 and the following capture/compare registers. This
+    /// allows the duty cycles to be changed every PWM period, without CPU
+    /// intervention. A circular transfer repeats the buffer indefinitely,
+    /// which is useful for generating waveforms.
+    ///
+    /// The returned transfer needs to be started. Only TIM2 and TIM3 support
+    /// DMA bursts.
+    ///
+    /// Returns an error, if `channels` is not in `1..=4`, or if the length of
+    /// the buffer is not a multiple of `channels`.
+    pub fn dma_burst<Buffer, Channel>(
+        &mut self,
+        dma: &mut dma::Handle,
+        buffer: PinnedBuffer<Buffer>,
+        channels: u8,
+        circular: bool,
+        channel: Channel,
+    ) -> BurstSetupResult<I, Channel, Buffer>
+    where
+        DmaToken<I>: dma::Target<Channel>,
+        Buffer: Deref + 'static,
+        Buffer::Target: AsSlice<Element = u16>,
+        Channel: dma::Channel,
+    {
+        let num_words = buffer.len();
+        let err = if !(1..=4).contains(&channels) {
+            Some(config::Error::BurstLength)
+        } else if num_words / usize::from(channels) * usize::from(channels) != num_words {
+            Some(config::Error::BufferLength)
+        } else {
+            None
+        };
+        if let Some(err) = err {
+            let res = dma::TransferResources {
+                target: DmaToken(PhantomData),
+                channel,
+                buffer,
+            };
+            return Err((res, err));
+        }
+
+        // CCR1 is the 13th register, counted from CR1.
+        self.tim().dcr.write(|w| {
+            w.dba().bits(13);
+            // Safe, as we checked the burst length above.
+            unsafe { w.dbl().bits(channels - 1) }
+        });
+        self.tim().dier.modify(|_, w| w.ude().set_bit());
+
+        // Safe, because we're only taking the address of a register.
+        let address = &self.tim().dmar as *const _ as u32;
+
+        // Safe, because the trait bounds of this method guarantee that the
+        // buffer can be read from.
+        unsafe {
+            dma::Transfer::new(
+                dma,
+                DmaToken(PhantomData),
+                channel,
+                buffer,
+                num_words,
+                address,
+                dma::Priority::high(),
+                dma::Direction::memory_to_peripheral(),
+                circular,
+            )
+        }
+    }
+
+    /// Stops the timer and returns it, so it can be used by any else
+    pub fn free(mut self) -> I {
+        self.stop();
         self.instance
     }
 }
 
-fn get_clock_config(freq: u32, clk: u32) -> (u16, u16) {
-    let ticks = clk / freq;
-    let psc = u16((ticks - 1) / (1 << 16)).unwrap();
-    let arr = u16(ticks / u32(psc + 1)).unwrap();
-    return (psc, arr);
+/// Returns the prescaler values for a PWM frequency, from the timer clock
+/// frequency `clk`
+fn frequency_prescaler(
+    clk: u32,
+    frequency: u32,
+    center_aligned: bool,
+) -> Result<Prescaler, config::Error> {
+    if frequency == 0 {
+        return Err(config::Error::Frequency);
+    }
+    period_prescaler(clk / frequency, center_aligned)
 }
 
-pub trait Instance: Deref<Target = tim2::RegisterBlock> {
+/// Returns the prescaler values for a PWM period of `ticks` timer clock
+/// cycles
+///
+/// In the center-aligned modes, the counter counts up to `arr` and back down
+/// again, so a period takes `2 * arr` instead of `arr + 1` counts.
+fn period_prescaler(ticks: u32, center_aligned: bool) -> Result<Prescaler, config::Error> {
+    if !center_aligned {
+        return Prescaler::from_ticks(ticks);
+    }
+
+    let half = ticks / 2;
+    if half == 0 {
+        return Err(config::Error::Frequency);
+    }
+
+    // The smallest prescaler for which `arr` fits into 16 bits
+    let psc = (half - 1) / 0xffff;
+    Ok(Prescaler {
+        psc: psc as u16,
+        arr: (half / (psc + 1)) as u16,
+    })
+}
+
+/// A timer that can be used for PWM generation
+///
+/// TIM21 and TIM22 only provide channels 1 and 2. Their register layout
+/// matches the one of TIM2/TIM3 for all registers used here, so they are
+/// accessed through `tim2::RegisterBlock` as well. Pins are only provided for
+/// the channels a timer actually has.
+pub trait Instance {
     fn ptr() -> *const tim2::RegisterBlock;
     fn enable(&self, _: &mut Rcc);
     fn clock_frequency(&self, _: &Rcc) -> u32;
@@ -103,23 +407,18 @@ macro_rules! impl_instance {
     (
         $(
             $name:ty,
-            $apbXenr:ident,
-            $apbXrstr:ident,
-            $timXen:ident,
-            $timXrst:ident,
             $apbX_clk:ident;
         )*
     ) => {
         $(
             impl Instance for $name {
                 fn ptr() -> *const tim2::RegisterBlock {
-                    Self::ptr()
+                    <$name>::ptr() as *const _
                 }
 
                 fn enable(&self, rcc: &mut Rcc) {
-                    rcc.rb.$apbXenr.modify(|_, w| w.$timXen().set_bit());
-                    rcc.rb.$apbXrstr.modify(|_, w| w.$timXrst().set_bit());
-                    rcc.rb.$apbXrstr.modify(|_, w| w.$timXrst().clear_bit());
+                    <$name as Enable>::enable(rcc);
+                    <$name as Reset>::reset(rcc);
                 }
 
                 fn clock_frequency(&self, rcc: &Rcc) -> u32 {
@@ -131,15 +430,58 @@ macro_rules! impl_instance {
 }
 
 impl_instance!(
-    TIM2, apb1enr, apb1rstr, tim2en, tim2rst, apb1_clk;
-    TIM3, apb1enr, apb1rstr, tim3en, tim3rst, apb1_clk;
+    TIM2, apb1_tim_clk;
+    TIM21, apb2_tim_clk;
+);
+
+#[cfg(any(
+    feature = "io-STM32L031",
+    feature = "io-STM32L051",
+    feature = "io-STM32L071"
+))]
+impl_instance!(
+    TIM22, apb2_tim_clk;
 );
 
+#[cfg(feature = "io-STM32L071")]
+impl_instance!(
+    TIM3, apb1_tim_clk;
+);
+
+/// Output compare modes
+///
+/// Determines how the output reacts to the counter matching the value of the
+/// capture/compare register.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputCompareMode {
+    /// The output is not affected by a match
+    Frozen = 0b000,
+    /// The output is set active on a match
+    ActiveOnMatch = 0b001,
+    /// The output is set inactive on a match
+    InactiveOnMatch = 0b010,
+    /// The output toggles on a match
+    ///
+    /// This generates a square wave at half the timer frequency, with the
+    /// phase determined by the compare value.
+    Toggle = 0b011,
+    /// The output is forced inactive, independent of the counter
+    ForceInactive = 0b100,
+    /// The output is forced active, independent of the counter
+    ForceActive = 0b101,
+    /// The output is active while the counter is below the compare value
+    PwmMode1 = 0b110,
+    /// The output is inactive while the counter is below the compare value
+    PwmMode2 = 0b111,
+}
+
 pub trait Channel {
     fn disable(_: &tim2::RegisterBlock);
     fn enable(_: &tim2::RegisterBlock);
+    fn set_mode(_: &tim2::RegisterBlock, mode: OutputCompareMode);
     fn get_duty(_: &tim2::RegisterBlock) -> u16;
     fn set_duty(_: &tim2::RegisterBlock, duty: u16);
+    fn set_ocref_clear(_: &tim2::RegisterBlock, enabled: bool);
 }
 
 macro_rules! impl_channel {
@@ -150,6 +492,7 @@ macro_rules! impl_channel {
             $ccmr_output:ident,
             $ocxpe:ident,
             $ocxm:ident,
+            $ocxce:ident,
             $ccrx:ident;
         )*
     ) => {
@@ -162,11 +505,21 @@ macro_rules! impl_channel {
                 }
 
                 fn enable(tim: &tim2::RegisterBlock) {
+                    tim.ccer.modify(|_, w| w.$ccxe().set_bit());
+                }
+
+                fn set_mode(tim: &tim2::RegisterBlock, mode: OutputCompareMode) {
+                    // The compare value is only buffered in the PWM modes. In
+                    // the other modes, a new value needs to take effect
+                    // right away.
+                    let preload = matches!(
+                        mode,
+                        OutputCompareMode::PwmMode1 | OutputCompareMode::PwmMode2
+                    );
                     tim.$ccmr_output().modify(|_, w| {
-                        w.$ocxpe().set_bit();
-                        w.$ocxm().bits(0b110)
+                        w.$ocxpe().bit(preload);
+                        w.$ocxm().bits(mode as u8)
                     });
-                    tim.ccer.modify(|_, w| w.$ccxe().set_bit());
                 }
 
                 fn get_duty(tim: &tim2::RegisterBlock) -> u16 {
@@ -180,16 +533,20 @@ macro_rules! impl_channel {
                 fn set_duty(tim: &tim2::RegisterBlock, duty: u16) {
                     tim.$ccrx.write(|w| w.ccr().bits(duty.into()));
                 }
+
+                fn set_ocref_clear(tim: &tim2::RegisterBlock, enabled: bool) {
+                    tim.$ccmr_output().modify(|_, w| w.$ocxce().bit(enabled));
+                }
             }
         )*
     }
 }
 
 impl_channel!(
-    C1, cc1e, ccmr1_output, oc1pe, oc1m, ccr1;
-    C2, cc2e, ccmr1_output, oc2pe, oc2m, ccr2;
-    C3, cc3e, ccmr2_output, oc3pe, oc3m, ccr3;
-    C4, cc4e, ccmr2_output, oc4pe, oc4m, ccr4;
+    C1, cc1e, ccmr1_output, oc1pe, oc1m, oc1ce, ccr1;
+    C2, cc2e, ccmr1_output, oc2pe, oc2m, oc2ce, ccr2;
+    C3, cc3e, ccmr2_output, oc3pe, oc3m, oc3ce, ccr3;
+    C4, cc4e, ccmr2_output, oc4pe, oc4m, oc4ce, ccr4;
 );
 
 pub struct Pwm<I, C, State> {
@@ -207,11 +564,19 @@ impl<I, C> Pwm<I, C, Unassigned> {
         }
     }
 
+    /// Assigns a pin to the channel
+    ///
+    /// The channel is configured for `OutputCompareMode::PwmMode1`.
     pub fn assign<P>(self, pin: P) -> Pwm<I, C, Assigned<P>>
     where
+        I: Instance,
+        C: Channel,
         P: Pin<I, C>,
     {
         pin.setup();
+        interrupt::free(|_|
+            // Safe, as the read-modify-write within the critical section
+            C::set_mode(unsafe { &*I::ptr() }, OutputCompareMode::PwmMode1));
         Pwm {
             channel: self.channel,
             timer: self.timer,
@@ -220,6 +585,47 @@ impl<I, C> Pwm<I, C, Unassigned> {
     }
 }
 
+impl<I, C, P> Pwm<I, C, Assigned<P>>
+where
+    I: Instance,
+    C: Channel,
+{
+    /// Selects the output compare mode
+    ///
+    /// Outside of the PWM modes, the compare value set with `set_compare`
+    /// takes effect immediately, instead of at the next update event.
+    pub fn set_mode(&mut self, mode: OutputCompareMode) {
+        interrupt::free(|_|
+            // Safe, as the read-modify-write within the critical section
+            C::set_mode(unsafe { &*I::ptr() }, mode))
+    }
+
+    /// Sets the value the counter is compared against
+    ///
+    /// This is the same register as the duty cycle in the PWM modes.
+    pub fn set_compare(&mut self, value: u16) {
+        // Safe, as we're only doing an atomic write.
+        C::set_duty(unsafe { &*I::ptr() }, value);
+    }
+
+    /// Returns the value the counter is compared against
+    pub fn compare(&self) -> u16 {
+        // Safe, as we're only doing an atomic read.
+        C::get_duty(unsafe { &*I::ptr() })
+    }
+
+    /// Enables or disables clearing of the output by the OCREF clear input
+    ///
+    /// While enabled, the output is forced inactive as soon as the external
+    /// trigger input goes high, until the next update event. See
+    /// [`Timer::select_ocref_clear`].
+    pub fn set_ocref_clear(&mut self, enabled: bool) {
+        interrupt::free(|_|
+            // Safe, as the read-modify-write within the critical section
+            C::set_ocref_clear(unsafe { &*I::ptr() }, enabled))
+    }
+}
+
 impl<I, C, P> hal::PwmPin for Pwm<I, C, Assigned<P>>
 where
     I: Instance,
@@ -261,6 +667,27 @@ where
     }
 }
 
+#[cfg(feature = "eh1")]
+impl<I, C, P> eh1::pwm::ErrorType for Pwm<I, C, Assigned<P>> {
+    type Error = core::convert::Infallible;
+}
+
+#[cfg(feature = "eh1")]
+impl<I, C, P> eh1::pwm::SetDutyCycle for Pwm<I, C, Assigned<P>>
+where
+    I: Instance,
+    C: Channel,
+{
+    fn max_duty_cycle(&self) -> u16 {
+        hal::PwmPin::get_max_duty(self)
+    }
+
+    fn set_duty_cycle(&mut self, duty: u16) -> Result<(), Self::Error> {
+        hal::PwmPin::set_duty(self, duty);
+        Ok(())
+    }
+}
+
 pub trait Pin<I, C> {
     fn setup(&self);
 }
@@ -298,6 +725,49 @@ impl_pin!(
     )
 );
 
+impl_pin!(
+    TIM21: (
+        PA2, C1, AF0;
+        PA3, C2, AF0;
+    )
+);
+
+#[cfg(any(
+    feature = "io-STM32L031",
+    feature = "io-STM32L051",
+    feature = "io-STM32L071"
+))]
+impl_pin!(
+    TIM22: (
+        PA6, C1, AF5;
+        PA7, C2, AF5;
+    )
+);
+
+#[cfg(any(feature = "io-STM32L051", feature = "io-STM32L071"))]
+impl_pin!(
+    TIM21: (
+        PB13, C1, AF6;
+        PB14, C2, AF6;
+    )
+    TIM22: (
+        PB4, C1, AF4;
+        PB5, C2, AF4;
+    )
+);
+
+#[cfg(feature = "io-STM32L071")]
+impl_pin!(
+    TIM21: (
+        PD0, C1, AF0;
+        PD7, C2, AF1;
+    )
+    TIM22: (
+        PC6, C1, AF0;
+        PC7, C2, AF0;
+    )
+);
+
 #[cfg(any(feature = "stm32l0x2", feature = "stm32l0x3"))]
 impl_pin!(
     TIM2: (
@@ -341,8 +811,67 @@ impl_pin!(
     )
 );
 
+/// Token used for DMA burst transfers
+///
+/// This is an internal implementation detail. It is only public because it
+/// leaks out of a public API in the form of a `where` clause.
+pub struct DmaToken<I>(PhantomData<I>);
+
+/// The result of preparing a DMA burst transfer, see [`Timer::dma_burst`]
+pub type BurstSetupResult<I, Channel, Buffer> = dma::SetupResult<
+    dma::Transfer<DmaToken<I>, Channel, Buffer, dma::Ready>,
+    DmaToken<I>,
+    Channel,
+    Buffer,
+>;
+
 /// Indicates that a PWM channel has not been assigned to a pin
 pub struct Unassigned;
 
 /// Indicates that a PWM channel has been assigned to the given pin
-pub struct Assigned<P>(P);
+pub struct Assigned<P>(pub(crate) P);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn period_prescaler_edge_aligned() {
+        assert_eq!(
+            period_prescaler(16_000, false),
+            Prescaler::from_ticks(16_000)
+        );
+    }
+
+    #[test]
+    fn period_prescaler_center_aligned() {
+        // The counter counts up to `arr` and back down again.
+        assert_eq!(
+            period_prescaler(16_000, true),
+            Ok(Prescaler { psc: 0, arr: 8_000 })
+        );
+        assert_eq!(
+            period_prescaler(131_070, true),
+            Ok(Prescaler {
+                psc: 0,
+                arr: 65_535
+            })
+        );
+        assert_eq!(
+            period_prescaler(131_072, true),
+            Ok(Prescaler {
+                psc: 1,
+                arr: 32_768
+            })
+        );
+        assert_eq!(
+            period_prescaler(u32::MAX, true),
+            Ok(Prescaler {
+                psc: 32_768,
+                arr: 65_534
+            })
+        );
+
+        assert_eq!(period_prescaler(1, true), Err(config::Error::Frequency));
+    }
+}
@@ -4,9 +4,17 @@
 
 use cortex_m::{asm, peripheral::SCB};
 
+#[cfg(any(feature = "io-STM32L021", feature = "io-STM32L031"))]
+use crate::gpio::gpioa::PA2;
+#[cfg(feature = "io-STM32L071")]
+use crate::gpio::gpioe::PE6;
 use crate::{
+    config,
+    gpio::{gpioa::PA0, gpioc::PC13},
     pac,
-    rcc::{ClockSrc, Clocks, PLLSource, Rcc},
+    rcc::{ClockSrc, Clocks, Enable, LseDrive, PLLSource, Rcc},
+    rtc::ClockSource,
+    time::{Hertz, U32Ext},
 };
 
 /// Entry point to the PWR API
@@ -21,7 +29,7 @@ impl PWR {
         // See STM32L0x2 reference manual, section 6.4.1 (VOS field).
 
         // Enable peripheral clock
-        rcc.rb.apb1enr.modify(|_, w| w.pwren().set_bit());
+        pac::PWR::enable(rcc);
 
         // Disable backup write protection. This is required to access various
         // register of various peripherals, so don't remove this unless you know
@@ -32,6 +40,28 @@ impl PWR {
         Self(pwr)
     }
 
+    /// Runs `f` with write access to the backup domain
+    ///
+    /// The backup domain contains the LSE oscillator, the RTC clock selection,
+    /// the RTC and its backup registers. Write access is controlled by the DBP
+    /// bit, which is set for the duration of `f`, then restored to its
+    /// previous state.
+    ///
+    /// Please note that [`PWR::new`] already sets the DBP bit, as it is
+    /// required by the RTC API.
+    pub fn with_backup_domain_access<F, R>(&mut self, rcc: &mut Rcc, f: F) -> R
+    where
+        F: FnOnce(&mut BackupDomain) -> R,
+    {
+        let dbp = self.0.cr.read().dbp().bit_is_set();
+        self.0.cr.modify(|_, w| w.dbp().set_bit());
+
+        let result = f(&mut BackupDomain { rcc });
+
+        self.0.cr.modify(|_, w| w.dbp().bit(dbp));
+        result
+    }
+
     /// Switch voltage range of internal regulator
     ///
     /// Please note that switching Vcore has consequences, so please make sure
@@ -51,6 +81,30 @@ impl PWR {
         while self.0.csr.read().vosf().bit_is_set() {}
     }
 
+    /// Switch voltage range of internal regulator, after checking the clocks
+    ///
+    /// Like [`PWR::switch_vcore_range`], but makes sure that the current
+    /// system clock frequency is supported in the new voltage range. Use this
+    /// to drop to a lower range, after the clocks have been slowed down.
+    ///
+    /// # Panics
+    ///
+    /// Panics, if the system clock frequency exceeds the maximum for `range`,
+    /// or if it requires a Flash wait state in that range, but none is
+    /// configured.
+    pub fn set_vcore_range(&mut self, range: VcoreRange, rcc: &Rcc) {
+        let sys_clk = rcc.clocks.sys_clk();
+        assert!(sys_clk <= range.max_sys_clk());
+
+        if sys_clk > range.max_sys_clk_zero_wait_states() {
+            // Safe, as this is a read without side effects.
+            let latency = unsafe { (*pac::FLASH::ptr()).acr.read().latency().bit() };
+            assert!(latency);
+        }
+
+        self.switch_vcore_range(range);
+    }
+
     /// Returns currently configured internal regulator voltage range
     pub fn get_vcore_range(&mut self) -> VcoreRange {
         let vos = self.0.cr.read().vos().bits();
@@ -126,6 +180,42 @@ impl PWR {
         LowPowerSleepMode { pwr: self, scb }
     }
 
+    /// Enters Sleep mode, until an interrupt occurs
+    ///
+    /// Only the CPU clock is stopped. All peripherals keep running from their
+    /// usual clocks, and any enabled interrupt will wake up the
+    /// microcontroller. See [`SleepMode`].
+    pub fn sleep(&mut self, scb: &mut SCB) {
+        self.sleep_mode(scb).enter();
+    }
+
+    /// Enters low-power sleep mode, until an interrupt occurs
+    ///
+    /// Like Sleep mode, but with the voltage regulator in low-power mode. The
+    /// peripherals keep running, but the system clock is limited to MSI range
+    /// 1 (131.072 kHz). See [`LowPowerSleepMode`].
+    ///
+    /// # Panics
+    ///
+    /// Panics, if the system clock frequency exceeds 131.072 kHz.
+    pub fn low_power_sleep(&mut self, scb: &mut SCB, rcc: &mut Rcc) {
+        self.low_power_sleep_mode(scb, rcc).enter();
+    }
+
+    /// Configures whether to go back to sleep after an interrupt handler
+    ///
+    /// If enabled, the microcontroller re-enters the last Sleep, low-power
+    /// sleep or Stop mode after returning from an interrupt handler, without
+    /// returning to the code that entered the low-power mode. This is useful
+    /// for applications that run entirely from interrupt handlers.
+    pub fn set_sleep_on_exit(&mut self, scb: &mut SCB, enable: bool) {
+        if enable {
+            scb.set_sleeponexit();
+        } else {
+            scb.clear_sleeponexit();
+        }
+    }
+
     /// Returns a struct that can be used to enter Stop mode
     pub fn stop_mode<'r>(
         &'r mut self,
@@ -146,17 +236,216 @@ impl PWR {
         StandbyMode { pwr: self, scb }
     }
 
+    /// Returns what woke the microcontroller up from Standby mode
+    ///
+    /// This should be called early after startup, as it reads and clears the
+    /// Standby and wakeup flags. Calling it again will return
+    /// [`WakeupCause::Reset`].
+    pub fn wakeup_cause(&mut self) -> WakeupCause {
+        let csr = self.0.csr.read();
+
+        // Clear the flags for the next time
+        self.0.cr.modify(|_, w| w.csbf().set_bit().cwuf().set_bit());
+
+        if csr.sbf().bit_is_clear() {
+            return WakeupCause::Reset;
+        }
+        if csr.wuf().bit_is_clear() {
+            return WakeupCause::Other;
+        }
+
+        // Both wakeup pins and RTC events set WUF. The RTC flags persist in
+        // Standby mode, so check those to tell them apart. The flags for
+        // alarm A/B, the wakeup timer, the timestamp and tampers 1-3 are
+        // checked.
+        //
+        // Safe, as this is a read without side effects.
+        let isr = unsafe { (*pac::RTC::ptr()).isr.read().bits() };
+        if isr & 0b1110_1111_0000_0000 != 0 {
+            WakeupCause::Rtc
+        } else {
+            WakeupCause::WakeupPin
+        }
+    }
+
+    /// Configures ultra-low-power mode
+    ///
+    /// If enabled, the internal voltage reference (Vrefint) is switched off in
+    /// Stop and Standby mode, which significantly reduces their current
+    /// consumption. This also disables the brown out reset (BOR), the
+    /// programmable voltage detector (PVD) and the temperature sensor in those
+    /// modes.
+    ///
+    /// Please note that [`StopMode`] overrides this setting with
+    /// [`StopModeConfig::ultra_low_power`].
+    pub fn set_ultra_low_power(&mut self, enable: bool) {
+        self.0.cr.modify(|_, w| w.ulp().bit(enable));
+    }
+
+    /// Configures fast wakeup
+    ///
+    /// Only has an effect, if ultra-low-power mode is enabled. If fast wakeup
+    /// is enabled, the microcontroller doesn't wait for Vrefint to start up
+    /// again (which takes up to 3 ms) when leaving a low-power mode. In that
+    /// case, the ADC, the comparators and the PVD must not be used, until
+    /// [`PWR::is_vrefint_ready`] returns `true`.
+    pub fn set_fast_wakeup(&mut self, enable: bool) {
+        self.0.cr.modify(|_, w| w.fwu().bit(enable));
+    }
+
+    /// Indicates whether the internal voltage reference is ready
+    pub fn is_vrefint_ready(&self) -> bool {
+        self.0.csr.read().vrefintrdyf().bit_is_set()
+    }
+
+    /// Enables a wakeup pin
+    ///
+    /// A rising edge on an enabled wakeup pin wakes the microcontroller up
+    /// from Standby mode. While enabled, the pin is forced to input mode with
+    /// a pull-down by hardware, regardless of its GPIO configuration.
+    pub fn enable_wakeup_pin<P: WakeupPin>(&mut self, _: &P) {
+        self.set_wakeup_pin(P::ID, true);
+    }
+
+    /// Disables a wakeup pin
+    pub fn disable_wakeup_pin<P: WakeupPin>(&mut self, _: &P) {
+        self.set_wakeup_pin(P::ID, false);
+    }
+
+    /// Private method to set or clear EWUPx
+    fn set_wakeup_pin(&mut self, id: u8, enable: bool) {
+        self.0.csr.modify(|_, w| match id {
+            1 => w.ewup1().bit(enable),
+            2 => w.ewup2().bit(enable),
+            _ => w.ewup3().bit(enable),
+        });
+    }
+
     /// Private method to set LPSDSR
     fn set_lpsdsr(&mut self) {
         self.0.cr.modify(|_, w| w.lpsdsr().low_power_mode());
     }
 
+    /// Private method to check whether low-power run mode is enabled
+    fn is_low_power_run(&self) -> bool {
+        self.0.cr.read().lprun().bit_is_set()
+    }
+
     /// Private method to clear LPSDSR
     fn clear_lpsdsr(&mut self) {
         self.0.cr.modify(|_, w| w.lpsdsr().main_mode());
     }
 }
 
+/// Write access to the backup domain
+///
+/// You can get access to an instance of this struct by calling
+/// [`PWR::with_backup_domain_access`].
+pub struct BackupDomain<'r> {
+    rcc: &'r mut Rcc,
+}
+
+impl BackupDomain<'_> {
+    /// Enables the LSE oscillator and waits for it to be ready
+    ///
+    /// Uses the current drive level and bypass configuration, which is the
+    /// lowest drive level with a crystal after a backup domain reset.
+    pub fn enable_lse(&mut self) {
+        self.rcc.rb.csr.modify(|_, w| w.lseon().set_bit());
+        while self.rcc.rb.csr.read().lserdy().bit_is_clear() {}
+    }
+
+    /// Configures the LSE oscillator, enables it and waits for it to be ready
+    ///
+    /// `drive` selects the drive capability of the oscillator, which depends
+    /// on the crystal. If `bypass` is `true`, an external clock is expected on
+    /// OSC32_IN instead of a crystal.
+    ///
+    /// The LSE is stopped first if it is already running, as its
+    /// configuration can only be changed while it's off.
+    pub fn enable_lse_with(&mut self, drive: LseDrive, bypass: bool) {
+        self.disable_lse();
+
+        self.rcc.rb.csr.modify(|_, w| {
+            w.lsedrv().bits(drive as u8);
+            w.lsebyp().bit(bypass)
+        });
+        self.enable_lse();
+    }
+
+    /// Enables the clock security system (CSS) for the LSE
+    ///
+    /// If the LSE fails while the CSS is enabled, the RTC clock is no longer
+    /// provided, and an interrupt can be generated (see
+    /// [`Rcc::listen_lse_css`]). This also works in the low-power modes,
+    /// which makes it possible to wake up on a failure.
+    ///
+    /// Returns [`config::Error::ClockNotReady`], if the LSE is not running,
+    /// or not selected as the RTC clock.
+    pub fn enable_lse_css(&mut self) -> Result<(), config::Error> {
+        if self.rcc.rb.csr.read().lserdy().bit_is_clear()
+            || self.rtc_clock() != ClockSource::Lse as u8
+        {
+            return Err(config::Error::ClockNotReady);
+        }
+
+        self.rcc.rb.csr.modify(|_, w| w.csslseon().set_bit());
+        Ok(())
+    }
+
+    /// Disables the LSE oscillator
+    pub fn disable_lse(&mut self) {
+        self.rcc.rb.csr.modify(|_, w| w.lseon().clear_bit());
+        while self.rcc.rb.csr.read().lserdy().bit_is_set() {}
+    }
+
+    /// Selects and enables the RTC clock
+    ///
+    /// The clock selection can only be changed by a backup domain reset. If a
+    /// different clock has been selected before, the backup domain is reset
+    /// first, which also resets the RTC and clears the backup registers.
+    ///
+    /// If `ClockSource::Lse` is selected and the LSE isn't running yet, it is
+    /// enabled using [`BackupDomain::enable_lse`]. The LSI is not part of the
+    /// backup domain, and has to be enabled separately using
+    /// [`Rcc::enable_lsi`].
+    pub fn set_rtc_clock(&mut self, source: ClockSource) {
+        let rtcsel = self.rtc_clock();
+        if rtcsel != 0 && rtcsel != source as u8 {
+            self.reset();
+        }
+
+        if source == ClockSource::Lse && self.rcc.rb.csr.read().lserdy().bit_is_clear() {
+            self.enable_lse();
+        }
+
+        self.enable_rtc_clock(source as u8);
+    }
+
+    /// Resets the backup domain
+    ///
+    /// This stops the LSE, resets the RTC clock selection, the RTC including
+    /// the date/time, and clears the backup registers.
+    pub fn reset(&mut self) {
+        self.rcc.rb.csr.modify(|_, w| w.rtcrst().set_bit());
+        self.rcc.rb.csr.modify(|_, w| w.rtcrst().clear_bit());
+    }
+
+    /// Returns the raw RTC clock selection (RTCSEL)
+    pub(crate) fn rtc_clock(&self) -> u8 {
+        self.rcc.rb.csr.read().rtcsel().bits()
+    }
+
+    /// Selects the RTC clock (RTCSEL) and enables it
+    pub(crate) fn enable_rtc_clock(&mut self, rtcsel: u8) {
+        self.rcc.rb.csr.modify(|_, w| {
+            // This is safe, as the caller passes a valid bit pattern.
+            w.rtcsel().bits(rtcsel);
+            w.rtcen().set_bit()
+        });
+    }
+}
+
 /// Voltage range selection for internal voltage regulator
 ///
 /// Used as an argument for [`PWR::switch_vcore_range`].
@@ -187,6 +476,99 @@ impl VcoreRange {
             bits => panic!("Bits don't represent valud Vcore range: {}", bits),
         }
     }
+
+    /// Returns the maximum system clock frequency in this range
+    ///
+    /// See STM32L0x2 reference manual, section 6.1.4.
+    pub fn max_sys_clk(&self) -> Hertz {
+        match self {
+            VcoreRange::Range1 => 32.mhz(),
+            VcoreRange::Range2 => 16.mhz(),
+            VcoreRange::Range3 => 4_200.khz(),
+        }
+    }
+
+    /// Returns the maximum PLL VCO output frequency in this range
+    ///
+    /// See STM32L0x2 reference manual, section 7.2.4.
+    pub fn max_pll_vco(&self) -> Hertz {
+        match self {
+            VcoreRange::Range1 => 96.mhz(),
+            VcoreRange::Range2 => 48.mhz(),
+            VcoreRange::Range3 => 24.mhz(),
+        }
+    }
+
+    /// Returns the maximum system clock frequency in this range, without Flash
+    /// wait states
+    ///
+    /// See STM32L0x2 reference manual, section 3.3.3.
+    pub fn max_sys_clk_zero_wait_states(&self) -> Hertz {
+        match self {
+            VcoreRange::Range1 => 16.mhz(),
+            VcoreRange::Range2 => 8.mhz(),
+            VcoreRange::Range3 => 4_200.khz(),
+        }
+    }
+}
+
+/// Implemented for pins that can be used as wakeup pins
+///
+/// See [`PWR::enable_wakeup_pin`].
+pub trait WakeupPin: sealed::Sealed {
+    /// The number of the wakeup pin (1 to 3)
+    #[doc(hidden)]
+    const ID: u8;
+}
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+macro_rules! wakeup_pins {
+    ($($pin:ident: $id:expr,)*) => {
+        $(
+            impl<MODE> sealed::Sealed for $pin<MODE> {}
+            impl<MODE> WakeupPin for $pin<MODE> {
+                const ID: u8 = $id;
+            }
+        )*
+    };
+}
+
+wakeup_pins!(
+    PA0: 1,
+    PC13: 2,
+);
+
+#[cfg(any(feature = "io-STM32L021", feature = "io-STM32L031"))]
+wakeup_pins!(
+    PA2: 3,
+);
+
+#[cfg(feature = "io-STM32L071")]
+wakeup_pins!(
+    PE6: 3,
+);
+
+/// Cause of the last wakeup from Standby mode
+///
+/// Returned by [`PWR::wakeup_cause`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WakeupCause {
+    /// The microcontroller wasn't in Standby mode, i.e. this is a regular
+    /// reset
+    Reset,
+
+    /// Woken up by one of the wakeup pins
+    WakeupPin,
+
+    /// Woken up by an RTC alarm, the wakeup timer, a timestamp or tamper event
+    Rtc,
+
+    /// Left Standby mode without a wakeup event, e.g. through the NRST pin or
+    /// the independent watchdog
+    Other,
 }
 
 /// Implemented for all low-power modes
@@ -211,7 +593,10 @@ pub struct SleepMode<'r> {
 
 impl PowerMode for SleepMode<'_> {
     fn enter(&mut self) {
-        self.pwr.clear_lpsdsr();
+        // In low-power run mode, the regulator must stay in low-power mode.
+        if !self.pwr.is_low_power_run() {
+            self.pwr.clear_lpsdsr();
+        }
         self.scb.clear_sleepdeep();
 
         asm::dsb();
@@ -224,6 +609,9 @@ impl PowerMode for SleepMode<'_> {
 /// You can get an instance of this struct by calling
 /// [`PWR::low_power_sleep_mode`].
 ///
+/// The voltage regulator is in low-power mode, while the microcontroller is
+/// sleeping. Vcore is switched to range 2 for that time, then restored.
+///
 /// The `PowerMode` implementation of this type will block until something wakes
 /// the microcontroller up again. Please make sure to configure an interrupt, or
 /// it could block forever.
@@ -248,6 +636,12 @@ impl PowerMode for LowPowerSleepMode<'_> {
         asm::dsb();
         asm::wfi();
 
+        // Switch the regulator back to main mode, unless we're in low-power
+        // run mode, which requires it to stay in low-power mode.
+        if !self.pwr.is_low_power_run() {
+            self.pwr.clear_lpsdsr();
+        }
+
         // Switch back to previous voltage range.
         self.pwr.switch_vcore_range(old_vcore);
     }
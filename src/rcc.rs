@@ -1,4 +1,16 @@
-use crate::pac::RCC;
+//! Reset and clock control (RCC)
+//!
+//! The kernel clock getters of [`Clocks`], e.g. [`Clocks::usart2_clk`],
+//! decode the kernel clock selection in `RCC_CCIPR` when called. That
+//! selection is at its reset value, i.e. the APB clock, unless it has been
+//! changed by writing to `RCC_CCIPR` directly.
+
+use core::ptr::addr_of;
+
+use crate::config;
+use crate::exti::{ConfigurableLine, Exti, TriggerEdge};
+use crate::pac::{self, RCC};
+use crate::pwr::VcoreRange;
 use crate::time::{Hertz, U32Ext};
 
 #[cfg(any(feature = "stm32l0x2", feature = "stm32l0x3"))]
@@ -13,6 +25,43 @@ pub enum ClockSrc {
     HSI16,
 }
 
+impl ClockSrc {
+    /// Returns the system clock frequency, and the PLL VCO output frequency
+    fn frequencies(&self) -> (u32, Option<u32>) {
+        match *self {
+            ClockSrc::MSI(range) => (32_768 * (1 << (range as u8 + 1)), None),
+            ClockSrc::HSI16 => (HSI_FREQ, None),
+            ClockSrc::HSE(freq) => (freq.0, None),
+            ClockSrc::PLL(src, mul, div) => {
+                let freq = match src {
+                    PLLSource::HSE(freq) => freq.0,
+                    PLLSource::HSI16 => HSI_FREQ,
+                };
+
+                let vco = match mul {
+                    PLLMul::Mul3 => freq * 3,
+                    PLLMul::Mul4 => freq * 4,
+                    PLLMul::Mul6 => freq * 6,
+                    PLLMul::Mul8 => freq * 8,
+                    PLLMul::Mul12 => freq * 12,
+                    PLLMul::Mul16 => freq * 16,
+                    PLLMul::Mul24 => freq * 24,
+                    PLLMul::Mul32 => freq * 32,
+                    PLLMul::Mul48 => freq * 48,
+                };
+
+                let freq = match div {
+                    PLLDiv::Div2 => vco / 2,
+                    PLLDiv::Div3 => vco / 3,
+                    PLLDiv::Div4 => vco / 4,
+                };
+
+                (freq, Some(vco))
+            }
+        }
+    }
+}
+
 /// MSI Clock Range
 ///
 /// These ranges control the frequency of the MSI. Internally, these ranges map
@@ -41,6 +90,20 @@ impl Default for MSIRange {
     }
 }
 
+impl MSIRange {
+    fn from_bits(bits: u8) -> Self {
+        match bits {
+            0 => MSIRange::Range0,
+            1 => MSIRange::Range1,
+            2 => MSIRange::Range2,
+            3 => MSIRange::Range3,
+            4 => MSIRange::Range4,
+            5 => MSIRange::Range5,
+            _ => MSIRange::Range6,
+        }
+    }
+}
+
 /// PLL divider
 #[derive(Clone, Copy)]
 pub enum PLLDiv {
@@ -64,7 +127,7 @@ pub enum PLLMul {
 }
 
 /// AHB prescaler
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum AHBPrescaler {
     NotDivided = 0,
     Div2 = 0b1000,
@@ -77,8 +140,23 @@ pub enum AHBPrescaler {
     Div512 = 0b1111,
 }
 
+impl AHBPrescaler {
+    /// Returns the division factor
+    pub fn divisor(self) -> u32 {
+        match self {
+            AHBPrescaler::NotDivided => 1,
+            // There is no division by 32.
+            AHBPrescaler::Div64
+            | AHBPrescaler::Div128
+            | AHBPrescaler::Div256
+            | AHBPrescaler::Div512 => 1 << (self as u8 - 6),
+            pre => 1 << (pre as u8 - 7),
+        }
+    }
+}
+
 /// APB prescaler
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum APBPrescaler {
     NotDivided = 0,
     Div2 = 0b100,
@@ -87,6 +165,29 @@ pub enum APBPrescaler {
     Div16 = 0b111,
 }
 
+impl APBPrescaler {
+    /// Returns the division factor
+    pub fn divisor(self) -> u32 {
+        match self {
+            APBPrescaler::NotDivided => 1,
+            pre => 1 << (pre as u8 - 3),
+        }
+    }
+
+    /// Returns the bus and timer frequencies for the given AHB frequency
+    ///
+    /// The timers run at twice the bus frequency, if the bus is divided.
+    fn frequencies(self, ahb_freq: u32) -> (u32, u32) {
+        match self {
+            APBPrescaler::NotDivided => (ahb_freq, ahb_freq),
+            pre => {
+                let freq = ahb_freq / pre.divisor();
+                (freq, freq * 2)
+            }
+        }
+    }
+}
+
 /// PLL clock input source
 #[derive(Clone, Copy)]
 pub enum PLLSource {
@@ -94,15 +195,41 @@ pub enum PLLSource {
     HSE(Hertz),
 }
 
+/// LSE oscillator drive capability
+///
+/// Used as an argument for [`BackupDomain::enable_lse_with`].
+///
+/// [`BackupDomain::enable_lse_with`]: crate::pwr::BackupDomain::enable_lse_with
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum LseDrive {
+    /// Lowest drive (reset value)
+    Low = 0b00,
+    /// Medium low drive
+    MediumLow = 0b01,
+    /// Medium high drive
+    MediumHigh = 0b10,
+    /// Highest drive
+    High = 0b11,
+}
+
 /// HSI speed
 pub const HSI_FREQ: u32 = 16_000_000;
 
+/// LSE frequency
+pub const LSE_FREQ: u32 = 32_768;
+
 /// Clocks configutation
+///
+/// The AHB and APB buses can be run slower than the core, using the
+/// prescalers. This reduces the current consumption of the peripherals
+/// connected to them.
 pub struct Config {
     mux: ClockSrc,
     ahb_pre: AHBPrescaler,
     apb1_pre: APBPrescaler,
     apb2_pre: APBPrescaler,
+    hse_bypass: bool,
+    flash_prefetch: bool,
 }
 
 impl Default for Config {
@@ -113,6 +240,8 @@ impl Default for Config {
             ahb_pre: AHBPrescaler::NotDivided,
             apb1_pre: APBPrescaler::NotDivided,
             apb2_pre: APBPrescaler::NotDivided,
+            hse_bypass: false,
+            flash_prefetch: true,
         }
     }
 }
@@ -124,24 +253,49 @@ impl Config {
         self
     }
 
+    /// Sets the AHB prescaler
     #[inline]
     pub fn ahb_pre(mut self, pre: AHBPrescaler) -> Self {
         self.ahb_pre = pre;
         self
     }
 
+    /// Sets the APB1 prescaler
     #[inline]
     pub fn apb1_pre(mut self, pre: APBPrescaler) -> Self {
         self.apb1_pre = pre;
         self
     }
 
+    /// Sets the APB2 prescaler
     #[inline]
     pub fn apb2_pre(mut self, pre: APBPrescaler) -> Self {
         self.apb2_pre = pre;
         self
     }
 
+    /// Selects an external clock on OSC_IN as the HSE, instead of a crystal
+    ///
+    /// This allows feeding the system clock or the PLL from an external
+    /// oscillator (e.g. a TCXO), or from the clock output of another device.
+    /// Only has an effect, if the HSE is used.
+    #[inline]
+    pub fn hse_bypass(mut self, bypass: bool) -> Self {
+        self.hse_bypass = bypass;
+        self
+    }
+
+    /// Enables the Flash prefetch buffer, if a Flash wait state is required
+    ///
+    /// With a wait state, the prefetch buffer reads the next 64 bits ahead,
+    /// which speeds up sequential code execution at the cost of a slightly
+    /// higher current consumption. Enabled by default.
+    #[inline]
+    pub fn flash_prefetch(mut self, enabled: bool) -> Self {
+        self.flash_prefetch = enabled;
+        self
+    }
+
     #[inline]
     pub fn hsi16() -> Config {
         Config {
@@ -149,6 +303,8 @@ impl Config {
             ahb_pre: AHBPrescaler::NotDivided,
             apb1_pre: APBPrescaler::NotDivided,
             apb2_pre: APBPrescaler::NotDivided,
+            hse_bypass: false,
+            flash_prefetch: true,
         }
     }
 
@@ -159,6 +315,8 @@ impl Config {
             ahb_pre: AHBPrescaler::NotDivided,
             apb1_pre: APBPrescaler::NotDivided,
             apb2_pre: APBPrescaler::NotDivided,
+            hse_bypass: false,
+            flash_prefetch: true,
         }
     }
 
@@ -169,6 +327,8 @@ impl Config {
             ahb_pre: AHBPrescaler::NotDivided,
             apb1_pre: APBPrescaler::NotDivided,
             apb2_pre: APBPrescaler::NotDivided,
+            hse_bypass: false,
+            flash_prefetch: true,
         }
     }
 
@@ -182,6 +342,8 @@ impl Config {
             ahb_pre: AHBPrescaler::NotDivided,
             apb1_pre: APBPrescaler::NotDivided,
             apb2_pre: APBPrescaler::NotDivided,
+            hse_bypass: false,
+            flash_prefetch: true,
         }
     }
 }
@@ -192,20 +354,250 @@ pub struct Rcc {
     pub(crate) rb: RCC,
 }
 
+impl Rcc {
+    /// Enables the LSI oscillator and waits for it to be ready
+    ///
+    /// The LSI runs at roughly 37 kHz, and can clock the RTC, LPTIM and the
+    /// independent watchdog.
+    pub fn enable_lsi(&mut self) {
+        self.rb.csr.modify(|_, w| w.lsion().set_bit());
+        while self.rb.csr.read().lsirdy().bit_is_clear() {}
+    }
+
+    /// Disables the LSI oscillator
+    pub fn disable_lsi(&mut self) {
+        self.rb.csr.modify(|_, w| w.lsion().clear_bit());
+    }
+
+    /// Returns the factory calibration value of the HSI16 (HSI16CAL)
+    pub fn hsi16_calibration(&self) -> u8 {
+        self.rb.icscr.read().hsi16cal().bits()
+    }
+
+    /// Returns the user trimming value of the HSI16 (HSI16TRIM)
+    pub fn hsi16_trim(&self) -> u8 {
+        self.rb.icscr.read().hsi16trim().bits()
+    }
+
+    /// Sets the user trimming value of the HSI16 (HSI16TRIM)
+    ///
+    /// The trimming value is added to the factory calibration, to compensate
+    /// for variations in voltage and temperature. The reset value is 16, and
+    /// each step changes the frequency by roughly 0.5%. The actual frequency
+    /// can be determined by measuring the HSI16 against an accurate reference,
+    /// like the LSE.
+    ///
+    /// Please note that `clocks` is not updated, as it always assumes the
+    /// nominal frequency of 16 MHz.
+    ///
+    /// Returns [`config::Error::ClockFrequency`], if `trim` is larger than 31,
+    /// i.e. outside of the trimming range.
+    pub fn set_hsi16_trim(&mut self, trim: u8) -> Result<(), config::Error> {
+        if trim > 31 {
+            return Err(config::Error::ClockFrequency);
+        }
+        self.rb.icscr.modify(|_, w| w.hsi16trim().bits(trim));
+        Ok(())
+    }
+
+    /// Changes the MSI range at runtime
+    ///
+    /// If the MSI is the current system clock, this changes the system clock
+    /// frequency, and `clocks` is updated accordingly. This can be used to
+    /// throttle the system clock between bursts of activity.
+    ///
+    /// Please note that peripherals that have been configured based on the
+    /// previous frequencies (timers, serial baud rates, delays, etc.) are not
+    /// reconfigured and need to be set up again.
+    pub fn set_msi_range(&mut self, range: MSIRange) {
+        // Set MSI range. This is allowed while the MSI is off, or while it's
+        // on and ready. See STM32L0x2 reference manual, section 7.3.2.
+        while self.rb.cr.read().msion().bit_is_set() && self.rb.cr.read().msirdy().bit_is_clear() {}
+        self.rb.icscr.modify(|_, w| w.msirange().bits(range as u8));
+
+        if let ClockSrc::MSI(_) = self.clocks.source {
+            while self.rb.cr.read().msirdy().bit_is_clear() {}
+
+            let source = ClockSrc::MSI(range);
+            let (sys_clk, _) = source.frequencies();
+            self.clocks = Clocks::new(
+                source,
+                sys_clk,
+                self.clocks.ahb_pre,
+                self.clocks.apb1_pre,
+                self.clocks.apb2_pre,
+            );
+        }
+    }
+
+    /// Changes the clock configuration at runtime
+    ///
+    /// This switches the system clock to the new source, reconfiguring the
+    /// PLL if necessary, and updates the Flash wait states and `clocks`. If
+    /// the new frequencies require it, the core voltage is switched to
+    /// range 1. Switching to a lower voltage range, to save power after
+    /// switching to a slower clock, is left to [`PWR::set_vcore_range`].
+    ///
+    /// Peripherals that have been configured based on the previous
+    /// frequencies (timers, serial baud rates, delays, etc.) are not
+    /// reconfigured, and need to be updated using the new `clocks`, e.g. via
+    /// `Serial::set_baudrate`.
+    ///
+    /// [`PWR::set_vcore_range`]: crate::pwr::PWR::set_vcore_range
+    ///
+    /// # Panics
+    ///
+    /// Panics, if the configuration is invalid. See [`Rcc::try_reconfigure`].
+    pub fn reconfigure(&mut self, cfgr: Config) {
+        self.try_reconfigure(cfgr).unwrap();
+    }
+
+    /// Changes the clock configuration at runtime, if it is valid
+    ///
+    /// Like [`Rcc::reconfigure`], but returns an error instead of panicking,
+    /// if any of the frequencies is outside of its supported range. The
+    /// current configuration is left unchanged in that case.
+    pub fn try_reconfigure(&mut self, cfgr: Config) -> Result<(), config::Error> {
+        self.clocks = configure(&self.rb, cfgr)?;
+        Ok(())
+    }
+
+    /// Enables the clock security system (CSS) for the HSE
+    ///
+    /// If the HSE fails while the CSS is enabled, the HSE is disabled, the
+    /// system clock is switched to the MSI (if it was derived from the HSE),
+    /// and a non-maskable interrupt (NMI) is triggered. The NMI handler must
+    /// call [`Rcc::clear_hse_failure`], or the NMI will be triggered again
+    /// immediately.
+    ///
+    /// Returns [`config::Error::ClockNotReady`], if the HSE is not running.
+    pub fn enable_hse_css(&mut self) -> Result<(), config::Error> {
+        if self.rb.cr.read().hserdy().bit_is_clear() {
+            return Err(config::Error::ClockNotReady);
+        }
+        self.rb.cr.modify(|_, w| w.csshseon().set_bit());
+        Ok(())
+    }
+
+    /// Returns whether the CSS has detected a failure of the HSE
+    pub fn is_hse_failure(&self) -> bool {
+        self.rb.cifr.read().csshsef().bit_is_set()
+    }
+
+    /// Clears the HSE failure flag, and updates `clocks` after the fallback
+    ///
+    /// Call this from the NMI handler after a failure of the HSE. If the
+    /// system clock was derived from the HSE, directly or through the PLL, the
+    /// hardware has switched it to the MSI, and `clocks` is updated
+    /// accordingly. Peripherals that have been configured based on the
+    /// previous frequencies need to be set up again.
+    pub fn clear_hse_failure(&mut self) {
+        self.write_cicr(1 << 8);
+
+        let source = match self.rb.cfgr.read().sws().bits() {
+            0b00 => ClockSrc::MSI(MSIRange::from_bits(self.rb.icscr.read().msirange().bits())),
+            0b01 => ClockSrc::HSI16,
+            // On a failure, the system clock is switched to the MSI, if it is
+            // derived from the HSE. So if it's still the HSE or the PLL, the
+            // failure didn't affect it.
+            _ => self.clocks.source,
+        };
+
+        let (sys_clk, _) = source.frequencies();
+        self.clocks = Clocks::new(
+            source,
+            sys_clk,
+            self.clocks.ahb_pre,
+            self.clocks.apb1_pre,
+            self.clocks.apb2_pre,
+        );
+    }
+
+    /// Enables the interrupt for failures of the LSE
+    ///
+    /// The clock security system for the LSE needs to be enabled using
+    /// [`BackupDomain::enable_lse_css`]. The interrupt is routed through EXTI
+    /// line 19, which is shared with the RTC tamper events.
+    ///
+    /// [`BackupDomain::enable_lse_css`]: crate::pwr::BackupDomain::enable_lse_css
+    pub fn listen_lse_css(&mut self, exti: &mut Exti) {
+        // `CIER` is marked as read-only in the PAC, although it is writable.
+        // Safe, as it is only ever modified through `&mut Rcc`.
+        let cier = unsafe { addr_of!((*RCC::ptr()).cier) as *mut u32 };
+        unsafe { cier.write_volatile(cier.read_volatile() | 1 << 7) };
+
+        exti.listen_configurable(ConfigurableLine::RtcTamper_CssLse, TriggerEdge::Rising);
+    }
+
+    /// Returns whether the CSS has detected a failure of the LSE
+    ///
+    /// After a failure, the LSE can no longer be used as the RTC clock until
+    /// the backup domain has been reset.
+    pub fn is_lse_failure(&self) -> bool {
+        self.rb.csr.read().csslsed().bit_is_set()
+    }
+
+    /// Clears the LSE failure interrupt flag, and the pending EXTI line
+    pub fn clear_lse_failure(&mut self) {
+        self.write_cicr(1 << 7);
+        Exti::unpend(ConfigurableLine::RtcTamper_CssLse);
+    }
+
+    /// Writes the interrupt clear register
+    ///
+    /// `CICR` is marked as read-only in the PAC, although it is write-only.
+    fn write_cicr(&mut self, bits: u32) {
+        // Safe, as writing zeros has no effect.
+        unsafe { (addr_of!((*RCC::ptr()).cicr) as *mut u32).write_volatile(bits) };
+    }
+}
+
 #[cfg(any(feature = "stm32l0x2", feature = "stm32l0x3"))]
 impl Rcc {
+    /// Enables the HSI48 clock, trimmed by the CRS using the LSE
+    ///
+    /// See [`Rcc::enable_hsi48_with_sync`] for details.
     pub fn enable_hsi48(&mut self, syscfg: &mut SYSCFG, crs: CRS) -> HSI48 {
+        self.enable_hsi48_with_sync(syscfg, crs, CrsSyncSource::Lse)
+    }
+
+    /// Enables the HSI48 clock, trimmed by the CRS using the given source
+    ///
+    /// This also enables the VREFINT reference required by the HSI48, and
+    /// selects the HSI48 as the USB and RNG clock.
+    ///
+    /// For crystal-less USB operation, use [`CrsSyncSource::UsbSof`]. The
+    /// HSI48 will then be trimmed continuously, once the host has started
+    /// sending start-of-frame packets.
+    pub fn enable_hsi48_with_sync(
+        &mut self,
+        syscfg: &mut SYSCFG,
+        crs: CRS,
+        sync: CrsSyncSource,
+    ) -> HSI48 {
+        self.enable_hsi48_with_crs(syscfg, crs, sync).0
+    }
+
+    /// Enables the HSI48 clock, trimmed by the CRS using the given source
+    ///
+    /// Like [`Rcc::enable_hsi48_with_sync`], but also returns a [`Crs`]
+    /// handle, which can be used to monitor the synchronization.
+    pub fn enable_hsi48_with_crs(
+        &mut self,
+        syscfg: &mut SYSCFG,
+        crs: CRS,
+        sync: CrsSyncSource,
+    ) -> (HSI48, Crs) {
         // Reset CRS peripheral
-        self.rb.apb1rstr.modify(|_, w| w.crsrst().set_bit());
-        self.rb.apb1rstr.modify(|_, w| w.crsrst().clear_bit());
+        CRS::reset(self);
 
         // Enable CRS peripheral
-        self.rb.apb1enr.modify(|_, w| w.crsen().set_bit());
+        CRS::enable(self);
 
         // Initialize CRS
-        crs.cfgr.write(|w|
-            // Select LSE as synchronization source
-            unsafe { w.syncsrc().bits(0b01) });
+        crs.cfgr.modify(|_, w|
+            // Select synchronization source
+            unsafe { w.syncsrc().bits(sync as u8) });
         crs.cr
             .modify(|_, w| w.autotrimen().set_bit().cen().set_bit());
 
@@ -222,13 +614,97 @@ impl Rcc {
         self.rb.crrcr.modify(|_, w| w.hsi48on().set_bit());
         while self.rb.crrcr.read().hsi48rdy().bit_is_clear() {}
 
-        HSI48(())
+        (HSI48(()), Crs { crs })
+    }
+}
+
+/// The clock recovery system (CRS), which trims the HSI48
+///
+/// Returned by [`Rcc::enable_hsi48_with_crs`]. The status flags are set on
+/// every synchronization event and stay set until they are cleared.
+#[cfg(any(feature = "stm32l0x2", feature = "stm32l0x3"))]
+pub struct Crs {
+    crs: CRS,
+}
+
+#[cfg(any(feature = "stm32l0x2", feature = "stm32l0x3"))]
+impl Crs {
+    /// Returns whether the frequency error was within the OK range (SYNCOKF)
+    pub fn is_sync_ok(&self) -> bool {
+        self.crs.isr.read().syncokf().bit_is_set()
+    }
+
+    /// Returns whether the frequency error was within the warning range
+    /// (SYNCWARNF)
+    ///
+    /// The HSI48 is still trimmed, but might have drifted out of the USB
+    /// specification until then.
+    pub fn is_sync_warning(&self) -> bool {
+        self.crs.isr.read().syncwarnf().bit_is_set()
+    }
+
+    /// Returns whether a synchronization event was missed (SYNCMISS)
+    ///
+    /// With [`CrsSyncSource::UsbSof`], this happens while the host isn't
+    /// sending start-of-frame packets, e.g. during USB suspend.
+    pub fn is_sync_miss(&self) -> bool {
+        self.crs.isr.read().syncmiss().bit_is_set()
+    }
+
+    /// Returns whether the frequency error was too large to be trimmed
+    /// (SYNCERR)
+    pub fn is_sync_error(&self) -> bool {
+        self.crs.isr.read().syncerr().bit_is_set()
+    }
+
+    /// Returns whether the trim value has reached its limit (TRIMOVF)
+    pub fn is_trim_overflow(&self) -> bool {
+        self.crs.isr.read().trimovf().bit_is_set()
+    }
+
+    /// Clears all status flags
+    pub fn clear_flags(&mut self) {
+        self.crs.icr.write(|w| {
+            w.syncokc()
+                .set_bit()
+                .syncwarnc()
+                .set_bit()
+                .errc()
+                .set_bit()
+                .esyncc()
+                .set_bit()
+        });
+    }
+
+    /// Returns the current trim value of the HSI48
+    pub fn trim(&self) -> u8 {
+        self.crs.cr.read().trim().bits()
+    }
+
+    /// Releases the CRS peripheral
+    ///
+    /// The CRS keeps trimming the HSI48.
+    pub fn free(self) -> CRS {
+        self.crs
     }
 }
 
 /// Extension trait that freezes the `RCC` peripheral with provided clocks configuration
-pub trait RccExt {
-    fn freeze(self, config: Config) -> Rcc;
+pub trait RccExt: Sized {
+    /// Applies the clock configuration
+    ///
+    /// # Panics
+    ///
+    /// Panics, if the configuration is invalid. See [`RccExt::try_freeze`].
+    fn freeze(self, config: Config) -> Rcc {
+        self.try_freeze(config).unwrap()
+    }
+
+    /// Applies the clock configuration, if it is valid
+    ///
+    /// Returns an error, if any of the frequencies is outside of its supported
+    /// range. No clocks are changed in that case.
+    fn try_freeze(self, config: Config) -> Result<Rcc, config::Error>;
 }
 
 impl RccExt for RCC {
@@ -236,142 +712,215 @@ impl RccExt for RCC {
     // marking this function and all `Config` constructors and setters as `#[inline]`.
     // This saves ~900 Bytes for the `pwr.rs` example.
     #[inline]
-    fn freeze(self, cfgr: Config) -> Rcc {
-        let (sys_clk, sw_bits) = match cfgr.mux {
-            ClockSrc::MSI(range) => {
-                let range = range as u8;
-                // Set MSI range
-                self.icscr.write(|w| w.msirange().bits(range));
-
-                // Enable MSI
-                self.cr.write(|w| w.msion().set_bit());
-                while self.cr.read().msirdy().bit_is_clear() {}
-
-                let freq = 32_768 * (1 << (range + 1));
-                (freq, 0)
-            }
-            ClockSrc::HSI16 => {
-                // Enable HSI16
-                self.cr.write(|w| w.hsi16on().set_bit());
-                while self.cr.read().hsi16rdyf().bit_is_clear() {}
+    fn try_freeze(self, cfgr: Config) -> Result<Rcc, config::Error> {
+        let clocks = configure(&self, cfgr)?;
+        Ok(Rcc { rb: self, clocks })
+    }
+}
 
-                (HSI_FREQ, 1)
-            }
-            ClockSrc::HSE(freq) => {
-                // Enable HSE
-                self.cr.write(|w| w.hseon().set_bit());
-                while self.cr.read().hserdy().bit_is_clear() {}
+/// Applies the clock configuration, returning the resulting frequencies
+///
+/// This is used both initially by [`RccExt::freeze`], and at runtime by
+/// [`Rcc::reconfigure`], so it must not make any assumptions about the
+/// current configuration. The configuration is checked before any clocks are
+/// changed.
+#[inline]
+fn configure(rcc: &RCC, cfgr: Config) -> Result<Clocks, config::Error> {
+    let (sys_clk, vco) = cfgr.mux.frequencies();
 
-                (freq.0, 2)
-            }
-            ClockSrc::PLL(src, mul, div) => {
-                let (src_bit, freq) = match src {
-                    PLLSource::HSE(freq) => {
-                        // Enable HSE
-                        self.cr.write(|w| w.hseon().set_bit());
-                        while self.cr.read().hserdy().bit_is_clear() {}
-                        (true, freq.0)
-                    }
-                    PLLSource::HSI16 => {
-                        // Enable HSI
-                        self.cr.write(|w| w.hsi16on().set_bit());
-                        while self.cr.read().hsi16rdyf().bit_is_clear() {}
-                        (false, HSI_FREQ)
-                    }
-                };
+    // See the datasheets for the supported HSE frequencies.
+    let hse = match cfgr.mux {
+        ClockSrc::HSE(freq) | ClockSrc::PLL(PLLSource::HSE(freq), _, _) => Some(freq.0),
+        _ => None,
+    };
+    if let Some(freq) = hse {
+        let valid = if cfgr.hse_bypass {
+            freq <= 32_000_000
+        } else {
+            (1_000_000..=25_000_000).contains(&freq)
+        };
+        if !valid {
+            return Err(config::Error::ClockFrequency);
+        }
+    }
+    if let ClockSrc::PLL(src, _, _) = cfgr.mux {
+        let freq = match src {
+            PLLSource::HSE(freq) => freq.0,
+            PLLSource::HSI16 => HSI_FREQ,
+        };
+        if !(2_000_000..=24_000_000).contains(&freq) {
+            return Err(config::Error::ClockFrequency);
+        }
+    }
+    if sys_clk > 32.mhz().0 || vco.unwrap_or(0) > VcoreRange::Range1.max_pll_vco().0 {
+        return Err(config::Error::ClockFrequency);
+    }
 
-                // Disable PLL
-                self.cr.modify(|_, w| w.pllon().clear_bit());
-                while self.cr.read().pllrdy().bit_is_set() {}
+    // Make sure the voltage range supports the requested frequencies,
+    // switching to range 1 if necessary. See STM32L0x2 reference manual,
+    // section 6.1.4.
+    rcc.apb1enr.modify(|_, w| w.pwren().set_bit());
+    // Safe, as we're only accessing the VOS field, which the `PWR` API only
+    // modifies after making sure the current clocks are compatible.
+    let pwr = unsafe { &*pac::PWR::ptr() };
+    let mut range = VcoreRange::from_bits(pwr.cr.read().vos().bits());
+    if sys_clk > range.max_sys_clk().0 || vco.unwrap_or(0) > range.max_pll_vco().0 {
+        while pwr.csr.read().vosf().bit_is_set() {}
+        // Safe, as we're writing a valid bit pattern.
+        pwr.cr
+            .modify(|_, w| unsafe { w.vos().bits(VcoreRange::Range1 as u8) });
+        while pwr.csr.read().vosf().bit_is_set() {}
 
-                let mul_bytes = mul as u8;
-                let div_bytes = div as u8;
+        range = VcoreRange::Range1;
+    }
 
-                let freq = match mul {
-                    PLLMul::Mul3 => freq * 3,
-                    PLLMul::Mul4 => freq * 4,
-                    PLLMul::Mul6 => freq * 6,
-                    PLLMul::Mul8 => freq * 8,
-                    PLLMul::Mul12 => freq * 12,
-                    PLLMul::Mul16 => freq * 16,
-                    PLLMul::Mul24 => freq * 24,
-                    PLLMul::Mul32 => freq * 32,
-                    PLLMul::Mul48 => freq * 48,
-                };
+    // Configure a Flash wait state, if required, before switching to the
+    // faster clock. See STM32L0x2 reference manual, section 3.3.3.
+    //
+    // Safe, as the `FLASH` API only ever modifies the LATENCY and PRFTEN
+    // fields in the same way.
+    let flash = unsafe { &*pac::FLASH::ptr() };
+    let wait_state = sys_clk > range.max_sys_clk_zero_wait_states().0;
+    if wait_state {
+        flash.acr.modify(|_, w| w.latency().set_bit());
+        while flash.acr.read().latency().bit_is_clear() {}
 
-                let freq = match div {
-                    PLLDiv::Div2 => freq / 2,
-                    PLLDiv::Div3 => freq / 3,
-                    PLLDiv::Div4 => freq / 4,
-                };
-                assert!(freq <= 32.mhz().0);
-
-                self.cfgr.write(move |w| unsafe {
-                    w.pllmul()
-                        .bits(mul_bytes)
-                        .plldiv()
-                        .bits(div_bytes)
-                        .pllsrc()
-                        .bit(src_bit)
-                });
-
-                // Enable PLL
-                self.cr.modify(|_, w| w.pllon().set_bit());
-                while self.cr.read().pllrdy().bit_is_clear() {}
-
-                (freq, 3)
-            }
-        };
+        // The prefetch buffer only has an effect with a wait state.
+        flash.acr.modify(|_, w| w.prften().bit(cfgr.flash_prefetch));
+    }
 
-        self.cfgr.modify(|_, w| unsafe {
-            w.sw()
-                .bits(sw_bits)
-                .hpre()
-                .bits(cfgr.ahb_pre as u8)
-                .ppre1()
-                .bits(cfgr.apb1_pre as u8)
-                .ppre2()
-                .bits(cfgr.apb2_pre as u8)
-        });
+    let sw_bits = match cfgr.mux {
+        ClockSrc::MSI(range) => {
+            let range = range as u8;
+            // Set MSI range
+            rcc.icscr.modify(|_, w| w.msirange().bits(range));
 
-        let ahb_freq = match cfgr.ahb_pre {
-            AHBPrescaler::NotDivided => sys_clk,
-            pre => sys_clk / (1 << (pre as u8 - 7)),
-        };
+            // Enable MSI
+            rcc.cr.modify(|_, w| w.msion().set_bit());
+            while rcc.cr.read().msirdy().bit_is_clear() {}
 
-        let (apb1_freq, apb1_tim_freq) = match cfgr.apb1_pre {
-            APBPrescaler::NotDivided => (ahb_freq, ahb_freq),
-            pre => {
-                let freq = ahb_freq / (1 << (pre as u8 - 3));
-                (freq, freq * 2)
-            }
-        };
+            0
+        }
+        ClockSrc::HSI16 => {
+            enable_hsi16(rcc);
+            1
+        }
+        ClockSrc::HSE(_) => {
+            enable_hse(rcc, cfgr.hse_bypass);
+            2
+        }
+        ClockSrc::PLL(src, mul, div) => {
+            let src_bit = match src {
+                PLLSource::HSE(_) => {
+                    enable_hse(rcc, cfgr.hse_bypass);
+                    true
+                }
+                PLLSource::HSI16 => {
+                    enable_hsi16(rcc);
+                    false
+                }
+            };
 
-        let (apb2_freq, apb2_tim_freq) = match cfgr.apb2_pre {
-            APBPrescaler::NotDivided => (ahb_freq, ahb_freq),
-            pre => {
-                let freq = ahb_freq / (1 << (pre as u8 - 3));
-                (freq, freq * 2)
+            // The PLL can't be reconfigured while it's the system clock, so
+            // switch to the HSI16 temporarily.
+            if rcc.cfgr.read().sws().bits() == 3 {
+                enable_hsi16(rcc);
+                switch_sys_clk(rcc, 1);
             }
-        };
 
-        let clocks = Clocks {
-            source: cfgr.mux,
-            sys_clk: sys_clk.hz(),
-            ahb_clk: ahb_freq.hz(),
-            apb1_clk: apb1_freq.hz(),
-            apb2_clk: apb2_freq.hz(),
-            apb1_tim_clk: apb1_tim_freq.hz(),
-            apb2_tim_clk: apb2_tim_freq.hz(),
-        };
+            // Disable PLL
+            rcc.cr.modify(|_, w| w.pllon().clear_bit());
+            while rcc.cr.read().pllrdy().bit_is_set() {}
+
+            let mul_bytes = mul as u8;
+            let div_bytes = div as u8;
+
+            rcc.cfgr.modify(move |_, w| unsafe {
+                w.pllmul()
+                    .bits(mul_bytes)
+                    .plldiv()
+                    .bits(div_bytes)
+                    .pllsrc()
+                    .bit(src_bit)
+            });
+
+            // Enable PLL
+            rcc.cr.modify(|_, w| w.pllon().set_bit());
+            while rcc.cr.read().pllrdy().bit_is_clear() {}
 
-        Rcc { rb: self, clocks }
+            3
+        }
+    };
+
+    rcc.cfgr.modify(|_, w| unsafe {
+        w.hpre()
+            .bits(cfgr.ahb_pre as u8)
+            .ppre1()
+            .bits(cfgr.apb1_pre as u8)
+            .ppre2()
+            .bits(cfgr.apb2_pre as u8)
+    });
+    switch_sys_clk(rcc, sw_bits);
+
+    // Stop the PLL and HSE, if they are no longer in use. The MSI and HSI16
+    // are left running, as they might be used as kernel clocks.
+    if !matches!(cfgr.mux, ClockSrc::PLL(..)) {
+        rcc.cr.modify(|_, w| w.pllon().clear_bit());
+    }
+    if !matches!(
+        cfgr.mux,
+        ClockSrc::HSE(_) | ClockSrc::PLL(PLLSource::HSE(_), _, _)
+    ) {
+        rcc.cr.modify(|_, w| w.hseon().clear_bit());
+    }
+
+    // Remove the Flash wait state, if it isn't required.
+    if !wait_state {
+        flash.acr.modify(|_, w| w.prften().clear_bit());
+        flash.acr.modify(|_, w| w.latency().clear_bit());
+    }
+
+    Ok(Clocks::new(
+        cfgr.mux,
+        sys_clk,
+        cfgr.ahb_pre,
+        cfgr.apb1_pre,
+        cfgr.apb2_pre,
+    ))
+}
+
+fn enable_hsi16(rcc: &RCC) {
+    rcc.cr.modify(|_, w| w.hsi16on().set_bit());
+    while rcc.cr.read().hsi16rdyf().bit_is_clear() {}
+}
+
+fn enable_hse(rcc: &RCC, bypass: bool) {
+    // HSEBYP can only be changed while the HSE is off, which isn't possible
+    // while it's in use.
+    if rcc.cr.read().hsebyp().bit() != bypass {
+        let sws = rcc.cfgr.read().sws().bits();
+        assert!(sws != 2 && !(sws == 3 && rcc.cfgr.read().pllsrc().bit_is_set()));
+
+        rcc.cr.modify(|_, w| w.hseon().clear_bit());
+        while rcc.cr.read().hserdy().bit_is_set() {}
+        rcc.cr.modify(|_, w| w.hsebyp().bit(bypass));
     }
+
+    rcc.cr.modify(|_, w| w.hseon().set_bit());
+    while rcc.cr.read().hserdy().bit_is_clear() {}
+}
+
+/// Switches the system clock, and waits for the switch to take effect
+fn switch_sys_clk(rcc: &RCC, sw_bits: u8) {
+    rcc.cfgr.modify(|_, w| w.sw().bits(sw_bits));
+    while rcc.cfgr.read().sws().bits() != sw_bits {}
 }
 
 /// Frozen clock frequencies
 ///
-/// The existence of this value indicates that the clock configuration can no longer be changed
+/// Returned by [`RccExt::freeze`] as part of [`Rcc`], and used by the
+/// peripheral constructors to derive their timing (baud rates, prescalers,
+/// etc.) from the actual clock configuration.
 #[derive(Clone, Copy)]
 pub struct Clocks {
     source: ClockSrc,
@@ -381,9 +930,38 @@ pub struct Clocks {
     apb1_tim_clk: Hertz,
     apb2_clk: Hertz,
     apb2_tim_clk: Hertz,
+    ahb_pre: AHBPrescaler,
+    apb1_pre: APBPrescaler,
+    apb2_pre: APBPrescaler,
 }
 
 impl Clocks {
+    fn new(
+        source: ClockSrc,
+        sys_clk: u32,
+        ahb_pre: AHBPrescaler,
+        apb1_pre: APBPrescaler,
+        apb2_pre: APBPrescaler,
+    ) -> Self {
+        let ahb_freq = sys_clk / ahb_pre.divisor();
+
+        let (apb1_freq, apb1_tim_freq) = apb1_pre.frequencies(ahb_freq);
+        let (apb2_freq, apb2_tim_freq) = apb2_pre.frequencies(ahb_freq);
+
+        Clocks {
+            source,
+            sys_clk: sys_clk.hz(),
+            ahb_clk: ahb_freq.hz(),
+            apb1_clk: apb1_freq.hz(),
+            apb2_clk: apb2_freq.hz(),
+            apb1_tim_clk: apb1_tim_freq.hz(),
+            apb2_tim_clk: apb2_tim_freq.hz(),
+            ahb_pre,
+            apb1_pre,
+            apb2_pre,
+        }
+    }
+
     /// Returns the clock source
     pub fn source(&self) -> &ClockSrc {
         &self.source
@@ -418,6 +996,85 @@ impl Clocks {
     pub fn apb2_tim_clk(&self) -> Hertz {
         self.apb2_tim_clk
     }
+
+    /// Returns the AHB prescaler
+    pub fn ahb_pre(&self) -> AHBPrescaler {
+        self.ahb_pre
+    }
+
+    /// Returns the APB1 prescaler
+    pub fn apb1_pre(&self) -> APBPrescaler {
+        self.apb1_pre
+    }
+
+    /// Returns the APB2 prescaler
+    pub fn apb2_pre(&self) -> APBPrescaler {
+        self.apb2_pre
+    }
+
+    /// Returns the kernel clock frequency of USART1
+    pub fn usart1_clk(&self) -> Hertz {
+        self.kernel_clk(ccipr().usart1sel().bits(), self.apb2_clk)
+    }
+
+    /// Returns the kernel clock frequency of USART2
+    pub fn usart2_clk(&self) -> Hertz {
+        self.kernel_clk(ccipr().usart2sel().bits(), self.apb1_clk)
+    }
+
+    /// Returns the kernel clock frequency of LPUART1
+    pub fn lpuart1_clk(&self) -> Hertz {
+        self.kernel_clk(ccipr().lpuart1sel().bits(), self.apb1_clk)
+    }
+
+    /// Returns the kernel clock frequency of I2C1
+    pub fn i2c1_clk(&self) -> Hertz {
+        self.i2c_kernel_clk(ccipr().i2c1sel().bits())
+    }
+
+    /// Returns the kernel clock frequency of I2C3
+    pub fn i2c3_clk(&self) -> Hertz {
+        self.i2c_kernel_clk(ccipr().i2c3sel().bits())
+    }
+
+    /// Returns the frequency of a USART/LPUART kernel clock selection
+    fn kernel_clk(&self, sel: u8, apb_clk: Hertz) -> Hertz {
+        match sel {
+            0b00 => apb_clk,
+            0b01 => self.sys_clk,
+            0b10 => HSI_FREQ.hz(),
+            _ => LSE_FREQ.hz(),
+        }
+    }
+
+    /// Returns the frequency of an I2C kernel clock selection
+    ///
+    /// The value `0b11` is reserved for I2C, and selects the APB1 clock as well.
+    fn i2c_kernel_clk(&self, sel: u8) -> Hertz {
+        match sel {
+            0b11 => self.apb1_clk,
+            sel => self.kernel_clk(sel, self.apb1_clk),
+        }
+    }
+}
+
+/// Reads `RCC_CCIPR`
+fn ccipr() -> pac::rcc::ccipr::R {
+    // Safe, as we're only doing an atomic read of a register that isn't
+    // modified by this access.
+    unsafe { (*RCC::ptr()).ccipr.read() }
+}
+
+/// Synchronization source of the clock recovery system (CRS)
+#[cfg(any(feature = "stm32l0x2", feature = "stm32l0x3"))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CrsSyncSource {
+    /// CRS_SYNC pin
+    Gpio = 0b00,
+    /// LSE oscillator
+    Lse = 0b01,
+    /// USB start-of-frame packets
+    UsbSof = 0b10,
 }
 
 /// Token that exists only, if the HSI48 clock has been enabled
@@ -425,3 +1082,228 @@ impl Clocks {
 /// You can get an instance of this struct by calling [`Rcc::enable_hsi48`].
 #[derive(Clone, Copy)]
 pub struct HSI48(());
+
+/// Enables and disables the clock of a peripheral
+///
+/// This is implemented for all peripherals that have an enable bit in one of
+/// the RCC's `AHBENR`, `APB1ENR`, `APB2ENR` or `IOPENR` registers. Disabling
+/// the clock of an unused peripheral reduces the current consumption.
+pub trait Enable {
+    /// Enables the peripheral clock
+    fn enable(rcc: &mut Rcc);
+
+    /// Disables the peripheral clock
+    fn disable(rcc: &mut Rcc);
+
+    /// Returns whether the peripheral clock is enabled
+    fn is_enabled(rcc: &Rcc) -> bool;
+}
+
+/// Resets a peripheral
+///
+/// This is implemented for all peripherals that have a reset bit in one of
+/// the RCC's `AHBRSTR`, `APB1RSTR`, `APB2RSTR` or `IOPRSTR` registers.
+pub trait Reset {
+    /// Resets the peripheral, restoring the reset value of all its registers
+    fn reset(rcc: &mut Rcc);
+}
+
+/// Enables and disables the clock of a peripheral in Sleep mode
+///
+/// After reset, the clocks of all enabled peripherals keep running in Sleep
+/// and Low-power sleep mode. Disabling the clocks of peripherals that are not
+/// needed to wake up reduces the current consumption in these modes. This is
+/// controlled by the RCC's `AHBSMENR`, `APB1SMENR`, `APB2SMENR` and `IOPSMEN`
+/// registers.
+pub trait SleepEnable {
+    /// Keeps the peripheral clock running in Sleep mode (reset value)
+    fn enable_in_sleep(rcc: &mut Rcc);
+
+    /// Stops the peripheral clock in Sleep mode
+    fn disable_in_sleep(rcc: &mut Rcc);
+}
+
+macro_rules! bus {
+    ($(
+        $(#[$attr:meta])*
+        $PER:ident => (
+            $enr:ident, $en:ident
+            $(, $rstr:ident, $rst:ident, $smenr:ident, $smen:ident)?
+        ),
+    )+) => {
+        $(
+            $(#[$attr])*
+            impl Enable for pac::$PER {
+                #[inline]
+                fn enable(rcc: &mut Rcc) {
+                    rcc.rb.$enr.modify(|_, w| w.$en().set_bit());
+                }
+
+                #[inline]
+                fn disable(rcc: &mut Rcc) {
+                    rcc.rb.$enr.modify(|_, w| w.$en().clear_bit());
+                }
+
+                #[inline]
+                fn is_enabled(rcc: &Rcc) -> bool {
+                    rcc.rb.$enr.read().$en().bit_is_set()
+                }
+            }
+
+            bus!(@reset_sleep [$(#[$attr])*] $PER $(, $rstr, $rst, $smenr, $smen)?);
+        )+
+    };
+    (
+        @reset_sleep [$(#[$attr:meta])*] $PER:ident,
+        $rstr:ident, $rst:ident, $smenr:ident, $smen:ident
+    ) => {
+        $(#[$attr])*
+        impl Reset for pac::$PER {
+            #[inline]
+            fn reset(rcc: &mut Rcc) {
+                rcc.rb.$rstr.modify(|_, w| w.$rst().set_bit());
+                rcc.rb.$rstr.modify(|_, w| w.$rst().clear_bit());
+            }
+        }
+
+        $(#[$attr])*
+        impl SleepEnable for pac::$PER {
+            #[inline]
+            fn enable_in_sleep(rcc: &mut Rcc) {
+                rcc.rb.$smenr.modify(|_, w| w.$smen().set_bit());
+            }
+
+            #[inline]
+            fn disable_in_sleep(rcc: &mut Rcc) {
+                rcc.rb.$smenr.modify(|_, w| w.$smen().clear_bit());
+            }
+        }
+    };
+    (@reset_sleep [$(#[$attr:meta])*] $PER:ident) => {};
+}
+
+bus! {
+    DMA1 => (ahbenr, dmaen, ahbrstr, dmarst, ahbsmenr, dmasmen),
+    FLASH => (ahbenr, mifen, ahbrstr, mifrst, ahbsmenr, mifsmen),
+    CRC => (ahbenr, crcen, ahbrstr, crcrst, ahbsmenr, crcsmen),
+    #[cfg(feature = "aes")]
+    AES => (ahbenr, crypen, ahbrstr, cryprst, ahbsmenr, crypsmen),
+    #[cfg(any(feature = "stm32l0x2", feature = "stm32l0x3"))]
+    RNG => (ahbenr, rngen, ahbrstr, rngrst, ahbsmenr, rngsmen),
+    #[cfg(any(feature = "stm32l0x2", feature = "stm32l0x3"))]
+    TSC => (ahbenr, touchen, ahbrstr, touchrst, ahbsmenr, touchsmen),
+
+    GPIOA => (iopenr, iopaen, ioprstr, ioparst, iopsmen, iopasmen),
+    GPIOB => (iopenr, iopben, ioprstr, iopbrst, iopsmen, iopbsmen),
+    GPIOC => (iopenr, iopcen, ioprstr, iopcrst, iopsmen, iopcsmen),
+    GPIOD => (iopenr, iopden, ioprstr, iopdrst, iopsmen, iopdsmen),
+    #[cfg(feature = "io-STM32L071")]
+    GPIOE => (iopenr, iopeen, ioprstr, ioperst, iopsmen, iopesmen),
+    GPIOH => (iopenr, iophen, ioprstr, iophrst, iopsmen, iophsmen),
+
+    TIM2 => (apb1enr, tim2en, apb1rstr, tim2rst, apb1smenr, tim2smen),
+    #[cfg(feature = "io-STM32L071")]
+    TIM3 => (apb1enr, tim3en, apb1rstr, tim3rst, apb1smenr, tim3smen),
+    #[cfg(any(feature = "io-STM32L051", feature = "io-STM32L071"))]
+    TIM6 => (apb1enr, tim6en, apb1rstr, tim6rst, apb1smenr, tim6smen),
+    #[cfg(feature = "io-STM32L071")]
+    TIM7 => (apb1enr, tim7en, apb1rstr, tim7rst, apb1smenr, tim7smen),
+    SPI2 => (apb1enr, spi2en, apb1rstr, spi2rst, apb1smenr, spi2smen),
+    #[cfg(feature = "stm32l0x1")]
+    USART2 => (apb1enr, usart2en, apb1rstr, usart2rst, apb1smenr, usart2smen),
+    // The reset bit of USART2 is misnamed in the PAC.
+    #[cfg(any(feature = "stm32l0x2", feature = "stm32l0x3"))]
+    USART2 => (apb1enr, usart2en, apb1rstr, lpuart12rst, apb1smenr, usart2smen),
+    LPUART1 => (apb1enr, lpuart1en, apb1rstr, lpuart1rst, apb1smenr, lpuart1smen),
+    #[cfg(feature = "io-STM32L071")]
+    USART4 => (apb1enr, usart4en, apb1rstr, usart4rst, apb1smenr, usart4smen),
+    #[cfg(feature = "io-STM32L071")]
+    USART5 => (apb1enr, usart5en, apb1rstr, usart5rst, apb1smenr, usart5smen),
+    I2C1 => (apb1enr, i2c1en, apb1rstr, i2c1rst, apb1smenr, i2c1smen),
+    #[cfg(any(feature = "io-STM32L051", feature = "io-STM32L071"))]
+    I2C2 => (apb1enr, i2c2en, apb1rstr, i2c2rst, apb1smenr, i2c2smen),
+    #[cfg(feature = "io-STM32L071")]
+    I2C3 => (apb1enr, i2c3en, apb1rstr, i2c3rst, apb1smenr, i2c3smen),
+    #[cfg(any(feature = "stm32l0x2", feature = "stm32l0x3"))]
+    USB => (apb1enr, usben, apb1rstr, usbrst, apb1smenr, usbsmen),
+    #[cfg(any(feature = "stm32l0x2", feature = "stm32l0x3"))]
+    CRS => (apb1enr, crsen, apb1rstr, crsrst, apb1smenr, crssmen),
+    PWR => (apb1enr, pwren, apb1rstr, pwrrst, apb1smenr, pwrsmen),
+    #[cfg(any(feature = "stm32l0x2", feature = "stm32l0x3"))]
+    DAC => (apb1enr, dacen, apb1rstr, dacrst, apb1smenr, dacsmen),
+    LPTIM => (apb1enr, lptim1en, apb1rstr, lptim1rst, apb1smenr, lptim1smen),
+    #[cfg(feature = "stm32l0x1")]
+    WWDG => (apb1enr, wwdgen, apb1rstr, wwdgrst, apb1smenr, wwdgsmen),
+    // The reset bit of WWDG is misnamed in the PAC.
+    #[cfg(any(feature = "stm32l0x2", feature = "stm32l0x3"))]
+    WWDG => (apb1enr, wwdgen, apb1rstr, wwdrst, apb1smenr, wwdgsmen),
+
+    SYSCFG => (apb2enr, syscfgen, apb2rstr, syscfgrst, apb2smenr, syscfgsmen),
+    TIM21 => (apb2enr, tim21en, apb2rstr, tim21rst, apb2smenr, tim21smen),
+    TIM22 => (apb2enr, tim22en, apb2rstr, tim22rst, apb2smenr, tim22smen),
+    ADC => (apb2enr, adcen, apb2rstr, adcrst, apb2smenr, adcsmen),
+    SPI1 => (apb2enr, spi1en, apb2rstr, spi1rst, apb2smenr, spi1smen),
+    USART1 => (apb2enr, usart1en, apb2rstr, usart1rst, apb2smenr, usart1smen),
+    DBG => (apb2enr, dbgen, apb2rstr, dbgrst, apb2smenr, dbgsmen),
+    #[cfg(feature = "stm32l0x1")]
+    FW => (apb2enr, fwen),
+    #[cfg(any(feature = "stm32l0x2", feature = "stm32l0x3"))]
+    FW => (apb2enr, mifien),
+}
+
+// The LCD bits (bit 9 of APB1ENR, APB1RSTR and APB1SMENR) are missing from
+// the PAC.
+#[cfg(feature = "stm32l0x3")]
+const LCD_BIT: u32 = 1 << 9;
+
+#[cfg(feature = "stm32l0x3")]
+impl Enable for pac::LCD {
+    #[inline]
+    fn enable(rcc: &mut Rcc) {
+        rcc.rb
+            .apb1enr
+            .modify(|r, w| unsafe { w.bits(r.bits() | LCD_BIT) });
+    }
+
+    #[inline]
+    fn disable(rcc: &mut Rcc) {
+        rcc.rb
+            .apb1enr
+            .modify(|r, w| unsafe { w.bits(r.bits() & !LCD_BIT) });
+    }
+
+    #[inline]
+    fn is_enabled(rcc: &Rcc) -> bool {
+        rcc.rb.apb1enr.read().bits() & LCD_BIT != 0
+    }
+}
+
+#[cfg(feature = "stm32l0x3")]
+impl Reset for pac::LCD {
+    #[inline]
+    fn reset(rcc: &mut Rcc) {
+        rcc.rb
+            .apb1rstr
+            .modify(|r, w| unsafe { w.bits(r.bits() | LCD_BIT) });
+        rcc.rb
+            .apb1rstr
+            .modify(|r, w| unsafe { w.bits(r.bits() & !LCD_BIT) });
+    }
+}
+
+#[cfg(feature = "stm32l0x3")]
+impl SleepEnable for pac::LCD {
+    #[inline]
+    fn enable_in_sleep(rcc: &mut Rcc) {
+        rcc.rb
+            .apb1smenr
+            .modify(|r, w| unsafe { w.bits(r.bits() | LCD_BIT) });
+    }
+
+    #[inline]
+    fn disable_in_sleep(rcc: &mut Rcc) {
+        rcc.rb
+            .apb1smenr
+            .modify(|r, w| unsafe { w.bits(r.bits() & !LCD_BIT) });
+    }
+}
@@ -1,4 +1,4 @@
-use crate::rcc::{Rcc, HSI48};
+use crate::rcc::{Enable, Rcc, Reset, HSI48};
 
 pub use crate::pac::{rng, RNG};
 
@@ -10,11 +10,10 @@ impl Rng {
     // Initializes the peripheral
     pub fn new(rng: RNG, rcc: &mut Rcc, _: HSI48) -> Rng {
         // Reset peripheral
-        rcc.rb.ahbrstr.modify(|_, w| w.rngrst().set_bit());
-        rcc.rb.ahbrstr.modify(|_, w| w.rngrst().clear_bit());
+        RNG::reset(rcc);
 
         // Enable peripheral clock
-        rcc.rb.ahbenr.modify(|_, w| w.rngen().set_bit());
+        RNG::enable(rcc);
 
         rng.cr.write(|w| w.rngen().set_bit().ie().clear_bit());
 
@@ -26,7 +25,7 @@ impl Rng {
     }
 
     pub fn enable(&mut self) {
-        self.rng.cr.write(|w| w.rngen().set_bit().ie().clear_bit());
+        self.rng.cr.modify(|_, w| w.rngen().set_bit());
     }
 
     pub fn disable(&mut self) {
@@ -42,4 +41,75 @@ impl Rng {
     pub fn take_result(&mut self) -> u32 {
         self.rng.dr.read().bits()
     }
+
+    /// Returns a random number, once one is available
+    ///
+    /// Unlike [`Rng::wait`] and [`Rng::take_result`], this checks for errors
+    /// first, so a failure of the entropy source can't go unnoticed.
+    ///
+    /// After a seed error, the peripheral is re-initialized and a random
+    /// number is available again after a short while. A clock error resolves
+    /// itself, once the RNG clock is fast enough again.
+    pub fn read(&mut self) -> nb::Result<u32, Error> {
+        let sr = self.rng.sr.read();
+
+        if sr.seis().bit_is_set() {
+            // Clear the flag and re-initialize the peripheral, which discards
+            // the random numbers that were affected.
+            self.rng.sr.modify(|_, w| w.seis().clear_bit());
+            self.rng.cr.modify(|_, w| w.rngen().clear_bit());
+            self.rng.cr.modify(|_, w| w.rngen().set_bit());
+
+            return Err(nb::Error::Other(Error::SeedError));
+        }
+        if sr.ceis().bit_is_set() {
+            self.rng.sr.modify(|_, w| w.ceis().clear_bit());
+
+            return Err(nb::Error::Other(Error::ClockError));
+        }
+        if sr.drdy().bit_is_clear() {
+            return Err(nb::Error::WouldBlock);
+        }
+
+        Ok(self.rng.dr.read().bits())
+    }
+
+    /// Fills `buffer` with random bytes
+    ///
+    /// Blocks until enough random numbers are available, or an error occurs.
+    pub fn fill(&mut self, buffer: &mut [u8]) -> Result<(), Error> {
+        for chunk in buffer.chunks_mut(4) {
+            let random = nb::block!(self.read())?.to_le_bytes();
+            chunk.copy_from_slice(&random[..chunk.len()]);
+        }
+
+        Ok(())
+    }
+
+    /// Enables the RNG interrupt
+    ///
+    /// The interrupt is triggered, when a random number is available, or an
+    /// error occurred. Call [`Rng::read`] from the interrupt handler, which
+    /// also acknowledges the errors.
+    pub fn listen(&mut self) {
+        self.rng.cr.modify(|_, w| w.ie().set_bit());
+    }
+
+    /// Disables the RNG interrupt
+    pub fn unlisten(&mut self) {
+        self.rng.cr.modify(|_, w| w.ie().clear_bit());
+    }
+}
+
+/// RNG errors
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    /// The RNG clock is too slow (CEIS)
+    ///
+    /// It must be at least 1/16 of the AHB clock.
+    ClockError,
+
+    /// The entropy source failed (SEIS)
+    SeedError,
 }
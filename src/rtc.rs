@@ -1,65 +1,111 @@
 //! Interface to the Real-time clock (RTC) peripheral
 //!
 //! See STM32L0x2 reference manual, chapter 26.
+//!
+//! With the `rtcc` feature, [`RTC`] implements the `rtcc` traits, which use the
+//! `chrono` date/time types.
+
+#[cfg(feature = "rtcc")]
+use core::convert::TryFrom;
 
+#[cfg(feature = "rtcc")]
+use rtcc::{
+    DateTimeAccess, Datelike as _, Hours, NaiveDate, NaiveDateTime, NaiveTime, Rtcc, Timelike as _,
+};
 use void::Void;
 
 use crate::{
+    exti::{ConfigurableLine, Exti, TriggerEdge},
     hal::timer::{self, Cancel as _},
     pac,
     pwr::PWR,
     rcc::Rcc,
-    time::U32Ext,
+    time::{Hertz, U32Ext},
 };
 
+/// Number of backup registers
+pub const BACKUP_REGISTERS: usize = 5;
+
+/// Clock source of the RTC
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ClockSource {
+    /// The Low-Speed External oscillator, at 32768 Hz
+    ///
+    /// This is the only source that keeps the RTC accurate and functional
+    /// over system resets and in all low-power modes.
+    Lse = 0b01,
+
+    /// The Low-Speed Internal oscillator, at roughly 37 kHz
+    ///
+    /// The LSI is not very accurate, but doesn't require an external crystal.
+    Lsi = 0b10,
+}
+
+impl ClockSource {
+    fn frequency(self) -> Hertz {
+        match self {
+            ClockSource::Lse => 32_768u32.hz(),
+            ClockSource::Lsi => 37_000u32.hz(),
+        }
+    }
+}
+
 /// Entry point to the RTC API
 pub struct RTC {
     rtc: pac::RTC,
     read_twice: bool,
+    prediv_s: u16,
 }
 
 impl RTC {
-    /// Initializes the RTC API
+    /// Initializes the RTC API, using the LSE as clock source
     ///
     /// The `initial_instant` argument will only be used, if the real-time clock
-    /// is not already configured. Its `subsecond` field will be ignore in any
-    /// case.
+    /// is not already configured. Its `microsecond` field will be ignored in
+    /// any case.
+    ///
+    /// # Panics
+    ///
+    /// Panics, if the ABP1 clock frequency is lower than the RTC clock
+    /// frequency of 32768 Hz.
+    pub fn new(rtc: pac::RTC, rcc: &mut Rcc, pwr: &mut PWR, init: Instant) -> Self {
+        Self::with_clock_source(rtc, rcc, pwr, ClockSource::Lse, init)
+    }
+
+    /// Initializes the RTC API, using the given clock source
+    ///
+    /// The `init` argument will only be used, if the real-time clock is not
+    /// already configured.
+    ///
+    /// The clock source can only be changed by resetting the backup domain.
+    /// If the RTC is already running from another source, this method does
+    /// that, which resets the date/time and clears the backup registers.
     ///
     /// # Panics
     ///
     /// Panics, if the ABP1 clock frequency is lower than the RTC clock
-    /// frequency. The RTC is currently hardcoded to use the LSE as clock source
-    /// which runs at 32768 Hz.
-    pub fn new(rtc: pac::RTC, rcc: &mut Rcc, _: &PWR, init: Instant) -> Self {
+    /// frequency.
+    pub fn with_clock_source(
+        rtc: pac::RTC,
+        rcc: &mut Rcc,
+        pwr: &mut PWR,
+        source: ClockSource,
+        init: Instant,
+    ) -> Self {
         // Backup write protection must be disabled by setting th DBP bit in
         // PWR_CR, otherwise it's not possible to access the RTC registers. We
         // assume that this was done during PWR initialization. To make sure it
         // already happened, this function requires a reference to PWR.
 
-        // Select the LSE clock as the clock source for the RTC clock, as that's
-        // the only source that keeps the RTC clocked and functional over system
-        // resets.
-        // Other clock sources are currently not supported.
-        //
-        // ATTENTION:
-        // The prescaler settings in `set` assume that the LSE is selected, and
-        // that the frequency is 32768 Hz. If you change the clock selection
-        // here, you have to adapt the prescaler settings too.
-        rcc.rb.csr.modify(|_, w| {
-            // Select LSE as RTC clock source.
-            // This is safe, as we're writing a valid bit pattern.
-            w.rtcsel().bits(0b01);
-            // Enable RTC clock
-            w.rtcen().set_bit();
-            // Enable LSE clock
-            w.lseon().set_bit()
-        });
+        if source == ClockSource::Lsi {
+            // The LSI is not part of the backup domain.
+            rcc.enable_lsi();
+        }
 
-        // Wait for LSE to be ready
-        while rcc.rb.csr.read().lserdy().bit_is_clear() {}
+        pwr.with_backup_domain_access(rcc, |bd| bd.set_rtc_clock(source));
 
         let apb1_clk = rcc.clocks.apb1_clk();
-        let rtc_clk = 32_768u32.hz(); // LSE crystal frequency
+        let rtc_clk = source.frequency();
 
         // The APB1 clock must not be slower than the RTC clock.
         if apb1_clk < rtc_clk {
@@ -73,11 +119,19 @@ impl RTC {
         // frequency, special care must be taken when reading some registers.
         let read_twice = apb1_clk.0 < 7 * rtc_clk.0;
 
-        let mut rtc = RTC { rtc, read_twice };
+        // The calendar needs a 1 Hz clock. Keep the asynchronous prescaler as
+        // high as possible, to minimize power consumption.
+        let prediv_s = (rtc_clk.0 / 128 - 1) as u16;
+
+        let mut rtc = RTC {
+            rtc,
+            read_twice,
+            prediv_s,
+        };
 
         if rtc.rtc.isr.read().inits().bit_is_clear() {
             // RTC not yet initialized. Do that now.
-            rtc.set(init);
+            rtc.set_datetime(init);
         }
 
         // Disable wakeup timer. It's periodic and persists over resets, but for
@@ -89,16 +143,32 @@ impl RTC {
 
         // Clear RSF bit, in case we woke up from Stop or Standby mode. This is
         // necessary, according to section 26.4.8.
-        rtc.rtc.isr.write(|w| w.rsf().set_bit());
+        rtc.write(|rtc| rtc.isr.write(|w| unsafe { w.bits(ISR_FLAGS) }.rsf().clear_bit()));
 
         rtc
     }
 
     /// Sets the date/time
+    #[deprecated(note = "Use `set_datetime` instead")]
     pub fn set(&mut self, instant: Instant) {
+        self.set_datetime(instant)
+    }
+
+    /// Returns the current date/time
+    #[deprecated(note = "Use `datetime` instead")]
+    pub fn now(&mut self) -> Instant {
+        self.datetime()
+    }
+
+    /// Sets the date/time
+    ///
+    /// Enters initialization mode, during which the calendar is stopped.
+    pub fn set_datetime(&mut self, instant: Instant) {
+        let prediv_s = self.prediv_s;
+
         self.write(|rtc| {
             // Start initialization
-            rtc.isr.modify(|_, w| w.init().set_bit());
+            rtc.isr.write(|w| unsafe { w.bits(ISR_FLAGS) }.init().set_bit());
 
             // Wait until RTC register access is allowed
             while rtc.isr.read().initf().bit_is_clear() {}
@@ -108,15 +178,11 @@ impl RTC {
 
             // Configure the prescaler to generate a 1 Hz clock for the
             // calendar.
-            //
-            // ATTENTION:
-            // This assumes the RTC clock frequency is 32768 Hz. If this
-            // assumption holds no longer true, you need to change this code.
             rtc.prer.write(|w|
                 // Safe, because we're only writing valid values to the fields.
                 unsafe {
                     w.prediv_a().bits(0x7f);
-                    w.prediv_s().bits(0xff)
+                    w.prediv_s().bits(prediv_s)
                 });
 
             // Write time
@@ -141,7 +207,7 @@ impl RTC {
             // Write date
             rtc.dr.write(|w|
                 // Safe, as `Instant` verifies that its fields are valid.
-                w
+                unsafe { w
                     // Year tens
                     .yt().bits(instant.year / 10)
                     // Year units
@@ -150,18 +216,20 @@ impl RTC {
                     .mt().bit(instant.month / 10 == 1)
                     // Month units
                     .mu().bits(instant.month % 10)
+                    // Week day units
+                    .wdu().bits(instant.weekday)
                     // Date tens
                     .dt().bits(instant.day / 10)
                     // Date units
-                    .du().bits(instant.day % 10));
+                    .du().bits(instant.day % 10) });
 
             // Exit initialization
-            rtc.isr.modify(|_, w| w.init().clear_bit());
+            rtc.isr.write(|w| unsafe { w.bits(ISR_FLAGS) }.init().clear_bit());
         })
     }
 
     /// Returns the current date/time
-    pub fn now(&mut self) -> Instant {
+    pub fn datetime(&mut self) -> Instant {
         // We need to wait until the RSF bit is set, for a multitude of reasons:
         // - In case the last read was within two cycles of RTCCLK. Not sure why
         //   that's important, but the documentation says so.
@@ -172,9 +240,11 @@ impl RTC {
         // All of this is explain in section 26.4.8.
         while self.rtc.isr.read().rsf().bit_is_clear() {}
 
-        // Reading the TR register locks the DR register until we clear the RSF
-        // flag, so there's no danger of reading something weird here, as long
-        // as this order of access is kept.
+        // Reading the SSR register locks the TR and DR registers, and reading
+        // TR locks DR, until we clear the RSF flag, so there's no danger of
+        // reading something weird here, as long as this order of access is
+        // kept.
+        let mut ssr = self.rtc.ssr.read().ss().bits();
         let mut tr = self.rtc.tr.read();
         let mut dr = self.rtc.dr.read();
 
@@ -183,12 +253,14 @@ impl RTC {
         // the two reads match. See section 26.4.8.
         if self.read_twice {
             loop {
+                let ssr2 = self.rtc.ssr.read().ss().bits();
                 let tr2 = self.rtc.tr.read();
                 let dr2 = self.rtc.dr.read();
 
-                if tr.bits() == tr2.bits() && dr.bits() == dr2.bits() {
+                if ssr == ssr2 && tr.bits() == tr2.bits() && dr.bits() == dr2.bits() {
                     break;
                 } else {
+                    ssr = ssr2;
                     tr = tr2;
                     dr = dr2;
                 }
@@ -197,17 +269,27 @@ impl RTC {
 
         self.write(|rtc| {
             // Clear the RSF flag, to unlock the TR and DR registers.
-            rtc.isr.write(|w| w.rsf().set_bit());
+            rtc.isr.write(|w| unsafe { w.bits(ISR_FLAGS) }.rsf().clear_bit());
         });
 
+        // The sub-second counter counts down from PREDIV_S. It can be larger
+        // than PREDIV_S for up to a second after a negative shift, in which
+        // case the fraction is really negative. Let's not return a time that
+        // lies in the future.
+        let prediv_s = self.prediv_s as u32;
+        let fraction = prediv_s.saturating_sub(ssr as u32);
+        let microsecond = fraction * 1_000_000 / (prediv_s + 1);
+
         Instant {
             year: dr.yt().bits() * 10 + dr.yu().bits(),
             month: dr.mt().bit() as u8 * 10 + dr.mu().bits(),
             day: dr.dt().bits() * 10 + dr.du().bits(),
+            weekday: dr.wdu().bits(),
 
             hour: tr.ht().bits() * 10 + tr.hu().bits(),
             minute: tr.mnt().bits() * 10 + tr.mnu().bits(),
             second: tr.st().bits() * 10 + tr.su().bits(),
+            microsecond,
         }
     }
 
@@ -259,6 +341,451 @@ impl RTC {
         })
     }
 
+    /// Starts listening for an interrupt event
+    ///
+    /// The events need to be routed through their EXTI lines, to wake up the
+    /// microcontroller from Stop and Standby mode.
+    pub fn listen(&mut self, event: Event) {
+        self.enable_interrupts(event.interrupts());
+    }
+
+    /// Stops listening for an interrupt event
+    pub fn unlisten(&mut self, event: Event) {
+        self.disable_interrupts(event.interrupts());
+    }
+
+    /// Returns whether the flag of an interrupt event is set
+    ///
+    /// This doesn't depend on whether the event is listened for.
+    pub fn is_pending(&self, event: Event) -> bool {
+        let isr = self.rtc.isr.read();
+        match event {
+            Event::AlarmA => isr.alraf().bit_is_set(),
+            Event::AlarmB => isr.alrbf().bit_is_set(),
+            Event::WakeupTimer => isr.wutf().bit_is_set(),
+            Event::Timestamp => isr.tsf().bit_is_set(),
+        }
+    }
+
+    /// Clears the flag of an interrupt event
+    ///
+    /// The pending bit of the EXTI line is left alone.
+    pub fn clear(&mut self, event: Event) {
+        self.write(|rtc| {
+            rtc.isr.write(|w| {
+                let w = unsafe { w.bits(ISR_FLAGS) };
+                match event {
+                    Event::AlarmA => w.alraf().clear_bit(),
+                    Event::AlarmB => w.alrbf().clear_bit(),
+                    Event::WakeupTimer => w.wutf().clear_bit(),
+                    Event::Timestamp => w.tsf().clear_bit(),
+                }
+            })
+        });
+    }
+
+    /// Configures and enables an alarm
+    ///
+    /// Any previous configuration of the alarm is overwritten, and its flag is
+    /// cleared.
+    pub fn set_alarm(&mut self, id: AlarmId, alarm: Alarm) {
+        self.write(|rtc| {
+            match id {
+                AlarmId::A => {
+                    // Disable the alarm and wait until we may configure it
+                    rtc.cr.modify(|_, w| w.alrae().clear_bit());
+                    while rtc.isr.read().alrawf().bit_is_clear() {}
+
+                    // Safe, as `Alarm` verifies that its fields are valid.
+                    rtc.alrmar.write(|w| unsafe { w.bits(alarm.alrmr()) });
+                    rtc.alrmassr.write(|w| unsafe { w.bits(alarm.alrmssr()) });
+
+                    rtc.isr.write(|w| unsafe { w.bits(ISR_FLAGS) }.alraf().clear_bit());
+                    rtc.cr.modify(|_, w| w.alrae().set_bit());
+                }
+                AlarmId::B => {
+                    // Disable the alarm and wait until we may configure it
+                    rtc.cr.modify(|_, w| w.alrbe().clear_bit());
+                    while rtc.isr.read().alrbwf().bit_is_clear() {}
+
+                    // Safe, as `Alarm` verifies that its fields are valid.
+                    rtc.alrmbr.write(|w| unsafe { w.bits(alarm.alrmr()) });
+                    rtc.alrmbssr.write(|w| unsafe { w.bits(alarm.alrmssr()) });
+
+                    rtc.isr.write(|w| unsafe { w.bits(ISR_FLAGS) }.alrbf().clear_bit());
+                    rtc.cr.modify(|_, w| w.alrbe().set_bit());
+                }
+            }
+        })
+    }
+
+    /// Disables an alarm and clears its flag
+    pub fn disable_alarm(&mut self, id: AlarmId) {
+        self.write(|rtc| match id {
+            AlarmId::A => {
+                rtc.cr.modify(|_, w| w.alrae().clear_bit());
+                rtc.isr.write(|w| unsafe { w.bits(ISR_FLAGS) }.alraf().clear_bit());
+            }
+            AlarmId::B => {
+                rtc.cr.modify(|_, w| w.alrbe().clear_bit());
+                rtc.isr.write(|w| unsafe { w.bits(ISR_FLAGS) }.alrbf().clear_bit());
+            }
+        })
+    }
+
+    /// Enables the alarm interrupt and starts listening on its EXTI line
+    ///
+    /// Both alarms share EXTI line 17 ([`ConfigurableLine::RtcAlarm`]) and the
+    /// `RTC` interrupt. The EXTI line allows the alarms to wake up the
+    /// microcontroller from Stop and Standby mode.
+    pub fn listen_alarm(&mut self, id: AlarmId, exti: &mut Exti) {
+        self.enable_interrupts(Interrupts {
+            alarm_a: id == AlarmId::A,
+            alarm_b: id == AlarmId::B,
+            ..Interrupts::default()
+        });
+        exti.listen_configurable(ConfigurableLine::RtcAlarm, TriggerEdge::Rising);
+    }
+
+    /// Checks whether an alarm has fired
+    ///
+    /// Returns `Ok(())` and clears the alarm flag, as well as the pending bit
+    /// of the EXTI line, if the alarm has fired. This should be called from the
+    /// `RTC` interrupt handler, otherwise the alarm won't fire again.
+    pub fn wait_alarm(&mut self, id: AlarmId) -> nb::Result<(), Void> {
+        let isr = self.rtc.isr.read();
+        let fired = match id {
+            AlarmId::A => isr.alraf().bit_is_set(),
+            AlarmId::B => isr.alrbf().bit_is_set(),
+        };
+        if !fired {
+            return Err(nb::Error::WouldBlock);
+        }
+
+        self.write(|rtc| match id {
+            AlarmId::A => rtc.isr.write(|w| unsafe { w.bits(ISR_FLAGS) }.alraf().clear_bit()),
+            AlarmId::B => rtc.isr.write(|w| unsafe { w.bits(ISR_FLAGS) }.alrbf().clear_bit()),
+        });
+        Exti::unpend(ConfigurableLine::RtcAlarm);
+
+        Ok(())
+    }
+
+    /// Adds one hour to the calendar, for the start of daylight saving time
+    ///
+    /// Also sets the BKP bit, which keeps track of daylight saving time over
+    /// resets. See [`RTC::is_daylight_saving`].
+    pub fn add_hour(&mut self) {
+        self.write(|rtc| {
+            rtc.cr.modify(|_, w| {
+                w.add1h().set_bit();
+                w.bkp().set_bit()
+            });
+        })
+    }
+
+    /// Subtracts one hour from the calendar, for the end of daylight saving
+    /// time
+    ///
+    /// Also clears the BKP bit. Please note that the calendar is not changed,
+    /// if the current hour is 0.
+    pub fn sub_hour(&mut self) {
+        self.write(|rtc| {
+            rtc.cr.modify(|_, w| {
+                w.sub1h().set_bit();
+                w.bkp().clear_bit()
+            });
+        })
+    }
+
+    /// Indicates whether daylight saving time is in effect
+    ///
+    /// Returns the BKP bit, as last modified by [`RTC::add_hour`] or
+    /// [`RTC::sub_hour`].
+    pub fn is_daylight_saving(&self) -> bool {
+        self.rtc.cr.read().bkp().bit_is_set()
+    }
+
+    /// Shifts the clock by a fraction of a second
+    ///
+    /// A positive `microseconds` advances the clock, a negative one delays
+    /// it. The shift is rounded to the resolution of the sub-second counter,
+    /// which is 1/256 second with the LSE. This allows synchronizing the RTC to
+    /// an external time reference, without stopping the calendar.
+    ///
+    /// # Panics
+    ///
+    /// Panics, if `microseconds` is not within the range of +/- one second.
+    pub fn shift(&mut self, microseconds: i32) {
+        assert!((-999_999..=999_999).contains(&microseconds));
+
+        let ticks_per_second = self.prediv_s as i32 + 1;
+        let ticks = (microseconds as i64 * ticks_per_second as i64 / 1_000_000) as i32;
+        if ticks == 0 {
+            return;
+        }
+
+        self.write(|rtc| {
+            // Wait until a previous shift has been applied
+            while rtc.isr.read().shpf().bit_is_set() {}
+
+            rtc.shiftr.write(|w| {
+                if ticks > 0 {
+                    // Advance by a second, then delay by the remainder.
+                    w.add1s().set_bit();
+                    w.subfs().bits((ticks_per_second - ticks) as u16)
+                } else {
+                    w.add1s().clear_bit();
+                    w.subfs().bits(-ticks as u16)
+                }
+            });
+        });
+
+        while self.rtc.isr.read().shpf().bit_is_set() {}
+    }
+
+    /// Configures the smooth digital calibration
+    ///
+    /// Use [`Calibration::from_ppm`] to compensate a measured frequency error
+    /// of the RTC clock.
+    pub fn set_calibration(&mut self, calibration: Calibration) {
+        self.write(|rtc| {
+            // Wait until a previous calibration has been applied
+            while rtc.isr.read().recalpf().bit_is_set() {}
+
+            rtc.calr.write(|w| {
+                w.calp().bit(calibration.plus);
+                w.calw8()
+                    .bit(calibration.window == CalibrationWindow::Seconds8);
+                w.calw16()
+                    .bit(calibration.window == CalibrationWindow::Seconds16);
+                w.calm().bits(calibration.minus)
+            });
+        })
+    }
+
+    /// Returns the current smooth digital calibration
+    pub fn calibration(&self) -> Calibration {
+        let calr = self.rtc.calr.read();
+        let window = if calr.calw8().bit_is_set() {
+            CalibrationWindow::Seconds8
+        } else if calr.calw16().bit_is_set() {
+            CalibrationWindow::Seconds16
+        } else {
+            CalibrationWindow::Seconds32
+        };
+
+        Calibration {
+            plus: calr.calp().bit_is_set(),
+            minus: calr.calm().bits(),
+            window,
+        }
+    }
+
+    /// Enables the calibration output on RTC_OUT
+    ///
+    /// The output frequencies assume a 32768 Hz RTC clock. The 512 Hz output
+    /// is not affected by the smooth digital calibration, the 1 Hz output is.
+    pub fn enable_calibration_output(&mut self, output: CalibrationOutput) {
+        self.write(|rtc| {
+            rtc.cr.modify(|_, w| {
+                w.cosel().bit(output == CalibrationOutput::Hz1);
+                w.coe().set_bit()
+            });
+        })
+    }
+
+    /// Disables the calibration output
+    pub fn disable_calibration_output(&mut self) {
+        self.write(|rtc| {
+            rtc.cr.modify(|_, w| w.coe().clear_bit());
+        })
+    }
+
+    /// Routes an alarm or the wakeup timer flag to RTC_OUT
+    ///
+    /// The output is active as long as the respective flag is set. If the
+    /// calibration output is enabled too, it takes precedence, unless the
+    /// outputs are moved apart via [`RTC::set_output_pin`].
+    pub fn enable_alarm_output(
+        &mut self,
+        output: AlarmOutput,
+        polarity: OutputPolarity,
+        output_type: OutputType,
+    ) {
+        self.write(|rtc| {
+            rtc.or
+                .modify(|_, w| w.rtc_alarm_type().bit(output_type == OutputType::PushPull));
+            rtc.cr.modify(|_, w| {
+                w.pol().bit(polarity == OutputPolarity::Low);
+                w.osel().bits(output as u8)
+            });
+        })
+    }
+
+    /// Disables the alarm output
+    pub fn disable_alarm_output(&mut self) {
+        self.write(|rtc| {
+            rtc.cr.modify(|_, w| w.osel().disabled());
+        })
+    }
+
+    /// Selects the pin for RTC_OUT
+    ///
+    /// With `OutputPin::PB14`, the calibration output is moved to PB14. The
+    /// alarm output is moved there too, unless the calibration output is
+    /// enabled at the same time, in which case the alarm output stays on PC13.
+    /// PB14 needs to be configured to its RTC_OUT alternate function, while
+    /// PC13 is controlled by the RTC directly.
+    pub fn set_output_pin(&mut self, pin: OutputPin) {
+        self.write(|rtc| {
+            rtc.or
+                .modify(|_, w| w.rtc_out_rmp().bit(pin == OutputPin::PB14));
+        })
+    }
+
+    /// Reads a backup register
+    ///
+    /// The backup registers keep their value over system resets and in all
+    /// low-power modes, including Standby. They are only cleared by a backup
+    /// domain reset, a tamper event (see [`RTC::enable_tamper`]), or when
+    /// the readout protection level is changed from 1 to 0.
+    ///
+    /// # Panics
+    ///
+    /// Panics, if `index` is not smaller than [`BACKUP_REGISTERS`].
+    pub fn read_backup(&self, index: usize) -> u32 {
+        self.rtc.bkpr[index].read().bits()
+    }
+
+    /// Writes a backup register
+    ///
+    /// See [`RTC::read_backup`].
+    ///
+    /// # Panics
+    ///
+    /// Panics, if `index` is not smaller than [`BACKUP_REGISTERS`].
+    pub fn write_backup(&mut self, index: usize, value: u32) {
+        // The backup registers are not protected by WPR.
+        // This is safe, as the field accepts the full range of 32-bit values.
+        self.rtc.bkpr[index].write(|w| w.bkp().bits(value));
+    }
+
+    /// Configures the settings shared by all tamper inputs
+    ///
+    /// This must only be called while all tamper inputs are disabled.
+    pub fn configure_tampers(&mut self, config: TamperConfig) {
+        self.write(|rtc| {
+            rtc.tampcr.modify(|r, w| {
+                let mut bits = r.bits() & !TAMPCR_SHARED;
+                bits |= (config.sample_rate as u32) << 8;
+                bits |= (config.filter as u32) << 11;
+                bits |= (config.precharge as u32) << 13;
+                if !config.pull_up {
+                    bits |= 1 << 15;
+                }
+                if config.timestamp {
+                    bits |= 1 << 7;
+                }
+                // Safe, as we're only writing valid bit patterns.
+                unsafe { w.bits(bits) }
+            });
+        })
+    }
+
+    /// Enables a tamper input
+    ///
+    /// The RTC takes control of the pin, no GPIO configuration is required.
+    /// Edge triggers require [`TamperFilter::Edge`] to be configured via
+    /// [`RTC::configure_tampers`], level triggers require one of the other
+    /// filter settings.
+    ///
+    /// If `erase_backup` is `true`, a tamper event resets all backup
+    /// registers. This happens in hardware, even if the microcontroller is
+    /// not running at the time.
+    ///
+    /// # Panics
+    ///
+    /// Panics, if `trigger` doesn't match the configured filter.
+    pub fn enable_tamper(&mut self, tamper: Tamper, trigger: TamperTrigger, erase_backup: bool) {
+        let edge = self.rtc.tampcr.read().tampflt().bits() == TamperFilter::Edge as u8;
+        let active_high = match trigger {
+            TamperTrigger::RisingEdge => {
+                assert!(edge);
+                false
+            }
+            TamperTrigger::FallingEdge => {
+                assert!(edge);
+                true
+            }
+            TamperTrigger::LowLevel => {
+                assert!(!edge);
+                false
+            }
+            TamperTrigger::HighLevel => {
+                assert!(!edge);
+                true
+            }
+        };
+
+        self.write(|rtc| {
+            rtc.tampcr.modify(|r, w| {
+                let mut bits = r.bits() & !(tamper.trg() | tamper.noerase());
+                if active_high {
+                    bits |= tamper.trg();
+                }
+                if !erase_backup {
+                    bits |= tamper.noerase();
+                }
+                // Safe, as we're only writing valid bit patterns.
+                unsafe { w.bits(bits | tamper.e()) }
+            });
+        })
+    }
+
+    /// Disables a tamper input, including its interrupt, and clears its flag
+    pub fn disable_tamper(&mut self, tamper: Tamper) {
+        self.write(|rtc| {
+            rtc.tampcr.modify(|r, w|
+                // Safe, as we're only clearing bits.
+                unsafe { w.bits(r.bits() & !(tamper.e() | tamper.ie())) });
+            rtc.isr.write(|w| unsafe { w.bits(ISR_FLAGS & !tamper.f()) });
+        })
+    }
+
+    /// Enables the tamper interrupt and starts listening on its EXTI line
+    ///
+    /// Tamper events share EXTI line 19 ([`ConfigurableLine::RtcTamper_CssLse`])
+    /// with the timestamp and the LSE clock security system. The EXTI line
+    /// allows tamper events to wake up the microcontroller from Stop and
+    /// Standby mode.
+    pub fn listen_tamper(&mut self, tamper: Tamper, exti: &mut Exti) {
+        self.write(|rtc| {
+            rtc.tampcr.modify(|r, w|
+                // Safe, as we're only setting a valid bit.
+                unsafe { w.bits(r.bits() | tamper.ie()) });
+        });
+        exti.listen_configurable(ConfigurableLine::RtcTamper_CssLse, TriggerEdge::Rising);
+    }
+
+    /// Checks whether a tamper event was detected
+    ///
+    /// Returns `Ok(())` and clears the tamper flag, as well as the pending bit
+    /// of the EXTI line, if a tamper event was detected. New tamper events are
+    /// only detected after the flag has been cleared.
+    pub fn wait_tamper(&mut self, tamper: Tamper) -> nb::Result<(), Void> {
+        if self.rtc.isr.read().bits() & tamper.f() == 0 {
+            return Err(nb::Error::WouldBlock);
+        }
+
+        self.write(|rtc| {
+            rtc.isr.write(|w| unsafe { w.bits(ISR_FLAGS & !tamper.f()) });
+        });
+        Exti::unpend(ConfigurableLine::RtcTamper_CssLse);
+
+        Ok(())
+    }
+
     /// Access the wakeup timer
     pub fn wakeup_timer(&mut self) -> WakeupTimer {
         WakeupTimer { rtc: self }
@@ -286,24 +813,26 @@ impl RTC {
 /// An instant in time
 ///
 /// You can create an instance of this struct using [`Instant::new`] or
-/// [`RTC::now`].
+/// [`RTC::datetime`].
 #[derive(Clone, Copy, Debug)]
 pub struct Instant {
     year: u8,
     month: u8,
     day: u8,
+    weekday: u8,
 
     hour: u8,
     minute: u8,
     second: u8,
+    microsecond: u32,
 }
 
 impl Instant {
     /// Creates a new `Instant`
     ///
-    /// Initializes all fields with a default state, with `year`/`month`/`day`
-    /// being `1` and `hour`/`minute`/`second` being `0`. You can use the
-    /// various `set_*` methods to change the fields.
+    /// Initializes all fields with a default state, with `year`/`month`/`day`/
+    /// `weekday` being `1` and `hour`/`minute`/`second` being `0`. You can use
+    /// the various `set_*` methods to change the fields.
     ///
     /// Please note that all `set_*` methods validate their input, and will
     /// panic, if you pass an invalid value.
@@ -315,10 +844,12 @@ impl Instant {
             year: 1,
             month: 1,
             day: 1,
+            weekday: 1,
 
             hour: 0,
             minute: 0,
             second: 0,
+            microsecond: 0,
         }
     }
 
@@ -355,6 +886,20 @@ impl Instant {
         self
     }
 
+    /// Change the day of the week
+    ///
+    /// The RTC doesn't calculate the day of the week from the date, it only
+    /// advances it along with the date. `1` is Monday, `7` is Sunday.
+    ///
+    /// # Panics
+    ///
+    /// Panics, if `weekday` is not a value from `1` to `7`.
+    pub fn set_weekday(mut self, weekday: u8) -> Self {
+        assert!((1..=7).contains(&weekday));
+        self.weekday = weekday;
+        self
+    }
+
     /// Change the hour
     ///
     /// # Panics
@@ -388,6 +933,20 @@ impl Instant {
         self
     }
 
+    /// Change the microsecond
+    ///
+    /// Only used by the value returned from [`RTC::datetime`]. The fraction
+    /// of a second can't be set, use [`RTC::shift`] to adjust it.
+    ///
+    /// # Panics
+    ///
+    /// Panics, if `microsecond` is larger than `999_999`.
+    pub fn set_microsecond(mut self, microsecond: u32) -> Self {
+        assert!(microsecond <= 999_999);
+        self.microsecond = microsecond;
+        self
+    }
+
     pub fn year(&self) -> u8 {
         self.year
     }
@@ -400,6 +959,10 @@ impl Instant {
         self.day
     }
 
+    pub fn weekday(&self) -> u8 {
+        self.weekday
+    }
+
     pub fn hour(&self) -> u8 {
         self.hour
     }
@@ -411,6 +974,645 @@ impl Instant {
     pub fn second(&self) -> u8 {
         self.second
     }
+
+    pub fn microsecond(&self) -> u32 {
+        self.microsecond
+    }
+}
+
+/// Error returned by the [`rtcc`] implementations
+///
+/// Requires the `rtcc` feature.
+#[cfg(feature = "rtcc")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// The value can't be represented by the RTC
+    ///
+    /// E.g. a year outside of 2000 to 2099, or an hour larger than 23.
+    InvalidInputData,
+    /// The RTC holds a date/time that doesn't exist, e.g. February 31
+    InvalidDateTime,
+}
+
+/// Converts an [`Instant`] to a `chrono` date/time in the years 2000 to 2099
+#[cfg(feature = "rtcc")]
+impl TryFrom<Instant> for NaiveDateTime {
+    type Error = Error;
+
+    fn try_from(instant: Instant) -> Result<Self, Error> {
+        NaiveDate::from_ymd_opt(
+            2000 + i32::from(instant.year),
+            u32::from(instant.month),
+            u32::from(instant.day),
+        )
+        .and_then(|date| {
+            date.and_hms_micro_opt(
+                u32::from(instant.hour),
+                u32::from(instant.minute),
+                u32::from(instant.second),
+                instant.microsecond,
+            )
+        })
+        .ok_or(Error::InvalidDateTime)
+    }
+}
+
+/// Converts a `chrono` date/time to an [`Instant`]
+///
+/// The day of the week is derived from the date. Fails, if the year is
+/// outside of 2000 to 2099.
+#[cfg(feature = "rtcc")]
+impl TryFrom<&NaiveDateTime> for Instant {
+    type Error = Error;
+
+    fn try_from(datetime: &NaiveDateTime) -> Result<Self, Error> {
+        let year = rtc_year(datetime.year())?;
+
+        // A leap second is represented as a nanosecond value beyond one second.
+        let microsecond = (datetime.nanosecond() / 1000).min(999_999);
+
+        Ok(Instant::new()
+            .set_year(year)
+            .set_month(datetime.month() as u8)
+            .set_day(datetime.day() as u8)
+            .set_weekday(datetime.weekday().number_from_monday() as u8)
+            .set_hour(datetime.hour() as u8)
+            .set_minute(datetime.minute() as u8)
+            .set_second(datetime.second() as u8)
+            .set_microsecond(microsecond))
+    }
+}
+
+#[cfg(feature = "rtcc")]
+impl TryFrom<NaiveDateTime> for Instant {
+    type Error = Error;
+
+    fn try_from(datetime: NaiveDateTime) -> Result<Self, Error> {
+        Self::try_from(&datetime)
+    }
+}
+
+/// Returns the two-digit year stored by the RTC
+#[cfg(feature = "rtcc")]
+fn rtc_year(year: i32) -> Result<u8, Error> {
+    if (2000..=2099).contains(&year) {
+        Ok((year - 2000) as u8)
+    } else {
+        Err(Error::InvalidInputData)
+    }
+}
+
+#[cfg(feature = "rtcc")]
+impl RTC {
+    /// Changes the date/time, after validating the new value of a field
+    ///
+    /// Like [`RTC::set_datetime`], this restarts the current second.
+    fn update(&mut self, valid: bool, f: impl FnOnce(Instant) -> Instant) -> Result<(), Error> {
+        if !valid {
+            return Err(Error::InvalidInputData);
+        }
+        let instant = f(RTC::datetime(self));
+        RTC::set_datetime(self, instant);
+        Ok(())
+    }
+}
+
+/// The RTC keeps the date/time in 24-hour format, with `1` being Monday.
+#[cfg(feature = "rtcc")]
+impl DateTimeAccess for RTC {
+    type Error = Error;
+
+    fn datetime(&mut self) -> Result<NaiveDateTime, Error> {
+        NaiveDateTime::try_from(RTC::datetime(self))
+    }
+
+    fn set_datetime(&mut self, datetime: &NaiveDateTime) -> Result<(), Error> {
+        let instant = Instant::try_from(datetime)?;
+        RTC::set_datetime(self, instant);
+        Ok(())
+    }
+}
+
+#[cfg(feature = "rtcc")]
+impl Rtcc for RTC {
+    fn seconds(&mut self) -> Result<u8, Error> {
+        Ok(RTC::datetime(self).second())
+    }
+
+    fn minutes(&mut self) -> Result<u8, Error> {
+        Ok(RTC::datetime(self).minute())
+    }
+
+    fn hours(&mut self) -> Result<Hours, Error> {
+        Ok(Hours::H24(RTC::datetime(self).hour()))
+    }
+
+    fn time(&mut self) -> Result<NaiveTime, Error> {
+        Ok(DateTimeAccess::datetime(self)?.time())
+    }
+
+    fn weekday(&mut self) -> Result<u8, Error> {
+        Ok(RTC::datetime(self).weekday())
+    }
+
+    fn day(&mut self) -> Result<u8, Error> {
+        Ok(RTC::datetime(self).day())
+    }
+
+    fn month(&mut self) -> Result<u8, Error> {
+        Ok(RTC::datetime(self).month())
+    }
+
+    fn year(&mut self) -> Result<u16, Error> {
+        Ok(2000 + u16::from(RTC::datetime(self).year()))
+    }
+
+    fn date(&mut self) -> Result<NaiveDate, Error> {
+        Ok(DateTimeAccess::datetime(self)?.date())
+    }
+
+    fn set_seconds(&mut self, seconds: u8) -> Result<(), Error> {
+        self.update(seconds <= 59, |instant| instant.set_second(seconds))
+    }
+
+    fn set_minutes(&mut self, minutes: u8) -> Result<(), Error> {
+        self.update(minutes <= 59, |instant| instant.set_minute(minutes))
+    }
+
+    fn set_hours(&mut self, hours: Hours) -> Result<(), Error> {
+        let (valid, hour) = match hours {
+            Hours::AM(hour) => ((1..=12).contains(&hour), hour % 12),
+            Hours::PM(hour) => ((1..=12).contains(&hour), hour % 12 + 12),
+            Hours::H24(hour) => (hour <= 23, hour),
+        };
+        self.update(valid, |instant| instant.set_hour(hour))
+    }
+
+    fn set_time(&mut self, time: &NaiveTime) -> Result<(), Error> {
+        self.update(true, |instant| {
+            instant
+                .set_hour(time.hour() as u8)
+                .set_minute(time.minute() as u8)
+                .set_second(time.second() as u8)
+        })
+    }
+
+    fn set_weekday(&mut self, weekday: u8) -> Result<(), Error> {
+        self.update((1..=7).contains(&weekday), |instant| {
+            instant.set_weekday(weekday)
+        })
+    }
+
+    fn set_day(&mut self, day: u8) -> Result<(), Error> {
+        self.update((1..=31).contains(&day), |instant| instant.set_day(day))
+    }
+
+    fn set_month(&mut self, month: u8) -> Result<(), Error> {
+        self.update((1..=12).contains(&month), |instant| {
+            instant.set_month(month)
+        })
+    }
+
+    fn set_year(&mut self, year: u16) -> Result<(), Error> {
+        let year = rtc_year(i32::from(year))?;
+        self.update(true, |instant| instant.set_year(year))
+    }
+
+    fn set_date(&mut self, date: &NaiveDate) -> Result<(), Error> {
+        let year = rtc_year(date.year())?;
+        self.update(true, |instant| {
+            instant
+                .set_year(year)
+                .set_month(date.month() as u8)
+                .set_day(date.day() as u8)
+                .set_weekday(date.weekday().number_from_monday() as u8)
+        })
+    }
+}
+
+/// Identifies one of the two RTC alarms
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AlarmId {
+    A,
+    B,
+}
+
+/// Configuration of an RTC alarm
+///
+/// Fields that are not set are masked, i.e. they are "don't care" for the
+/// alarm. An alarm created with [`Alarm::new`] thus fires every second.
+///
+/// Please note that all `set_*` methods validate their input, and will panic,
+/// if you pass an invalid value.
+#[derive(Clone, Copy, Debug)]
+pub struct Alarm {
+    day: Option<AlarmDay>,
+    hour: Option<u8>,
+    minute: Option<u8>,
+    second: Option<u8>,
+    subsecond: u16,
+    subsecond_bits: u8,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum AlarmDay {
+    Date(u8),
+    Weekday(u8),
+}
+
+impl Default for Alarm {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Alarm {
+    /// Creates a new `Alarm`, with all fields masked
+    pub fn new() -> Self {
+        Alarm {
+            day: None,
+            hour: None,
+            minute: None,
+            second: None,
+            subsecond: 0,
+            subsecond_bits: 0,
+        }
+    }
+
+    /// Match the day of the month
+    ///
+    /// Replaces a previous call to `set_weekday`.
+    ///
+    /// # Panics
+    ///
+    /// Panics, if `day` is not a value from `1` to `31`.
+    pub fn set_day(mut self, day: u8) -> Self {
+        assert!((1..=31).contains(&day));
+        self.day = Some(AlarmDay::Date(day));
+        self
+    }
+
+    /// Match the day of the week
+    ///
+    /// Replaces a previous call to `set_day`. `1` is Monday, `7` is Sunday.
+    ///
+    /// # Panics
+    ///
+    /// Panics, if `weekday` is not a value from `1` to `7`.
+    pub fn set_weekday(mut self, weekday: u8) -> Self {
+        assert!((1..=7).contains(&weekday));
+        self.day = Some(AlarmDay::Weekday(weekday));
+        self
+    }
+
+    /// Match the hour
+    ///
+    /// # Panics
+    ///
+    /// Panics, if `hour` is larger than `23`.
+    pub fn set_hour(mut self, hour: u8) -> Self {
+        assert!(hour <= 23);
+        self.hour = Some(hour);
+        self
+    }
+
+    /// Match the minute
+    ///
+    /// # Panics
+    ///
+    /// Panics, if `minute` is larger than `59`.
+    pub fn set_minute(mut self, minute: u8) -> Self {
+        assert!(minute <= 59);
+        self.minute = Some(minute);
+        self
+    }
+
+    /// Match the second
+    ///
+    /// # Panics
+    ///
+    /// Panics, if `second` is larger than `59`.
+    pub fn set_second(mut self, second: u8) -> Self {
+        assert!(second <= 59);
+        self.second = Some(second);
+        self
+    }
+
+    /// Match the sub-second counter
+    ///
+    /// Only the `bits` least significant bits of `subsecond` are compared to
+    /// the RTC's sub-second down-counter. With `bits` being `0`, the default,
+    /// the sub-second counter is ignored. This allows alarms to fire at
+    /// fractions of a second, e.g. every 1/8 second with `bits` being `5`
+    /// (assuming the LSE and a sub-second counter running at 256 Hz).
+    ///
+    /// # Panics
+    ///
+    /// Panics, if `subsecond` is larger than `0x7fff`, or `bits` larger than
+    /// `15`.
+    pub fn set_subsecond(mut self, subsecond: u16, bits: u8) -> Self {
+        assert!(subsecond <= 0x7fff);
+        assert!(bits <= 15);
+        self.subsecond = subsecond;
+        self.subsecond_bits = bits;
+        self
+    }
+
+    /// Returns the value of the ALRMxR register
+    fn alrmr(&self) -> u32 {
+        fn bcd(value: Option<u8>, mask: u32) -> u32 {
+            match value {
+                Some(value) => ((value / 10) as u32) << 4 | (value % 10) as u32,
+                None => mask,
+            }
+        }
+
+        let day = match self.day {
+            Some(AlarmDay::Date(day)) => bcd(Some(day), 0),
+            Some(AlarmDay::Weekday(weekday)) => 1 << 6 | weekday as u32,
+            None => 1 << 7,
+        };
+
+        day << 24
+            | bcd(self.hour, 1 << 7) << 16
+            | bcd(self.minute, 1 << 7) << 8
+            | bcd(self.second, 1 << 7)
+    }
+
+    /// Returns the value of the ALRMxSSR register
+    fn alrmssr(&self) -> u32 {
+        (self.subsecond_bits as u32) << 24 | self.subsecond as u32
+    }
+}
+
+/// Calibration cycle period of the smooth digital calibration
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CalibrationWindow {
+    /// 32 seconds, with a resolution of 0.954 ppm
+    Seconds32,
+    /// 16 seconds, with a resolution of 1.907 ppm
+    Seconds16,
+    /// 8 seconds, with a resolution of 3.815 ppm
+    Seconds8,
+}
+
+/// Smooth digital calibration settings
+///
+/// During each 32 second cycle (2^20 RTC clock cycles for a 32768 Hz clock),
+/// `minus` clock pulses are masked, and if `plus` is set, 512 pulses are
+/// inserted. This allows a correction from -487.1 ppm to +488.5 ppm.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Calibration {
+    plus: bool,
+    minus: u16,
+    window: CalibrationWindow,
+}
+
+impl Calibration {
+    /// Creates a new `Calibration` from the raw register values
+    ///
+    /// With the 16 second window, bit 0 of `minus` is ignored, with the 8
+    /// second window, bits 0 and 1.
+    ///
+    /// # Panics
+    ///
+    /// Panics, if `minus` is larger than `511`.
+    pub fn new(plus: bool, minus: u16, window: CalibrationWindow) -> Self {
+        assert!(minus <= 511);
+        let minus = match window {
+            CalibrationWindow::Seconds32 => minus,
+            CalibrationWindow::Seconds16 => minus & !0b1,
+            CalibrationWindow::Seconds8 => minus & !0b11,
+        };
+        Calibration {
+            plus,
+            minus,
+            window,
+        }
+    }
+
+    /// Creates a `Calibration` that corrects the given frequency error
+    ///
+    /// `ppm` is the measured error of the RTC clock, in parts per million. A
+    /// positive value means that the clock runs fast and will be slowed down.
+    /// The 32 second window is used, for the best resolution.
+    ///
+    /// # Panics
+    ///
+    /// Panics, if `ppm` is outside of the range -488.5 to +487.1.
+    pub fn from_ppm(ppm: f32) -> Self {
+        // Pulses to mask per 2^20 clock cycles, rounded to nearest
+        let pulses = ppm * (1 << 20) as f32 / 1e6;
+        let pulses = if pulses < 0.0 {
+            pulses - 0.5
+        } else {
+            pulses + 0.5
+        } as i32;
+        assert!((-512..=511).contains(&pulses));
+
+        if pulses < 0 {
+            Self::new(true, (512 + pulses) as u16, CalibrationWindow::Seconds32)
+        } else {
+            Self::new(false, pulses as u16, CalibrationWindow::Seconds32)
+        }
+    }
+
+    /// Returns the frequency correction, in parts per million
+    ///
+    /// A positive value means that the clock is sped up.
+    pub fn ppm(&self) -> f32 {
+        let pulses = 512 * self.plus as i32 - self.minus as i32;
+        pulses as f32 * 1e6 / ((1 << 20) - pulses) as f32
+    }
+}
+
+/// Frequency of the calibration output
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CalibrationOutput {
+    Hz512,
+    Hz1,
+}
+
+/// Flag routed to the alarm output
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AlarmOutput {
+    AlarmA = 0b01,
+    AlarmB = 0b10,
+    Wakeup = 0b11,
+}
+
+/// Level of the alarm output while active
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputPolarity {
+    High,
+    Low,
+}
+
+/// Output type of the alarm output on PC13
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputType {
+    OpenDrain,
+    PushPull,
+}
+
+/// Pin used for RTC_OUT
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputPin {
+    PC13,
+    PB14,
+}
+
+/// The flags in ISR that are cleared by writing zero: RSF and ALRAF to TAMP3F
+///
+/// Writing one leaves these flags unchanged, so they are cleared by writing
+/// this value, with the bits to clear set to zero. A read-modify-write would
+/// also clear flags that are set between the read and the write. INIT is
+/// written as zero, unless it's set explicitly.
+const ISR_FLAGS: u32 = 0xff << 8 | 1 << 5;
+
+/// Bits in TAMPCR that are shared by all tamper inputs
+const TAMPCR_SHARED: u32 = 0xff << 8 | 1 << 7;
+
+/// Identifies one of the RTC tamper inputs
+///
+/// `Tamper1` is on PC13, `Tamper2` on PA0, and `Tamper3` on PE6. Not all of
+/// these pins are available in every package.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Tamper {
+    Tamper1,
+    Tamper2,
+    Tamper3,
+}
+
+impl Tamper {
+    /// TAMPxE bit in TAMPCR
+    fn e(self) -> u32 {
+        match self {
+            Tamper::Tamper1 => 1 << 0,
+            Tamper::Tamper2 => 1 << 3,
+            Tamper::Tamper3 => 1 << 5,
+        }
+    }
+
+    /// TAMPxTRG bit in TAMPCR
+    fn trg(self) -> u32 {
+        self.e() << 1
+    }
+
+    /// TAMPxIE bit in TAMPCR
+    fn ie(self) -> u32 {
+        1 << (16 + 3 * self as u32)
+    }
+
+    /// TAMPxNOERASE bit in TAMPCR
+    fn noerase(self) -> u32 {
+        self.ie() << 1
+    }
+
+    /// TAMPxF bit in ISR
+    fn f(self) -> u32 {
+        1 << (13 + self as u32)
+    }
+}
+
+/// Event that triggers a tamper input
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TamperTrigger {
+    /// Rising edge, requires [`TamperFilter::Edge`]
+    RisingEdge,
+    /// Falling edge, requires [`TamperFilter::Edge`]
+    FallingEdge,
+    /// Low level, requires a sampling filter
+    LowLevel,
+    /// High level, requires a sampling filter
+    HighLevel,
+}
+
+/// Tamper input filter
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TamperFilter {
+    /// Edge detection, no sampling and no precharge
+    Edge = 0,
+    /// Level detection, triggered by 2 consecutive samples
+    Samples2,
+    /// Level detection, triggered by 4 consecutive samples
+    Samples4,
+    /// Level detection, triggered by 8 consecutive samples
+    Samples8,
+}
+
+/// Tamper input sampling frequency, as a fraction of the RTC clock
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TamperSampleRate {
+    Div32768 = 0,
+    Div16384,
+    Div8192,
+    Div4096,
+    Div2048,
+    Div1024,
+    Div512,
+    Div256,
+}
+
+/// Duration of the tamper input precharge before sampling, in RTC clock cycles
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TamperPrecharge {
+    Cycles1 = 0,
+    Cycles2,
+    Cycles4,
+    Cycles8,
+}
+
+/// Settings shared by all tamper inputs
+pub struct TamperConfig {
+    pub filter: TamperFilter,
+    /// Sampling frequency, for level detection
+    pub sample_rate: TamperSampleRate,
+    /// Precharge duration, for level detection
+    pub precharge: TamperPrecharge,
+    /// Precharge the inputs using the internal pull-up, for level detection
+    pub pull_up: bool,
+    /// Save a timestamp on tamper events
+    pub timestamp: bool,
+}
+
+impl Default for TamperConfig {
+    fn default() -> Self {
+        Self {
+            filter: TamperFilter::Edge,
+            sample_rate: TamperSampleRate::Div32768,
+            precharge: TamperPrecharge::Cycles1,
+            pull_up: true,
+            timestamp: false,
+        }
+    }
+}
+
+/// Interrupt events of the RTC
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Event {
+    /// Alarm A has fired (ALRAF)
+    AlarmA,
+
+    /// Alarm B has fired (ALRBF)
+    AlarmB,
+
+    /// The wakeup timer has elapsed (WUTF)
+    WakeupTimer,
+
+    /// A timestamp event has occurred (TSF)
+    Timestamp,
+}
+
+impl Event {
+    fn interrupts(self) -> Interrupts {
+        Interrupts {
+            alarm_a: self == Event::AlarmA,
+            alarm_b: self == Event::AlarmB,
+            wakeup_timer: self == Event::WakeupTimer,
+            timestamp: self == Event::Timestamp,
+        }
+    }
 }
 
 pub struct Interrupts {
@@ -507,7 +1709,7 @@ impl timer::CountDown for WakeupTimer<'_> {
         if self.rtc.rtc.isr.read().wutf().bit_is_set() {
             self.rtc.write(|rtc| {
                 // Clear wakeup timer flag
-                rtc.isr.modify(|_, w| w.wutf().clear_bit());
+                rtc.isr.write(|w| unsafe { w.bits(ISR_FLAGS) }.wutf().clear_bit());
             });
 
             return Ok(());
@@ -529,7 +1731,7 @@ impl timer::Cancel for WakeupTimer<'_> {
             while rtc.isr.read().wutwf().bit_is_clear() {}
 
             // Clear wakeup timer flag
-            rtc.isr.modify(|_, w| w.wutf().clear_bit());
+            rtc.isr.write(|w| unsafe { w.bits(ISR_FLAGS) }.wutf().clear_bit());
 
             // According to the reference manual, section 26.7.4, the WUTF flag
             // must be cleared at least 1.5 RTCCLK periods "before WUTF is set
@@ -547,3 +1749,87 @@ impl timer::Cancel for WakeupTimer<'_> {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Asserts that `calibration` corrects an error of `ppm` to within the
+    /// resolution of the 32 second window (0.954 ppm)
+    fn assert_corrects(calibration: Calibration, ppm: f32) {
+        let remaining = ppm + calibration.ppm();
+        assert!(remaining.abs() < 0.7, "{} ppm remaining", remaining);
+    }
+
+    #[test]
+    fn calibration_from_zero_ppm() {
+        let calibration = Calibration::from_ppm(0.0);
+        assert_eq!(
+            calibration,
+            Calibration::new(false, 0, CalibrationWindow::Seconds32)
+        );
+        assert_eq!(calibration.ppm(), 0.0);
+    }
+
+    #[test]
+    fn calibration_slows_down_fast_clock() {
+        let calibration = Calibration::from_ppm(100.0);
+        assert_eq!(
+            calibration,
+            Calibration::new(false, 105, CalibrationWindow::Seconds32)
+        );
+        assert!(calibration.ppm() < 0.0);
+        assert_corrects(calibration, 100.0);
+    }
+
+    #[test]
+    fn calibration_speeds_up_slow_clock() {
+        let calibration = Calibration::from_ppm(-100.0);
+        assert_eq!(
+            calibration,
+            Calibration::new(true, 512 - 105, CalibrationWindow::Seconds32)
+        );
+        assert!(calibration.ppm() > 0.0);
+        assert_corrects(calibration, -100.0);
+
+        // Inserting 512 pulses, without masking any
+        assert_eq!(
+            Calibration::from_ppm(-488.28),
+            Calibration::new(true, 0, CalibrationWindow::Seconds32)
+        );
+    }
+
+    #[test]
+    fn calibration_round_trip() {
+        for ppm in -48..=48 {
+            let ppm = ppm as f32 * 10.0;
+            assert_corrects(Calibration::from_ppm(ppm), ppm);
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn calibration_too_fast() {
+        Calibration::from_ppm(500.0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn calibration_too_slow() {
+        Calibration::from_ppm(-500.0);
+    }
+
+    #[test]
+    fn calibration_window_resolution() {
+        let calibration = Calibration::new(false, 0b111, CalibrationWindow::Seconds16);
+        assert_eq!(
+            calibration,
+            Calibration::new(false, 0b110, CalibrationWindow::Seconds16)
+        );
+        let calibration = Calibration::new(false, 0b111, CalibrationWindow::Seconds8);
+        assert_eq!(
+            calibration,
+            Calibration::new(false, 0b100, CalibrationWindow::Seconds8)
+        );
+    }
+}
@@ -1,14 +1,23 @@
 use core::fmt;
+#[cfg(feature = "async")]
+use core::future::poll_fn;
 use core::marker::PhantomData;
 use core::ptr;
+#[cfg(feature = "async")]
+use core::task::Poll;
 
+#[cfg(feature = "async")]
+use cortex_m::interrupt;
 use nb::block;
 
+#[cfg(feature = "async")]
+use crate::asynch::{InterruptHandler, InterruptWaker};
+use crate::config;
 use crate::gpio::{AltMode, PinMode};
 use crate::hal;
 use crate::hal::prelude::*;
 pub use crate::pac::{LPUART1, USART1, USART2, USART4, USART5};
-use crate::rcc::Rcc;
+use crate::rcc::{Enable, Rcc};
 
 #[cfg(any(feature = "stm32l0x2", feature = "stm32l0x3"))]
 use core::{
@@ -36,7 +45,8 @@ use crate::gpio::{gpioa::*, gpiob::*};
 use crate::gpio::{gpiod::*, gpioe::*};
 
 /// Serial error
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Error {
     /// Framing error
     Framing,
@@ -50,7 +60,24 @@ pub enum Error {
     _Extensible,
 }
 
+#[cfg(feature = "embedded-io")]
+impl embedded_io::Error for Error {
+    fn kind(&self) -> embedded_io::ErrorKind {
+        match self {
+            Error::Framing | Error::Noise | Error::Parity => embedded_io::ErrorKind::InvalidData,
+            _ => embedded_io::ErrorKind::Other,
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+trait AsyncInstance {
+    fn rx_waker() -> &'static InterruptWaker;
+    fn tx_waker() -> &'static InterruptWaker;
+}
+
 /// Interrupt event
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum Event {
     /// New data has been received.
     ///
@@ -71,17 +98,20 @@ pub enum Event {
 use crate::time::Bps;
 use crate::time::U32Ext;
 
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum WordLength {
     DataBits8,
     DataBits9,
 }
 
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Parity {
     ParityNone,
     ParityEven,
     ParityOdd,
 }
 
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum StopBits {
     #[doc = "1 stop bit"]
     STOP1,
@@ -93,6 +123,7 @@ pub enum StopBits {
     STOP1P5,
 }
 
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct Config {
     pub baudrate: Bps,
     pub wordlength: WordLength,
@@ -137,9 +168,71 @@ impl Config {
     }
 }
 
+/// The serial port configuration is invalid
+///
+/// The baud rate can't be generated from the peripheral clock.
 #[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct InvalidConfig;
 
+impl From<InvalidConfig> for config::Error {
+    fn from(_: InvalidConfig) -> Self {
+        config::Error::BaudRate
+    }
+}
+
+/// Calculates the baud rate divisor (BRR) of a USART
+///
+/// As this is a `const fn`, it can be evaluated at compile time for a fixed
+/// peripheral clock, making an unreachable baud rate a compile error:
+///
+/// ``` ignore
+/// const BRR: u32 = match serial::usart_brr(16_000_000, 115_200) {
+///     Ok(brr) => brr,
+///     Err(_) => panic!("Unreachable baud rate"),
+/// };
+///
+/// serial.set_brr(BRR);
+/// ```
+pub const fn usart_brr(pclk: u32, baudrate: u32) -> Result<u32, config::Error> {
+    if baudrate == 0 || baudrate > pclk {
+        return Err(config::Error::BaudRate);
+    }
+
+    // See STM32L0x2 reference manual, section 24.5.4, for the valid range.
+    let brr = brr_16(pclk, baudrate);
+    if brr < 16 || brr >= 1 << 16 {
+        return Err(config::Error::BaudRate);
+    }
+
+    Ok(brr as u32)
+}
+
+/// Calculates the baud rate divisor (BRR) of the LPUART
+///
+/// See [`usart_brr`].
+pub const fn lpuart_brr(pclk: u32, baudrate: u32) -> Result<u32, config::Error> {
+    if baudrate == 0 || baudrate > pclk {
+        return Err(config::Error::BaudRate);
+    }
+
+    // See STM32L0x2 reference manual, section 25.4.4, for the valid range.
+    let brr = brr_16(pclk, baudrate) * 256;
+    if brr < 0x300 || brr >= 1 << 20 {
+        return Err(config::Error::BaudRate);
+    }
+
+    Ok(brr as u32)
+}
+
+/// Returns `pclk / baudrate`, rounded to sixteenths
+const fn brr_16(pclk: u32, baudrate: u32) -> u64 {
+    let div = (pclk as u64 * 25) / (4 * baudrate as u64);
+    let mantissa = div / 100;
+    let fraction = ((div - mantissa * 100) * 16 + 50) / 100;
+    mantissa << 4 | fraction
+}
+
 impl Default for Config {
     fn default() -> Config {
         let baudrate = 9_600_u32.bps();
@@ -279,10 +372,12 @@ impl_pins!(
 );
 
 /// Serial abstraction
-pub struct Serial<USART> {
+pub struct Serial<USART, TX, RX> {
     usart: USART,
     rx: Rx<USART>,
     tx: Tx<USART>,
+    tx_pin: TX,
+    rx_pin: RX,
 }
 
 /// Serial receiver
@@ -297,11 +392,11 @@ pub struct Tx<USART> {
 
 macro_rules! usart {
     ($(
-        $USARTX:ident: ($usartX:ident, $apbXenr:ident, $usartXen:ident, $pclkX:ident, $SerialExt:ident),
+        $USARTX:ident: ($usartX:ident, $pclkX:ident, $SerialExt:ident),
     )+) => {
         $(
             pub trait $SerialExt<TX, RX> {
-                fn usart(self, tx: TX, rx: RX, config: Config, rcc: &mut Rcc) -> Result<Serial<$USARTX>, InvalidConfig>;
+                fn usart(self, tx: TX, rx: RX, config: Config, rcc: &mut Rcc) -> Result<Serial<$USARTX, TX, RX>, InvalidConfig>;
             }
 
             impl<TX, RX> $SerialExt<TX, RX> for $USARTX
@@ -309,13 +404,13 @@ macro_rules! usart {
                     TX: TxPin<$USARTX>,
                     RX: RxPin<$USARTX>,
             {
-                fn usart(self, tx: TX, rx: RX, config: Config, rcc: &mut Rcc) -> Result<Serial<$USARTX>, InvalidConfig> {
+                fn usart(self, tx: TX, rx: RX, config: Config, rcc: &mut Rcc) -> Result<Serial<$USARTX, TX, RX>, InvalidConfig> {
                     Serial::$usartX(self, tx, rx, config, rcc)
                 }
             }
 
-            impl Serial<$USARTX> {
-                pub fn $usartX<TX, RX>(
+            impl<TX, RX> Serial<$USARTX, TX, RX> {
+                pub fn $usartX(
                     usart: $USARTX,
                     tx: TX,
                     rx: RX,
@@ -326,21 +421,13 @@ macro_rules! usart {
                     TX: TxPin<$USARTX>,
                     RX: RxPin<$USARTX>,
                 {
+                    let brr = Self::brr(config.baudrate, rcc)?;
+
                     tx.setup();
                     rx.setup();
 
                     // Enable clock for USART
-                    rcc.rb.$apbXenr.modify(|_, w| w.$usartXen().set_bit());
-
-                    // Calculate correct baudrate divisor on the fly
-                    let div = (rcc.clocks.$pclkX().0 * 25) / (4 * config.baudrate.0);
-                    let mantissa = div / 100;
-                    let fraction = ((div - mantissa * 100) * 16 + 50) / 100;
-                    let mut brr = mantissa << 4 | fraction;
-
-                    if stringify!($usartX) == "lpuart1" {
-                        brr = brr*256
-                    }
+                    $USARTX::enable(rcc);
 
                     usart
                         .brr
@@ -395,9 +482,49 @@ macro_rules! usart {
                         usart,
                         tx: Tx { _usart: PhantomData },
                         rx: Rx { _usart: PhantomData },
+                        tx_pin: tx,
+                        rx_pin: rx,
                     })
                 }
 
+                /// Changes the baud rate
+                ///
+                /// This recomputes the baud rate divisor from the current
+                /// clocks, so it can also be used to keep the baud rate after
+                /// the clocks have been changed using [`Rcc::reconfigure`].
+                ///
+                /// Returns an error, and leaves the baud rate unchanged, if
+                /// the baud rate can't be generated from the current clocks.
+                pub fn set_baudrate(&mut self, baudrate: Bps, rcc: &Rcc) -> Result<(), InvalidConfig> {
+                    let brr = Self::brr(baudrate, rcc)?;
+                    self.set_brr(brr);
+                    Ok(())
+                }
+
+                /// Sets the baud rate divisor directly
+                ///
+                /// `brr` needs to be calculated for the current clocks, using
+                /// [`usart_brr`] or [`lpuart_brr`]. As those are `const fn`s,
+                /// this allows setting a fixed baud rate without any divisions
+                /// at runtime.
+                pub fn set_brr(&mut self, brr: u32) {
+                    // The baud rate can only be changed while the USART is
+                    // disabled.
+                    self.usart.cr1.modify(|_, w| w.ue().clear_bit());
+                    self.usart.brr.write(|w| unsafe { w.bits(brr) });
+                    self.usart.cr1.modify(|_, w| w.ue().set_bit());
+                }
+
+                fn brr(baudrate: Bps, rcc: &Rcc) -> Result<u32, InvalidConfig> {
+                    let pclk = rcc.clocks.$pclkX().0;
+                    let brr = if stringify!($usartX) == "lpuart1" {
+                        lpuart_brr(pclk, baudrate.0)
+                    } else {
+                        usart_brr(pclk, baudrate.0)
+                    };
+                    brr.map_err(|_| InvalidConfig)
+                }
+
                 /// Starts listening for an interrupt event
                 pub fn listen(&mut self, event: Event) {
                     match event {
@@ -428,6 +555,34 @@ macro_rules! usart {
                     }
                 }
 
+                /// Returns whether the flag of an interrupt event is set
+                ///
+                /// This doesn't depend on whether the event is listened for.
+                pub fn is_pending(&self, event: Event) -> bool {
+                    let isr = self.usart.isr.read();
+                    match event {
+                        Event::Rxne => isr.rxne().bit_is_set(),
+                        Event::Txe => isr.txe().bit_is_set(),
+                        Event::Idle => isr.idle().bit_is_set(),
+                    }
+                }
+
+                /// Clears the flag of an interrupt event
+                ///
+                /// Clearing `Rxne` discards the received data. `Txe` can only
+                /// be cleared by writing data, so this has no effect on it.
+                pub fn clear(&mut self, event: Event) {
+                    match event {
+                        Event::Rxne => {
+                            self.usart.rqr.write(|w| w.rxfrq().set_bit())
+                        },
+                        Event::Txe => {},
+                        Event::Idle => {
+                            self.usart.icr.write(|w| w.idlecf().set_bit())
+                        },
+                    }
+                }
+
                 /// Returns a pending and enabled `Event`.
                 ///
                 /// Multiple `Event`s can be signaled at the same time. In that case, an arbitrary
@@ -473,12 +628,14 @@ macro_rules! usart {
                     (self.tx, self.rx)
                 }
 
-                pub fn release(self) -> $USARTX {
-                    self.usart
+                /// Disables the USART and releases it, together with the pins
+                pub fn release(self) -> ($USARTX, TX, RX) {
+                    self.usart.cr1.modify(|_, w| w.ue().clear_bit());
+                    (self.usart, self.tx_pin, self.rx_pin)
                 }
             }
 
-            impl hal::serial::Read<u8> for Serial<$USARTX> {
+            impl<TX, RX> hal::serial::Read<u8> for Serial<$USARTX, TX, RX> {
                 type Error = Error;
 
                 fn read(&mut self) -> nb::Result<u8, Error> {
@@ -486,7 +643,7 @@ macro_rules! usart {
                 }
             }
 
-             impl hal::serial::Write<u8> for  Serial<$USARTX> {
+             impl<TX, RX> hal::serial::Write<u8> for Serial<$USARTX, TX, RX> {
                 type Error = Error;
 
                 fn flush(&mut self) -> nb::Result<(), Self::Error> {
@@ -547,7 +704,7 @@ macro_rules! usart {
                     buffer:  Pin<Buffer>,
                     channel: Channel,
                 )
-                    -> dma::Transfer<Self, Channel, Buffer, dma::Ready>
+                    -> dma::SetupResult<dma::Transfer<Self, Channel, Buffer, dma::Ready>, Self, Channel, Buffer>
                     where
                         Self:           dma::Target<Channel>,
                         Buffer:         DerefMut + 'static,
@@ -564,7 +721,7 @@ macro_rules! usart {
                     num_words: usize,
                     channel: Channel,
                 )
-                    -> dma::Transfer<Self, Channel, Buffer, dma::Ready>
+                    -> dma::SetupResult<dma::Transfer<Self, Channel, Buffer, dma::Ready>, Self, Channel, Buffer>
                     where
                         Self:           dma::Target<Channel>,
                         Buffer:         DerefMut + 'static,
@@ -643,7 +800,7 @@ macro_rules! usart {
                     if isr.txe().bit_is_set() {
                         // NOTE(unsafe) atomic write to stateless register
                         // NOTE(write_volatile) 8-bit write that's not possible through the svd2rust API
-                        unsafe { ptr::write_volatile(&(*$USARTX::ptr()).tdr as *const _ as *mut _, byte) }
+                        unsafe { ptr::write_volatile(ptr::addr_of!((*$USARTX::ptr()).tdr) as *mut u8, byte) }
 
                         Ok(())
                     } else {
@@ -652,6 +809,268 @@ macro_rules! usart {
                 }
             }
 
+            #[cfg(feature = "embedded-io")]
+            impl embedded_io::ErrorType for Rx<$USARTX> {
+                type Error = Error;
+            }
+
+            #[cfg(feature = "embedded-io")]
+            impl embedded_io::Read for Rx<$USARTX> {
+                fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+                    if buf.is_empty() {
+                        return Ok(0);
+                    }
+
+                    // Block until the first byte is received, then return
+                    // what's available.
+                    buf[0] = block!(hal::serial::Read::read(self))?;
+
+                    let mut len = 1;
+                    for word in &mut buf[1..] {
+                        match hal::serial::Read::read(self) {
+                            Ok(received) => *word = received,
+                            Err(nb::Error::WouldBlock) => break,
+                            Err(nb::Error::Other(error)) => return Err(error),
+                        }
+                        len += 1;
+                    }
+                    Ok(len)
+                }
+            }
+
+            #[cfg(feature = "embedded-io")]
+            impl embedded_io::ReadReady for Rx<$USARTX> {
+                fn read_ready(&mut self) -> Result<bool, Error> {
+                    // NOTE(unsafe) atomic read with no side effects
+                    Ok(unsafe { (*$USARTX::ptr()).isr.read().rxne().bit_is_set() })
+                }
+            }
+
+            #[cfg(feature = "embedded-io")]
+            impl embedded_io::ErrorType for Tx<$USARTX> {
+                type Error = Error;
+            }
+
+            #[cfg(feature = "embedded-io")]
+            impl embedded_io::Write for Tx<$USARTX> {
+                fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+                    if buf.is_empty() {
+                        return Ok(0);
+                    }
+
+                    // Block until the first byte is written, then write as
+                    // much as fits.
+                    block!(hal::serial::Write::write(self, buf[0]))?;
+
+                    let mut len = 1;
+                    for &word in &buf[1..] {
+                        if hal::serial::Write::write(self, word).is_err() {
+                            break;
+                        }
+                        len += 1;
+                    }
+                    Ok(len)
+                }
+
+                fn flush(&mut self) -> Result<(), Error> {
+                    block!(hal::serial::Write::flush(self))
+                }
+            }
+
+            #[cfg(feature = "embedded-io")]
+            impl embedded_io::WriteReady for Tx<$USARTX> {
+                fn write_ready(&mut self) -> Result<bool, Error> {
+                    // NOTE(unsafe) atomic read with no side effects
+                    Ok(unsafe { (*$USARTX::ptr()).isr.read().txe().bit_is_set() })
+                }
+            }
+
+            #[cfg(feature = "embedded-io")]
+            impl<TX, RX> embedded_io::ErrorType for Serial<$USARTX, TX, RX> {
+                type Error = Error;
+            }
+
+            #[cfg(feature = "embedded-io")]
+            impl<TX, RX> embedded_io::Read for Serial<$USARTX, TX, RX> {
+                fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+                    embedded_io::Read::read(&mut self.rx, buf)
+                }
+            }
+
+            #[cfg(feature = "embedded-io")]
+            impl<TX, RX> embedded_io::ReadReady for Serial<$USARTX, TX, RX> {
+                fn read_ready(&mut self) -> Result<bool, Error> {
+                    self.rx.read_ready()
+                }
+            }
+
+            #[cfg(feature = "embedded-io")]
+            impl<TX, RX> embedded_io::Write for Serial<$USARTX, TX, RX> {
+                fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+                    embedded_io::Write::write(&mut self.tx, buf)
+                }
+
+                fn flush(&mut self) -> Result<(), Error> {
+                    embedded_io::Write::flush(&mut self.tx)
+                }
+            }
+
+            #[cfg(feature = "embedded-io")]
+            impl<TX, RX> embedded_io::WriteReady for Serial<$USARTX, TX, RX> {
+                fn write_ready(&mut self) -> Result<bool, Error> {
+                    self.tx.write_ready()
+                }
+            }
+
+            #[cfg(feature = "async")]
+            impl AsyncInstance for $USARTX {
+                fn rx_waker() -> &'static InterruptWaker {
+                    static WAKER: InterruptWaker = InterruptWaker::new();
+                    &WAKER
+                }
+
+                fn tx_waker() -> &'static InterruptWaker {
+                    static WAKER: InterruptWaker = InterruptWaker::new();
+                    &WAKER
+                }
+            }
+
+            #[cfg(feature = "async")]
+            impl InterruptHandler for $USARTX {
+                fn on_interrupt() {
+                    // Safe, as the interrupts are only enabled by the async
+                    // API, which is waiting for them.
+                    let usart = unsafe { &*$USARTX::ptr() };
+                    usart.cr1.modify(|_, w| {
+                        w.rxneie().clear_bit();
+                        w.txeie().clear_bit();
+                        w.tcie().clear_bit()
+                    });
+
+                    $USARTX::rx_waker().wake();
+                    $USARTX::tx_waker().wake();
+                }
+            }
+
+            #[cfg(feature = "async")]
+            impl embedded_io_async::Read for Rx<$USARTX> {
+                async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+                    if buf.is_empty() {
+                        return Ok(0);
+                    }
+
+                    poll_fn(|cx| {
+                        $USARTX::rx_waker().register(cx.waker());
+                        match hal::serial::Read::read(self) {
+                            Ok(word) => Poll::Ready(Ok(word)),
+                            Err(nb::Error::Other(error)) => Poll::Ready(Err(error)),
+                            Err(nb::Error::WouldBlock) => {
+                                // Safe, as the modification happens within a
+                                // critical section.
+                                interrupt::free(|_| unsafe {
+                                    (*$USARTX::ptr()).cr1.modify(|_, w| w.rxneie().set_bit())
+                                });
+                                Poll::Pending
+                            }
+                        }
+                    })
+                    .await
+                    .map(|word| buf[0] = word)?;
+
+                    // Return what's available, without waiting
+                    let mut len = 1;
+                    for word in &mut buf[1..] {
+                        match hal::serial::Read::read(self) {
+                            Ok(received) => *word = received,
+                            Err(nb::Error::WouldBlock) => break,
+                            Err(nb::Error::Other(error)) => return Err(error),
+                        }
+                        len += 1;
+                    }
+                    Ok(len)
+                }
+            }
+
+            #[cfg(feature = "async")]
+            impl Tx<$USARTX> {
+                /// Waits for the transmit data register to be empty, or for
+                /// the transmission to be complete, using the interrupts
+                async fn wait_for(&mut self, complete: bool) {
+                    poll_fn(|cx| {
+                        $USARTX::tx_waker().register(cx.waker());
+
+                        // NOTE(unsafe) atomic read with no side effects
+                        let isr = unsafe { (*$USARTX::ptr()).isr.read() };
+                        let ready = if complete {
+                            isr.tc().bit_is_set()
+                        } else {
+                            isr.txe().bit_is_set()
+                        };
+                        if ready {
+                            return Poll::Ready(());
+                        }
+
+                        // Safe, as the modification happens within a critical
+                        // section.
+                        interrupt::free(|_| unsafe {
+                            (*$USARTX::ptr()).cr1.modify(|_, w| {
+                                if complete {
+                                    w.tcie().set_bit()
+                                } else {
+                                    w.txeie().set_bit()
+                                }
+                            })
+                        });
+                        Poll::Pending
+                    })
+                    .await
+                }
+            }
+
+            #[cfg(feature = "async")]
+            impl embedded_io_async::Write for Tx<$USARTX> {
+                async fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+                    if buf.is_empty() {
+                        return Ok(0);
+                    }
+
+                    self.wait_for(false).await;
+
+                    // Write as much as fits, without waiting
+                    let mut len = 0;
+                    for &word in buf {
+                        match hal::serial::Write::write(self, word) {
+                            Ok(()) => len += 1,
+                            Err(_) => break,
+                        }
+                    }
+                    Ok(len)
+                }
+
+                async fn flush(&mut self) -> Result<(), Error> {
+                    self.wait_for(true).await;
+                    Ok(())
+                }
+            }
+
+            #[cfg(feature = "async")]
+            impl<TX, RX> embedded_io_async::Read for Serial<$USARTX, TX, RX> {
+                async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+                    embedded_io_async::Read::read(&mut self.rx, buf).await
+                }
+            }
+
+            #[cfg(feature = "async")]
+            impl<TX, RX> embedded_io_async::Write for Serial<$USARTX, TX, RX> {
+                async fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+                    embedded_io_async::Write::write(&mut self.tx, buf).await
+                }
+
+                async fn flush(&mut self) -> Result<(), Error> {
+                    embedded_io_async::Write::flush(&mut self.tx).await
+                }
+            }
+
             #[cfg(any(feature = "stm32l0x2", feature = "stm32l0x3"))]
             impl Tx<$USARTX> {
                 pub fn write_all<Buffer, Channel>(self,
@@ -659,7 +1078,7 @@ macro_rules! usart {
                     buffer:  Pin<Buffer>,
                     channel: Channel,
                 )
-                    -> dma::Transfer<Self, Channel, Buffer, dma::Ready>
+                    -> dma::SetupResult<dma::Transfer<Self, Channel, Buffer, dma::Ready>, Self, Channel, Buffer>
                     where
                         Self:           dma::Target<Channel>,
                         Buffer:         Deref + 'static,
@@ -676,7 +1095,7 @@ macro_rules! usart {
                     num_words:  usize,
                     channel: Channel,
                 )
-                    -> dma::Transfer<Self, Channel, Buffer, dma::Ready>
+                    -> dma::SetupResult<dma::Transfer<Self, Channel, Buffer, dma::Ready>, Self, Channel, Buffer>
                     where
                         Self:           dma::Target<Channel>,
                         Buffer:         Deref + 'static,
@@ -717,26 +1136,26 @@ macro_rules! usart {
     feature = "io-STM32L071",
 ))]
 usart! {
-    LPUART1: (lpuart1, apb1enr, lpuart1en, apb1_clk, Serial1LpExt),
-    USART2: (usart2, apb1enr, usart2en, apb1_clk, Serial2Ext),
+    LPUART1: (lpuart1, lpuart1_clk, Serial1LpExt),
+    USART2: (usart2, usart2_clk, Serial2Ext),
 }
 
 // USART1 is available on category 3/5 MCUs
 #[cfg(any(feature = "io-STM32L051", feature = "io-STM32L071"))]
 usart! {
-    USART1: (usart1, apb2enr, usart1en, apb1_clk, Serial1Ext),
+    USART1: (usart1, usart1_clk, Serial1Ext),
 }
 
 // USART4 and USART5 are available on category 5 MCUs
 #[cfg(feature = "io-STM32L071")]
 usart! {
-    USART4: (usart4, apb1enr, usart4en, apb1_clk, Serial4Ext),
-    USART5: (usart5, apb1enr, usart5en, apb1_clk, Serial5Ext),
+    USART4: (usart4, apb1_clk, Serial4Ext),
+    USART5: (usart5, apb1_clk, Serial5Ext),
 }
 
-impl<USART> fmt::Write for Serial<USART>
+impl<USART, TX, RX> fmt::Write for Serial<USART, TX, RX>
 where
-    Serial<USART>: hal::serial::Write<u8>,
+    Serial<USART, TX, RX>: hal::serial::Write<u8>,
 {
     fn write_str(&mut self, s: &str) -> fmt::Result {
         let _ = s
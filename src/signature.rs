@@ -0,0 +1,118 @@
+//! Device electronic signature
+//!
+//! The signature is stored in the system memory area during production, and
+//! identifies the individual device. It is typically used for serial numbers,
+//! or to derive per-device keys.
+//!
+//! See STM32L0x2 reference manual, chapter "Device electronic signature".
+
+/// Address of bits 31:0 of the unique ID
+const UID_0: u32 = 0x1FF8_0050;
+
+/// Address of bits 63:32 of the unique ID
+const UID_1: u32 = 0x1FF8_0054;
+
+/// Address of bits 95:64 of the unique ID
+const UID_2: u32 = 0x1FF8_0064;
+
+/// Address of the flash size register
+const FLASH_SIZE: u32 = 0x1FF8_007C;
+
+/// Returns the 96-bit unique device ID, as three words
+///
+/// The first word contains bits 31:0 of the ID.
+pub fn unique_id() -> [u32; 3] {
+    // Safe, as these are valid addresses in the system memory area, which are
+    // aligned to 32 bits and read-only.
+    unsafe {
+        [
+            (UID_0 as *const u32).read_volatile(),
+            (UID_1 as *const u32).read_volatile(),
+            (UID_2 as *const u32).read_volatile(),
+        ]
+    }
+}
+
+/// Returns the 96-bit unique device ID, as little-endian bytes
+pub fn unique_id_bytes() -> [u8; 12] {
+    let mut bytes = [0; 12];
+    for (chunk, word) in bytes.chunks_mut(4).zip(unique_id().iter()) {
+        chunk.copy_from_slice(&word.to_le_bytes());
+    }
+    bytes
+}
+
+/// Returns the size of the flash memory in KiB
+pub fn flash_size_kb() -> u16 {
+    // Safe, as this is a valid address in the system memory area, which is
+    // aligned to 16 bits and read-only.
+    unsafe { (FLASH_SIZE as *const u16).read_volatile() }
+}
+
+/// Returns the package of the device
+///
+/// The STM32L0 doesn't have a package data register, so this is derived from
+/// the package feature (usually applied by the `mcu-*` feature). Returns
+/// `None`, if no package feature is enabled.
+pub fn package() -> Option<Package> {
+    if cfg!(feature = "ewlcsp49") {
+        Some(Package::Ewlcsp49)
+    } else if cfg!(feature = "lqfp32") {
+        Some(Package::Lqfp32)
+    } else if cfg!(feature = "lqfp48") {
+        Some(Package::Lqfp48)
+    } else if cfg!(feature = "lqfp64") {
+        Some(Package::Lqfp64)
+    } else if cfg!(feature = "lqfp100") {
+        Some(Package::Lqfp100)
+    } else if cfg!(feature = "tfbga64") {
+        Some(Package::Tfbga64)
+    } else if cfg!(feature = "tssop14") {
+        Some(Package::Tssop14)
+    } else if cfg!(feature = "tssop20") {
+        Some(Package::Tssop20)
+    } else if cfg!(feature = "ufbga64") {
+        Some(Package::Ufbga64)
+    } else if cfg!(feature = "ufbga100") {
+        Some(Package::Ufbga100)
+    } else if cfg!(feature = "ufqfpn20") {
+        Some(Package::Ufqfpn20)
+    } else if cfg!(feature = "ufqfpn28") {
+        Some(Package::Ufqfpn28)
+    } else if cfg!(feature = "ufqfpn32") {
+        Some(Package::Ufqfpn32)
+    } else if cfg!(feature = "ufqfpn48") {
+        Some(Package::Ufqfpn48)
+    } else if cfg!(feature = "wlcsp25") {
+        Some(Package::Wlcsp25)
+    } else if cfg!(feature = "wlcsp36") {
+        Some(Package::Wlcsp36)
+    } else if cfg!(feature = "wlcsp49") {
+        Some(Package::Wlcsp49)
+    } else {
+        None
+    }
+}
+
+/// Physical package
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Package {
+    Ewlcsp49,
+    Lqfp32,
+    Lqfp48,
+    Lqfp64,
+    Lqfp100,
+    Tfbga64,
+    Tssop14,
+    Tssop20,
+    Ufbga64,
+    Ufbga100,
+    Ufqfpn20,
+    Ufqfpn28,
+    Ufqfpn32,
+    Ufqfpn48,
+    Wlcsp25,
+    Wlcsp36,
+    Wlcsp49,
+}
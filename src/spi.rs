@@ -2,20 +2,27 @@ use crate::gpio::gpioa::*;
 #[cfg(any(feature = "stm32l0x2", feature = "stm32l0x3"))]
 use crate::gpio::gpiob::*;
 
+#[cfg(feature = "async")]
+use crate::asynch::{InterruptHandler, InterruptWaker};
 use crate::gpio::{AltMode, Analog};
 use crate::hal;
+#[cfg(feature = "async")]
+use crate::pac::spi1::sr;
 use crate::pac::SPI1;
 #[cfg(any(feature = "stm32l0x2", feature = "stm32l0x3"))]
 use crate::pac::SPI2;
-use crate::rcc::Rcc;
+use crate::rcc::{Enable, Rcc};
 use crate::time::Hertz;
+#[cfg(feature = "async")]
+use core::{future::poll_fn, task::Poll};
 use core::ptr;
 use nb;
 
 pub use hal::spi::{Mode, Phase, Polarity, MODE_0, MODE_1, MODE_2, MODE_3};
 
 /// SPI error
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Error {
     Busy,
     FrameError,
@@ -29,6 +36,25 @@ pub enum Error {
     _Extensible,
 }
 
+#[cfg(feature = "eh1")]
+impl eh1::spi::Error for Error {
+    fn kind(&self) -> eh1::spi::ErrorKind {
+        use eh1::spi::ErrorKind;
+
+        match self {
+            Error::FrameError => ErrorKind::FrameFormat,
+            Error::Overrun => ErrorKind::Overrun,
+            Error::ModeFault => ErrorKind::ModeFault,
+            _ => ErrorKind::Other,
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+trait AsyncInstance {
+    fn waker() -> &'static InterruptWaker;
+}
+
 pub trait Pins<SPI> {
     fn setup(&self);
 }
@@ -172,7 +198,7 @@ pub trait SpiExt<SPI>: Sized {
 }
 
 macro_rules! spi {
-    ($($SPIX:ident: ($spiX:ident, $apbXenr:ident, $spiXen:ident, $pclkX:ident),)+) => {
+    ($($SPIX:ident: ($spiX:ident, $pclkX:ident),)+) => {
         $(
             impl<PINS> Spi<$SPIX, PINS> {
                 pub fn $spiX<T>(
@@ -189,7 +215,7 @@ macro_rules! spi {
                     pins.setup();
 
                     // Enable clock for SPI
-                    rcc.rb.$apbXenr.modify(|_, w| w.$spiXen().set_bit());
+                    $SPIX::enable(rcc);
 
                     // disable SS output
                     spi.cr2.write(|w| w.ssoe().clear_bit());
@@ -244,9 +270,16 @@ macro_rules! spi {
                     Spi { spi, pins }
                 }
 
-                pub fn free(self) -> ($SPIX, PINS) {
+                /// Disables the SPI and releases it, together with the pins
+                pub fn release(self) -> ($SPIX, PINS) {
+                    self.spi.cr1.modify(|_, w| w.spe().clear_bit());
                     (self.spi, self.pins)
                 }
+
+                #[deprecated(note = "Use `release` instead")]
+                pub fn free(self) -> ($SPIX, PINS) {
+                    self.release()
+                }
             }
 
             impl SpiExt<$SPIX> for $SPIX {
@@ -293,7 +326,7 @@ macro_rules! spi {
                         nb::Error::Other(Error::Crc)
                     } else if sr.txe().bit_is_set() {
                         // NOTE(write_volatile) see note above
-                        unsafe { ptr::write_volatile(&self.spi.dr as *const _ as *mut u8, byte) }
+                        unsafe { ptr::write_volatile(ptr::addr_of!(self.spi.dr) as *mut u8, byte) }
                         return Ok(());
                     } else {
                         nb::Error::WouldBlock
@@ -305,15 +338,171 @@ macro_rules! spi {
             impl<PINS> crate::hal::blocking::spi::transfer::Default<u8> for Spi<$SPIX, PINS> {}
 
             impl<PINS> crate::hal::blocking::spi::write::Default<u8> for Spi<$SPIX, PINS> {}
+
+            #[cfg(feature = "eh1")]
+            impl<PINS> Spi<$SPIX, PINS> {
+                fn exchange(&mut self, byte: u8) -> Result<u8, Error> {
+                    nb::block!(hal::spi::FullDuplex::send(self, byte))?;
+                    nb::block!(hal::spi::FullDuplex::read(self))
+                }
+            }
+
+            #[cfg(feature = "eh1")]
+            impl<PINS> eh1::spi::ErrorType for Spi<$SPIX, PINS> {
+                type Error = Error;
+            }
+
+            #[cfg(feature = "eh1")]
+            impl<PINS> eh1::spi::SpiBus<u8> for Spi<$SPIX, PINS> {
+                fn read(&mut self, words: &mut [u8]) -> Result<(), Error> {
+                    for word in words {
+                        *word = self.exchange(0)?;
+                    }
+                    Ok(())
+                }
+
+                fn write(&mut self, words: &[u8]) -> Result<(), Error> {
+                    for &word in words {
+                        self.exchange(word)?;
+                    }
+                    Ok(())
+                }
+
+                fn transfer(&mut self, read: &mut [u8], write: &[u8]) -> Result<(), Error> {
+                    for i in 0..read.len().max(write.len()) {
+                        let byte = self.exchange(write.get(i).copied().unwrap_or(0))?;
+                        if let Some(word) = read.get_mut(i) {
+                            *word = byte;
+                        }
+                    }
+                    Ok(())
+                }
+
+                fn transfer_in_place(&mut self, words: &mut [u8]) -> Result<(), Error> {
+                    for word in words {
+                        *word = self.exchange(*word)?;
+                    }
+                    Ok(())
+                }
+
+                fn flush(&mut self) -> Result<(), Error> {
+                    while self.spi.sr.read().bsy().bit_is_set() {}
+                    Ok(())
+                }
+            }
+
+            #[cfg(feature = "async")]
+            impl AsyncInstance for $SPIX {
+                fn waker() -> &'static InterruptWaker {
+                    static WAKER: InterruptWaker = InterruptWaker::new();
+                    &WAKER
+                }
+            }
+
+            #[cfg(feature = "async")]
+            impl InterruptHandler for $SPIX {
+                fn on_interrupt() {
+                    // Safe, as the interrupts are only enabled by the async
+                    // API, which is waiting for them.
+                    let spi = unsafe { &*$SPIX::ptr() };
+                    spi.cr2.modify(|_, w| {
+                        w.txeie().clear_bit();
+                        w.rxneie().clear_bit();
+                        w.errie().clear_bit()
+                    });
+
+                    $SPIX::waker().wake();
+                }
+            }
+
+            #[cfg(feature = "async")]
+            impl<PINS> Spi<$SPIX, PINS> {
+                /// Waits for `flag` to be set, using the interrupts
+                async fn wait_for(&mut self, flag: fn(&sr::R) -> bool) -> Result<(), Error> {
+                    poll_fn(|cx| {
+                        $SPIX::waker().register(cx.waker());
+
+                        let sr = self.spi.sr.read();
+                        if sr.ovr().bit_is_set() {
+                            return Poll::Ready(Err(Error::Overrun));
+                        } else if sr.modf().bit_is_set() {
+                            return Poll::Ready(Err(Error::ModeFault));
+                        } else if sr.crcerr().bit_is_set() {
+                            return Poll::Ready(Err(Error::Crc));
+                        } else if flag(&sr) {
+                            return Poll::Ready(Ok(()));
+                        }
+
+                        self.spi.cr2.modify(|_, w| {
+                            w.txeie().set_bit();
+                            w.rxneie().set_bit();
+                            w.errie().set_bit()
+                        });
+                        Poll::Pending
+                    })
+                    .await
+                }
+
+                async fn exchange_async(&mut self, byte: u8) -> Result<u8, Error> {
+                    self.wait_for(|sr| sr.txe().bit_is_set()).await?;
+                    // NOTE(write_volatile) see note in `FullDuplex::read`
+                    unsafe { ptr::write_volatile(ptr::addr_of!((*$SPIX::ptr()).dr) as *mut u8, byte) }
+
+                    self.wait_for(|sr| sr.rxne().bit_is_set()).await?;
+                    // NOTE(read_volatile) see note in `FullDuplex::read`
+                    Ok(unsafe { ptr::read_volatile(ptr::addr_of!((*$SPIX::ptr()).dr) as *const u8) })
+                }
+            }
+
+            #[cfg(feature = "async")]
+            impl<PINS> embedded_hal_async::spi::SpiBus<u8> for Spi<$SPIX, PINS> {
+                async fn read(&mut self, words: &mut [u8]) -> Result<(), Error> {
+                    for word in words {
+                        *word = self.exchange_async(0).await?;
+                    }
+                    Ok(())
+                }
+
+                async fn write(&mut self, words: &[u8]) -> Result<(), Error> {
+                    for &word in words {
+                        self.exchange_async(word).await?;
+                    }
+                    Ok(())
+                }
+
+                async fn transfer(&mut self, read: &mut [u8], write: &[u8]) -> Result<(), Error> {
+                    for i in 0..read.len().max(write.len()) {
+                        let byte = self
+                            .exchange_async(write.get(i).copied().unwrap_or(0))
+                            .await?;
+                        if let Some(word) = read.get_mut(i) {
+                            *word = byte;
+                        }
+                    }
+                    Ok(())
+                }
+
+                async fn transfer_in_place(&mut self, words: &mut [u8]) -> Result<(), Error> {
+                    for word in words {
+                        *word = self.exchange_async(*word).await?;
+                    }
+                    Ok(())
+                }
+
+                async fn flush(&mut self) -> Result<(), Error> {
+                    while self.spi.sr.read().bsy().bit_is_set() {}
+                    Ok(())
+                }
+            }
         )+
     }
 }
 
 spi! {
-    SPI1: (spi1, apb2enr, spi1en, apb2_clk),
+    SPI1: (spi1, apb2_clk),
 }
 
 #[cfg(any(feature = "stm32l0x2", feature = "stm32l0x3"))]
 spi! {
-    SPI2: (spi2, apb1enr, spi2en, apb1_clk),
+    SPI2: (spi2, apb1_clk),
 }
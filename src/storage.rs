@@ -0,0 +1,700 @@
+//! Wear-leveled key-value store in EEPROM or Flash memory
+//!
+//! [`KvStore`] keeps small values, addressed by a 16-bit key, in a region of
+//! EEPROM or Flash memory. The region is split into two banks. Updated values
+//! are appended to a log in the active bank, which spreads the writes over the
+//! whole bank, instead of wearing out a single location. Once the active bank
+//! is full, the current values are copied into the other bank, which then
+//! becomes the active one.
+//!
+//! Updates are safe against power failure. A record only becomes valid once
+//! its header, which is written last, has been written completely. A bank only
+//! becomes active once all current values have been copied into it. After a
+//! power failure during an update, the store contains either the old or the
+//! new value.
+
+use core::{iter, slice};
+
+use crate::flash::{self, FLASH, PAGE_SIZE};
+
+/// The maximum length of a value, in bytes
+pub const MAX_VALUE_LEN: usize = 254;
+
+/// Identifies a bank header, in the upper half-word
+///
+/// The lower half-word contains the sequence number of the bank.
+const BANK_MAGIC: u32 = 0x4B56_0000;
+
+/// Set in the header of every record
+const RECORD_VALID: u32 = 0x8000_0000;
+
+/// The length of a record that removes a key
+const REMOVED: u32 = 0xFF;
+
+/// A key-value store in EEPROM or Flash memory
+///
+/// Every record in the log consists of a header word, containing the key, the
+/// length of the value and a checksum, followed by the value, padded to whole
+/// words.
+pub struct KvStore {
+    banks: [usize; 2],
+    bank_words: u32,
+    in_flash: bool,
+    active: usize,
+    sequence: u16,
+    head: u32,
+    clean: bool,
+}
+
+impl KvStore {
+    /// Opens the store in `size` bytes of memory at `address`
+    ///
+    /// If the memory doesn't contain a store yet, an empty one is created.
+    ///
+    /// If the store is located in Flash memory, you must make sure that your
+    /// program is not executed from the same Flash bank, as pages need to be
+    /// erased. See [`FLASH::erase_flash_page`].
+    ///
+    /// # Panics
+    ///
+    /// This method will panic, unless all of the following is true:
+    /// - The memory is located entirely in EEPROM or entirely in Flash memory
+    /// - For EEPROM, `address` is word-aligned and `size` is a multiple of 8
+    /// - For Flash memory, `address` is aligned to a page boundary and `size`
+    ///   is a multiple of two pages
+    pub fn new(flash: &mut FLASH, address: *mut u32, size: u32) -> Result<Self, Error> {
+        let start = address as u32;
+
+        if size == 0 {
+            panic!("Store has a size of zero");
+        }
+
+        let memory = flash.verify_address(address);
+        if flash.verify_address((start + size - 4) as *mut u32) != memory {
+            panic!("Store is not entirely in Flash memory or EEPROM");
+        }
+
+        let in_flash = memory.is_flash();
+        if in_flash && (start & (PAGE_SIZE - 1) != 0 || size & (2 * PAGE_SIZE - 1) != 0) {
+            panic!("Store is not made up of whole Flash memory pages");
+        }
+        if memory.is_eeprom() && (start & 0x3 != 0 || size & 0x7 != 0) {
+            panic!("Store is not made up of whole words");
+        }
+
+        Self::open(flash, address as usize, size, in_flash)
+    }
+
+    /// Opens the store in memory that has already been validated
+    fn open(
+        memory: &mut impl Backend,
+        start: usize,
+        size: u32,
+        in_flash: bool,
+    ) -> Result<Self, Error> {
+        let mut store = KvStore {
+            banks: [start, start + size as usize / 2],
+            bank_words: size / 8,
+            in_flash,
+            active: 0,
+            sequence: 0,
+            head: 1,
+            clean: false,
+        };
+
+        let headers = [read(store.banks[0]), read(store.banks[1])];
+        let is_valid = |header: u32| header & 0xffff_0000 == BANK_MAGIC;
+
+        let active = match (is_valid(headers[0]), is_valid(headers[1])) {
+            (true, true) => {
+                // The sequence number is incremented with every bank change.
+                let difference = (headers[1] as u16).wrapping_sub(headers[0] as u16);
+                if difference as i16 > 0 {
+                    Some(1)
+                } else {
+                    Some(0)
+                }
+            }
+            (true, false) => Some(0),
+            (false, true) => Some(1),
+            (false, false) => None,
+        };
+
+        match active {
+            Some(bank) => {
+                store.active = bank;
+                store.sequence = headers[bank] as u16;
+                store.scan();
+            }
+            None => {
+                store.erase_bank(memory, 0)?;
+                memory.write_word(store.banks[0], BANK_MAGIC)?;
+                store.clean = true;
+            }
+        }
+
+        Ok(store)
+    }
+
+    /// Returns the value of `key`, if it is present
+    ///
+    /// The returned slice points directly into the memory of the store.
+    pub fn get(&self, key: u16) -> Option<&[u8]> {
+        let mut value = None;
+
+        for (pos, k, len) in self.records(self.active, self.head) {
+            if k == key {
+                value = Some((pos, len));
+            }
+        }
+
+        match value {
+            Some((pos, len)) if len != REMOVED => Some(self.data(self.active, pos, len)),
+            _ => None,
+        }
+    }
+
+    /// Sets the value of `key`
+    ///
+    /// Nothing is written, if `key` already has this value.
+    pub fn set(&mut self, flash: &mut FLASH, key: u16, value: &[u8]) -> Result<(), Error> {
+        self.set_value(flash, key, value)
+    }
+
+    /// Removes `key` from the store
+    ///
+    /// Nothing is written, if `key` is not present.
+    pub fn remove(&mut self, flash: &mut FLASH, key: u16) -> Result<(), Error> {
+        self.remove_value(flash, key)
+    }
+
+    fn set_value(
+        &mut self,
+        memory: &mut impl Backend,
+        key: u16,
+        value: &[u8],
+    ) -> Result<(), Error> {
+        if value.len() > MAX_VALUE_LEN {
+            return Err(Error::ValueTooLarge);
+        }
+        if self.get(key) == Some(value) {
+            return Ok(());
+        }
+
+        self.update(memory, key, value.len() as u32, value)
+    }
+
+    fn remove_value(&mut self, memory: &mut impl Backend, key: u16) -> Result<(), Error> {
+        if self.get(key).is_none() {
+            return Ok(());
+        }
+
+        self.update(memory, key, REMOVED, &[])
+    }
+
+    fn update(
+        &mut self,
+        memory: &mut impl Backend,
+        key: u16,
+        len: u32,
+        data: &[u8],
+    ) -> Result<(), Error> {
+        let words = 1 + data_words(len);
+
+        if !self.clean || self.head + words > self.bank_words {
+            return self.compact(memory, key, len, data);
+        }
+
+        // If writing fails halfway, the rest of the bank is no longer erased.
+        self.clean = false;
+        self.write_record(memory, self.active, self.head, key, len, data)?;
+        self.clean = true;
+
+        self.head += words;
+
+        Ok(())
+    }
+
+    /// Copies the current values, and the update, into the inactive bank
+    fn compact(
+        &mut self,
+        memory: &mut impl Backend,
+        key: u16,
+        len: u32,
+        data: &[u8],
+    ) -> Result<(), Error> {
+        let bank = 1 - self.active;
+        let mut head = 1;
+
+        self.erase_bank(memory, bank)?;
+
+        for (pos, k, l) in self.records(self.active, self.head) {
+            if k == key || l == REMOVED {
+                continue;
+            }
+            // Only the latest record of every key is current.
+            if self
+                .records(self.active, self.head)
+                .any(|(p, k2, _)| p > pos && k2 == k)
+            {
+                continue;
+            }
+
+            let words = 1 + data_words(l);
+            if head + words > self.bank_words {
+                return Err(Error::Full);
+            }
+
+            self.write_record(memory, bank, head, k, l, self.data(self.active, pos, l))?;
+            head += words;
+        }
+
+        if len != REMOVED {
+            let words = 1 + data_words(len);
+            if head + words > self.bank_words {
+                return Err(Error::Full);
+            }
+
+            self.write_record(memory, bank, head, key, len, data)?;
+            head += words;
+        }
+
+        // Writing the header activates the bank.
+        let sequence = self.sequence.wrapping_add(1);
+        memory.write_word(self.banks[bank], BANK_MAGIC | sequence as u32)?;
+
+        self.active = bank;
+        self.sequence = sequence;
+        self.head = head;
+        self.clean = true;
+
+        Ok(())
+    }
+
+    /// Finds the end of the log in the active bank
+    fn scan(&mut self) {
+        let mut pos = 1;
+        while let Some((_, len)) = self.record(self.active, pos, self.bank_words) {
+            pos += 1 + data_words(len);
+        }
+
+        self.head = pos;
+        self.clean = (pos..self.bank_words).all(|p| read(self.address(self.active, p)) == 0);
+    }
+
+    /// Iterates over the records in `bank`, up to word `end`
+    fn records(&self, bank: usize, end: u32) -> impl Iterator<Item = (u32, u16, u32)> + '_ {
+        let mut pos = 1;
+
+        iter::from_fn(move || {
+            let (key, len) = self.record(bank, pos, end)?;
+            let record = (pos, key, len);
+            pos += 1 + data_words(len);
+            Some(record)
+        })
+    }
+
+    /// Returns key and length of the record at word `pos`, if it is valid
+    fn record(&self, bank: usize, pos: u32, end: u32) -> Option<(u16, u32)> {
+        if pos >= end {
+            return None;
+        }
+
+        let header = read(self.address(bank, pos));
+        if header & RECORD_VALID == 0 {
+            return None;
+        }
+
+        let key = header as u16;
+        let len = (header >> 16) & 0xff;
+        if pos + 1 + data_words(len) > end {
+            return None;
+        }
+        if (header >> 24) & 0x7f != checksum(key, len, self.data(bank, pos, len)) {
+            return None;
+        }
+
+        Some((key, len))
+    }
+
+    /// Returns the value of the record at word `pos`
+    fn data(&self, bank: usize, pos: u32, len: u32) -> &[u8] {
+        if len == REMOVED {
+            return &[];
+        }
+
+        // Safe, as the record is located within the bank.
+        unsafe { slice::from_raw_parts(self.address(bank, pos + 1) as *const u8, len as usize) }
+    }
+
+    /// Writes a record, the header last
+    fn write_record(
+        &self,
+        memory: &mut impl Backend,
+        bank: usize,
+        pos: u32,
+        key: u16,
+        len: u32,
+        data: &[u8],
+    ) -> Result<(), Error> {
+        for (i, chunk) in data.chunks(4).enumerate() {
+            let mut bytes = [0; 4];
+            bytes[..chunk.len()].copy_from_slice(chunk);
+
+            // The memory is erased, so there's no need to write zeros.
+            let word = u32::from_le_bytes(bytes);
+            if word != 0 {
+                let address = self.address(bank, pos + 1 + i as u32);
+                memory.write_word(address, word)?;
+            }
+        }
+
+        let header = RECORD_VALID | checksum(key, len, data) << 24 | len << 16 | key as u32;
+        memory.write_word(self.address(bank, pos), header)?;
+
+        Ok(())
+    }
+
+    /// Erases `bank`, starting with its header
+    fn erase_bank(&self, memory: &mut impl Backend, bank: usize) -> Result<(), Error> {
+        // Flash memory can only be erased page by page.
+        let unit = if self.in_flash { PAGE_SIZE / 4 } else { 1 };
+
+        for pos in (0..self.bank_words).step_by(unit as usize) {
+            // Skip memory that is already erased, to reduce wear.
+            if (pos..pos + unit).all(|p| read(self.address(bank, p)) == 0) {
+                continue;
+            }
+
+            let address = self.address(bank, pos);
+            if self.in_flash {
+                memory.erase_page(address)?;
+            } else {
+                memory.write_word(address, 0)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn address(&self, bank: usize, pos: u32) -> usize {
+        self.banks[bank] + pos as usize * 4
+    }
+}
+
+/// Writes to and erases the memory of a store
+trait Backend {
+    fn write_word(&mut self, address: usize, word: u32) -> Result<(), flash::Error>;
+    fn erase_page(&mut self, address: usize) -> Result<(), flash::Error>;
+}
+
+impl Backend for FLASH {
+    fn write_word(&mut self, address: usize, word: u32) -> Result<(), flash::Error> {
+        FLASH::write_word(self, address as *mut u32, word)
+    }
+
+    fn erase_page(&mut self, address: usize) -> Result<(), flash::Error> {
+        self.erase_flash_page(address as *mut u32)
+    }
+}
+
+/// Returns the number of words taken up by a value of length `len`
+fn data_words(len: u32) -> u32 {
+    if len == REMOVED {
+        0
+    } else {
+        (len + 3) >> 2
+    }
+}
+
+/// Computes the 7-bit checksum of a record
+fn checksum(key: u16, len: u32, data: &[u8]) -> u32 {
+    let header = [key as u8, (key >> 8) as u8, len as u8];
+
+    let mut crc: u8 = 0xff;
+    for byte in header.iter().chain(data) {
+        crc ^= byte;
+        for _ in 0..8 {
+            crc = if crc & 0x80 != 0 {
+                (crc << 1) ^ 0x07
+            } else {
+                crc << 1
+            };
+        }
+    }
+
+    (crc & 0x7f) as u32
+}
+
+fn read(address: usize) -> u32 {
+    // Safe, as all addresses passed here are within the store.
+    unsafe { (address as *const u32).read_volatile() }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    /// Writing to or erasing the memory failed
+    Flash(flash::Error),
+
+    /// The value is longer than [`MAX_VALUE_LEN`]
+    ValueTooLarge,
+
+    /// The current values don't fit into a bank
+    Full,
+}
+
+impl From<flash::Error> for Error {
+    fn from(error: flash::Error) -> Self {
+        Error::Flash(error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Size of the test stores, with 8 words per bank
+    const SIZE: u32 = 64;
+
+    /// Memory on the host, which loses power after a number of writes
+    struct TestMemory {
+        buffer: Box<[u32]>,
+        /// Index of the first word of the memory in `buffer`, which is aligned
+        /// to a page boundary
+        first: usize,
+        len: usize,
+        in_flash: bool,
+        writes_left: Option<usize>,
+    }
+
+    impl TestMemory {
+        fn new(size: u32, in_flash: bool) -> Self {
+            let len = size as usize / 4;
+            let buffer = vec![0; len + PAGE_SIZE as usize / 4].into_boxed_slice();
+            let misalignment = buffer.as_ptr() as usize & (PAGE_SIZE as usize - 1);
+            let first = (PAGE_SIZE as usize - misalignment) % PAGE_SIZE as usize / 4;
+
+            Self {
+                buffer,
+                first,
+                len,
+                in_flash,
+                writes_left: None,
+            }
+        }
+
+        fn words(&mut self) -> &mut [u32] {
+            &mut self.buffer[self.first..self.first + self.len]
+        }
+
+        fn start(&self) -> usize {
+            self.buffer[self.first..].as_ptr() as usize
+        }
+
+        fn index(&self, address: usize) -> usize {
+            assert_eq!(address & 0x3, 0);
+            let index = (address - self.start()) / 4;
+            assert!(index < self.len);
+            index
+        }
+
+        /// Opens a store in the whole memory, like after a reset
+        fn open(&mut self) -> KvStore {
+            let (start, size) = (self.start(), self.len as u32 * 4);
+            let in_flash = self.in_flash;
+            KvStore::open(self, start, size, in_flash).unwrap()
+        }
+
+        /// Overwrites a byte, as if the memory had been corrupted
+        fn corrupt(&mut self, word: usize, byte: usize, value: u8) {
+            let mut bytes = self.words()[word].to_le_bytes();
+            bytes[byte] = value;
+            self.words()[word] = u32::from_le_bytes(bytes);
+        }
+    }
+
+    impl Backend for TestMemory {
+        fn write_word(&mut self, address: usize, word: u32) -> Result<(), flash::Error> {
+            let index = self.index(address);
+            match &mut self.writes_left {
+                Some(0) => return Err(flash::Error::AbortedByFetch),
+                Some(left) => *left -= 1,
+                None => {}
+            }
+            if self.in_flash && word != 0 && self.words()[index] != 0 {
+                return Err(flash::Error::NotErased);
+            }
+            self.words()[index] = word;
+            Ok(())
+        }
+
+        fn erase_page(&mut self, address: usize) -> Result<(), flash::Error> {
+            assert!(self.in_flash);
+            assert_eq!(address & (PAGE_SIZE as usize - 1), 0);
+            let index = self.index(address);
+            for word in &mut self.words()[index..index + PAGE_SIZE as usize / 4] {
+                *word = 0;
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn set_get_remove() {
+        let mut memory = TestMemory::new(SIZE, false);
+        let mut store = memory.open();
+
+        store.set_value(&mut memory, 1, b"hello").unwrap();
+        store.set_value(&mut memory, 2, b"").unwrap();
+        assert_eq!(store.get(1), Some(&b"hello"[..]));
+        assert_eq!(store.get(2), Some(&b""[..]));
+        assert_eq!(store.get(3), None);
+
+        store.remove_value(&mut memory, 1).unwrap();
+        assert_eq!(store.get(1), None);
+
+        let store = memory.open();
+        assert_eq!(store.get(1), None);
+        assert_eq!(store.get(2), Some(&b""[..]));
+    }
+
+    #[test]
+    fn value_too_large() {
+        let mut memory = TestMemory::new(SIZE, false);
+        let mut store = memory.open();
+
+        let value = [0; MAX_VALUE_LEN + 1];
+        assert_eq!(
+            store.set_value(&mut memory, 1, &value),
+            Err(Error::ValueTooLarge)
+        );
+    }
+
+    #[test]
+    fn checksum_covers_header_and_value() {
+        let crc = checksum(1, 3, b"abc");
+        assert!(crc < 0x80);
+        assert_ne!(checksum(2, 3, b"abc"), crc);
+        assert_ne!(checksum(1, 2, b"abc"), crc);
+        assert_ne!(checksum(1, 3, b"abd"), crc);
+    }
+
+    #[test]
+    fn corrupted_record_ends_log() {
+        let mut memory = TestMemory::new(SIZE, false);
+        let mut store = memory.open();
+        store.set_value(&mut memory, 1, b"a").unwrap();
+        store.set_value(&mut memory, 1, b"b").unwrap();
+
+        // The second record has its header in word 3, and its value in word 4.
+        memory.corrupt(4, 0, b'x');
+        let mut store = memory.open();
+        assert_eq!(store.get(1), Some(&b"a"[..]));
+
+        // The rest of the bank isn't erased, so the next update compacts.
+        store.set_value(&mut memory, 2, b"c").unwrap();
+        assert_eq!(store.active, 1);
+        let store = memory.open();
+        assert_eq!(store.get(1), Some(&b"a"[..]));
+        assert_eq!(store.get(2), Some(&b"c"[..]));
+    }
+
+    #[test]
+    fn torn_write() {
+        let mut memory = TestMemory::new(SIZE, false);
+        let mut store = memory.open();
+        store.set_value(&mut memory, 1, b"old").unwrap();
+
+        // The value is written, but not the header.
+        memory.writes_left = Some(1);
+        assert_eq!(
+            store.set_value(&mut memory, 1, b"new"),
+            Err(Error::Flash(flash::Error::AbortedByFetch))
+        );
+        memory.writes_left = None;
+
+        let mut store = memory.open();
+        assert_eq!(store.get(1), Some(&b"old"[..]));
+
+        store.set_value(&mut memory, 1, b"new").unwrap();
+        assert_eq!(memory.open().get(1), Some(&b"new"[..]));
+    }
+
+    #[test]
+    fn interrupted_swap() {
+        // Copying two records and writing the bank header takes 5 writes.
+        for writes in 0..=5 {
+            let mut memory = TestMemory::new(SIZE, false);
+            let mut store = memory.open();
+            store.set_value(&mut memory, 1, b"aaaa").unwrap();
+            store.set_value(&mut memory, 2, b"bbbb").unwrap();
+            store.set_value(&mut memory, 1, b"cccc").unwrap();
+
+            // The bank is full, so this update is done by the swap.
+            memory.writes_left = Some(writes);
+            let result = store.set_value(&mut memory, 2, b"dddd");
+            memory.writes_left = None;
+
+            let mut store = memory.open();
+            assert_eq!(store.get(1), Some(&b"cccc"[..]));
+            if writes < 5 {
+                assert!(result.is_err());
+                assert_eq!(store.active, 0);
+                assert_eq!(store.get(2), Some(&b"bbbb"[..]));
+
+                store.set_value(&mut memory, 2, b"dddd").unwrap();
+                assert_eq!(memory.open().get(2), Some(&b"dddd"[..]));
+            } else {
+                assert_eq!(result, Ok(()));
+                assert_eq!(store.active, 1);
+                assert_eq!(store.get(2), Some(&b"dddd"[..]));
+            }
+        }
+    }
+
+    #[test]
+    fn full_bank() {
+        let mut memory = TestMemory::new(SIZE, false);
+        let mut store = memory.open();
+
+        // Takes up the whole bank, besides the bank header.
+        store.set_value(&mut memory, 1, &[1; 24]).unwrap();
+        assert_eq!(store.set_value(&mut memory, 2, b"x"), Err(Error::Full));
+        assert_eq!(store.get(1), Some(&[1; 24][..]));
+        assert_eq!(store.get(2), None);
+
+        // Replacing the value still fits.
+        store.set_value(&mut memory, 1, &[2; 24]).unwrap();
+        let store = memory.open();
+        assert_eq!(store.get(1), Some(&[2; 24][..]));
+        assert_eq!(store.get(2), None);
+    }
+
+    #[test]
+    fn newer_bank_after_sequence_wrap() {
+        let mut memory = TestMemory::new(SIZE, false);
+        memory.words()[0] = BANK_MAGIC | 0xffff;
+        memory.words()[8] = BANK_MAGIC;
+
+        let store = memory.open();
+        assert_eq!(store.active, 1);
+        assert_eq!(store.sequence, 0);
+    }
+
+    #[test]
+    fn flash_pages_are_erased() {
+        let mut memory = TestMemory::new(4 * PAGE_SIZE, true);
+        let mut store = memory.open();
+
+        // Two values fill a bank, so every further update swaps banks.
+        for i in 0..4 {
+            store.set_value(&mut memory, 1, &[i; 120]).unwrap();
+            store.set_value(&mut memory, 2, &[i; 120]).unwrap();
+        }
+
+        let store = memory.open();
+        assert_eq!(store.get(1), Some(&[3; 120][..]));
+        assert_eq!(store.get(2), Some(&[3; 120][..]));
+    }
+}
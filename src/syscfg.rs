@@ -2,23 +2,43 @@
 //!
 //! See STM32L0x2 reference manual, chapter 10.
 
-use crate::{pac, rcc::Rcc};
+use crate::{
+    comp::{COMP1, COMP2},
+    pac,
+    rcc::{Enable, Rcc, Reset},
+};
 
 type PacSyscfg = pac::SYSCFG;
 
 pub struct SYSCFG {
     pub(crate) syscfg: PacSyscfg,
+    comparators_taken: bool,
 }
 
 impl SYSCFG {
     pub fn new(syscfg: PacSyscfg, rcc: &mut Rcc) -> Self {
         // Reset SYSCFG peripheral
-        rcc.rb.apb2rstr.modify(|_, w| w.syscfgrst().set_bit());
-        rcc.rb.apb2rstr.modify(|_, w| w.syscfgrst().clear_bit());
+        PacSyscfg::reset(rcc);
 
         // Enable SYSCFG peripheral
-        rcc.rb.apb2enr.modify(|_, w| w.syscfgen().set_bit());
+        PacSyscfg::enable(rcc);
 
-        SYSCFG { syscfg }
+        SYSCFG {
+            syscfg,
+            comparators_taken: false,
+        }
+    }
+
+    /// Takes ownership of both comparators
+    ///
+    /// The comparators are handed out only once. Returns `None`, if they have
+    /// been taken before.
+    pub fn comparators(&mut self) -> Option<(COMP1, COMP2)> {
+        if self.comparators_taken {
+            return None;
+        }
+        self.comparators_taken = true;
+
+        Some((COMP1(()), COMP2(())))
     }
 }
@@ -1,6 +1,7 @@
 use core::fmt;
 
 #[derive(Debug, PartialEq, PartialOrd, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct Bps(pub u32);
 
 impl fmt::Display for Bps {
@@ -10,6 +11,7 @@ impl fmt::Display for Bps {
 }
 
 #[derive(Debug, PartialEq, PartialOrd, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct Hertz(pub u32);
 
 impl fmt::Display for Hertz {
@@ -19,6 +21,7 @@ impl fmt::Display for Hertz {
 }
 
 #[derive(Debug, PartialEq, PartialOrd, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct MicroSeconds(pub u32);
 
 impl fmt::Display for MicroSeconds {
@@ -89,3 +92,39 @@ impl Into<Hertz> for MicroSeconds {
         Hertz(1_000_000 / period)
     }
 }
+
+// Conversions from `fugit` are only implemented for its hertz and microsecond
+// types. Extension methods like `10.kHz()` or `5.millis()` produce those
+// directly, while other units need an explicit `convert()`, which makes any
+// rounding visible at the call site.
+
+impl From<fugit::HertzU32> for Hertz {
+    fn from(freq: fugit::HertzU32) -> Self {
+        Hertz(freq.raw())
+    }
+}
+
+impl From<Hertz> for fugit::HertzU32 {
+    fn from(freq: Hertz) -> Self {
+        fugit::HertzU32::from_raw(freq.0)
+    }
+}
+
+impl From<fugit::MicrosDurationU32> for MicroSeconds {
+    fn from(period: fugit::MicrosDurationU32) -> Self {
+        MicroSeconds(period.ticks())
+    }
+}
+
+impl From<MicroSeconds> for fugit::MicrosDurationU32 {
+    fn from(period: MicroSeconds) -> Self {
+        fugit::MicrosDurationU32::from_ticks(period.0)
+    }
+}
+
+/// Converts a period into the corresponding frequency
+impl From<fugit::MicrosDurationU32> for Hertz {
+    fn from(period: fugit::MicrosDurationU32) -> Self {
+        MicroSeconds::from(period).into()
+    }
+}
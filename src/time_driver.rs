@@ -0,0 +1,177 @@
+//! Time driver for `embassy-time`, using the Low-Power Timer
+//!
+//! Requires the `time-driver-lptim` feature.
+//!
+//! The driver extends the 16-bit counter of `LPTIM` to 64 bits, by counting its overflows, and
+//! schedules the wakeups of the timer queue using its compare register. It's clocked from LSE at
+//! 32.768 kHz, which is the tick rate of `embassy-time`, so it keeps counting in Stop mode.
+//!
+//! The driver needs to be started with [`init`], and the `LPTIM1` interrupt needs to be unmasked
+//! in the NVIC and call [`on_interrupt`]:
+//!
+//! ``` ignore
+//! time_driver::init(dp.LPTIM, &mut exti, &mut pwr, &mut rcc);
+//!
+//! #[interrupt]
+//! fn LPTIM1() {
+//!     stm32l0xx_hal::time_driver::on_interrupt();
+//! }
+//! ```
+
+use core::cell::{Cell, RefCell};
+use core::task::Waker;
+
+use cortex_m::interrupt::{self, CriticalSection, Mutex};
+use embassy_time_driver::Driver;
+use embassy_time_queue_utils::Queue;
+
+use crate::exti::{DirectLine, Exti};
+use crate::lptim::{ClockSrc, LpTimer, Periodic};
+use crate::pac::LPTIM;
+use crate::pwr::PWR;
+use crate::rcc::Rcc;
+
+/// Minimum distance of an alarm from the current time, in ticks
+///
+/// A write to the compare register takes a few cycles of the LPTIM clock to complete. Alarms that
+/// are closer are handled by waiting for them.
+const MIN_ALARM_TICKS: u64 = 4;
+
+struct LptimDriver {
+    timer: Mutex<RefCell<Option<LpTimer<Periodic>>>>,
+    /// Number of overflows of the counter
+    overflows: Mutex<Cell<u64>>,
+    /// Time of the next alarm, `u64::MAX` if there is none
+    alarm: Mutex<Cell<u64>>,
+    queue: Mutex<RefCell<Queue>>,
+}
+
+embassy_time_driver::time_driver_impl!(static DRIVER: LptimDriver = LptimDriver {
+    timer: Mutex::new(RefCell::new(None)),
+    overflows: Mutex::new(Cell::new(0)),
+    alarm: Mutex::new(Cell::new(u64::MAX)),
+    queue: Mutex::new(RefCell::new(Queue::new())),
+});
+
+/// Starts the time driver
+///
+/// Turns on LSE, and starts listening for the `LPTIM1` EXTI line, so alarms wake up the
+/// microcontroller from Stop mode.
+pub fn init(lptim: LPTIM, exti: &mut Exti, pwr: &mut PWR, rcc: &mut Rcc) {
+    let mut timer = LpTimer::init(lptim, pwr, rcc, ClockSrc::Lse);
+
+    // IER can only be modified when the timer is disabled, which it is after `init`. So the
+    // compare interrupt stays enabled, and also fires once per overflow while no alarm is close.
+    timer
+        .lptim
+        .ier
+        .write(|w| w.arrmie().enabled().cmpmie().enabled());
+    exti.listen_direct(DirectLine::Lptim1);
+
+    timer.start_counting();
+
+    interrupt::free(|cs| DRIVER.timer.borrow(cs).replace(Some(timer)));
+}
+
+/// Handles the `LPTIM1` interrupt
+///
+/// Needs to be called from the `LPTIM1` interrupt handler.
+pub fn on_interrupt() {
+    interrupt::free(|cs| DRIVER.on_interrupt(cs));
+}
+
+impl LptimDriver {
+    fn on_interrupt(&self, cs: &CriticalSection) {
+        if let Some(timer) = self.timer.borrow(cs).borrow().as_ref() {
+            let isr = timer.lptim.isr.read();
+            if isr.arrm().bit_is_set() {
+                timer.lptim.icr.write(|w| w.arrmcf().set_bit());
+                let overflows = self.overflows.borrow(cs);
+                overflows.set(overflows.get() + 1);
+            }
+            if isr.cmpm().bit_is_set() {
+                timer.lptim.icr.write(|w| w.cmpmcf().set_bit());
+            }
+        }
+
+        // Setting the alarm again also updates the compare register, if the alarm has come within
+        // reach after an overflow.
+        let alarm = self.alarm.borrow(cs).get();
+        if !self.set_alarm(cs, alarm) {
+            let mut queue = self.queue.borrow(cs).borrow_mut();
+            let mut next = queue.next_expiration(self.now_cs(cs));
+            while !self.set_alarm(cs, next) {
+                next = queue.next_expiration(self.now_cs(cs));
+            }
+        }
+    }
+
+    fn now_cs(&self, cs: &CriticalSection) -> u64 {
+        let timer = self.timer.borrow(cs).borrow();
+        let timer = match timer.as_ref() {
+            Some(timer) => timer,
+            None => return 0,
+        };
+
+        let cnt = timer.read_counter();
+
+        // An overflow might have happened, that hasn't been handled in the interrupt yet. If the
+        // counter value is low, it has been read after that overflow.
+        let mut overflows = self.overflows.borrow(cs).get();
+        if timer.lptim.isr.read().arrm().bit_is_set() && cnt < 0x8000 {
+            overflows += 1;
+        }
+
+        overflows << 16 | u64::from(cnt)
+    }
+
+    /// Schedules the alarm at `at`
+    ///
+    /// Returns `false`, if `at` has already passed (or is too close to be scheduled).
+    fn set_alarm(&self, cs: &CriticalSection, at: u64) -> bool {
+        let alarm = self.alarm.borrow(cs);
+        alarm.set(at);
+        if at == u64::MAX {
+            return true;
+        }
+
+        let now = self.now_cs(cs);
+        if at < now + MIN_ALARM_TICKS {
+            alarm.set(u64::MAX);
+            return false;
+        }
+
+        // The compare register matches once per overflow, so it can only be set once the alarm
+        // is less than a full period ahead. Otherwise, this is repeated after the next overflows.
+        if at - now <= 0xffff {
+            if let Some(timer) = self.timer.borrow(cs).borrow().as_ref() {
+                timer.write_compare(at as u16);
+            }
+
+            if at <= self.now_cs(cs) {
+                alarm.set(u64::MAX);
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+impl Driver for LptimDriver {
+    fn now(&self) -> u64 {
+        interrupt::free(|cs| self.now_cs(cs))
+    }
+
+    fn schedule_wake(&self, at: u64, waker: &Waker) {
+        interrupt::free(|cs| {
+            let mut queue = self.queue.borrow(cs).borrow_mut();
+            if queue.schedule_wake(at, waker) {
+                let mut next = queue.next_expiration(self.now_cs(cs));
+                while !self.set_alarm(cs, next) {
+                    next = queue.next_expiration(self.now_cs(cs));
+                }
+            }
+        });
+    }
+}
@@ -0,0 +1,178 @@
+//! Timeouts for blocking bus operations
+//!
+//! [`TimeoutGuard`] wraps a serial, SPI or I2C driver together with a
+//! count-down timer, and provides the blocking operations with a timeout. If an
+//! operation doesn't complete before the timer expires, e.g. because a slave is
+//! stuck, [`Error::Timeout`] is returned instead of waiting forever.
+//!
+//! For I2C, the guard implements the blocking embedded-hal traits. As those
+//! for serial and SPI are implemented generically by embedded-hal, it provides
+//! these operations as inherent methods instead:
+//!
+//! ``` ignore
+//! let timer = dp.TIM2.timer(1.Hz(), &mut rcc);
+//!
+//! // Each operation needs to complete within 10 ms
+//! let mut i2c = TimeoutGuard::new(i2c, timer, 100.Hz());
+//! match i2c.write(ADDRESS, &[REGISTER]) {
+//!     Err(timeout::Error::Timeout) => {
+//!         // recover the bus
+//!     }
+//!     _ => {}
+//! }
+//! ```
+
+use crate::hal::{serial, spi, timer::CountDown};
+
+/// Error of an operation with a timeout
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error<E> {
+    /// The operation didn't complete before the timer expired
+    Timeout,
+    /// The bus driver returned an error
+    Bus(E),
+}
+
+impl<E> From<E> for Error<E> {
+    fn from(error: E) -> Self {
+        Error::Bus(error)
+    }
+}
+
+/// A bus driver, whose blocking operations time out
+pub struct TimeoutGuard<Bus, Timer: CountDown> {
+    bus: Bus,
+    timer: Timer,
+    timeout: Timer::Time,
+}
+
+impl<Bus, Timer> TimeoutGuard<Bus, Timer>
+where
+    Timer: CountDown,
+    Timer::Time: Clone,
+{
+    /// Wraps `bus`, using `timer` to limit each operation to `timeout`
+    ///
+    /// `timeout` is in the time unit of the timer, i.e. the frequency at which
+    /// the TIM and SYST timers expire.
+    pub fn new(bus: Bus, timer: Timer, timeout: Timer::Time) -> Self {
+        Self {
+            bus,
+            timer,
+            timeout,
+        }
+    }
+
+    /// Changes the timeout of the following operations
+    pub fn set_timeout(&mut self, timeout: Timer::Time) {
+        self.timeout = timeout;
+    }
+
+    /// Returns the bus driver and the timer
+    pub fn release(self) -> (Bus, Timer) {
+        (self.bus, self.timer)
+    }
+
+    /// Starts the timer for a new operation
+    pub(crate) fn start(&mut self) {
+        self.timer.start(self.timeout.clone());
+    }
+
+    /// Returns `Err(Error::Timeout)`, if the timer has expired
+    pub(crate) fn check<E>(timer: &mut Timer) -> Result<(), Error<E>> {
+        match timer.wait() {
+            Ok(()) => Err(Error::Timeout),
+            Err(_) => Ok(()),
+        }
+    }
+
+    /// Splits the guard into the bus driver and the timer
+    pub(crate) fn split_mut(&mut self) -> (&mut Bus, &mut Timer) {
+        (&mut self.bus, &mut self.timer)
+    }
+
+    /// Polls `f` until it completes, or the timer expires
+    fn poll<T, E>(
+        &mut self,
+        mut f: impl FnMut(&mut Bus) -> nb::Result<T, E>,
+    ) -> Result<T, Error<E>> {
+        loop {
+            match f(&mut self.bus) {
+                Ok(value) => return Ok(value),
+                Err(nb::Error::Other(error)) => return Err(Error::Bus(error)),
+                Err(nb::Error::WouldBlock) => Self::check(&mut self.timer)?,
+            }
+        }
+    }
+
+    /// Reads a single word from a serial port
+    pub fn read<Word>(&mut self) -> Result<Word, Error<Bus::Error>>
+    where
+        Bus: serial::Read<Word>,
+    {
+        self.start();
+        self.poll(|bus| bus.read())
+    }
+}
+
+impl<Bus, Timer> TimeoutGuard<Bus, Timer>
+where
+    Timer: CountDown,
+    Timer::Time: Clone,
+{
+    /// Writes all words to a serial port
+    ///
+    /// This doesn't wait for the last word to be transmitted, see [`Self::bflush`].
+    pub fn bwrite_all<Word>(&mut self, buffer: &[Word]) -> Result<(), Error<Bus::Error>>
+    where
+        Bus: serial::Write<Word>,
+        Word: Clone,
+    {
+        self.start();
+        for word in buffer {
+            self.poll(|bus| bus.write(word.clone()))?;
+        }
+        Ok(())
+    }
+
+    /// Waits until a serial port has transmitted all words
+    pub fn bflush<Word>(&mut self) -> Result<(), Error<Bus::Error>>
+    where
+        Bus: serial::Write<Word>,
+    {
+        self.start();
+        self.poll(|bus| bus.flush())
+    }
+
+    /// Sends `words` over SPI, replacing them with the received words
+    pub fn transfer<'w, Word>(
+        &mut self,
+        words: &'w mut [Word],
+    ) -> Result<&'w [Word], Error<Bus::Error>>
+    where
+        Bus: spi::FullDuplex<Word>,
+        Word: Clone,
+    {
+        self.start();
+        for word in words.iter_mut() {
+            self.poll(|bus| bus.send(word.clone()))?;
+            *word = self.poll(|bus| bus.read())?;
+        }
+        Ok(words)
+    }
+
+    /// Sends `words` over SPI, discarding the received words
+    pub fn write<Word>(&mut self, words: &[Word]) -> Result<(), Error<Bus::Error>>
+    where
+        Bus: spi::FullDuplex<Word>,
+        Word: Clone,
+    {
+        self.start();
+        for word in words {
+            self.poll(|bus| bus.send(word.clone()))?;
+            self.poll(|bus| bus.read())?;
+        }
+        Ok(())
+    }
+}
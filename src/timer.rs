@@ -1,14 +1,100 @@
 //! Timers
+use crate::config;
 use crate::hal::timer::{CountDown, Periodic};
-use crate::pac::{tim2, tim21, tim22, tim6, TIM2, TIM21, TIM22, TIM3, TIM6};
-use crate::rcc::{Clocks, Rcc};
+#[cfg(feature = "io-STM32L071")]
+use crate::pac::TIM3;
+use crate::pac::{tim2, tim21, tim22, TIM2, TIM21, TIM22};
+#[cfg(any(feature = "io-STM32L051", feature = "io-STM32L071"))]
+use crate::pac::{tim6, TIM6};
+use crate::rcc::{Clocks, Enable, Rcc, Reset};
 use crate::time::Hertz;
-use cast::{u16, u32};
+use cast::u32;
 use cortex_m::peripheral::syst::SystClkSource;
 use cortex_m::peripheral::SYST;
 use nb;
 use void::Void;
 
+/// Interrupt events
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Event {
+    /// Timer timed out / count down ended
+    Update,
+}
+
+/// Prescaler and auto-reload values for the period of a timer
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Prescaler {
+    /// Prescaler value (`PSC`)
+    pub psc: u16,
+    /// Auto-reload value (`ARR`)
+    pub arr: u16,
+}
+
+impl Prescaler {
+    /// Computes the values for a period of `freq` (in Hz), from the timer
+    /// clock frequency `clk` (in Hz)
+    ///
+    /// Returns [`config::Error::Frequency`], if the period can't be derived
+    /// from `clk`.
+    ///
+    /// As this is a `const fn`, the values can be computed at compile time,
+    /// so an invalid combination fails the build:
+    ///
+    /// ``` ignore
+    /// const PRESCALER: Prescaler = match Prescaler::new(16_000_000, 10) {
+    ///     Ok(prescaler) => prescaler,
+    ///     Err(_) => panic!("invalid timer frequency"),
+    /// };
+    ///
+    /// timer.start_with_prescaler(PRESCALER);
+    /// ```
+    pub const fn new(clk: u32, freq: u32) -> Result<Self, config::Error> {
+        if freq == 0 {
+            return Err(config::Error::Frequency);
+        }
+        Self::from_ticks(clk / freq)
+    }
+
+    /// Computes the values for a period of `ticks` timer clock cycles
+    ///
+    /// The counter runs for `(psc + 1) * (arr + 1)` timer clock cycles,
+    /// which is rounded down to `ticks`, if `ticks` isn't a multiple of the
+    /// prescaler. Returns [`config::Error::Frequency`], if `ticks` is below 2,
+    /// as the counter doesn't run with an auto-reload value of 0.
+    pub const fn from_ticks(ticks: u32) -> Result<Self, config::Error> {
+        if ticks < 2 {
+            return Err(config::Error::Frequency);
+        }
+
+        // The smallest prescaler for which `arr` fits into 16 bits. Neither
+        // value can exceed 16 bits, as `ticks` is a 32-bit value.
+        let psc = (ticks - 1) >> 16;
+        let arr = ticks / (psc + 1) - 1;
+
+        Ok(Prescaler {
+            psc: psc as u16,
+            arr: arr as u16,
+        })
+    }
+}
+
+/// Computes the prescaler value, that lets a counter run at `freq` (in Hz)
+/// from the timer clock frequency `clk` (in Hz)
+///
+/// Returns [`config::Error::Frequency`], if `freq` is 0 Hz, higher than `clk`
+/// or too low for the 16-bit prescaler.
+pub(crate) const fn counter_prescaler(clk: u32, freq: u32) -> Result<u16, config::Error> {
+    if freq == 0 || freq > clk {
+        return Err(config::Error::Frequency);
+    }
+    let psc = clk / freq - 1;
+    if psc > 0xffff {
+        return Err(config::Error::Frequency);
+    }
+    Ok(psc as u16)
+}
+
 pub trait TimerExt<TIM> {
     fn timer<T>(self, timeout: T, rcc: &mut Rcc) -> Timer<TIM>
     where
@@ -23,6 +109,11 @@ pub struct Timer<TIM> {
 
 impl Timer<SYST> {
     /// Configures the SYST clock as a periodic count down timer
+    ///
+    /// # Panics
+    ///
+    /// Panics, if the timeout can't be derived from the clock. See
+    /// [`Timer::try_start`].
     pub fn syst<T>(mut syst: SYST, timeout: T, rcc: &mut Rcc) -> Self
     where
         T: Into<Hertz>,
@@ -36,30 +127,85 @@ impl Timer<SYST> {
         timer
     }
 
-    /// Starts listening
-    pub fn listen(&mut self) {
-        self.tim.enable_interrupt()
+    /// Starts listening for an `event`
+    pub fn listen(&mut self, event: Event) {
+        match event {
+            Event::Update => self.tim.enable_interrupt(),
+        }
     }
 
-    /// Stops listening
-    pub fn unlisten(&mut self) {
-        self.tim.disable_interrupt()
+    /// Stops listening for an `event`
+    pub fn unlisten(&mut self, event: Event) {
+        match event {
+            Event::Update => self.tim.disable_interrupt(),
+        }
     }
-}
 
-impl CountDown for Timer<SYST> {
-    type Time = Hertz;
+    /// Returns whether the flag of an `event` is set
+    ///
+    /// The flag of the SYST timer is cleared by reading it, so this clears it
+    /// as well.
+    pub fn is_pending(&mut self, event: Event) -> bool {
+        match event {
+            Event::Update => self.tim.has_wrapped(),
+        }
+    }
 
-    fn start<T>(&mut self, timeout: T)
+    /// Clears the flag of an `event`
+    pub fn clear(&mut self, event: Event) {
+        match event {
+            Event::Update => {
+                self.tim.has_wrapped();
+            }
+        }
+    }
+
+    /// Restarts the count down from the reload value
+    pub fn reset(&mut self) {
+        self.tim.clear_current();
+    }
+
+    /// Starts a new count down, if the timeout is valid
+    ///
+    /// Like [`CountDown::start`], but returns [`config::Error::Frequency`]
+    /// instead of panicking, if the timeout can't be derived from the system
+    /// clock.
+    pub fn try_start<T>(&mut self, timeout: T) -> Result<(), config::Error>
     where
         T: Into<Hertz>,
     {
-        let rvr = self.clocks.sys_clk().0 / timeout.into().0 - 1;
-        assert!(rvr < (1 << 24));
+        let freq = timeout.into().0;
+        let sys_clk = self.clocks.sys_clk().0;
+        if freq == 0 || freq > sys_clk {
+            return Err(config::Error::Frequency);
+        }
+        let rvr = sys_clk / freq - 1;
+        if rvr >= (1 << 24) {
+            return Err(config::Error::Frequency);
+        }
 
         self.tim.set_reload(rvr);
         self.tim.clear_current();
         self.tim.enable_counter();
+        Ok(())
+    }
+
+    /// Stops the count down and releases the SYST peripheral
+    pub fn release(mut self) -> SYST {
+        self.tim.disable_interrupt();
+        self.tim.disable_counter();
+        self.tim
+    }
+}
+
+impl CountDown for Timer<SYST> {
+    type Time = Hertz;
+
+    fn start<T>(&mut self, timeout: T)
+    where
+        T: Into<Hertz>,
+    {
+        self.try_start(timeout).unwrap();
     }
 
     fn wait(&mut self) -> nb::Result<(), Void> {
@@ -83,7 +229,7 @@ impl TimerExt<SYST> for SYST {
 impl Periodic for Timer<SYST> {}
 
 macro_rules! timers {
-    ($($TIM:ident: ($tim:ident, $timXen:ident, $timXrst:ident, $apbenr:ident, $apbrstr:ident, $timclk:ident, $mms:ty),)+) => {
+    ($($TIM:ident: ($tim:ident, $timclk:ident, $mms:ty),)+) => {
         $(
             impl TimerExt<$TIM> for $TIM {
                 fn timer<T>(self, timeout: T, rcc: &mut Rcc) -> Timer<$TIM>
@@ -96,13 +242,17 @@ macro_rules! timers {
 
             impl Timer<$TIM> where $TIM: GeneralPurposeTimer {
                 /// Configures a TIM peripheral as a periodic count down timer
+                ///
+                /// # Panics
+                ///
+                /// Panics, if the timeout can't be derived from the clock. See
+                /// [`Timer::try_start`].
                 pub fn $tim<T>(tim: $TIM, timeout: T, rcc: &mut Rcc) -> Self
                 where
                     T: Into<Hertz>,
                 {
-                    rcc.rb.$apbenr.modify(|_, w| w.$timXen().set_bit());
-                    rcc.rb.$apbrstr.modify(|_, w| w.$timXrst().set_bit());
-                    rcc.rb.$apbrstr.modify(|_, w| w.$timXrst().clear_bit());
+                    $TIM::enable(rcc);
+                    $TIM::reset(rcc);
 
                     let mut timer = Timer {
                         tim,
@@ -112,19 +262,39 @@ macro_rules! timers {
                     timer
                 }
 
-                /// Starts listening
-                pub fn listen(&mut self) {
-                    self.tim.dier.write(|w| w.uie().set_bit());
+                /// Starts listening for an `event`
+                pub fn listen(&mut self, event: Event) {
+                    match event {
+                        Event::Update => self.tim.dier.modify(|_, w| w.uie().set_bit()),
+                    }
                 }
 
-                /// Stops listening
-                pub fn unlisten(&mut self) {
-                    self.tim.dier.write(|w| w.uie().clear_bit());
+                /// Stops listening for an `event`
+                pub fn unlisten(&mut self, event: Event) {
+                    match event {
+                        Event::Update => self.tim.dier.modify(|_, w| w.uie().clear_bit()),
+                    }
+                }
+
+                /// Returns whether the flag of an `event` is set
+                ///
+                /// This doesn't depend on whether the event is listened for.
+                pub fn is_pending(&self, event: Event) -> bool {
+                    match event {
+                        Event::Update => self.tim.sr.read().uif().bit_is_set(),
+                    }
+                }
+
+                /// Clears the flag of an `event`
+                pub fn clear(&mut self, event: Event) {
+                    match event {
+                        Event::Update => self.tim.sr.modify(|_, w| w.uif().clear_bit()),
+                    }
                 }
 
                 /// Clears interrupt flag
                 pub fn clear_irq(&mut self) {
-                    self.tim.sr.write(|w| w.uif().clear_bit());
+                    self.clear(Event::Update);
                 }
 
                 /// Releases the TIM peripheral
@@ -149,30 +319,39 @@ macro_rules! timers {
                 ) {
                     self.tim.select_master_mode(variant);
                 }
-            }
-
-            impl CountDown for Timer<$TIM> {
-                type Time = Hertz;
 
-                fn start<T>(&mut self, timeout: T)
+                /// Starts a new count down, if the timeout is valid
+                ///
+                /// Like [`CountDown::start`], but returns
+                /// [`config::Error::Frequency`] instead of panicking, if the
+                /// timeout can't be derived from the timer clock.
+                pub fn try_start<T>(&mut self, timeout: T) -> Result<(), config::Error>
                 where
                     T: Into<Hertz>,
                 {
+                    let freq = timeout.into().0;
+                    let prescaler = Prescaler::new(self.clocks.$timclk().0, freq)?;
+                    self.start_with_prescaler(prescaler);
+                    Ok(())
+                }
+
+                /// Starts the count down with a precomputed [`Prescaler`]
+                ///
+                /// This skips the prescaler calculation of
+                /// [`CountDown::start`] at runtime.
+                pub fn start_with_prescaler(&mut self, prescaler: Prescaler) {
                     // pause
                     self.tim.cr1.modify(|_, w| w.cen().clear_bit());
                     // reset counter
                     self.tim.cnt.reset();
 
-                    let freq = timeout.into().0;
-                    let ticks = self.clocks.$timclk().0 / freq;
-                    let psc = u16((ticks - 1) / (1 << 16)).unwrap();
-                    self.tim.psc.write(|w| w.psc().bits(psc));
+                    self.tim.psc.write(|w| w.psc().bits(prescaler.psc));
                     // This is only unsafe for some timers, so we need this to
                     // suppress the warnings.
                     #[allow(unused_unsafe)]
                     self.tim.arr.write(|w|
                         unsafe {
-                            w.arr().bits(u16(ticks / u32(psc + 1)).unwrap())
+                            w.arr().bits(prescaler.arr)
                         }
                     );
 
@@ -180,9 +359,23 @@ macro_rules! timers {
                     // Setting URS makes sure no interrupt is generated.
                     self.tim.cr1.modify(|_, w| w.urs().set_bit());
                     self.tim.egr.write(|w| w.ug().set_bit());
+                    // Clear an update of the previous period, so `wait`
+                    // doesn't return early.
+                    self.tim.sr.modify(|_, w| w.uif().clear_bit());
 
                     self.tim.cr1.modify(|_, w| w.cen().set_bit());
                 }
+            }
+
+            impl CountDown for Timer<$TIM> {
+                type Time = Hertz;
+
+                fn start<T>(&mut self, timeout: T)
+                where
+                    T: Into<Hertz>,
+                {
+                    self.try_start(timeout).unwrap();
+                }
 
                 fn wait(&mut self) -> nb::Result<(), Void> {
                     if self.tim.sr.read().uif().bit_is_clear() {
@@ -208,20 +401,367 @@ macro_rules! timers {
 }
 
 timers! {
-    TIM2: (tim2, tim2en, tim2rst, apb1enr, apb1rstr, apb1_tim_clk,
+    TIM2: (tim2, apb1_tim_clk,
         tim2::cr2::MMS_A),
-    TIM3: (tim3, tim3en, tim3rst, apb1enr, apb1rstr, apb1_tim_clk,
-        tim2::cr2::MMS_A),
-    TIM6: (tim6, tim6en, tim6rst, apb1enr, apb1rstr, apb1_tim_clk,
-        tim6::cr2::MMS_A),
-    TIM21: (tim21, tim21en, tim21rst, apb2enr, apb2rstr, apb2_tim_clk,
+    TIM21: (tim21, apb2_tim_clk,
         tim21::cr2::MMS_A),
-    TIM22: (tim22, tim22en, tim22rst, apb2enr, apb2rstr, apb2_tim_clk,
+    TIM22: (tim22, apb2_tim_clk,
         tim22::cr2::MMS_A),
 }
 
+#[cfg(any(feature = "io-STM32L051", feature = "io-STM32L071"))]
+timers! {
+    TIM6: (tim6, apb1_tim_clk,
+        tim6::cr2::MMS_A),
+}
+
+#[cfg(feature = "io-STM32L071")]
+timers! {
+    TIM3: (tim3, apb1_tim_clk,
+        tim2::cr2::MMS_A),
+}
+
 pub trait GeneralPurposeTimer {
     type MasterMode;
 
     fn select_master_mode(&mut self, variant: Self::MasterMode);
 }
+
+/// Timers that have a slave mode controller
+///
+/// The slave mode controller allows a timer to be reset, gated, triggered or
+/// clocked by a trigger input. Together with the master mode of another timer
+/// (see `GeneralPurposeTimer::select_master_mode`), this allows timers to be
+/// chained. The internal trigger (ITRx) connections between the timers are
+/// listed in the timer chapters of the STM32L0x2 reference manual.
+pub trait SlaveTimer {
+    type SlaveMode;
+    type TriggerSource;
+
+    fn select_slave_mode(&mut self, mode: Self::SlaveMode, trigger: Self::TriggerSource);
+}
+
+macro_rules! slave_timers {
+    ($($TIM:ident: ($sms:ty, $ts:ty),)+) => {
+        $(
+            impl Timer<$TIM> where $TIM: SlaveTimer {
+                /// Select slave mode and trigger input
+                pub fn select_slave_mode(&mut self,
+                    mode: <$TIM as SlaveTimer>::SlaveMode,
+                    trigger: <$TIM as SlaveTimer>::TriggerSource,
+                ) {
+                    self.tim.select_slave_mode(mode, trigger);
+                }
+            }
+
+            impl SlaveTimer for $TIM {
+                type SlaveMode = $sms;
+                type TriggerSource = $ts;
+
+                fn select_slave_mode(&mut self, mode: Self::SlaveMode, trigger: Self::TriggerSource) {
+                    // The trigger input must only be changed while the slave
+                    // mode is disabled.
+                    self.smcr.modify(|_, w| w.sms().disabled());
+                    self.smcr.modify(|_, w| w.ts().variant(trigger));
+                    self.smcr.modify(|_, w| w.sms().variant(mode));
+                }
+            }
+        )+
+    }
+}
+
+slave_timers! {
+    TIM2: (tim2::smcr::SMS_A, tim2::smcr::TS_A),
+    TIM21: (tim21::smcr::SMS_A, tim21::smcr::TS_A),
+    TIM22: (tim22::smcr::SMS_A, tim22::smcr::TS_A),
+}
+
+#[cfg(feature = "io-STM32L071")]
+slave_timers! {
+    TIM3: (tim2::smcr::SMS_A, tim2::smcr::TS_A),
+}
+
+/// Two timers, chained to form a 32-bit timer
+///
+/// The low timer's update event (i.e. its overflow) is used as trigger
+/// output, which clocks the high timer through the internal trigger
+/// connection between the two (external clock mode 1).
+pub struct ChainedTimer<LOW, HIGH> {
+    low: LOW,
+    high: HIGH,
+}
+
+macro_rules! chained_timers {
+    ($(
+        ($LOW:ident, $HIGH:ident): (
+            $new:ident, $try_new:ident,
+            $low_clk:ident,
+            $mms:ty, $sms:ty, $ts:expr
+        ),
+    )+) => {
+        $(
+            impl ChainedTimer<$LOW, $HIGH> {
+                /// Chains the two timers and starts counting at `frequency`
+                ///
+                /// # Panics
+                ///
+                /// Panics, if the timer clock can't be divided down to the
+                /// given frequency. See [`Self::$try_new`].
+                pub fn $new<T>(low: $LOW, high: $HIGH, frequency: T, rcc: &mut Rcc) -> Self
+                where
+                    T: Into<Hertz>,
+                {
+                    Self::$try_new(low, high, frequency, rcc)
+                        .map_err(|(_, err)| err)
+                        .unwrap()
+                }
+
+                /// Chains the two timers and starts counting at `frequency`, if
+                /// it is valid
+                ///
+                /// Returns [`config::Error::Frequency`], if the timer clock
+                /// can't be divided down to the given frequency. The timers
+                /// are returned along with the error, untouched.
+                pub fn $try_new<T>(
+                    low: $LOW,
+                    high: $HIGH,
+                    frequency: T,
+                    rcc: &mut Rcc,
+                ) -> Result<Self, (($LOW, $HIGH), config::Error)>
+                where
+                    T: Into<Hertz>,
+                {
+                    let psc = match counter_prescaler(rcc.clocks.$low_clk().0, frequency.into().0) {
+                        Ok(psc) => psc,
+                        Err(err) => return Err(((low, high), err)),
+                    };
+
+                    $LOW::enable(rcc);
+                    $LOW::reset(rcc);
+                    $HIGH::enable(rcc);
+                    $HIGH::reset(rcc);
+
+                    low.psc.write(|w| w.psc().bits(psc));
+                    // Load the prescaler value
+                    low.egr.write(|w| w.ug().set_bit());
+
+                    // Output a trigger on each update event of the low timer,
+                    // and let it clock the high timer.
+                    low.cr2.modify(|_, w| w.mms().variant(<$mms>::UPDATE));
+                    high.smcr.modify(|_, w| w.ts().variant($ts));
+                    high.smcr.modify(|_, w| w.sms().variant(<$sms>::EXT_CLOCK_MODE));
+
+                    let mut timer = ChainedTimer { low, high };
+                    timer.reset();
+                    Ok(timer)
+                }
+
+                /// Returns the current 32-bit count
+                ///
+                /// The high half is read twice, to detect an overflow of the
+                /// low half between the reads. If that happens, the count is
+                /// read again.
+                pub fn count(&self) -> u32 {
+                    loop {
+                        let high = self.high.cnt.read().cnt().bits();
+                        let low = self.low.cnt.read().cnt().bits();
+
+                        if self.high.cnt.read().cnt().bits() == high {
+                            return u32(high) << 16 | u32(low);
+                        }
+                    }
+                }
+
+                /// Resets the count to zero
+                pub fn reset(&mut self) {
+                    // pause
+                    self.low.cr1.modify(|_, w| w.cen().clear_bit());
+                    // reset counters
+                    self.low.cnt.reset();
+                    self.high.cnt.reset();
+                    // continue
+                    self.high.cr1.modify(|_, w| w.cen().set_bit());
+                    self.low.cr1.modify(|_, w| w.cen().set_bit());
+                }
+
+                /// Releases the TIM peripherals
+                pub fn release(self) -> ($LOW, $HIGH) {
+                    self.low.cr1.modify(|_, w| w.cen().clear_bit());
+                    self.high.cr1.modify(|_, w| w.cen().clear_bit());
+                    self.high.smcr.modify(|_, w| w.sms().disabled());
+                    (self.low, self.high)
+                }
+            }
+        )+
+    }
+}
+
+// ITR0 of TIM3 is connected to TIM2, and ITR0 of TIM22 to TIM21. See the
+// internal trigger connection tables in the STM32L0 reference manuals.
+chained_timers! {
+    (TIM21, TIM22): (tim21_tim22, try_tim21_tim22,
+        apb2_tim_clk,
+        tim21::cr2::MMS_A, tim22::smcr::SMS_A, tim22::smcr::TS_A::ITR0),
+}
+
+#[cfg(feature = "io-STM32L071")]
+chained_timers! {
+    (TIM2, TIM3): (tim2_tim3, try_tim2_tim3,
+        apb1_tim_clk,
+        tim2::cr2::MMS_A, tim2::smcr::SMS_A, tim2::smcr::TS_A::ITR0),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prescaler_from_ticks() {
+        assert_eq!(Prescaler::from_ticks(2), Ok(Prescaler { psc: 0, arr: 1 }));
+        assert_eq!(
+            Prescaler::from_ticks(65_536),
+            Ok(Prescaler {
+                psc: 0,
+                arr: 65_535
+            })
+        );
+
+        // The first values that need a prescaler
+        assert_eq!(
+            Prescaler::from_ticks(65_537),
+            Ok(Prescaler {
+                psc: 1,
+                arr: 32_767
+            })
+        );
+        assert_eq!(
+            Prescaler::from_ticks(131_072),
+            Ok(Prescaler {
+                psc: 1,
+                arr: 65_535
+            })
+        );
+        assert_eq!(
+            Prescaler::from_ticks(131_073),
+            Ok(Prescaler {
+                psc: 2,
+                arr: 43_690
+            })
+        );
+
+        assert_eq!(
+            Prescaler::from_ticks(u32::MAX),
+            Ok(Prescaler {
+                psc: 65_535,
+                arr: 65_534
+            })
+        );
+
+        // The counter doesn't run with an auto-reload value of 0.
+        assert_eq!(Prescaler::from_ticks(0), Err(config::Error::Frequency));
+        assert_eq!(Prescaler::from_ticks(1), Err(config::Error::Frequency));
+    }
+
+    #[test]
+    fn prescaler_period() {
+        for &ticks in &[
+            2,
+            3,
+            1_000,
+            65_535,
+            65_536,
+            65_537,
+            100_000,
+            16_000_000,
+            u32::MAX,
+        ] {
+            let Prescaler { psc, arr } = Prescaler::from_ticks(ticks).unwrap();
+            let period = (u64::from(psc) + 1) * (u64::from(arr) + 1);
+
+            // Rounded down by less than one prescaled tick
+            assert!(period <= u64::from(ticks));
+            assert!(u64::from(ticks) - period <= u64::from(psc));
+        }
+    }
+
+    #[test]
+    fn prescaler_rounds_down() {
+        // `arr + 1 = ticks / (psc + 1)`, with the remainder dropped
+        for ticks in 100_000..=100_001 {
+            assert_eq!(
+                Prescaler::from_ticks(ticks),
+                Ok(Prescaler {
+                    psc: 1,
+                    arr: 49_999
+                })
+            );
+        }
+        assert_eq!(
+            Prescaler::from_ticks(200_003),
+            Ok(Prescaler {
+                psc: 3,
+                arr: 49_999
+            })
+        );
+    }
+
+    #[test]
+    fn prescaler_new() {
+        assert_eq!(
+            Prescaler::new(16_000_000, 1_000),
+            Ok(Prescaler {
+                psc: 0,
+                arr: 15_999
+            })
+        );
+        assert_eq!(
+            Prescaler::new(16_000_000, 1),
+            Ok(Prescaler {
+                psc: 244,
+                arr: 65_305
+            })
+        );
+        assert_eq!(Prescaler::new(16_000_000, 0), Err(config::Error::Frequency));
+        // The period is shorter than two timer clock cycles.
+        assert_eq!(
+            Prescaler::new(16_000_000, 16_000_000),
+            Err(config::Error::Frequency)
+        );
+        assert_eq!(
+            Prescaler::new(16_000_000, 32_000_000),
+            Err(config::Error::Frequency)
+        );
+
+        const PRESCALER: Prescaler = match Prescaler::new(32_000_000, 10) {
+            Ok(prescaler) => prescaler,
+            Err(_) => panic!("invalid timer frequency"),
+        };
+        assert_eq!(
+            PRESCALER,
+            Prescaler {
+                psc: 48,
+                arr: 65_305
+            }
+        );
+    }
+
+    #[test]
+    fn counter_prescaler_range() {
+        assert_eq!(counter_prescaler(16_000_000, 16_000_000), Ok(0));
+        assert_eq!(counter_prescaler(16_000_000, 1_000_000), Ok(15));
+        assert_eq!(counter_prescaler(16_000_000, 245), Ok(65_305));
+
+        assert_eq!(
+            counter_prescaler(16_000_000, 244),
+            Err(config::Error::Frequency)
+        );
+        assert_eq!(
+            counter_prescaler(16_000_000, 0),
+            Err(config::Error::Frequency)
+        );
+        assert_eq!(
+            counter_prescaler(16_000_000, 16_000_001),
+            Err(config::Error::Frequency)
+        );
+    }
+}
@@ -0,0 +1,497 @@
+//! Interface to the touch sensing controller (TSC)
+//!
+//! The TSC measures the capacitance of electrodes, using the charge transfer
+//! method. The electrodes are organized in up to 8 groups of 4 I/Os. In every
+//! group that is used, one I/O is connected to a sampling capacitor, and one
+//! or more I/Os are connected to electrodes (channels). The TSC counts the
+//! number of charge transfer cycles required to charge the sampling
+//! capacitor. A touch increases the capacitance of the electrode, which
+//! decreases the count.
+//!
+//! Sampling capacitor pins need to be configured as open drain outputs,
+//! channel pins as push-pull outputs, before they are passed to the TSC.
+
+use crate::{
+    gpio::{
+        gpioa::{PA0, PA1, PA10, PA11, PA12, PA2, PA3, PA4, PA5, PA6, PA7, PA9},
+        gpiob::{PB0, PB1, PB11, PB12, PB13, PB14, PB2, PB3, PB4, PB6, PB7},
+        gpioc::{PC0, PC1, PC2, PC3, PC5, PC6, PC7, PC8, PC9},
+        AltMode, OpenDrain, Output, PushPull,
+    },
+    pac::TSC,
+    rcc::{Enable, Rcc, Reset},
+};
+
+/// Entry point to the TSC API
+pub struct Tsc {
+    tsc: TSC,
+}
+
+impl Tsc {
+    /// Initializes the TSC peripheral
+    pub fn new(tsc: TSC, rcc: &mut Rcc, config: Config) -> Self {
+        // Reset peripheral
+        TSC::reset(rcc);
+
+        // Enable peripheral clock
+        TSC::enable(rcc);
+
+        tsc.cr.write(|w| {
+            // Safe, as all values are masked to the width of the fields.
+            unsafe {
+                w.ctph()
+                    .bits(config.charge_transfer_high.saturating_sub(1) & 0xf);
+                w.ctpl()
+                    .bits(config.charge_transfer_low.saturating_sub(1) & 0xf);
+                w.pgpsc().bits(config.pulse_prescaler as u8);
+                w.mcv().bits(config.max_count as u8);
+            }
+            if let Some(spread_spectrum) = config.spread_spectrum {
+                // Safe, as the value is masked to the width of the field.
+                unsafe { w.ssd().bits(spread_spectrum.deviation & 0x7f) };
+                w.sspsc().bit(spread_spectrum.prescaler_div2);
+                w.sse().set_bit();
+            }
+            // Pull unused I/Os low, to reduce the noise on the channels
+            w.iodef().clear_bit();
+            w.tsce().set_bit()
+        });
+
+        Self { tsc }
+    }
+
+    /// Configures a pin as the sampling capacitor of its group
+    ///
+    /// This also enables the acquisition for the group.
+    pub fn setup_sample_pin<P>(&mut self, pin: &P)
+    where
+        P: SamplePin,
+    {
+        pin.setup();
+
+        let bit = io_bit(P::GROUP, P::IO);
+        self.disable_hysteresis(bit);
+        // Safe, as we're only setting the bit of a valid I/O.
+        self.tsc
+            .ioscr
+            .modify(|r, w| unsafe { w.bits(r.bits() | bit) });
+
+        self.enable_group(P::GROUP);
+    }
+
+    /// Configures a pin as a channel, which is measured during acquisition
+    ///
+    /// All enabled channels of a group are measured together. To measure
+    /// multiple electrodes of a group separately, enable them one after the
+    /// other.
+    pub fn enable_channel<P>(&mut self, pin: &P)
+    where
+        P: ChannelPin,
+    {
+        pin.setup();
+
+        let bit = io_bit(P::GROUP, P::IO);
+        self.disable_hysteresis(bit);
+        // Safe, as we're only setting the bit of a valid I/O.
+        self.tsc
+            .ioccr
+            .modify(|r, w| unsafe { w.bits(r.bits() | bit) });
+    }
+
+    /// Stops measuring a channel
+    pub fn disable_channel<P>(&mut self, _: &P)
+    where
+        P: ChannelPin,
+    {
+        let bit = io_bit(P::GROUP, P::IO);
+        // Safe, as we're only clearing the bit of a valid I/O.
+        self.tsc
+            .ioccr
+            .modify(|r, w| unsafe { w.bits(r.bits() & !bit) });
+    }
+
+    /// Acquires all enabled channels and waits for the acquisition to end
+    ///
+    /// The results can be read using [`Tsc::read`].
+    pub fn acquire(&mut self) -> Result<(), Error> {
+        self.start();
+        nb::block!(self.poll())
+    }
+
+    /// Starts the acquisition of all enabled channels
+    ///
+    /// This is the non-blocking alternative to [`Tsc::acquire`]. Use
+    /// [`Tsc::poll`] or the [`Event::EndOfAcquisition`] interrupt to find out
+    /// when the acquisition has ended.
+    ///
+    /// For low-power periodic scanning, start the acquisition from a timer
+    /// or RTC wakeup, sleep until the end of acquisition interrupt, and
+    /// configure the pulse generator and spread spectrum prescalers for the
+    /// lowest clock frequency that still gives acceptable results.
+    pub fn start(&mut self) {
+        // Clear flags of the previous acquisition
+        self.tsc
+            .icr
+            .write(|w| w.eoaic().set_bit().mceic().set_bit());
+
+        self.tsc.cr.modify(|_, w| w.start().set_bit());
+    }
+
+    /// Checks whether the acquisition has ended
+    ///
+    /// Returns an error, if the acquisition ended with a max count error. The
+    /// flags are left set until the next acquisition is started, so call
+    /// [`Tsc::clear`] from the interrupt handler.
+    pub fn poll(&self) -> nb::Result<(), Error> {
+        if self.tsc.isr.read().eoaf().bit_is_clear() {
+            return Err(nb::Error::WouldBlock);
+        }
+
+        self.check_errors().map_err(nb::Error::Other)
+    }
+
+    /// Starts listening for an interrupt event
+    pub fn listen(&mut self, event: Event) {
+        match event {
+            Event::EndOfAcquisition => self.tsc.ier.modify(|_, w| w.eoaie().set_bit()),
+            Event::MaxCountError => self.tsc.ier.modify(|_, w| w.mceie().set_bit()),
+        }
+    }
+
+    /// Stops listening for an interrupt event
+    pub fn unlisten(&mut self, event: Event) {
+        match event {
+            Event::EndOfAcquisition => self.tsc.ier.modify(|_, w| w.eoaie().clear_bit()),
+            Event::MaxCountError => self.tsc.ier.modify(|_, w| w.mceie().clear_bit()),
+        }
+    }
+
+    /// Clears an interrupt event flag
+    pub fn clear(&mut self, event: Event) {
+        match event {
+            Event::EndOfAcquisition => self.tsc.icr.write(|w| w.eoaic().set_bit()),
+            Event::MaxCountError => self.tsc.icr.write(|w| w.mceic().set_bit()),
+        }
+    }
+
+    /// Returns whether the acquisition of a group has completed
+    pub fn is_complete(&self, group: Group) -> bool {
+        self.tsc.iogcsr.read().bits() & 1 << (16 + group as u32) != 0
+    }
+
+    /// Enables the acquisition of a group
+    ///
+    /// Groups are enabled by [`Tsc::setup_sample_pin`] already. All enabled
+    /// groups are acquired in parallel.
+    pub fn enable_group(&mut self, group: Group) {
+        // Safe, as we're only setting the bit of a valid group.
+        self.tsc
+            .iogcsr
+            .modify(|r, w| unsafe { w.bits(r.bits() | 1 << group as u32) });
+    }
+
+    /// Disables the acquisition of a group
+    pub fn disable_group(&mut self, group: Group) {
+        // Safe, as we're only clearing the bit of a valid group.
+        self.tsc
+            .iogcsr
+            .modify(|r, w| unsafe { w.bits(r.bits() & !(1 << group as u32)) });
+    }
+
+    /// Returns the count of the last acquisition of a group
+    ///
+    /// The count is the number of charge transfer cycles that were required
+    /// to charge the sampling capacitor. It decreases, if the electrode is
+    /// touched.
+    pub fn read(&self, group: Group) -> u16 {
+        let counter = match group {
+            Group::G1 => self.tsc.iog1cr.read(),
+            Group::G2 => self.tsc.iog2cr.read(),
+            Group::G3 => self.tsc.iog3cr.read(),
+            Group::G4 => self.tsc.iog4cr.read(),
+            Group::G5 => self.tsc.iog5cr.read(),
+            Group::G6 => self.tsc.iog6cr.read(),
+            Group::G7 => self.tsc.iog7cr.read(),
+            Group::G8 => self.tsc.iog8cr.read(),
+        };
+
+        counter.cnt().bits()
+    }
+
+    /// Returns the counts of the last acquisition of all groups
+    ///
+    /// Contains `None` for groups that are disabled, or whose acquisition
+    /// didn't complete. The array is indexed by [`Group`].
+    pub fn read_all(&self) -> [Option<u16>; 8] {
+        let mut counts = [None; 8];
+
+        for &group in &GROUPS {
+            if self.is_complete(group) {
+                counts[group as usize] = Some(self.read(group));
+            }
+        }
+
+        counts
+    }
+
+    /// Checks for a max count error in the last acquisition
+    fn check_errors(&self) -> Result<(), Error> {
+        if self.tsc.isr.read().mcef().bit_is_clear() {
+            return Ok(());
+        }
+
+        let iogcsr = self.tsc.iogcsr.read().bits();
+        let enabled = iogcsr as u8;
+        let complete = (iogcsr >> 16) as u8;
+
+        Err(Error::MaxCountError {
+            groups: enabled & !complete,
+        })
+    }
+
+    /// Disables the TSC and releases the peripheral
+    pub fn free(self) -> TSC {
+        self.tsc.cr.modify(|_, w| w.tsce().clear_bit());
+        self.tsc
+    }
+
+    /// Disables the Schmitt trigger hysteresis of an I/O, as recommended for
+    /// all I/Os used by the TSC
+    fn disable_hysteresis(&mut self, bit: u32) {
+        // Safe, as we're only clearing the bit of a valid I/O.
+        self.tsc
+            .iohcr
+            .modify(|r, w| unsafe { w.bits(r.bits() & !bit) });
+    }
+}
+
+/// Returns the bit of an I/O in the I/O control registers
+fn io_bit(group: Group, io: u8) -> u32 {
+    1 << (group as u32 * 4 + io as u32 - 1)
+}
+
+/// TSC configuration
+pub struct Config {
+    /// Duration of the charge transfer pulse high phase
+    ///
+    /// In cycles of the pulse generator clock, from 1 to 16.
+    pub charge_transfer_high: u8,
+
+    /// Duration of the charge transfer pulse low phase
+    ///
+    /// In cycles of the pulse generator clock, from 1 to 16.
+    pub charge_transfer_low: u8,
+
+    /// Prescaler of the pulse generator clock, from the AHB clock
+    pub pulse_prescaler: PulsePrescaler,
+
+    /// Maximum number of charge transfer cycles, before an acquisition is
+    /// aborted with a max count error
+    pub max_count: MaxCount,
+
+    /// Spread spectrum, which varies the duration of the charge transfer
+    /// pulse high phase, to reduce the sensitivity to noise
+    pub spread_spectrum: Option<SpreadSpectrum>,
+}
+
+impl Config {
+    pub fn charge_transfer_high(mut self, cycles: u8) -> Self {
+        self.charge_transfer_high = cycles;
+        self
+    }
+
+    pub fn charge_transfer_low(mut self, cycles: u8) -> Self {
+        self.charge_transfer_low = cycles;
+        self
+    }
+
+    pub fn pulse_prescaler(mut self, prescaler: PulsePrescaler) -> Self {
+        self.pulse_prescaler = prescaler;
+        self
+    }
+
+    pub fn max_count(mut self, max_count: MaxCount) -> Self {
+        self.max_count = max_count;
+        self
+    }
+
+    pub fn spread_spectrum(mut self, spread_spectrum: SpreadSpectrum) -> Self {
+        self.spread_spectrum = Some(spread_spectrum);
+        self
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            charge_transfer_high: 2,
+            charge_transfer_low: 2,
+            pulse_prescaler: PulsePrescaler::Div2,
+            max_count: MaxCount::U8191,
+            spread_spectrum: None,
+        }
+    }
+}
+
+/// Spread spectrum configuration
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct SpreadSpectrum {
+    /// Maximum deviation of the charge transfer pulse high phase
+    ///
+    /// The deviation is `deviation + 1` cycles of the spread spectrum clock,
+    /// with `deviation` ranging from 0 to 127.
+    pub deviation: u8,
+
+    /// Divide the AHB clock by 2 for the spread spectrum clock
+    pub prescaler_div2: bool,
+}
+
+/// Prescaler of the pulse generator clock
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PulsePrescaler {
+    NotDivided = 0,
+    Div2 = 1,
+    Div4 = 2,
+    Div8 = 3,
+    Div16 = 4,
+    Div32 = 5,
+    Div64 = 6,
+    Div128 = 7,
+}
+
+/// Maximum number of charge transfer cycles
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum MaxCount {
+    U255 = 0,
+    U511 = 1,
+    U1023 = 2,
+    U2047 = 3,
+    U4095 = 4,
+    U8191 = 5,
+    U16383 = 6,
+}
+
+/// An I/O group of the TSC
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Group {
+    G1 = 0,
+    G2 = 1,
+    G3 = 2,
+    G4 = 3,
+    G5 = 4,
+    G6 = 5,
+    G7 = 6,
+    G8 = 7,
+}
+
+const GROUPS: [Group; 8] = [
+    Group::G1,
+    Group::G2,
+    Group::G3,
+    Group::G4,
+    Group::G5,
+    Group::G6,
+    Group::G7,
+    Group::G8,
+];
+
+/// Interrupt events of the TSC
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Event {
+    /// The acquisition has ended (EOAF)
+    EndOfAcquisition,
+
+    /// The maximum count was reached (MCEF)
+    MaxCountError,
+}
+
+/// TSC errors
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    /// The maximum count was reached in a group before the sampling
+    /// capacitor was charged
+    ///
+    /// This happens, if the electrode isn't connected, or if the sampling
+    /// capacitor is too large.
+    MaxCountError {
+        /// The groups whose acquisition didn't complete, one bit per group,
+        /// with [`Group::G1`] in the least significant bit
+        groups: u8,
+    },
+}
+
+/// A pin that can be used for a sampling capacitor
+pub trait SamplePin {
+    const GROUP: Group;
+    const IO: u8;
+
+    fn setup(&self);
+}
+
+/// A pin that can be used for a channel
+pub trait ChannelPin {
+    const GROUP: Group;
+    const IO: u8;
+
+    fn setup(&self);
+}
+
+macro_rules! pins {
+    ($($PIN:ident => ($group:ident, $io:expr),)+) => {
+        $(
+            impl SamplePin for $PIN<Output<OpenDrain>> {
+                const GROUP: Group = Group::$group;
+                const IO: u8 = $io;
+
+                fn setup(&self) {
+                    self.set_alt_mode(AltMode::AF3);
+                }
+            }
+
+            impl ChannelPin for $PIN<Output<PushPull>> {
+                const GROUP: Group = Group::$group;
+                const IO: u8 = $io;
+
+                fn setup(&self) {
+                    self.set_alt_mode(AltMode::AF3);
+                }
+            }
+        )+
+    };
+}
+
+pins!(
+    PA0 => (G1, 1),
+    PA1 => (G1, 2),
+    PA2 => (G1, 3),
+    PA3 => (G1, 4),
+    PA4 => (G2, 1),
+    PA5 => (G2, 2),
+    PA6 => (G2, 3),
+    PA7 => (G2, 4),
+    PC5 => (G3, 1),
+    PB0 => (G3, 2),
+    PB1 => (G3, 3),
+    PB2 => (G3, 4),
+    PA9 => (G4, 1),
+    PA10 => (G4, 2),
+    PA11 => (G4, 3),
+    PA12 => (G4, 4),
+    PB3 => (G5, 1),
+    PB4 => (G5, 2),
+    PB6 => (G5, 3),
+    PB7 => (G5, 4),
+    PB11 => (G6, 1),
+    PB12 => (G6, 2),
+    PB13 => (G6, 3),
+    PB14 => (G6, 4),
+    PC0 => (G7, 1),
+    PC1 => (G7, 2),
+    PC2 => (G7, 3),
+    PC3 => (G7, 4),
+    PC6 => (G8, 1),
+    PC7 => (G8, 2),
+    PC8 => (G8, 3),
+    PC9 => (G8, 4),
+);
@@ -13,8 +13,11 @@
 //! fits together.
 
 use crate::{
-    pac::{self, RCC},
-    rcc::HSI48,
+    exti::{DirectLine, Exti},
+    hal::blocking::delay::DelayMs,
+    pac::{self, CRS, RCC},
+    rcc::{Crs, CrsSyncSource, Rcc, HSI48},
+    syscfg::SYSCFG,
 };
 use stm32_usbd::UsbPeripheral;
 
@@ -28,6 +31,147 @@ impl USB {
     pub fn new(_: pac::USB, _dm: PA11<Analog>, _dp: PA12<Analog>, _: HSI48) -> Self {
         Self(())
     }
+
+    /// Creates the USB peripheral for crystal-less operation
+    ///
+    /// Enables the HSI48 and configures the CRS to trim it from the
+    /// start-of-frame packets sent by the host, which keeps it within the USB
+    /// specification over temperature. The returned [`Crs`] can be used to
+    /// monitor the synchronization.
+    pub fn new_with_crs(
+        usb: pac::USB,
+        dm: PA11<Analog>,
+        dp: PA12<Analog>,
+        rcc: &mut Rcc,
+        syscfg: &mut SYSCFG,
+        crs: CRS,
+    ) -> (Self, Crs) {
+        let (hsi48, crs) = rcc.enable_hsi48_with_crs(syscfg, crs, CrsSyncSource::UsbSof);
+
+        (Self::new(usb, dm, dp, hsi48), crs)
+    }
+
+    /// Detects the type of port the device is connected to
+    ///
+    /// Runs the battery charging detection sequence of the USB Battery
+    /// Charging Specification 1.2 (data contact detection, primary and
+    /// secondary detection), which takes about 150 ms, or up to 1 s if no
+    /// data contact is detected. This must be done before the device is
+    /// connected to the bus, i.e. before the `UsbBus` is created, so that the
+    /// charge current can be chosen before enumerating.
+    pub fn detect_charger<D>(&mut self, delay: &mut D) -> ChargingPort
+    where
+        D: DelayMs<u16>,
+    {
+        // Safe, as we own the USB peripheral, and `UsbBus` hasn't been
+        // created yet.
+        let usb = unsafe { &*pac::USB::ptr() };
+
+        // Power up the transceiver, without connecting to the bus
+        Self::enable();
+        usb.cntr.modify(|_, w| w.pdwn().clear_bit());
+        Self::startup_delay();
+        usb.cntr.modify(|_, w| w.fres().clear_bit());
+
+        usb.bcdr.modify(|_, w| w.bcden().set_bit());
+
+        // Data contact detection. Continue with the primary detection after
+        // a timeout, as proprietary chargers might not be detected.
+        usb.bcdr.modify(|_, w| w.dcden().set_bit());
+        for _ in 0..90 {
+            if usb.bcdr.read().dcdet().bit_is_set() {
+                break;
+            }
+            delay.delay_ms(10);
+        }
+        usb.bcdr.modify(|_, w| w.dcden().clear_bit());
+
+        // Primary detection distinguishes standard downstream ports from
+        // charging ports.
+        usb.bcdr.modify(|_, w| w.pden().set_bit());
+        delay.delay_ms(50);
+        let charging = usb.bcdr.read().pdet().bit_is_set();
+        usb.bcdr.modify(|_, w| w.pden().clear_bit());
+
+        let port = if charging {
+            // Secondary detection distinguishes charging downstream ports
+            // from dedicated charging ports.
+            usb.bcdr.modify(|_, w| w.sden().set_bit());
+            delay.delay_ms(50);
+            let dedicated = usb.bcdr.read().sdet().bit_is_set();
+            usb.bcdr.modify(|_, w| w.sden().clear_bit());
+
+            if dedicated {
+                ChargingPort::Dedicated
+            } else {
+                ChargingPort::Charging
+            }
+        } else {
+            ChargingPort::Standard
+        };
+
+        usb.bcdr.modify(|_, w| w.bcden().clear_bit());
+
+        port
+    }
+
+    /// Returns whether the USB peripheral is suspended
+    ///
+    /// `UsbBus` suspends the peripheral, once `UsbDevice::poll` has detected
+    /// that the bus has been idle for 3 ms. The device state is then
+    /// `UsbDeviceState::Suspend`.
+    pub fn is_suspended() -> bool {
+        // Safe, as this is a read without side effects.
+        let usb = unsafe { &*pac::USB::ptr() };
+        usb.cntr.read().fsusp().bit_is_set()
+    }
+
+    /// Enables waking up from Stop mode on USB bus activity
+    ///
+    /// While suspended, the USB transceiver keeps detecting resume signaling
+    /// and bus resets, even in Stop mode. This enables the USB wakeup line of
+    /// the EXTI, so the microcontroller can be put into Stop mode using
+    /// `PWR::stop_mode`, to meet the USB suspend current requirements. After
+    /// waking up, `UsbDevice::poll` resumes the peripheral.
+    ///
+    /// Please note that the HSI48 is stopped in Stop mode, so the system
+    /// clock needs to be configured to restart it, if it was used.
+    pub fn listen_wakeup(exti: &mut Exti) {
+        exti.listen_direct(DirectLine::Usb);
+    }
+
+    /// Disables waking up from Stop mode on USB bus activity
+    pub fn unlisten_wakeup(exti: &mut Exti) {
+        exti.unlisten(DirectLine::Usb);
+    }
+
+    /// Signals a remote wakeup to the host
+    ///
+    /// Drives resume signaling on the bus for 5 ms, which wakes up the host.
+    /// This must only be done while the peripheral is suspended, and if the
+    /// host has enabled remote wakeup, see
+    /// `UsbDevice::remote_wakeup_enabled`. The device needs to advertise
+    /// support for it, using `UsbDeviceBuilder::supports_remote_wakeup`.
+    pub fn remote_wakeup<D>(delay: &mut D)
+    where
+        D: DelayMs<u8>,
+    {
+        // Safe, as the registers are only accessed in critical sections, like
+        // `UsbBus` does.
+        let usb = unsafe { &*pac::USB::ptr() };
+
+        cortex_m::interrupt::free(|_| {
+            usb.cntr
+                .modify(|_, w| w.fsusp().clear_bit().lpmode().clear_bit())
+        });
+        cortex_m::interrupt::free(|_| usb.cntr.modify(|_, w| w.resume().set_bit()));
+
+        // The device has to drive resume signaling for 1 to 15 ms. See USB
+        // 2.0 specification, section 7.1.7.7.
+        delay.delay_ms(5);
+
+        cortex_m::interrupt::free(|_| usb.cntr.modify(|_, w| w.resume().clear_bit()));
+    }
 }
 
 unsafe impl Sync for USB {}
@@ -59,3 +203,20 @@ unsafe impl UsbPeripheral for USB {
 }
 
 pub type UsbBusType = UsbBus<USB>;
+
+/// Type of port, as detected by [`USB::detect_charger`]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ChargingPort {
+    /// Standard downstream port (SDP)
+    ///
+    /// May supply up to 100 mA, or 500 mA after enumeration.
+    Standard,
+    /// Charging downstream port (CDP)
+    ///
+    /// May supply up to 1.5 A, and supports enumeration.
+    Charging,
+    /// Dedicated charging port (DCP)
+    ///
+    /// May supply up to 1.5 A, but doesn't support enumeration.
+    Dedicated,
+}
@@ -1,26 +1,57 @@
 use crate::hal::watchdog;
 use crate::pac::{IWDG, WWDG};
-use crate::rcc::Rcc;
+use crate::rcc::{Enable, Rcc};
 use crate::time::Hertz;
 
+/// The independent watchdog (IWDG)
+///
+/// The IWDG is clocked by the LSI, so it keeps running if the main clock
+/// fails, and in Stop and Standby mode. Once started, it can't be stopped,
+/// except by a reset.
 pub struct IndependedWatchdog {
     iwdg: IWDG,
 }
 
 impl IndependedWatchdog {
+    /// Starts the watchdog, with the given prescaler and reload value
+    ///
+    /// The LSI is divided by `4 << pre`, with `pre` up to 6, and the watchdog
+    /// resets the microcontroller after `reload + 1` periods of the divided
+    /// clock, with `reload` up to `0xFFF`. This returns once the new values
+    /// are in effect.
     pub fn set_config(&mut self, pre: u8, reload: u16) {
-        self.iwdg.kr.write(|w| w.key().reset());
+        self.iwdg.kr.write(|w| w.key().start());
         self.iwdg.kr.write(|w| w.key().enable());
 
-        while self.iwdg.sr.read().pvu().bit() {}
         self.iwdg.pr.write(|w| w.pr().bits(pre));
-
-        while self.iwdg.sr.read().rvu().bit() {}
         self.iwdg.rlr.write(|w| w.rl().bits(reload));
 
-        self.iwdg.kr.write(|w| w.key().start());
+        // The registers are written in the LSI clock domain.
+        while self.iwdg.sr.read().bits() != 0 {}
+
         self.iwdg.kr.write(|w| w.key().reset());
     }
+
+    /// Sets the window value
+    ///
+    /// Feeding the watchdog while the counter is still above `window` resets
+    /// the microcontroller, like a timeout does. This catches code that feeds
+    /// the watchdog in a loop. `window` is in periods of the divided clock, see
+    /// [`IndependedWatchdog::set_config`], and is up to `0xFFF`, which
+    /// disables the window and is the value after reset.
+    ///
+    /// The watchdog needs to be started before. Writing the window value also
+    /// reloads the counter.
+    pub fn set_window(&mut self, window: u16) {
+        self.iwdg.kr.write(|w| w.key().enable());
+
+        // Wait for a previous prescaler or reload update to complete
+        while self.iwdg.sr.read().bits() != 0 {}
+
+        self.iwdg.winr.write(|w| w.win().bits(window & 0xFFF));
+
+        while self.iwdg.sr.read().wvu().bit_is_set() {}
+    }
 }
 
 impl watchdog::Watchdog for IndependedWatchdog {
@@ -32,6 +63,11 @@ impl watchdog::Watchdog for IndependedWatchdog {
 impl watchdog::WatchdogEnable for IndependedWatchdog {
     type Time = Hertz;
 
+    /// Starts the watchdog with a timeout of one `period`
+    ///
+    /// The timeout is based on the typical LSI frequency of 38 kHz, but the
+    /// LSI varies considerably between devices and with temperature. The
+    /// longest possible timeout is about 28 s.
     fn start<T>(&mut self, period: T)
     where
         T: Into<Hertz>,
@@ -41,15 +77,11 @@ impl watchdog::WatchdogEnable for IndependedWatchdog {
         let freq = period.into().0;
         let mut timeout = LSI_CLOCK / freq / 4;
         let mut pre = 0;
-        let mut reload = 0;
-        while pre < 7 {
-            reload = timeout;
-            if reload <= 0xFFF {
-                break;
-            }
+        while timeout > 0x1000 && pre < 6 {
             pre += 1;
             timeout /= 2;
         }
+        let reload = timeout.clamp(1, 0x1000) - 1;
         self.set_config(pre, reload as u16);
     }
 }
@@ -64,6 +96,12 @@ impl IndependedWatchdogExt for IWDG {
     }
 }
 
+/// The window watchdog (WWDG)
+///
+/// The WWDG is clocked by the APB1 clock. Its 7-bit down-counter resets the
+/// microcontroller when it reaches `0x3F`, or when it is fed while the counter
+/// is still above the window value. Once started, it can't be stopped, except
+/// by a reset.
 pub struct WindowWatchdog {
     wwdg: WWDG,
     clk: u32,
@@ -76,6 +114,7 @@ impl watchdog::Watchdog for WindowWatchdog {
 }
 
 impl WindowWatchdog {
+    /// Sets the prescaler and window from the window frequency
     pub fn set_window<T>(&mut self, window: T)
     where
         T: Into<Hertz>,
@@ -100,11 +139,41 @@ impl WindowWatchdog {
         };
         self.wwdg
             .cfr
-            .write(|w| w.wdgtb().bits(pre).w().bits(window_bits));
+            .modify(|_, w| w.wdgtb().bits(pre).w().bits(window_bits));
+    }
+
+    /// Sets the window value
+    ///
+    /// Feeding the watchdog while the counter is above `window` resets the
+    /// microcontroller. The window is disabled by setting it to `0x7F`, which
+    /// is the value after reset.
+    pub fn set_window_value(&mut self, window: u8) {
+        self.wwdg.cfr.modify(|_, w| w.w().bits(window & 0x7F));
+    }
+
+    /// Returns the current value of the down-counter
+    pub fn counter(&self) -> u8 {
+        self.wwdg.cr.read().t().bits()
     }
 
+    /// Enables the early wakeup interrupt
+    ///
+    /// The interrupt is raised when the counter reaches `0x40`, one tick of
+    /// the watchdog clock before the reset. This gives the application a last
+    /// chance to feed the watchdog, or to save the cause of the pending reset.
+    /// Once enabled, the interrupt can only be disabled by a reset.
     pub fn listen(&mut self) {
-        self.wwdg.cfr.write(|w| w.ewi().set_bit());
+        self.wwdg.cfr.modify(|_, w| w.ewi().set_bit());
+    }
+
+    /// Returns whether the early wakeup interrupt is pending
+    pub fn is_early_wakeup(&self) -> bool {
+        self.wwdg.sr.read().ewif().is_pending()
+    }
+
+    /// Clears the early wakeup interrupt flag
+    pub fn clear_early_wakeup(&mut self) {
+        self.wwdg.sr.write(|w| w.ewif().finished());
     }
 }
 
@@ -126,7 +195,7 @@ pub trait WindowWatchdogExt {
 
 impl WindowWatchdogExt for WWDG {
     fn watchdog(self, rcc: &mut Rcc) -> WindowWatchdog {
-        rcc.rb.apb1enr.modify(|_, w| w.wwdgen().set_bit());
+        WWDG::enable(rcc);
         WindowWatchdog {
             wwdg: self,
             clk: rcc.clocks.apb1_clk().0 / 4096,